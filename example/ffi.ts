@@ -0,0 +1,12 @@
+declare function echo(...s: any[]): void;
+
+// functions of the C library, whose int32 parameters and results are
+// marshalled from and to numbers
+declare function abs(n: int32): int32;
+declare function toupper(c: int32): int32;
+
+echo(abs(-42), abs(7));
+echo(toupper(97));
+
+// numbers are truncated to the low 32 bits of their integer part
+echo(abs(-2.75), abs(4294967295));
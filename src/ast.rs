@@ -1,15 +1,16 @@
 use indexmap::{IndexMap, IndexSet};
+use serde::Serialize;
 
 pub use crate::value::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum UnaryOperator {
     Positive,
     Negative,
     Not,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum BinaryOperator {
     Addition,
     Subtraction,
@@ -28,7 +29,39 @@ pub enum BinaryOperator {
     Or,
 }
 
-#[derive(Clone, Debug)]
+impl UnaryOperator {
+    pub fn get_symbol(&self) -> &'static str {
+        match self {
+            UnaryOperator::Positive => "+",
+            UnaryOperator::Negative => "-",
+            UnaryOperator::Not => "!",
+        }
+    }
+}
+
+impl BinaryOperator {
+    pub fn get_symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Addition => "+",
+            BinaryOperator::Subtraction => "-",
+            BinaryOperator::Multiplication => "*",
+            BinaryOperator::Division => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::StrictEqual => "===",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::StrictNotEqual => "!==",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub enum VariableIdentifier<'input> {
     Name {
         location: (usize, usize),
@@ -46,22 +79,59 @@ pub enum VariableIdentifier<'input> {
     },
 }
 
-#[derive(Clone, Debug)]
+/// The property or the index accessed by a `MemberExpression`.
+#[derive(Clone, Debug, Serialize)]
+pub enum Member<'input> {
+    Property(&'input str),
+    Index(Box<Expression<'input>>),
+    /// `[start:end]` of an array or a string, a left out bound being its start or its end.
+    Slice {
+        start: Option<Box<Expression<'input>>>,
+        end: Option<Box<Expression<'input>>>,
+    },
+}
+
+/// The pattern of a `match` arm.
+#[derive(Clone, Debug, Serialize)]
+pub enum Pattern<'input> {
+    /// Matches the values strictly equal to the constant.
+    Constant(Constant<'input>),
+    /// Matches any value and binds it to the name, unless the name is `_`.
+    Binding(&'input str),
+    /// `Symbol("name")`, which matches the symbols of the name.
+    Symbol(&'input str),
+    /// Matches the objects whose properties match the patterns, whatever other
+    /// properties they have. Arrays are objects to `typeof`, so matching one
+    /// fails like reading its properties does.
+    Object(IndexMap<&'input str, Pattern<'input>>),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MatchArm<'input> {
+    pub location: (usize, usize),
+    pub pattern: Pattern<'input>,
+    pub expression: Expression<'input>,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct VariableDefinition<'input> {
     pub location: (usize, usize),
     pub name: &'input str,
     pub kind: VariableKind,
+    pub is_annotated: bool,
     pub is_writable: bool,
     pub is_external: bool,
     pub decorators: IndexSet<&'input str>,
+    /// Lines of the `///` comments right before the definition.
+    pub docs: Vec<&'input str>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Program<'input> {
     pub statements: Vec<Statement<'input>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum Statement<'input> {
     ExpressionStatement {
         expression: Expression<'input>,
@@ -81,10 +151,37 @@ pub enum Statement<'input> {
         location: (usize, usize),
         expression: Option<Expression<'input>>,
     },
+    /// `defer expression;`, whose statements run when the block it is in is left,
+    /// the last deferred first. They are the expression, and the statements its
+    /// `match` expressions are lowered to.
+    DeferStatement {
+        location: (usize, usize),
+        statements: Vec<Statement<'input>>,
+    },
+    IfStatement {
+        location: (usize, usize),
+        condition: Expression<'input>,
+        statements: Vec<Statement<'input>>,
+        else_statements: Option<Vec<Statement<'input>>>,
+    },
+    /// `macro name(a, b) { ... }`, which `lower::lower` removes once it has
+    /// expanded its calls.
+    MacroStatement {
+        location: (usize, usize),
+        name: &'input str,
+        parameters: Vec<&'input str>,
+        statements: Vec<Statement<'input>>,
+    },
+    /// `name!(a, b);`, which `lower::lower` replaces with the statements of the macro.
+    MacroCallStatement {
+        location: (usize, usize),
+        name: &'input str,
+        arguments: Vec<Expression<'input>>,
+    },
     EmptyStatement,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum Expression<'input> {
     ConstantExpression {
         location: (usize, usize),
@@ -102,6 +199,11 @@ pub enum Expression<'input> {
         location: (usize, usize),
         expression: Box<Expression<'input>>,
     },
+    ValidateExpression {
+        location: (usize, usize),
+        kind: VariableKind,
+        expression: Box<Expression<'input>>,
+    },
     VariableExpression {
         location: (usize, usize),
         identifier: VariableIdentifier<'input>,
@@ -111,11 +213,45 @@ pub enum Expression<'input> {
         identifier: VariableIdentifier<'input>,
         arguments: Vec<Expression<'input>>,
     },
+    /// Accesses a member of a value that is not a variable, like `f().x`, or
+    /// slices any value, like `s[1:]`.
+    MemberExpression {
+        location: (usize, usize),
+        expression: Box<Expression<'input>>,
+        member: Member<'input>,
+    },
     AssignmentExpression {
         location: (usize, usize),
         identifier: VariableIdentifier<'input>,
         expression: Box<Expression<'input>>,
     },
+    /// `x += e` and the like, which `lower::lower` turns into `x = x + e`.
+    CompoundAssignmentExpression {
+        location: (usize, usize),
+        identifier: VariableIdentifier<'input>,
+        operator: BinaryOperator,
+        expression: Box<Expression<'input>>,
+    },
+    /// `match (x) { 1 => a, _ => b }`, which `lower::lower` turns into `if`
+    /// statements before the statement it is in.
+    MatchExpression {
+        location: (usize, usize),
+        subject: Box<Expression<'input>>,
+        arms: Vec<MatchArm<'input>>,
+    },
+    /// `Symbol(name)`, equal to the other symbols of the same name. Literal names
+    /// are interned once by the generated code.
+    SymbolExpression {
+        location: (usize, usize),
+        name: Box<Expression<'input>>,
+    },
+    /// `start..end`, or `start..=end` with the end in the range.
+    RangeExpression {
+        location: (usize, usize),
+        start: Box<Expression<'input>>,
+        end: Box<Expression<'input>>,
+        is_inclusive: bool,
+    },
     UnaryExpression {
         location: (usize, usize),
         operator: UnaryOperator,
@@ -129,3 +265,63 @@ pub enum Expression<'input> {
     },
     Empty,
 }
+
+impl<'input> VariableIdentifier<'input> {
+    /// Renders names like `p.x` or `xs[]` for diagnostics.
+    pub fn get_name(&self) -> String {
+        match self {
+            VariableIdentifier::Name { name, .. } => name.to_string(),
+            VariableIdentifier::Property { base, property, .. } => {
+                format!("{}.{}", base.get_name(), property)
+            }
+            VariableIdentifier::Index { base, .. } => format!("{}[]", base.get_name()),
+        }
+    }
+
+    pub fn get_location(&self) -> (usize, usize) {
+        match self {
+            VariableIdentifier::Name { location, .. } => *location,
+            VariableIdentifier::Index { location, .. } => *location,
+            VariableIdentifier::Property { location, .. } => *location,
+        }
+    }
+}
+
+impl<'input> Statement<'input> {
+    pub fn get_location(&self) -> Option<(usize, usize)> {
+        match self {
+            Statement::ExpressionStatement { expression } => expression.get_location(),
+            Statement::DefinitionStatement { location, .. } => Some(*location),
+            Statement::FunctionStatement { location, .. } => Some(*location),
+            Statement::ReturnStatement { location, .. } => Some(*location),
+            Statement::DeferStatement { location, .. } => Some(*location),
+            Statement::IfStatement { location, .. } => Some(*location),
+            Statement::MacroStatement { location, .. } => Some(*location),
+            Statement::MacroCallStatement { location, .. } => Some(*location),
+            Statement::EmptyStatement => None,
+        }
+    }
+}
+
+impl<'input> Expression<'input> {
+    pub fn get_location(&self) -> Option<(usize, usize)> {
+        match self {
+            Expression::ConstantExpression { location, .. } => Some(*location),
+            Expression::ArrayExpression { location, .. } => Some(*location),
+            Expression::ObjectExpression { location, .. } => Some(*location),
+            Expression::TypeOfExpression { location, .. } => Some(*location),
+            Expression::ValidateExpression { location, .. } => Some(*location),
+            Expression::VariableExpression { location, .. } => Some(*location),
+            Expression::CallExpression { location, .. } => Some(*location),
+            Expression::MemberExpression { location, .. } => Some(*location),
+            Expression::AssignmentExpression { location, .. } => Some(*location),
+            Expression::CompoundAssignmentExpression { location, .. } => Some(*location),
+            Expression::MatchExpression { location, .. } => Some(*location),
+            Expression::RangeExpression { location, .. } => Some(*location),
+            Expression::SymbolExpression { location, .. } => Some(*location),
+            Expression::UnaryExpression { location, .. } => Some(*location),
+            Expression::BinaryExpression { location, .. } => Some(*location),
+            Expression::Empty => None,
+        }
+    }
+}
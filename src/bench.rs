@@ -0,0 +1,175 @@
+use inkwell::context::Context;
+use inkwell::execution_engine::{ExecutionEngine, JitFunction, UnsafeFunctionPointer};
+use inkwell::targets::{InitializationConfig, Target};
+use inkwell::values::FunctionValue;
+use std::ffi::c_void;
+use std::time::{Duration, Instant};
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::gen;
+use crate::options::CompileOptions;
+use crate::pipeline;
+use crate::st;
+use crate::testing;
+use crate::typeck;
+
+type MainFunction = unsafe extern "C" fn() -> i32;
+type BenchFunction = unsafe extern "C" fn() -> *mut c_void;
+
+/// Wall times of the measured iterations of a benchmark.
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    pub iterations: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    pub std_dev: Duration,
+}
+
+impl Statistics {
+    fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+
+        let iterations = samples.len();
+        let total = samples.iter().sum::<Duration>();
+        let mean = total / iterations as u32;
+
+        let variance = samples
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean.as_secs_f64()).powi(2))
+            .sum::<f64>()
+            / iterations as f64;
+
+        Statistics {
+            iterations,
+            min: samples[0],
+            max: samples[iterations - 1],
+            mean,
+            median: samples[iterations / 2],
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+/// Compiles the program with the JIT, runs its top level statements once, then
+/// calls every `@bench` function `warmup` times before measuring `iterations`
+/// calls of it.
+pub fn run(
+    content: &str,
+    options: &CompileOptions,
+    warmup: u32,
+    iterations: u32,
+) -> Result<Vec<(String, Statistics)>, CompilerError> {
+    measure(content, options, warmup, iterations.max(1)).map_err(|err| options.report(err))
+}
+
+fn lookup<'ctx, F: UnsafeFunctionPointer>(
+    engine: &ExecutionEngine<'ctx>,
+    function: FunctionValue<'ctx>,
+) -> Result<JitFunction<'ctx, F>, CompilerError> {
+    let name = function.get_name().to_string_lossy().into_owned();
+
+    // SAFETY: the callers must ask for the type the function was generated with
+    unsafe { engine.get_function::<F>(&name) }.map_err(|err| {
+        CompilerError::CodeGenError(format!("Could not find a JIT function: {:?}", err))
+    })
+}
+
+fn measure(
+    content: &str,
+    options: &CompileOptions,
+    warmup: u32,
+    iterations: u32,
+) -> Result<Vec<(String, Statistics)>, CompilerError> {
+    let program = pipeline::parse(content)?;
+    let main_def = pipeline::main_definition(content);
+
+    let symbol_table = st::SymbolTable::from(&main_def, &program)?;
+    options.check_warnings(content, &symbol_table.warnings)?;
+
+    let casts = if options.strict {
+        typeck::TypeChecker::new(&symbol_table).check()?
+    } else {
+        typeck::RuntimeCasts::new()
+    };
+
+    let benchmarks = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            ast::Statement::FunctionStatement { definition, .. }
+                if testing::is_bench(definition) =>
+            {
+                Some(definition)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // the benchmarks are called without arguments
+    for definition in benchmarks.iter() {
+        if let ast::VariableKind::Function { parameters, .. } = &definition.kind {
+            if parameters.iter().any(|p| !p.is_optional && !p.is_rest) {
+                return Err(CompilerError::CliError(format!(
+                    "Benchmark `{}` cannot take required parameters",
+                    definition.name
+                )));
+            }
+        }
+    }
+
+    let context = Context::create();
+    let mut ir_generator = gen::IRGenerator::new(&symbol_table, None, &context, options)?;
+    ir_generator.set_casts(casts);
+    ir_generator.generate()?;
+
+    let callers = benchmarks
+        .iter()
+        .map(|definition| ir_generator.define_caller(symbol_table.definition_ref(definition)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Target::initialize_native(&InitializationConfig::default()).map_err(|err| {
+        CompilerError::CodeGenError(format!("Could not initialize the JIT: {}", err))
+    })?;
+
+    let engine = ir_generator
+        .module()
+        .create_jit_execution_engine(options.opt_level)
+        .map_err(|err| CompilerError::CodeGenError(format!("Could not create the JIT: {}", err)))?;
+
+    // the top level statements run once for their effects; the variables they
+    // define live in the frame of main and are gone once it returns
+    let main = ir_generator
+        .function(&symbol_table.main_function.unwrap())
+        .unwrap();
+    let main = lookup::<MainFunction>(&engine, main)?;
+    unsafe { main.call() };
+
+    let mut results = Vec::new();
+
+    for (definition, caller) in benchmarks.into_iter().zip(callers) {
+        // every caller takes no argument and returns a value pointer
+        let function = lookup::<BenchFunction>(&engine, caller)?;
+
+        for _ in 0..warmup {
+            unsafe { function.call() };
+        }
+
+        let samples = (0..iterations)
+            .map(|_| {
+                let start = Instant::now();
+                unsafe { function.call() };
+                start.elapsed()
+            })
+            .collect();
+
+        results.push((
+            definition.name.to_string(),
+            Statistics::from_samples(samples),
+        ));
+    }
+
+    Ok(results)
+}
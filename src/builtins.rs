@@ -0,0 +1,330 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::source::SourceFile;
+
+/// A function implemented by the std runtime that mini programs can call after
+/// declaring it with the given `declare function` statement.
+#[derive(Clone, Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub declaration: &'static str,
+    pub doc: &'static str,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "echo",
+        declaration: "declare function echo(...s: any[]): void;",
+        doc: "Prints the given values separated by spaces, followed by a newline.",
+    },
+    Builtin {
+        name: "assert",
+        declaration: "declare function assert(condition: any, message?: string): void;",
+        doc: "Stops the program with a runtime error, showing the message if given, when the condition is false.",
+    },
+    Builtin {
+        name: "on_error",
+        declaration: "declare function on_error(handler: any): void;",
+        doc: "Installs a function called with the message of a runtime error before the program exits with status 4, undefined or null removing it.",
+    },
+    Builtin {
+        name: "sleep_ms",
+        declaration: "declare function sleep_ms(ms: number): void;",
+        doc: "Pauses the program for the given number of milliseconds.",
+    },
+    Builtin {
+        name: "set_timeout",
+        declaration: "declare function set_timeout(callback: any, ms: number): number;",
+        doc: "Schedules the function to be called after the given number of milliseconds, once the program finished running, and returns the id of the timeout.",
+    },
+    Builtin {
+        name: "thread_spawn",
+        declaration: "declare function thread_spawn(callback: any, argument: any): any;",
+        doc: "Calls the function with a copy of the argument in a new thread, which has a heap of its own, and returns the thread.",
+    },
+    Builtin {
+        name: "thread_join",
+        declaration: "declare function thread_join(thread: any): any;",
+        doc: "Waits for the thread to finish and returns a copy of what its function returned.",
+    },
+    Builtin {
+        name: "channel_new",
+        declaration: "declare function channel_new(): any;",
+        doc: "Creates a channel, which copies of values are sent over between threads.",
+    },
+    Builtin {
+        name: "channel_send",
+        declaration: "declare function channel_send(channel: any, value: any): void;",
+        doc: "Sends a copy of the value over the channel, without waiting for it to be received.",
+    },
+    Builtin {
+        name: "channel_recv",
+        declaration: "declare function channel_recv(channel: any): any;",
+        doc: "Waits until a value was sent over the channel and returns the first one that was not received yet.",
+    },
+    Builtin {
+        name: "mem_stats",
+        declaration: "declare function mem_stats(): { values: number, bytes: number };",
+        doc: "Returns the number of values allocated on the heap that are still alive, and the bytes they take.",
+    },
+    Builtin {
+        name: "string_builder",
+        declaration: "declare function string_builder(): any;",
+        doc: "Creates an empty string builder, which strings are appended to in amortized constant time.",
+    },
+    Builtin {
+        name: "builder_append",
+        declaration: "declare function builder_append(builder: any, s: string): void;",
+        doc: "Appends the string to the string builder.",
+    },
+    Builtin {
+        name: "builder_to_string",
+        declaration: "declare function builder_to_string(builder: any): string;",
+        doc: "Returns the string built so far by the string builder.",
+    },
+    Builtin {
+        name: "buffer_alloc",
+        declaration: "declare function buffer_alloc(size: number): any;",
+        doc: "Creates a buffer of the given number of zeroed bytes.",
+    },
+    Builtin {
+        name: "buffer_length",
+        declaration: "declare function buffer_length(buffer: any): number;",
+        doc: "Returns the number of bytes in the buffer.",
+    },
+    Builtin {
+        name: "buffer_read_u8",
+        declaration: "declare function buffer_read_u8(buffer: any, index: number): number;",
+        doc: "Returns the byte at the index of the buffer.",
+    },
+    Builtin {
+        name: "buffer_write_u8",
+        declaration: "declare function buffer_write_u8(buffer: any, index: number, byte: number): void;",
+        doc: "Sets the byte at the index of the buffer.",
+    },
+    Builtin {
+        name: "buffer_slice",
+        declaration: "declare function buffer_slice(buffer: any, start: number, end: number): any;",
+        doc: "Copies the bytes of the buffer from start up to end into a new buffer, negative indices counting from the end.",
+    },
+    Builtin {
+        name: "buffer_from_string",
+        declaration: "declare function buffer_from_string(s: string): any;",
+        doc: "Creates a buffer holding the bytes of the string.",
+    },
+    Builtin {
+        name: "buffer_to_string",
+        declaration: "declare function buffer_to_string(buffer: any): string;",
+        doc: "Returns the bytes of the buffer as a string, up to the first null byte.",
+    },
+    Builtin {
+        name: "float64_array",
+        declaration: "declare function float64_array(len: number): Float64Array;",
+        doc: "Creates a Float64Array of the given number of zeros, whose items are stored unboxed.",
+    },
+    Builtin {
+        name: "int64_array",
+        declaration: "declare function int64_array(len: number): Int64Array;",
+        doc: "Creates an Int64Array of the given number of zeros, whose items are stored unboxed.",
+    },
+    Builtin {
+        name: "typed_array_len",
+        declaration: "declare function typed_array_len(items: any): number;",
+        doc: "Returns the number of items in the Float64Array or Int64Array.",
+    },
+    Builtin {
+        name: "array_add",
+        declaration: "declare function array_add(a: any, b: any): any;",
+        doc: "Returns the item-wise sums of two typed arrays of the same type and length.",
+    },
+    Builtin {
+        name: "array_scale",
+        declaration: "declare function array_scale(a: any, k: number): any;",
+        doc: "Returns the items of the typed array multiplied by the number, an integer for an Int64Array.",
+    },
+    Builtin {
+        name: "dot",
+        declaration: "declare function dot(a: any, b: any): number;",
+        doc: "Returns the dot product of two typed arrays of the same type and length.",
+    },
+    Builtin {
+        name: "val_array_len",
+        declaration: "declare function val_array_len(items: any[]): number;",
+        doc: "Returns the number of items in the array.",
+    },
+    Builtin {
+        name: "val_array_pop",
+        declaration: "declare function val_array_pop(items: any[]): any;",
+        doc: "Removes the last item of the array and returns it, or undefined if the array is empty.",
+    },
+    Builtin {
+        name: "val_array_shift",
+        declaration: "declare function val_array_shift(items: any[]): any;",
+        doc: "Removes the first item of the array and returns it, or undefined if the array is empty.",
+    },
+    Builtin {
+        name: "val_array_unshift",
+        declaration: "declare function val_array_unshift(items: any[], item: any): void;",
+        doc: "Inserts the item at the start of the array.",
+    },
+    Builtin {
+        name: "val_array_slice",
+        declaration: "declare function val_array_slice(items: any[], start: number, end: number): any[];",
+        doc: "Returns a new array with the items from start up to end, negative indices counting from the end.",
+    },
+    Builtin {
+        name: "val_array_concat",
+        declaration: "declare function val_array_concat(items: any[], others: any[]): any[];",
+        doc: "Returns a new array with the items of both arrays.",
+    },
+    Builtin {
+        name: "val_array_join",
+        declaration: "declare function val_array_join(items: any[], separator: string): string;",
+        doc: "Joins the text of the items of the array with the separator, undefined and null being empty.",
+    },
+    Builtin {
+        name: "val_array_index_of",
+        declaration: "declare function val_array_index_of(items: any[], item: any): number;",
+        doc: "Returns the index of the first item strictly equal to the given one, or -1 if there is none.",
+    },
+    Builtin {
+        name: "val_array_sort",
+        declaration: "declare function val_array_sort(items: any[]): void;",
+        doc: "Sorts the array in place in the order of `<`.",
+    },
+    Builtin {
+        name: "val_array_reverse",
+        declaration: "declare function val_array_reverse(items: any[]): void;",
+        doc: "Reverses the array in place.",
+    },
+    Builtin {
+        name: "val_str_slice",
+        declaration: "declare function val_str_slice(s: string, start: number, end: number): string;",
+        doc: "Returns the characters of the string from start up to end, negative indices counting from the end.",
+    },
+    Builtin {
+        name: "exec",
+        declaration: "declare function exec(cmd: string, args: string[]): { code: number, output: string };",
+        doc: "Runs the command with the arguments until it exits, returning its exit status and what it wrote to stdout.",
+    },
+    Builtin {
+        name: "spawn",
+        declaration: "declare function spawn(cmd: string, args: string[]): void;",
+        doc: "Starts the command with the arguments without waiting for it to exit.",
+    },
+    Builtin {
+        name: "fs_list_dir",
+        declaration: "declare function fs_list_dir(path: string): string[];",
+        doc: "Returns the names of the entries of the directory in order, without `.` and `..`.",
+    },
+    Builtin {
+        name: "fs_stat",
+        declaration: "declare function fs_stat(path: string): { size: number, is_file: any, is_dir: any, modified: number };",
+        doc: "Returns the size of the file, whether it is a regular file or a directory, and when it was last modified in seconds since the epoch.",
+    },
+    Builtin {
+        name: "fs_mkdir",
+        declaration: "declare function fs_mkdir(path: string): void;",
+        doc: "Creates the directory.",
+    },
+    Builtin {
+        name: "fs_remove",
+        declaration: "declare function fs_remove(path: string): void;",
+        doc: "Removes the file or the empty directory.",
+    },
+    Builtin {
+        name: "fs_exists",
+        declaration: "declare function fs_exists(path: string): any;",
+        doc: "Returns whether a file or a directory exists at the path.",
+    },
+    Builtin {
+        name: "fs_copy",
+        declaration: "declare function fs_copy(source: string, target: string): void;",
+        doc: "Copies the file, replacing the target if it exists.",
+    },
+    Builtin {
+        name: "path_join",
+        declaration: "declare function path_join(...parts: string[]): string;",
+        doc: "Joins the non-empty parts of a path with single slashes.",
+    },
+    Builtin {
+        name: "path_dirname",
+        declaration: "declare function path_dirname(path: string): string;",
+        doc: "Returns the path without its last segment, like `dirname` in POSIX shells.",
+    },
+    Builtin {
+        name: "path_basename",
+        declaration: "declare function path_basename(path: string): string;",
+        doc: "Returns the last segment of the path, ignoring trailing slashes.",
+    },
+    Builtin {
+        name: "path_ext",
+        declaration: "declare function path_ext(path: string): string;",
+        doc: "Returns the extension of the last segment of the path from its last dot, or an empty string if it has none.",
+    },
+    Builtin {
+        name: "format",
+        declaration: "declare function format(fmt: string, ...args: any[]): string;",
+        doc: "Replaces the `{}` and `{index}` placeholders of the format string with the arguments, padding them to `{:<width}`, `{:>width}`, `{:^width}` or `{:0width}` and limiting numbers and strings to `{:.precision}`.",
+    },
+    Builtin {
+        name: "val_to_str",
+        declaration: "declare function val_to_str(value: any): string;",
+        doc: "Returns the text of the value as `echo` prints it, arrays as `[1, 2]` and objects as `{ a: 1 }`.",
+    },
+    Builtin {
+        name: "embed",
+        declaration: "declare function embed(path: string): string;",
+        doc: "Returns the content of the text file at the literal path, relative to the source file, read at compile time and stored in the binary.",
+    },
+    Builtin {
+        name: "__llvm_ir",
+        declaration: "declare function __llvm_ir(body: string, ...operands: any[]): any;",
+        doc: "Runs the literal LLVM IR function body, which receives the operands as `%0`, `%1` and so on and returns a value, with `val_unbox_bool`, `val_unbox_int`, `val_unbox_float` and the `new_bool_val`, `new_int_val`, `new_float_val` functions declared for it. Only the compiled backend supports it.",
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// The content of the file a call of `embed` names, relative to the directory of
+/// the source file or to the working directory without one.
+pub fn read_embedded_file(
+    source: Option<&SourceFile>,
+    location: (usize, usize),
+    arguments: &[ast::Expression],
+) -> Result<String, CompilerError> {
+    let path = match arguments.first() {
+        Some(ast::Expression::ConstantExpression {
+            value: ast::Constant::String(path),
+            ..
+        }) => *path,
+        _ => {
+            return Err(CompilerError::CannotEmbedFile(
+                location,
+                "`embed` expects a literal path".to_string(),
+            ))
+        }
+    };
+
+    let full_path = match source.and_then(|source| Path::new(source.name).parent()) {
+        Some(directory) => directory.join(path),
+        None => PathBuf::from(path),
+    };
+
+    let content = fs::read_to_string(&full_path)
+        .map_err(|err| CompilerError::CannotEmbedFile(location, format!("{}: {}", path, err)))?;
+
+    if content.contains('\0') {
+        return Err(CompilerError::CannotEmbedFile(
+            location,
+            format!("{}: the file contains a NUL byte", path),
+        ));
+    }
+
+    Ok(content)
+}
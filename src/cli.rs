@@ -1,89 +1,1073 @@
-use clap::{App, Arg};
-use indexmap::IndexSet;
-use inkwell::context::Context;
-use inkwell::targets::TargetTriple;
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use inkwell::targets::{CodeModel, RelocMode};
+use inkwell::OptimizationLevel;
+use std::cell::RefCell;
+use std::env;
 use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
 
 use crate::ast;
-use crate::error::CompilerError;
-use crate::gen;
-use crate::parser;
+use crate::bench;
+use crate::builtins;
+use crate::doc;
+use crate::error::{CompilerError, DiagnosticEmitter, TerminalEmitter};
+use crate::explain;
+use crate::formatter;
+use crate::lsp;
+use crate::manifest::Manifest;
+use crate::nesting;
+use crate::options::{CompileOptions, Sanitizer};
+use crate::pipeline;
+use crate::repl::Repl;
+use crate::source::SourceFile;
 use crate::st;
+use crate::warning::{Level, WarningKind};
 
-fn compile(matches: &clap::ArgMatches) -> Result<(), String> {
-    let input_file = matches
-        .value_of("input")
-        .ok_or_else(|| "No input file provided".to_string())?;
-
-    let content =
-        fs::read_to_string(input_file).map_err(|_| format!("File not found: {}", input_file))?;
-
-    let program = parser::ProgramParser::new()
-        .parse(&content)
-        .map_err(|err| CompilerError::ParserError(err).to_string())?;
-
-    let main_def = ast::VariableDefinition {
-        location: (0, content.len()),
-        name: "main",
-        kind: ast::VariableKind::Function {
-            parameters: Vec::new(),
-            return_kind: Box::new(ast::VariableKind::Number),
-        },
-        is_writable: false,
-        is_external: false,
-        decorators: IndexSet::new(),
-    };
+/// Exit status for errors in the program, including its type errors.
+pub const EXIT_COMPILE_ERROR: i32 = 1;
+/// Exit status for invalid invocations, missing files and invalid manifests.
+pub const EXIT_USAGE_ERROR: i32 = 2;
+/// Exit status for failures of code generation or linking.
+pub const EXIT_CODEGEN_ERROR: i32 = 3;
+/// Exit status for errors raised while the interpreter runs the program.
+pub const EXIT_RUNTIME_ERROR: i32 = 4;
 
-    let symbol_table = st::SymbolTable::from(&main_def, &program).map_err(|err| err.to_string())?;
+fn exit_code(error: &CompilerError) -> i32 {
+    match error {
+        CompilerError::CliError(_) => EXIT_USAGE_ERROR,
+        CompilerError::BuilderError(_)
+        | CompilerError::CodeGenError(_)
+        | CompilerError::Internal(..) => EXIT_CODEGEN_ERROR,
+        CompilerError::RuntimeError(..) => EXIT_RUNTIME_ERROR,
+        _ => EXIT_COMPILE_ERROR,
+    }
+}
 
-    let triple = target_lexicon::Triple::host();
-    let llvm_triple = TargetTriple::create(&triple.to_string());
+#[derive(Parser)]
+#[clap(
+    name = "mini",
+    version = "0.1.0",
+    author = "OZAN AKIN",
+    about = "Mini language compiler",
+    arg_required_else_help = true
+)]
+pub struct Args {
+    #[clap(flatten)]
+    pub global: GlobalArgs,
 
-    let out_file: &String = matches.get_one::<String>("output").unwrap();
+    #[clap(subcommand)]
+    pub command: Command,
+}
 
-    let ir_context = Context::create();
-    gen::IRGenerator::generate(
-        &symbol_table,
-        &ir_context,
-        &llvm_triple,
-        matches.is_present("optimize"),
-        std::path::Path::new(out_file).to_path_buf(),
-    )
-    .map_err(|err| CompilerError::CodeGenError(err.to_string()).to_string())?;
+/// Options accepted by every subcommand.
+#[derive(clap::Args)]
+pub struct GlobalArgs {
+    /// When to use colors in the output
+    #[clap(long, global = true, value_enum, default_value = "auto")]
+    pub color: ColorMode,
+
+    /// Report what the compiler is doing, `-vv` also prints the duration of each pass
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print the duration of each compilation pass
+    #[clap(long, global = true)]
+    pub time_passes: bool,
+
+    /// Print counts of the generated code and the size of the output
+    #[clap(long, global = true)]
+    pub stats: bool,
+
+    /// Target triple to compile for, can be given more than once
+    #[clap(long, global = true, value_parser)]
+    pub target: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether errors and warnings are printed with colors. `auto` follows the
+    /// `NO_COLOR` and `CLICOLOR_FORCE` conventions, and otherwise colors only
+    /// when the standard output is a terminal.
+    pub fn is_enabled(&self) -> bool {
+        let is_set = |name| env::var_os(name).is_some_and(|value| !value.is_empty());
+
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto if is_set("NO_COLOR") => false,
+            ColorMode::Auto if is_set("CLICOLOR_FORCE") => {
+                env::var_os("CLICOLOR_FORCE").is_some_and(|value| value != "0")
+            }
+            ColorMode::Auto => {
+                io::stdout().is_terminal() && env::var_os("TERM").is_some_and(|term| term != "dumb")
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Compile a file, or the project of a mini.toml manifest, into an executable
+    Build(BuildArgs),
+    /// Compile and run a file, or the project of a mini.toml manifest
+    Run(RunArgs),
+    /// Check a file, or the project of a mini.toml manifest, without generating code
+    Check(CheckArgs),
+    /// Run the test functions of a file, or of the project of a mini.toml manifest
+    Test(TestArgs),
+    /// Measure the bench functions of a file, or of the project of a mini.toml manifest
+    Bench(BenchArgs),
+    /// Print source files in the canonical style
+    Fmt(FmtArgs),
+    /// Print the syntax tree of a file, or of the project of a mini.toml manifest
+    Ast(AstArgs),
+    /// Generate the API documentation of a file, or of the project of a mini.toml manifest
+    Doc(DocArgs),
+    /// Run statements interactively with the interpreter
+    Repl,
+    /// Run the language server over the standard input and output
+    Lsp,
+    /// Inspect the std runtime API
+    #[clap(subcommand)]
+    Std(StdCommand),
+    /// Describe an error code, or list them all
+    Explain {
+        /// Error code, such as E0001
+        code: Option<String>,
+    },
+}
+
+/// The program to work on, a single file or a project manifest.
+#[derive(clap::Args)]
+pub struct InputArgs {
+    /// Source file, the mini.toml manifest is used when it is not given
+    #[clap(value_parser)]
+    pub input: Option<PathBuf>,
+
+    /// Path of the manifest file
+    #[clap(long, value_parser, default_value = Manifest::FILE_NAME)]
+    pub manifest_path: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct CheckerArgs {
+    /// Report the given warning
+    #[clap(short = 'W', value_parser = PossibleValuesParser::new(WarningKind::NAMES))]
+    pub warn: Vec<String>,
+
+    /// Silence the given warning
+    #[clap(short = 'A', value_parser = PossibleValuesParser::new(WarningKind::NAMES))]
+    pub allow: Vec<String>,
+
+    /// Fail the compilation on any reported warning
+    #[clap(long)]
+    pub deny_warnings: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum RelocModeArg {
+    Default,
+    Pic,
+    Static,
+    DynamicNoPic,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum SanitizerArg {
+    Address,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum CodeModelArg {
+    Default,
+    Small,
+    Kernel,
+    Medium,
+    Large,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+#[derive(clap::Args)]
+pub struct CodegenArgs {
+    /// Check the types of the program before compiling it
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Optimize output
+    #[clap(long)]
+    pub optimize: bool,
+
+    /// Optimize the program together with the std runtime
+    #[clap(long)]
+    pub lto: bool,
+
+    /// Keep every function out of line, even the small ones and the ones decorated with @inline
+    #[clap(long)]
+    pub no_inline: bool,
+
+    /// Command used to link the executable
+    #[clap(long, value_parser, default_value = "gcc")]
+    pub linker: String,
+
+    /// LLVM bitcode file replacing the built-in std library, which it must be compatible with
+    #[clap(long = "std", alias = "std-lib", value_parser)]
+    pub std_lib: Option<PathBuf>,
+
+    /// Do not include the std library, its functions must be provided by a linked library
+    #[clap(long, conflicts_with = "std-lib")]
+    pub no_std: bool,
+
+    /// Keep every std function in the executable, not only the ones the program calls
+    #[clap(long)]
+    pub keep_all_std: bool,
+
+    /// Keep the object files next to the executables, or in the --temp-dir directory
+    #[clap(long)]
+    pub save_temps: bool,
+
+    /// Directory of the intermediate files, the directory of the executable by default
+    #[clap(long, value_parser)]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Print the values still alive when the program exits, with their types
+    #[clap(long)]
+    pub debug_leaks: bool,
+
+    /// Log every std runtime call with its argument types and source location to stderr
+    #[clap(long)]
+    pub trace_runtime: bool,
+
+    /// Name the generated functions with numbered labels instead of their source names
+    #[clap(long)]
+    pub opaque_symbols: bool,
+
+    /// Emit relocation-free constant tables for primitive loaders, implies static relocation
+    #[clap(long)]
+    pub embedded: bool,
+
+    /// Relocation model of the generated code
+    #[clap(long, value_enum, default_value = "default")]
+    pub reloc_mode: RelocModeArg,
+
+    /// Code model of the generated code
+    #[clap(long, value_enum, default_value = "default")]
+    pub code_model: CodeModelArg,
+
+    /// Embed source locations for runtime error messages
+    #[clap(long, value_enum, default_value = "on")]
+    pub source_info: Switch,
+
+    /// Instrument the program to catch memory bugs, linking it with the sanitizer runtime
+    #[clap(long, value_enum)]
+    pub sanitize: Option<SanitizerArg>,
+
+    /// Dynamic library adding compiler passes, can be given more than once
+    #[clap(long = "plugin", value_parser)]
+    pub plugins: Vec<PathBuf>,
+
+    /// How many levels deep statements and expressions may be nested
+    #[clap(long, value_parser, default_value_t = nesting::DEFAULT_MAX_NESTING)]
+    pub max_nesting: usize,
+}
+
+#[derive(clap::Args)]
+pub struct BuildArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    #[clap(flatten)]
+    pub checker: CheckerArgs,
+
+    #[clap(flatten)]
+    pub codegen: CodegenArgs,
+
+    /// Output file, `foo` when building a single file
+    #[clap(short, long, value_parser)]
+    pub output: Option<PathBuf>,
+
+    /// Merge the executables of all Apple targets into a universal binary
+    #[clap(long)]
+    pub universal: bool,
+
+    /// Function called after the top level statements, as the entry of the program
+    #[clap(long, value_parser)]
+    pub entry: Option<String>,
+
+    /// Stop compilation after the given phase
+    #[clap(long, value_parser = PossibleValuesParser::new(pipeline::Phase::NAMES))]
+    pub stop_after: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct RunArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    #[clap(flatten)]
+    pub checker: CheckerArgs,
+
+    #[clap(flatten)]
+    pub codegen: CodegenArgs,
+
+    /// Backend used to run the program, `interp` runs it without compiling
+    #[clap(long, value_parser = PossibleValuesParser::new(pipeline::Backend::NAMES), default_value = "llvm")]
+    pub backend: String,
+
+    /// Function called after the top level statements, as the entry of the program
+    #[clap(long, value_parser)]
+    pub entry: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct TestArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    #[clap(flatten)]
+    pub checker: CheckerArgs,
+
+    #[clap(flatten)]
+    pub codegen: CodegenArgs,
+
+    /// Backend used to run the tests, `interp` runs them without compiling
+    #[clap(long, value_parser = PossibleValuesParser::new(pipeline::Backend::NAMES), default_value = "llvm")]
+    pub backend: String,
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    #[clap(flatten)]
+    pub checker: CheckerArgs,
+
+    #[clap(flatten)]
+    pub codegen: CodegenArgs,
+
+    /// Number of calls before measuring each function
+    #[clap(long, value_parser, default_value_t = 10)]
+    pub warmup: u32,
+
+    /// Number of measured calls of each function
+    #[clap(long, value_parser, default_value_t = 100)]
+    pub iterations: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum EmitArg {
+    Ast,
+    Symbols,
+    Callgraph,
+}
+
+#[derive(clap::Args)]
+pub struct CheckArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    #[clap(flatten)]
+    pub checker: CheckerArgs,
+
+    /// Print the given compiler data instead of checking the types
+    #[clap(long, value_enum)]
+    pub emit: Option<EmitArg>,
+
+    /// Print the emitted symbols or call graph as a Graphviz graph
+    #[clap(long, requires = "emit")]
+    pub graphviz: bool,
+
+    /// Print the emitted data as JSON
+    #[clap(long, requires = "emit", conflicts_with = "graphviz")]
+    pub json: bool,
+}
+
+/// Prints the data requested with `--emit` once the phase producing it is done.
+struct EmitHook<'a> {
+    emit: EmitArg,
+    graphviz: bool,
+    json: bool,
+    source: SourceFile<'a>,
+}
+
+impl<'a> EmitHook<'a> {
+    fn print_json<T: serde::Serialize>(&self, data: &T) -> pipeline::HookAction {
+        match serde_json::to_string_pretty(data) {
+            Ok(json) => {
+                println!("{}", json);
+                pipeline::HookAction::Stop
+            }
+            Err(err) => pipeline::HookAction::Veto(format!("could not serialize: {}", err)),
+        }
+    }
+}
+
+impl<'a> pipeline::Hooks for EmitHook<'a> {
+    fn after_parse(&mut self, program: &ast::Program) -> pipeline::HookAction {
+        match (self.emit, self.json) {
+            (EmitArg::Ast, false) => println!("{:#?}", program),
+            (EmitArg::Ast, true) => return self.print_json(program),
+            _ => return pipeline::HookAction::Continue,
+        }
+
+        pipeline::HookAction::Stop
+    }
+
+    fn after_symbol_table(&mut self, symbol_table: &st::SymbolTable) -> pipeline::HookAction {
+        let output = match (self.emit, self.graphviz) {
+            // the snapshot lists the calls of each function as well
+            _ if self.json => match symbol_table.snapshot() {
+                Ok(snapshot) => return self.print_json(&snapshot),
+                Err(err) => Err(err),
+            },
+            (EmitArg::Symbols, false) => symbol_table.dump(&self.source),
+            (EmitArg::Symbols, true) => symbol_table.dump_dot(&self.source),
+            (EmitArg::Callgraph, false) => symbol_table.dump_call_graph(),
+            (EmitArg::Callgraph, true) => symbol_table.dump_call_graph_dot(),
+            (EmitArg::Ast, _) => unreachable!(),
+        };
+
+        match output {
+            Ok(output) => print!("{}", output),
+            Err(err) => return pipeline::HookAction::Veto(err.message()),
+        }
+
+        pipeline::HookAction::Stop
+    }
+}
+
+#[derive(clap::Args)]
+pub struct FmtArgs {
+    /// Files to format
+    #[clap(value_parser, required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Overwrite the files instead of printing them
+    #[clap(long)]
+    pub write: bool,
+
+    /// List the files that are not formatted and fail if there are any
+    #[clap(long, conflicts_with = "write")]
+    pub check: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum AstFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct AstArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    /// How to print the syntax tree
+    #[clap(long, value_enum, default_value = "text")]
+    pub format: AstFormat,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum DocFormatArg {
+    Markdown,
+    Html,
+}
+
+#[derive(clap::Args)]
+pub struct DocArgs {
+    #[clap(flatten)]
+    pub input: InputArgs,
+
+    /// Format of the documentation
+    #[clap(long, value_enum, default_value = "markdown")]
+    pub format: DocFormatArg,
+
+    /// Output file, the documentation is printed when it is not given
+    #[clap(short, long, value_parser)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum StdCommand {
+    /// List the available std functions
+    Ls,
+    /// Show the documentation of a std function
+    Doc { name: String },
+}
+
+/// Why a command failed. Errors of a compilation have already been printed by
+/// its diagnostics sink when they are returned.
+enum Failure {
+    Error(CompilerError),
+    Reported(CompilerError),
+    Exit(i32),
+}
+
+impl From<CompilerError> for Failure {
+    fn from(err: CompilerError) -> Self {
+        Failure::Error(err)
+    }
+}
+
+/// The program a command works on.
+struct Project {
+    name: String,
+    content: String,
+    manifest: Option<Manifest>,
+}
+
+impl Project {
+    fn load(input: &InputArgs) -> Result<Project, CompilerError> {
+        if let Some(input) = &input.input {
+            let content = fs::read_to_string(input).map_err(|_| {
+                CompilerError::CliError(format!("File not found: {}", input.display()))
+            })?;
+
+            return Ok(Project {
+                name: input.to_string_lossy().to_string(),
+                content,
+                manifest: None,
+            });
+        }
+
+        let manifest = Manifest::load(&input.manifest_path)?;
+
+        Ok(Project {
+            name: manifest.entry_path().to_string_lossy().to_string(),
+            content: manifest.read_source()?,
+            manifest: Some(manifest),
+        })
+    }
+
+    fn compile_options(
+        &self,
+        checker: &CheckerArgs,
+        global: &GlobalArgs,
+    ) -> Result<CompileOptions, CompilerError> {
+        let mut compile_options = CompileOptions::new()
+            .file_name(&self.name)
+            .time_passes(global.time_passes || global.verbose > 1)
+            .stats(global.stats)
+            .deny_warnings(checker.deny_warnings)
+            .diagnostics(Rc::new(RefCell::new(TerminalEmitter::with_source(
+                &self.name,
+                &self.content,
+            ))));
+
+        for (warnings, level) in [(&checker.warn, Level::Warn), (&checker.allow, Level::Allow)] {
+            for warning in warnings {
+                let kind = warning.parse().map_err(CompilerError::CliError)?;
+
+                compile_options = compile_options.warning_level(kind, level);
+            }
+        }
+
+        Ok(compile_options)
+    }
+
+    fn options(
+        &self,
+        compile_options: CompileOptions,
+        backend: pipeline::Backend,
+        global: &GlobalArgs,
+    ) -> Result<pipeline::Options, CompilerError> {
+        let targets = global
+            .target
+            .iter()
+            .map(|target| {
+                target_lexicon::Triple::from_str(target).map_err(|err| {
+                    CompilerError::CliError(format!("Invalid target `{}`: {}", target, err))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut options = match &self.manifest {
+            Some(manifest) => manifest.options(compile_options, backend)?,
+            None => pipeline::Options {
+                compile: compile_options,
+                backend,
+                targets: vec![target_lexicon::Triple::host()],
+                universal: false,
+                out_file: PathBuf::from("foo"),
+            },
+        };
+
+        if !targets.is_empty() {
+            options.targets = targets;
+        }
+
+        Ok(options)
+    }
+}
+
+impl CodegenArgs {
+    fn apply(&self, compile_options: CompileOptions) -> Result<CompileOptions, CompilerError> {
+        let reloc_mode = match self.reloc_mode {
+            RelocModeArg::Default => RelocMode::Default,
+            RelocModeArg::Pic => RelocMode::PIC,
+            RelocModeArg::Static => RelocMode::Static,
+            RelocModeArg::DynamicNoPic => RelocMode::DynamicNoPic,
+        };
+
+        let code_model = match self.code_model {
+            CodeModelArg::Default => CodeModel::Default,
+            CodeModelArg::Small => CodeModel::Small,
+            CodeModelArg::Kernel => CodeModel::Kernel,
+            CodeModelArg::Medium => CodeModel::Medium,
+            CodeModelArg::Large => CodeModel::Large,
+        };
+
+        let sanitizer = self.sanitize.map(|sanitizer| match sanitizer {
+            SanitizerArg::Address => Sanitizer::Address,
+        });
+
+        let mut compile_options = compile_options
+            .strict(self.strict)
+            .source_info(self.source_info == Switch::On)
+            .lto(self.lto)
+            .inline_functions(!self.no_inline)
+            .embedded(self.embedded)
+            .reloc_mode(reloc_mode)
+            .code_model(code_model)
+            .linker(&self.linker)
+            .no_std(self.no_std)
+            .keep_all_std(self.keep_all_std)
+            .save_temps(self.save_temps)
+            .debug_leaks(self.debug_leaks)
+            .trace_runtime(self.trace_runtime)
+            .opaque_symbols(self.opaque_symbols)
+            .max_nesting(self.max_nesting)
+            .sanitizer(sanitizer);
+
+        if self.optimize {
+            compile_options = compile_options.opt_level(OptimizationLevel::Aggressive);
+        }
+
+        if let Some(temp_dir) = &self.temp_dir {
+            compile_options = compile_options.temp_dir(temp_dir);
+        }
+
+        for plugin in &self.plugins {
+            compile_options = compile_options.plugin(plugin);
+        }
+
+        if let Some(std_lib) = &self.std_lib {
+            let std_library = fs::read(std_lib).map_err(|_| {
+                CompilerError::CliError(format!("File not found: {}", std_lib.display()))
+            })?;
+
+            compile_options = compile_options.std_library(std_library);
+        }
+
+        Ok(compile_options)
+    }
+}
+
+impl GlobalArgs {
+    /// Prints a cargo-like status line when running verbosely.
+    fn status(&self, action: &str, subject: &str) {
+        if self.verbose > 0 {
+            eprintln!("{:>12} {}", action.green().bold(), subject);
+        }
+    }
+}
+
+fn compile(
+    project: &Project,
+    options: &pipeline::Options,
+    hooks: &mut dyn pipeline::Hooks,
+) -> Result<(), Failure> {
+    pipeline::compile(&project.content, options, hooks)
+        .map(|_| ())
+        .map_err(Failure::Reported)
+}
+
+fn build_command(args: &BuildArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+
+    let stop_after = args
+        .stop_after
+        .as_ref()
+        .map(|phase| phase.parse::<pipeline::Phase>())
+        .transpose()
+        .map_err(CompilerError::CliError)?;
+
+    let compile_options = project.compile_options(&args.checker, global)?;
+    let mut options = project.options(compile_options, pipeline::Backend::Llvm, global)?;
+    options.compile = args.codegen.apply(options.compile)?;
+    options.universal = args.universal;
+
+    if let Some(entry) = &args.entry {
+        options.compile = options.compile.entry(entry);
+    }
+
+    if let Some(output) = &args.output {
+        options.out_file = output.clone();
+    }
+
+    global.status("Compiling", &project.name);
+    compile(&project, &options, &mut pipeline::StopAfter(stop_after))?;
+    global.status("Finished", &options.out_file.to_string_lossy());
 
     Ok(())
 }
 
-pub fn run() {
-    let app = App::new("mini compiler")
-        .setting(clap::AppSettings::ArgRequiredElseHelp)
-        .version("0.1.0")
-        .author("OZAN AKIN")
-        .about("Mini language compiler")
-        .arg(
-            Arg::with_name("input")
-                .help("Sets the input file to use")
-                .takes_value(true)
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::with_name("output")
-                .long("output")
-                .short('o')
-                .takes_value(true)
-                .default_value("foo")
-                .help("Output file"),
+/// Temporary directory for executables run right after compiling them, which
+/// must target the host.
+fn host_out_dir(options: &pipeline::Options) -> Result<tempfile::TempDir, Failure> {
+    if options.targets.len() != 1 || options.targets[0] != target_lexicon::Triple::host() {
+        return Err(CompilerError::CliError(
+            "Only programs compiled for the host can be run".to_string(),
         )
-        .arg(
-            Arg::with_name("optimize")
-                .long("optimize")
-                .help("Optimize output"),
+        .into());
+    }
+
+    tempfile::tempdir().map_err(|err| {
+        CompilerError::CliError(format!("Could not create a temporary directory: {}", err)).into()
+    })
+}
+
+fn run_command(args: &RunArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+    let backend = args.backend.parse().map_err(CompilerError::CliError)?;
+
+    let compile_options = project.compile_options(&args.checker, global)?;
+    let mut options = project.options(compile_options, backend, global)?;
+    options.compile = args.codegen.apply(options.compile)?;
+
+    if let Some(entry) = &args.entry {
+        options.compile = options.compile.entry(entry);
+    }
+
+    if backend == pipeline::Backend::Interp {
+        global.status("Running", &project.name);
+        let status = pipeline::compile(&project.content, &options, &mut pipeline::StopAfter(None))
+            .map_err(Failure::Reported)?;
+
+        return match status {
+            0 => Ok(()),
+            code => Err(Failure::Exit(code)),
+        };
+    }
+
+    let out_dir = host_out_dir(&options)?;
+    options.out_file = out_dir.path().join("main");
+
+    global.status("Compiling", &project.name);
+    compile(&project, &options, &mut pipeline::StopAfter(None))?;
+
+    global.status("Running", &project.name);
+    let status = std::process::Command::new(&options.out_file)
+        .status()
+        .map_err(|err| CompilerError::CliError(format!("Could not run the program: {}", err)))?;
+
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(Failure::Exit(code)),
+        None => Err(Failure::Exit(EXIT_RUNTIME_ERROR)),
+    }
+}
+
+/// Builds the program as a test harness running every test, each in its own
+/// process with the LLVM backend so that a failing assertion only fails its test.
+fn test_command(args: &TestArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+    let backend = args.backend.parse().map_err(CompilerError::CliError)?;
+
+    let compile_options = project.compile_options(&args.checker, global)?;
+    let mut options = project.options(compile_options, backend, global)?;
+    options.compile = args.codegen.apply(options.compile)?.test_harness(true);
+
+    if backend == pipeline::Backend::Interp {
+        global.status("Testing", &project.name);
+        let status = pipeline::compile(&project.content, &options, &mut pipeline::StopAfter(None))
+            .map_err(Failure::Reported)?;
+
+        return match status {
+            0 => Ok(()),
+            code => Err(Failure::Exit(code)),
+        };
+    }
+
+    let out_dir = host_out_dir(&options)?;
+    options.out_file = out_dir.path().join("tests");
+
+    global.status("Compiling", &project.name);
+    compile(&project, &options, &mut pipeline::StopAfter(None))?;
+
+    global.status("Testing", &project.name);
+    let status = std::process::Command::new(&options.out_file)
+        .status()
+        .map_err(|err| CompilerError::CliError(format!("Could not run the tests: {}", err)))?;
+
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(Failure::Exit(code)),
+        None => Err(Failure::Exit(EXIT_RUNTIME_ERROR)),
+    }
+}
+
+fn bench_command(args: &BenchArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+
+    let compile_options = project.compile_options(&args.checker, global)?;
+    let options = project.options(compile_options, pipeline::Backend::Llvm, global)?;
+    let compile_options = args.codegen.apply(options.compile)?;
+
+    global.status("Compiling", &project.name);
+    let results = bench::run(
+        &project.content,
+        &compile_options,
+        args.warmup,
+        args.iterations,
+    )
+    .map_err(Failure::Reported)?;
+
+    for (name, statistics) in results {
+        println!(
+            "bench {} ... {:?} ± {:?} (min {:?}, median {:?}, max {:?}, {} iterations)",
+            name.bold(),
+            statistics.mean,
+            statistics.std_dev,
+            statistics.min,
+            statistics.median,
+            statistics.max,
+            statistics.iterations
         );
+    }
+
+    Ok(())
+}
+
+fn check_command(args: &CheckArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+
+    let compile_options = project.compile_options(&args.checker, global)?.strict(true);
+    let options = project.options(compile_options, pipeline::Backend::Llvm, global)?;
+
+    if let Some(emit) = args.emit {
+        if emit == EmitArg::Ast && args.graphviz {
+            return Err(CompilerError::CliError(
+                "The syntax tree cannot be printed as a Graphviz graph".to_string(),
+            )
+            .into());
+        }
+
+        let mut hook = EmitHook {
+            emit,
+            graphviz: args.graphviz,
+            json: args.json,
+            source: SourceFile::new(&project.name, &project.content),
+        };
+
+        return compile(&project, &options, &mut hook);
+    }
+
+    global.status("Checking", &project.name);
+    compile(
+        &project,
+        &options,
+        &mut pipeline::StopAfter(Some(pipeline::Phase::Check)),
+    )
+}
+
+fn fmt_command(args: &FmtArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let mut unformatted = false;
+
+    for file in args.files.iter() {
+        let content = fs::read_to_string(file)
+            .map_err(|_| CompilerError::CliError(format!("File not found: {}", file.display())))?;
+
+        let name = file.to_string_lossy();
+
+        let formatted = match formatter::format(&content) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                TerminalEmitter::with_source(&name, &content).emit(&err);
+                return Err(Failure::Reported(err));
+            }
+        };
+
+        if args.check {
+            if formatted != content {
+                println!("{}", name);
+                unformatted = true;
+            }
+        } else if args.write {
+            if formatted != content {
+                global.status("Formatting", &name);
+                fs::write(file, formatted).map_err(|err| {
+                    CompilerError::CliError(format!("Could not write {}: {}", name, err))
+                })?;
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+
+    if unformatted {
+        return Err(Failure::Exit(EXIT_COMPILE_ERROR));
+    }
+
+    Ok(())
+}
+
+fn ast_command(args: &AstArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
+
+    let program = match pipeline::parse(&project.content) {
+        Ok(program) => program,
+        Err(err) => {
+            TerminalEmitter::with_source(&project.name, &project.content).emit(&err);
+            return Err(Failure::Reported(err));
+        }
+    };
+
+    match args.format {
+        AstFormat::Text => println!("{:#?}", program),
+        AstFormat::Json => {
+            let json = serde_json::to_string_pretty(&program).map_err(|err| {
+                CompilerError::CliError(format!("Could not serialize the syntax tree: {}", err))
+            })?;
+
+            println!("{}", json);
+        }
+    }
+
+    Ok(())
+}
+
+fn doc_command(args: &DocArgs, global: &GlobalArgs) -> Result<(), Failure> {
+    let project = Project::load(&args.input)?;
 
-    let matches = app.get_matches();
-    if let Err(err) = compile(&matches) {
-        println!("{}", err);
-        std::process::exit(1);
+    let format = match args.format {
+        DocFormatArg::Markdown => doc::DocFormat::Markdown,
+        DocFormatArg::Html => doc::DocFormat::Html,
+    };
+
+    let documentation = match doc::generate(&project.name, &project.content, format) {
+        Ok(documentation) => documentation,
+        Err(err) => {
+            TerminalEmitter::with_source(&project.name, &project.content).emit(&err);
+            return Err(Failure::Reported(err));
+        }
+    };
+
+    match &args.output {
+        Some(output) => {
+            global.status("Documenting", &project.name);
+            fs::write(output, documentation).map_err(|err| {
+                CompilerError::CliError(format!("Could not write {}: {}", output.display(), err))
+            })?;
+        }
+        None => print!("{}", documentation),
     }
+
+    Ok(())
+}
+
+fn std_command(command: &StdCommand) -> Result<(), Failure> {
+    match command {
+        StdCommand::Ls => {
+            for builtin in builtins::BUILTINS {
+                println!("{}", builtin.declaration);
+            }
+        }
+        StdCommand::Doc { name } => {
+            let builtin = builtins::find(name).ok_or_else(|| {
+                CompilerError::CliError(format!("No std function named `{}`", name))
+            })?;
+
+            println!("{}\n\n{}", builtin.declaration, builtin.doc);
+        }
+    }
+
+    Ok(())
+}
+
+fn explain_command(code: Option<&str>) -> Result<(), Failure> {
+    let code = match code {
+        Some(code) => code,
+        None => {
+            for error_code in explain::ERROR_CODES {
+                println!("{}  {}", error_code.code, error_code.summary);
+            }
+
+            return Ok(());
+        }
+    };
+
+    let error_code = explain::find(code)
+        .ok_or_else(|| CompilerError::CliError(format!("No error code named `{}`", code)))?;
+
+    println!(
+        "{} {}: {}\n\n{}",
+        error_code.code.bold(),
+        error_code.name,
+        error_code.summary,
+        error_code.explanation
+    );
+
+    Ok(())
+}
+
+pub fn run() {
+    let args = Args::parse();
+
+    colored::control::set_override(args.global.color.is_enabled());
+
+    // the remaining panics are compiler bugs as well, reported before the panic message
+    let panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        eprintln!(
+            "{}: internal compiler error, this is a bug in mini",
+            "error".red().bold()
+        );
+        panic_hook(info);
+    }));
+
+    let result = match &args.command {
+        Command::Build(build_args) => build_command(build_args, &args.global),
+        Command::Run(run_args) => run_command(run_args, &args.global),
+        Command::Check(check_args) => check_command(check_args, &args.global),
+        Command::Test(test_args) => test_command(test_args, &args.global),
+        Command::Bench(bench_args) => bench_command(bench_args, &args.global),
+        Command::Fmt(fmt_args) => fmt_command(fmt_args, &args.global),
+        Command::Ast(ast_args) => ast_command(ast_args),
+        Command::Doc(doc_args) => doc_command(doc_args, &args.global),
+        Command::Repl => Repl::new()
+            .run()
+            .map_err(|err| CompilerError::CliError(err.to_string()).into()),
+        Command::Lsp => lsp::run().map_err(Failure::from),
+        Command::Std(command) => std_command(command),
+        Command::Explain { code } => explain_command(code.as_deref()),
+    };
+
+    let code = match result {
+        Ok(()) => return,
+        Err(Failure::Error(err)) => {
+            TerminalEmitter::new().emit(&err);
+            exit_code(&err)
+        }
+        Err(Failure::Reported(err)) => exit_code(&err),
+        Err(Failure::Exit(code)) => code,
+    };
+
+    std::process::exit(code);
 }
@@ -0,0 +1,188 @@
+use std::rc::Rc;
+
+use crate::ast;
+use crate::interp::Value;
+use crate::st;
+
+/// The value of the expression when it only combines constants and `const`
+/// variables defined before it, computed with the operators of the interpreter.
+pub fn evaluate<'input>(
+    symbol_table: &st::SymbolTable<'input>,
+    expression: &'input ast::Expression<'input>,
+) -> Option<Value> {
+    match expression {
+        ast::Expression::ConstantExpression { value, .. } => Some(Value::from(value)),
+
+        ast::Expression::VariableExpression {
+            location,
+            identifier: identifier @ ast::VariableIdentifier::Name { .. },
+        } => {
+            let variable_id = symbol_table.identifier_ref(identifier);
+            let initializer = symbol_table.const_initializer(variable_id)?;
+
+            // before its definition runs the variable is still undefined
+            if initializer.get_location()?.1 > location.0 {
+                return None;
+            }
+
+            evaluate(symbol_table, initializer)
+        }
+
+        ast::Expression::UnaryExpression {
+            operator,
+            expression,
+            ..
+        } => unary_operation(operator, evaluate(symbol_table, expression)?).ok(),
+
+        ast::Expression::BinaryExpression {
+            operator,
+            left,
+            right,
+            ..
+        } => {
+            let left = evaluate(symbol_table, left)?;
+            let right = evaluate(symbol_table, right)?;
+
+            binary_operation(operator, left, right).ok()
+        }
+
+        _ => None,
+    }
+}
+
+pub fn unary_operation(operator: &ast::UnaryOperator, value: Value) -> Result<Value, String> {
+    match (operator, value) {
+        (ast::UnaryOperator::Positive, v @ (Value::Integer(_) | Value::Float(_))) => Ok(v),
+        (ast::UnaryOperator::Positive, Value::Boolean(b)) => Ok(Value::Integer(b as i64)),
+        (ast::UnaryOperator::Positive, v) => Err(format!(
+            "unary `+` expects a number or boolean, got {}",
+            v.get_type_name()
+        )),
+        (ast::UnaryOperator::Negative, Value::Integer(i)) => Ok(i
+            .checked_neg()
+            .map_or(Value::Float(-(i as f64)), Value::Integer)),
+        (ast::UnaryOperator::Negative, Value::Float(f)) => Ok(Value::Float(-f)),
+        (ast::UnaryOperator::Negative, v) => Err(format!(
+            "unary `-` expects a number, got {}",
+            v.get_type_name()
+        )),
+        (ast::UnaryOperator::Not, v) => Ok(Value::Boolean(!v.is_truthy())),
+    }
+}
+
+pub fn compare(left: &Value, right: &Value) -> Result<i8, String> {
+    let ordering = match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => Some(l.cmp(r)),
+        (Value::String(l), Value::String(r)) | (Value::Symbol(l), Value::Symbol(r)) => {
+            Some(l.cmp(r))
+        }
+        (Value::Boolean(l), Value::Boolean(r)) => Some(l.cmp(r)),
+        (Value::Range(ls, le), Value::Range(rs, re)) => Some((ls, le).cmp(&(rs, re))),
+        (l, r) => match (l.as_float(), r.as_float()) {
+            (Some(l), Some(r)) => l.partial_cmp(&r),
+            _ => {
+                return Err(format!(
+                    "cannot compare values of types {} and {}",
+                    left.get_type_name(),
+                    right.get_type_name()
+                ))
+            }
+        },
+    };
+
+    Ok(ordering.map_or(0, |ordering| ordering as i8))
+}
+
+/// `==`, which takes undefined and null as equal to each other and to nothing else.
+fn loose_equals(left: &Value, right: &Value) -> Result<bool, String> {
+    match (left, right) {
+        (Value::Undefined | Value::Null, Value::Undefined | Value::Null) => Ok(true),
+        (Value::Undefined | Value::Null, _) | (_, Value::Undefined | Value::Null) => Ok(false),
+        _ => Ok(compare(left, right)? == 0),
+    }
+}
+
+/// Applies the integer operation to two integers and the float one to other
+/// numbers, an overflowing integer result becoming a float like in the runtime.
+fn arithmetic(
+    verb: &str,
+    left: Value,
+    right: Value,
+    int_operation: fn(i64, i64) -> Option<i64>,
+    float_operation: fn(f64, f64) -> f64,
+) -> Result<Value, String> {
+    if let (Value::Integer(l), Value::Integer(r)) = (&left, &right) {
+        if let Some(result) = int_operation(*l, *r) {
+            return Ok(Value::Integer(result));
+        }
+    }
+
+    match (left.as_float(), right.as_float()) {
+        (Some(l), Some(r)) => Ok(Value::Float(float_operation(l, r))),
+        _ => Err(format!(
+            "cannot {} values of types {} and {}",
+            verb,
+            left.get_type_name(),
+            right.get_type_name()
+        )),
+    }
+}
+
+pub fn binary_operation(
+    operator: &ast::BinaryOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, String> {
+    match operator {
+        ast::BinaryOperator::Addition => {
+            match (&left, &right) {
+                (Value::String(_), _) | (_, Value::String(_)) => Ok(Value::String(Rc::from(
+                    format!("{}{}", left.to_text(), right.to_text()),
+                ))),
+                _ => arithmetic("add", left, right, i64::checked_add, |l, r| l + r),
+            }
+        }
+        ast::BinaryOperator::Subtraction => {
+            arithmetic("subtract", left, right, i64::checked_sub, |l, r| l - r)
+        }
+        ast::BinaryOperator::Multiplication => {
+            arithmetic("multiply", left, right, i64::checked_mul, |l, r| l * r)
+        }
+        ast::BinaryOperator::Division => {
+            arithmetic("divide", left, right, |_, _| None, |l, r| l / r)
+        }
+        ast::BinaryOperator::Mod => match (&left, &right) {
+            (Value::Integer(_), Value::Integer(0)) => Err("division by zero".to_string()),
+            (Value::Integer(l), Value::Integer(r)) => l
+                .checked_rem(*r)
+                .map(Value::Integer)
+                .ok_or_else(|| "integer overflow in modulo".to_string()),
+            _ => Err(format!(
+                "cannot take modulo of values of types {} and {}",
+                left.get_type_name(),
+                right.get_type_name()
+            )),
+        },
+        ast::BinaryOperator::Equal => Ok(Value::Boolean(loose_equals(&left, &right)?)),
+        ast::BinaryOperator::NotEqual => Ok(Value::Boolean(!loose_equals(&left, &right)?)),
+        ast::BinaryOperator::StrictEqual | ast::BinaryOperator::StrictNotEqual => {
+            let is_equal = match (&left, &right) {
+                (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+                (Value::Undefined | Value::Null, _) | (_, Value::Undefined | Value::Null) => false,
+                _ if left.get_type_name() != right.get_type_name() => false,
+                _ => compare(&left, &right)? == 0,
+            };
+
+            match operator {
+                ast::BinaryOperator::StrictEqual => Ok(Value::Boolean(is_equal)),
+                _ => Ok(Value::Boolean(!is_equal)),
+            }
+        }
+        ast::BinaryOperator::Less => Ok(Value::Boolean(compare(&left, &right)? < 0)),
+        ast::BinaryOperator::LessEqual => Ok(Value::Boolean(compare(&left, &right)? <= 0)),
+        ast::BinaryOperator::Greater => Ok(Value::Boolean(compare(&left, &right)? > 0)),
+        ast::BinaryOperator::GreaterEqual => Ok(Value::Boolean(compare(&left, &right)? >= 0)),
+        ast::BinaryOperator::And => Ok(Value::Boolean(left.is_truthy() && right.is_truthy())),
+        ast::BinaryOperator::Or => Ok(Value::Boolean(left.is_truthy() || right.is_truthy())),
+    }
+}
@@ -0,0 +1,103 @@
+use crate::ast;
+use crate::error::CompilerError;
+use crate::formatter;
+use crate::pipeline;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+/// A documented top level definition: its signature and the paragraphs of its
+/// `///` comments.
+struct Item {
+    signature: String,
+    paragraphs: Vec<String>,
+}
+
+fn paragraphs(docs: &[&str]) -> Vec<String> {
+    docs.split(|line| line.is_empty())
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| lines.join("\n"))
+        .collect()
+}
+
+fn items(program: &ast::Program) -> Vec<Item> {
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            ast::Statement::FunctionStatement {
+                definition,
+                parameters,
+                ..
+            } if !definition.is_external => Some(Item {
+                signature: formatter::function_header(definition, parameters),
+                paragraphs: paragraphs(&definition.docs),
+            }),
+            ast::Statement::DefinitionStatement { definition, .. } => Some(Item {
+                signature: formatter::definition_header(definition),
+                paragraphs: paragraphs(&definition.docs),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn markdown(title: &str, items: &[Item]) -> String {
+    let mut output = format!("# {}\n", title);
+
+    for item in items {
+        output.push_str(&format!("\n## `{}`\n", item.signature));
+
+        for paragraph in item.paragraphs.iter() {
+            output.push_str(&format!("\n{}\n", paragraph));
+        }
+    }
+
+    output
+}
+
+fn html(title: &str, items: &[Item]) -> String {
+    let title = escape_html(title);
+
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, title
+    );
+
+    for item in items {
+        output.push_str(&format!(
+            "<h2><code>{}</code></h2>\n",
+            escape_html(&item.signature)
+        ));
+
+        for paragraph in item.paragraphs.iter() {
+            output.push_str(&format!("<p>{}</p>\n", escape_html(paragraph)));
+        }
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Documents the functions and the variables defined at the top level of the
+/// program, in the order they are defined. Declared functions are left out as
+/// they belong to the std runtime.
+pub fn generate(title: &str, content: &str, format: DocFormat) -> Result<String, CompilerError> {
+    let program = pipeline::parse(content)?;
+    let items = items(&program);
+
+    Ok(match format {
+        DocFormat::Markdown => markdown(title, &items),
+        DocFormat::Html => html(title, &items),
+    })
+}
@@ -0,0 +1,49 @@
+use self_cell::self_cell;
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::pipeline;
+use crate::st;
+
+type Program<'input> = ast::Program<'input>;
+
+self_cell!(
+    struct ParsedContent {
+        owner: String,
+
+        #[covariant]
+        dependent: Program,
+    }
+);
+
+/// A program parsed like `pipeline::parse` does, together with the content it
+/// borrows. Unlike `ast::Program`, it can be kept around once the buffer it was
+/// parsed from is gone, like the documents of the language server or the
+/// history of the REPL.
+pub struct Document(ParsedContent);
+
+impl Document {
+    pub fn parse(content: String) -> Result<Document, CompilerError> {
+        ParsedContent::try_new(content, |content| pipeline::parse(content)).map(Document)
+    }
+
+    pub fn content(&self) -> &str {
+        self.0.borrow_owner()
+    }
+
+    pub fn program(&self) -> &ast::Program<'_> {
+        self.0.borrow_dependent()
+    }
+
+    /// Builds the symbol table of the program, which borrows the document, and
+    /// runs `f` with it.
+    pub fn with_symbol_table<T>(
+        &self,
+        f: impl FnOnce(&st::SymbolTable<'_>) -> T,
+    ) -> Result<T, CompilerError> {
+        let main_def = pipeline::main_definition(self.content());
+        let symbol_table = st::SymbolTable::from(&main_def, self.program())?;
+
+        Ok(f(&symbol_table))
+    }
+}
@@ -1,135 +1,437 @@
 use colored::Colorize;
 use inkwell::builder::BuilderError;
-use lalrpop_util::{lexer::Token, ParseError};
+use lalrpop_util::ParseError;
 use std::fmt;
 
 use crate::ast;
+use crate::lexer::LexicalError;
+use crate::pipeline::Phase;
+use crate::source::SourceFile;
+use crate::warning::Warning;
 
 #[derive(Debug)]
-pub enum CompilerError<'input> {
-    CliError(&'input str),
+pub enum CompilerError {
+    CliError(String),
     BuilderError(BuilderError),
-    ParserError(ParseError<usize, Token<'input>, &'static str>),
+    /// The error of the parser, its message and a hint to fix it, see `syntax::explain`.
+    ParserError(
+        ParseError<usize, String, LexicalError>,
+        String,
+        Option<String>,
+    ),
     CodeGenError(String),
-    VariableAlreadyDefined(&'input str),
-    VariableNotDefined(&'input str),
-    InvalidFunctionCall(&'input str),
-    InvalidNumberOfArguments(&'input str, usize, usize),
-    VariableTypeCannotBeInfered(&'input str),
-    InvalidArgumentType(&'input str, ast::VariableKind, ast::VariableKind),
-    InvalidAssignment(&'input str, ast::VariableKind, ast::VariableKind),
-    CannotAssignConstVariable(&'input str),
-    CannotReturnFromGlobalScope,
+    VariableAlreadyDefined((usize, usize), String),
+    VariableNotDefined((usize, usize), String),
+    InvalidFunctionCall((usize, usize), String),
+    InvalidNumberOfArguments((usize, usize), String, (usize, Option<usize>), usize),
+    VariableTypeCannotBeInfered((usize, usize), String),
+    InvalidArgumentType((usize, usize), String, ast::VariableKind, ast::VariableKind),
+    InvalidAssignment((usize, usize), String, ast::VariableKind, ast::VariableKind),
+    CannotAssignConstVariable((usize, usize), String),
+    CannotReturnFromGlobalScope((usize, usize)),
+    InvalidUnaryOperand((usize, usize), &'static str, ast::VariableKind),
+    InvalidBinaryOperands(
+        (usize, usize),
+        &'static str,
+        ast::VariableKind,
+        ast::VariableKind,
+    ),
+    InvalidReturnType((usize, usize), String, ast::VariableKind, ast::VariableKind),
+    UnknownProperty((usize, usize), String, ast::VariableKind),
+    CannotAssignReadonlyProperty((usize, usize), String, ast::VariableKind),
+    InvalidFormatString((usize, usize), String),
+    InvalidNumberOfFormatArguments((usize, usize), usize, usize),
+    CannotEmbedFile((usize, usize), String),
+    InvalidInlineIr((usize, usize), String),
+    InvalidCompoundAssignment((usize, usize)),
+    InvalidMacro((usize, usize), String),
+    DuplicateParameter((usize, usize), String, String),
+    ParameterShadowsFunction((usize, usize), String),
+    InvalidRestParameter((usize, usize), String),
+    TooDeeplyNested(Option<(usize, usize)>, usize),
+    CompilationVetoed(Phase, String),
+    PassFailed(String, String),
+    RuntimeError(Option<(usize, usize)>, String),
+    Internal(Option<(usize, usize)>, String),
+    DeniedWarning(Warning),
 }
 
-impl<'input> From<BuilderError> for CompilerError<'input> {
-    fn from(err: BuilderError) -> Self {
-        CompilerError::BuilderError(err)
+impl CompilerError {
+    /// Byte range of the source the error refers to, if it refers to any.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            CompilerError::ParserError(err, ..) => match err {
+                ParseError::InvalidToken { location } => Some((*location, *location)),
+                ParseError::UnrecognizedEof { location, .. } => Some((*location, *location)),
+                ParseError::UnrecognizedToken {
+                    token: (start, _, end),
+                    ..
+                } => Some((*start, *end)),
+                ParseError::ExtraToken {
+                    token: (start, _, end),
+                } => Some((*start, *end)),
+                ParseError::User { error } => Some((error.location, error.location)),
+            },
+            CompilerError::VariableAlreadyDefined(location, ..)
+            | CompilerError::VariableNotDefined(location, ..)
+            | CompilerError::InvalidFunctionCall(location, ..)
+            | CompilerError::InvalidNumberOfArguments(location, ..)
+            | CompilerError::VariableTypeCannotBeInfered(location, ..)
+            | CompilerError::InvalidArgumentType(location, ..)
+            | CompilerError::InvalidAssignment(location, ..)
+            | CompilerError::CannotAssignConstVariable(location, ..)
+            | CompilerError::CannotReturnFromGlobalScope(location)
+            | CompilerError::InvalidUnaryOperand(location, ..)
+            | CompilerError::InvalidBinaryOperands(location, ..)
+            | CompilerError::InvalidReturnType(location, ..)
+            | CompilerError::UnknownProperty(location, ..)
+            | CompilerError::CannotAssignReadonlyProperty(location, ..)
+            | CompilerError::InvalidFormatString(location, ..)
+            | CompilerError::InvalidNumberOfFormatArguments(location, ..)
+            | CompilerError::CannotEmbedFile(location, ..)
+            | CompilerError::InvalidInlineIr(location, ..)
+            | CompilerError::InvalidCompoundAssignment(location)
+            | CompilerError::InvalidMacro(location, ..)
+            | CompilerError::DuplicateParameter(location, ..)
+            | CompilerError::ParameterShadowsFunction(location, ..)
+            | CompilerError::InvalidRestParameter(location, ..) => Some(*location),
+            CompilerError::RuntimeError(location, _)
+            | CompilerError::TooDeeplyNested(location, _)
+            | CompilerError::Internal(location, _) => *location,
+            CompilerError::DeniedWarning(warning) => Some(warning.location()),
+            _ => None,
+        }
     }
-}
 
-impl<'input> fmt::Display for CompilerError<'input> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// The stable code of the error, which `mini explain` describes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompilerError::VariableNotDefined(..) => "E0001",
+            CompilerError::VariableAlreadyDefined(..) => "E0002",
+            CompilerError::InvalidFunctionCall(..) => "E0003",
+            CompilerError::InvalidNumberOfArguments(..) => "E0004",
+            CompilerError::VariableTypeCannotBeInfered(..) => "E0005",
+            CompilerError::InvalidArgumentType(..) => "E0006",
+            CompilerError::InvalidAssignment(..) => "E0007",
+            CompilerError::CannotAssignConstVariable(..) => "E0008",
+            CompilerError::CannotReturnFromGlobalScope(..) => "E0009",
+            CompilerError::InvalidUnaryOperand(..) => "E0010",
+            CompilerError::InvalidBinaryOperands(..) => "E0011",
+            CompilerError::InvalidReturnType(..) => "E0012",
+            CompilerError::UnknownProperty(..) => "E0014",
+            CompilerError::CannotAssignReadonlyProperty(..) => "E0015",
+            CompilerError::InvalidFormatString(..) => "E0016",
+            CompilerError::InvalidNumberOfFormatArguments(..) => "E0017",
+            CompilerError::CannotEmbedFile(..) => "E0018",
+            CompilerError::InvalidInlineIr(..) => "E0019",
+            CompilerError::InvalidCompoundAssignment(..) => "E0020",
+            CompilerError::InvalidMacro(..) => "E0021",
+            CompilerError::TooDeeplyNested(..) => "E0022",
+            CompilerError::ParserError(..) => "E0023",
+            CompilerError::DeniedWarning(..) => "E0024",
+            CompilerError::RuntimeError(..) => "E0025",
+            CompilerError::CompilationVetoed(..) => "E0026",
+            CompilerError::PassFailed(..) => "E0027",
+            CompilerError::CliError(..) => "E0028",
+            CompilerError::CodeGenError(..) => "E0029",
+            CompilerError::BuilderError(..) => "E0030",
+            CompilerError::Internal(..) => "E0031",
+            CompilerError::DuplicateParameter(..) => "E0032",
+            CompilerError::ParameterShadowsFunction(..) => "E0033",
+            CompilerError::InvalidRestParameter(..) => "E0034",
+        }
+    }
+
+    /// The error message without the `error:` prefix and terminal colors.
+    pub fn message(&self) -> String {
+        self.describe(|s| s.to_string())
+    }
+
+    /// A hint on how to fix the error, shown below its source line.
+    pub fn help(&self) -> Option<&str> {
         match self {
-            CompilerError::ParserError(err) => {
-                let mut lines = format!("{}", &err)
-                    .split_whitespace()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-
-                for index in 0..lines.len() {
-                    if index == 0 {
-                        lines[index] = format!("{} {}", "error:".red(), lines[index]);
-                    } else {
-                        lines[index] = format!("{} {}", " ".repeat(6), lines[index]);
-                    }
-                }
-
-                let s = lines.join("\n");
-
-                writeln!(f, "{}", s)
-            }
-            CompilerError::BuilderError(err) => write!(f, "{} {}", "error:".red(), err),
-            CompilerError::CliError(err) => write!(f, "{} {}", "error:".red(), err),
-            CompilerError::CodeGenError(err) => write!(f, "{} {}", "error:".red(), err),
-            CompilerError::VariableAlreadyDefined(v) => {
-                write!(
-                    f,
-                    "{} variable `{}` already defined",
-                    "error:".red(),
-                    v.yellow()
+            CompilerError::ParserError(_, _, help) => help.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The `error[E0001]:` prefix of the message.
+    pub(crate) fn header(&self) -> String {
+        format!("error[{}]:", self.code()).red().to_string()
+    }
+
+    /// The error message with highlighted names, without the `error:` prefix.
+    pub(crate) fn highlighted_message(&self) -> String {
+        self.describe(|s| s.yellow().to_string())
+    }
+
+    fn describe(&self, highlight: fn(&str) -> String) -> String {
+        match self {
+            CompilerError::ParserError(_, message, _) => message.to_string(),
+            CompilerError::BuilderError(err) => err.to_string(),
+            CompilerError::CliError(err) => err.to_string(),
+            CompilerError::CodeGenError(err) => err.to_string(),
+            CompilerError::RuntimeError(_, err) => format!("runtime error: {}", err),
+            CompilerError::Internal(_, err) => {
+                format!("internal compiler error: {}, this is a bug in mini", err)
+            }
+            CompilerError::VariableAlreadyDefined(_, v) => {
+                format!("variable `{}` already defined", highlight(v))
+            }
+            CompilerError::VariableNotDefined(_, v) => {
+                format!("variable `{}` not defined", highlight(v))
+            }
+            CompilerError::InvalidFunctionCall(_, v) => {
+                format!("function call on variable `{}` invalid", highlight(v))
+            }
+            CompilerError::InvalidNumberOfArguments(_, v, (min, max), got) => {
+                let expected = match max {
+                    Some(max) if max == min => min.to_string(),
+                    Some(max) => format!("{} to {}", min, max),
+                    None => format!("at least {}", min),
+                };
+
+                format!(
+                    "function `{}` expects {} arguments, but got {}",
+                    highlight(v),
+                    highlight(&expected),
+                    highlight(&got.to_string()),
+                )
+            }
+            CompilerError::VariableTypeCannotBeInfered(_, v) => {
+                format!("type of variable `{}` cannot be infered", highlight(v))
+            }
+            CompilerError::InvalidArgumentType(_, v, expected, got) => {
+                format!(
+                    "function `{}` expects argument type `{}`, but got `{}`",
+                    highlight(v),
+                    highlight(&expected.to_string()),
+                    highlight(&got.to_string()),
+                )
+            }
+            CompilerError::InvalidAssignment(_, v, expected, got) => {
+                format!(
+                    "cannot assign `{}` to variable `{}` of type `{}`",
+                    highlight(&got.to_string()),
+                    highlight(v),
+                    highlight(&expected.to_string()),
                 )
             }
-            CompilerError::VariableNotDefined(v) => {
-                write!(
-                    f,
-                    "{} variable `{}` not defined",
-                    "error:".red(),
-                    v.yellow()
+            CompilerError::CannotAssignConstVariable(_, v) => {
+                format!("cannot assign to const variable `{}`", highlight(v))
+            }
+            CompilerError::CannotReturnFromGlobalScope(_) => {
+                format!("cannot use `{}` in global scope", highlight("return"))
+            }
+            CompilerError::InvalidUnaryOperand(_, operator, kind) => {
+                format!(
+                    "operator `{}` cannot be applied to type `{}`",
+                    highlight(operator),
+                    highlight(&kind.to_string()),
                 )
             }
-            CompilerError::InvalidFunctionCall(v) => {
-                write!(
-                    f,
-                    "{} function call on variable `{}` invalid",
-                    "error:".red(),
-                    v.yellow(),
+            CompilerError::InvalidBinaryOperands(_, operator, left, right) => {
+                format!(
+                    "operator `{}` cannot be applied to types `{}` and `{}`",
+                    highlight(operator),
+                    highlight(&left.to_string()),
+                    highlight(&right.to_string()),
                 )
             }
-            CompilerError::InvalidNumberOfArguments(v, expected, got) => {
-                write!(
-                    f,
-                    "{} function `{}` expects {} arguments, but got {}",
-                    "error:".red(),
-                    v.yellow(),
-                    format!("{}", expected).yellow(),
-                    format!("{}", got).yellow(),
+            CompilerError::InvalidReturnType(_, v, expected, got) => {
+                format!(
+                    "function `{}` expects return type `{}`, but got `{}`",
+                    highlight(v),
+                    highlight(&expected.to_string()),
+                    highlight(&got.to_string()),
                 )
             }
-            CompilerError::VariableTypeCannotBeInfered(v) => {
-                write!(
-                    f,
-                    "{} type of variable `{}` cannot be infered",
-                    "error:".red(),
-                    v.yellow()
+            CompilerError::UnknownProperty(_, property, kind) => {
+                format!(
+                    "property `{}` does not exist on type `{}`",
+                    highlight(property),
+                    highlight(&kind.to_string()),
                 )
             }
-            CompilerError::InvalidArgumentType(v, expected, got) => {
-                write!(
-                    f,
-                    "{} function `{}` expects argument type `{}`, but got `{}`",
-                    "error:".red(),
-                    v.yellow(),
-                    expected.get_name().yellow(),
-                    got.get_name().yellow(),
+            CompilerError::CannotAssignReadonlyProperty(_, property, kind) => {
+                format!(
+                    "cannot assign to readonly property `{}` of type `{}`",
+                    highlight(property),
+                    highlight(&kind.to_string()),
                 )
             }
-            CompilerError::InvalidAssignment(v, expected, got) => {
-                write!(
-                    f,
-                    "{} cannot assign `{}` to variable `{}` of type `{}`",
-                    "error:".red(),
-                    got.get_name().yellow(),
-                    v.yellow(),
-                    expected.get_name().yellow(),
+            CompilerError::InvalidFormatString(_, reason) => {
+                format!("invalid format string: {}", reason)
+            }
+            CompilerError::InvalidNumberOfFormatArguments(_, expected, got) => {
+                format!(
+                    "format string expects {} arguments, but got {}",
+                    highlight(&expected.to_string()),
+                    highlight(&got.to_string()),
                 )
             }
-            CompilerError::CannotAssignConstVariable(v) => {
-                write!(
-                    f,
-                    "{} cannot assign to const variable `{}`",
-                    "error:".red(),
-                    v.yellow()
+            CompilerError::CannotEmbedFile(_, reason) => {
+                format!("cannot embed file: {}", reason)
+            }
+            CompilerError::InvalidInlineIr(_, reason) => {
+                format!("invalid inline IR: {}", reason)
+            }
+            CompilerError::InvalidCompoundAssignment(_) => {
+                "the index of a compound assignment must be a constant or a variable name"
+                    .to_string()
+            }
+            CompilerError::InvalidMacro(_, reason) => reason.to_string(),
+            CompilerError::DuplicateParameter(_, function, parameter) => {
+                format!(
+                    "function `{}` declares parameter `{}` more than once",
+                    highlight(function),
+                    highlight(parameter),
                 )
             }
-            CompilerError::CannotReturnFromGlobalScope => {
-                write!(
-                    f,
-                    "{} cannot use `{}` in global scope",
-                    "error:".red(),
-                    "return".yellow()
+            CompilerError::InvalidRestParameter(_, reason) => reason.to_string(),
+            CompilerError::ParameterShadowsFunction(_, function) => {
+                format!(
+                    "parameter `{}` has the name of its function",
+                    highlight(function)
                 )
             }
+            CompilerError::TooDeeplyNested(_, max_nesting) => {
+                format!(
+                    "the program is nested more than {} levels deep",
+                    max_nesting
+                )
+            }
+            CompilerError::PassFailed(name, reason) => {
+                format!("pass `{}` failed: {}", highlight(name), reason)
+            }
+            CompilerError::CompilationVetoed(phase, reason) => {
+                format!(
+                    "compilation vetoed after `{}` phase: {}",
+                    highlight(phase.get_name()),
+                    reason
+                )
+            }
+            CompilerError::DeniedWarning(warning) => warning.describe(highlight),
         }
     }
 }
+
+impl From<BuilderError> for CompilerError {
+    fn from(err: BuilderError) -> Self {
+        CompilerError::BuilderError(err)
+    }
+}
+
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.header(), self.highlighted_message())
+    }
+}
+
+impl std::error::Error for CompilerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompilerError::BuilderError(err) => Some(err),
+            CompilerError::ParserError(err, ..) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Receives the errors of a compilation, so that embedders can capture them
+/// instead of parsing the colored terminal output.
+pub trait DiagnosticEmitter {
+    fn emit(&mut self, error: &CompilerError);
+
+    fn warn(&mut self, _warning: &Warning) {}
+}
+
+/// Prints errors to the terminal, the way the command line compiler reports them.
+/// Once the source is known, errors with a location show the offending line.
+#[derive(Clone, Debug, Default)]
+pub struct TerminalEmitter {
+    source: Option<(String, String)>,
+}
+
+impl TerminalEmitter {
+    pub fn new() -> Self {
+        TerminalEmitter::default()
+    }
+
+    pub fn with_source(name: &str, content: &str) -> Self {
+        TerminalEmitter {
+            source: Some((name.to_string(), content.to_string())),
+        }
+    }
+}
+
+impl DiagnosticEmitter for TerminalEmitter {
+    fn emit(&mut self, error: &CompilerError) {
+        match &self.source {
+            Some((name, content)) => println!("{}", SourceFile::new(name, content).render(error)),
+            None => println!("{}", error),
+        }
+    }
+
+    fn warn(&mut self, warning: &Warning) {
+        match &self.source {
+            Some((name, content)) => {
+                println!("{}", SourceFile::new(name, content).render_warning(warning))
+            }
+            None => println!("{}", warning),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The code of errors, see `CompilerError::code`.
+    pub code: Option<&'static str>,
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl From<&CompilerError> for Diagnostic {
+    fn from(error: &CompilerError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: Some(error.code()),
+            location: error.location(),
+            message: error.message(),
+        }
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: None,
+            location: Some(warning.location()),
+            message: warning.message(),
+        }
+    }
+}
+
+/// Keeps every emitted error and warning as a plain `Diagnostic`.
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticCollector {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticEmitter for DiagnosticCollector {
+    fn emit(&mut self, error: &CompilerError) {
+        self.diagnostics.push(Diagnostic::from(error));
+    }
+
+    fn warn(&mut self, warning: &Warning) {
+        self.diagnostics.push(Diagnostic::from(warning));
+    }
+}
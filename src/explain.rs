@@ -0,0 +1,425 @@
+/// The stable code of a `CompilerError` variant and its extended description,
+/// shown by `mini explain`.
+#[derive(Clone, Debug)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub explanation: &'static str,
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        name: "VariableNotDefined",
+        summary: "a variable is used but never defined",
+        explanation: "\
+A variable or function is used, but no definition of it is visible from the
+scope of the use.
+
+    let a = 1;
+    echo(b); // error: variable `b` not defined
+
+Define the variable before using it, or check the spelling of its name. Std
+functions must be declared with `declare function` before they are called, see
+`mini std ls`.",
+    },
+    ErrorCode {
+        code: "E0002",
+        name: "VariableAlreadyDefined",
+        summary: "a variable is defined twice in the same scope",
+        explanation: "\
+A variable or function is defined twice in the same scope.
+
+    let a = 1;
+    let a = 2; // error: variable `a` already defined
+
+Give the second definition another name, or assign to the first one instead:
+
+    let a = 1;
+    a = 2;",
+    },
+    ErrorCode {
+        code: "E0003",
+        name: "InvalidFunctionCall",
+        summary: "something that is not a function is called",
+        explanation: "\
+A variable that is not a function is called.
+
+    let a = 1;
+    a(); // error: function call on variable `a` invalid
+
+Only functions defined with `function` or declared with `declare function` can
+be called.",
+    },
+    ErrorCode {
+        code: "E0004",
+        name: "InvalidNumberOfArguments",
+        summary: "a function is called with too few or too many arguments",
+        explanation: "\
+A function is called with a number of arguments its parameters do not accept.
+
+    function add(a: number, b: number): number {
+        return a + b;
+    }
+
+    add(1); // error: function `add` expects 2 arguments, but got 1
+
+Optional parameters, `b?: number`, may be left out and a rest parameter,
+`...b: number[]`, takes any number of arguments.",
+    },
+    ErrorCode {
+        code: "E0005",
+        name: "VariableTypeCannotBeInfered",
+        summary: "the type of a variable cannot be infered",
+        explanation: "\
+The type of a variable cannot be infered from its definition.
+
+Annotate the variable with its type:
+
+    let a: number;",
+    },
+    ErrorCode {
+        code: "E0006",
+        name: "InvalidArgumentType",
+        summary: "an argument does not have the type of its parameter",
+        explanation: "\
+An argument of a function call does not have the type of its parameter.
+
+    function double(a: number): number {
+        return a * 2;
+    }
+
+    double('two'); // error: function `double` expects argument type `number`, but got `string`
+
+Pass a value of the parameter type, or declare the parameter as `any`.",
+    },
+    ErrorCode {
+        code: "E0007",
+        name: "InvalidAssignment",
+        summary: "a value is assigned to a variable of another type",
+        explanation: "\
+A value is assigned to a variable whose type it does not have.
+
+    let a: number = 1;
+    a = 'one'; // error: cannot assign `string` to variable `a` of type `number`
+
+Assign a value of the variable type, or annotate the variable as `any`.",
+    },
+    ErrorCode {
+        code: "E0008",
+        name: "CannotAssignConstVariable",
+        summary: "a const variable is assigned",
+        explanation: "\
+A variable defined with `const` is assigned after its definition.
+
+    const a = 1;
+    a = 2; // error: cannot assign to const variable `a`
+
+Define the variable with `let` if it has to change.",
+    },
+    ErrorCode {
+        code: "E0009",
+        name: "CannotReturnFromGlobalScope",
+        summary: "`return` is used outside of a function",
+        explanation: "\
+A `return` statement is used at the top level of the program, outside of any
+function.
+
+    let a = 1;
+    return a; // error: cannot use `return` in global scope
+
+Only functions can return. The program ends after its last statement.",
+    },
+    ErrorCode {
+        code: "E0010",
+        name: "InvalidUnaryOperand",
+        summary: "a unary operator is applied to a type it does not support",
+        explanation: "\
+A unary operator is applied to a value whose type it does not support.
+
+    let a = -'one'; // error: operator `-` cannot be applied to type `string`
+
+Convert the value to a type the operator supports first.",
+    },
+    ErrorCode {
+        code: "E0011",
+        name: "InvalidBinaryOperands",
+        summary: "a binary operator is applied to types it does not support",
+        explanation: "\
+A binary operator is applied to values whose types it does not support.
+
+    let items = [1];
+    let a = items * 2; // error: operator `*` cannot be applied to types `number[]` and `number`
+
+Arithmetic needs numbers, `+` also joins strings.",
+    },
+    ErrorCode {
+        code: "E0012",
+        name: "InvalidReturnType",
+        summary: "a function returns a value of another type than it declares",
+        explanation: "\
+A function returns a value whose type is not the return type it declares.
+
+    function name(): string {
+        return 1; // error: function `name` expects return type `string`, but got `number`
+    }
+
+Return a value of the declared type, or change the declaration.",
+    },
+    ErrorCode {
+        code: "E0014",
+        name: "UnknownProperty",
+        summary: "a property that the type does not have is accessed",
+        explanation: "\
+A property is accessed on a value whose type does not have it.
+
+    let point = { x: 1, y: 2 };
+    echo(point.z); // error: property `z` does not exist on type `{ x: number, y: number }`
+
+Check the spelling of the property, or add it to the object.",
+    },
+    ErrorCode {
+        code: "E0015",
+        name: "CannotAssignReadonlyProperty",
+        summary: "a readonly property is assigned",
+        explanation: "\
+A property declared `readonly` in the type of an object is assigned.
+
+    let point: { readonly x: number } = { x: 1 };
+    point.x = 2; // error: cannot assign to readonly property `x` of type `{ readonly x: number }`
+
+Create a new object instead, or remove `readonly` from the type.",
+    },
+    ErrorCode {
+        code: "E0016",
+        name: "InvalidFormatString",
+        summary: "the format string of `format` is malformed",
+        explanation: "\
+The format string of a `format` call is malformed, e.g. a placeholder is never
+closed or has an unknown specifier.
+
+    format('{', 1); // error: invalid format string: ...
+
+Placeholders are `{}`, `{index}` and may be padded or limited, `{:>8}` or
+`{:.2}`, see `mini std doc format`. Write `{{` and `}}` for literal braces.",
+    },
+    ErrorCode {
+        code: "E0017",
+        name: "InvalidNumberOfFormatArguments",
+        summary: "`format` gets another number of arguments than its placeholders",
+        explanation: "\
+A `format` call does not pass as many arguments as its format string has
+placeholders.
+
+    format('{} and {}', 1); // error: format string expects 2 arguments, but got 1
+
+Pass one argument for each `{}`, and enough for the highest `{index}`.",
+    },
+    ErrorCode {
+        code: "E0018",
+        name: "CannotEmbedFile",
+        summary: "the file of an `embed` call cannot be read",
+        explanation: "\
+The file an `embed` call names cannot be read at compile time, or its path is
+not a string literal.
+
+    embed('missing.txt'); // error: cannot embed file: ...
+
+The path is relative to the source file and must name an existing text file.",
+    },
+    ErrorCode {
+        code: "E0019",
+        name: "InvalidInlineIr",
+        summary: "the body of `__llvm_ir` is not valid LLVM IR",
+        explanation: "\
+The body of an `__llvm_ir` call is not a string literal, or is not valid LLVM IR.
+
+The body is parsed by LLVM while generating code, the message shows what it
+rejected.",
+    },
+    ErrorCode {
+        code: "E0020",
+        name: "InvalidCompoundAssignment",
+        summary: "a compound assignment indexes with an expression",
+        explanation: "\
+The target of a compound assignment, such as `+=`, is indexed with an expression
+other than a constant or a variable name. The target is read and written, so its
+index would be evaluated twice.
+
+    items[next()] += 1; // error
+
+Store the index in a variable first:
+
+    let index = next();
+    items[index] += 1;",
+    },
+    ErrorCode {
+        code: "E0021",
+        name: "InvalidMacro",
+        summary: "a macro is defined or called wrongly",
+        explanation: "\
+A macro is defined or called in a way that cannot be expanded: it is defined
+twice, defined outside of the top level, called with another number of
+arguments than its parameters, called before it is defined or expands into too
+many nested macro calls.
+
+    macro twice(a) {
+        echo(a);
+        echo(a);
+    }
+
+    twice!(1, 2); // error: macro `twice` takes 1 arguments, but got 2
+
+The message names the cause.",
+    },
+    ErrorCode {
+        code: "E0022",
+        name: "TooDeeplyNested",
+        summary: "the program is nested deeper than the limit",
+        explanation: "\
+Statements or expressions of the program are nested deeper than the compiler
+allows, which protects it from running out of stack.
+
+Split the nested code into functions or variables, or raise the limit with
+`--max-nesting`.",
+    },
+    ErrorCode {
+        code: "E0023",
+        name: "ParserError",
+        summary: "the source is not valid syntax",
+        explanation: "\
+The source cannot be parsed, e.g. a statement is missing its `;`, a `{` is
+never closed or a string literal is never terminated.
+
+    let a = 1
+    let b = 2; // error: expected `;` after `1`, found `let`
+
+The message names what was expected and the hint below it a likely fix.",
+    },
+    ErrorCode {
+        code: "E0024",
+        name: "DeniedWarning",
+        summary: "a warning is reported while warnings are denied",
+        explanation: "\
+A warning is reported while `--deny-warnings` makes warnings fail the
+compilation.
+
+Fix the cause of the warning, silence it with `-A <warning>`, or silence it on
+one line with a `// mini-ignore: <warning>` comment.",
+    },
+    ErrorCode {
+        code: "E0025",
+        name: "RuntimeError",
+        summary: "the program fails while running",
+        explanation: "\
+The program fails while running, e.g. an `assert` does not hold or a value is
+used in a way its type does not allow.
+
+    assert(1 == 2, 'math'); // error: runtime error: assertion failed: math
+
+The program exits with status 4.",
+    },
+    ErrorCode {
+        code: "E0026",
+        name: "CompilationVetoed",
+        summary: "a compiler hook stops the compilation",
+        explanation: "\
+A hook of an embedder stops the compilation after one of its phases. The message
+is the reason the hook gives.",
+    },
+    ErrorCode {
+        code: "E0027",
+        name: "PassFailed",
+        summary: "a custom compiler pass fails",
+        explanation: "\
+A pass an embedder adds to the compilation fails. The message names the pass
+and the reason it gives.",
+    },
+    ErrorCode {
+        code: "E0028",
+        name: "CliError",
+        summary: "the command line cannot be carried out",
+        explanation: "\
+The command cannot be carried out, e.g. the input file or the manifest cannot
+be read, or the given options conflict.",
+    },
+    ErrorCode {
+        code: "E0029",
+        name: "CodeGenError",
+        summary: "the generated code cannot be compiled by LLVM",
+        explanation: "\
+LLVM rejects the generated code, or the object file or executable cannot be
+written.",
+    },
+    ErrorCode {
+        code: "E0030",
+        name: "BuilderError",
+        summary: "LLVM fails to build an instruction",
+        explanation: "\
+LLVM fails to build an instruction while generating code. This is a bug in
+mini, please report it with the program that triggers it.",
+    },
+    ErrorCode {
+        code: "E0031",
+        name: "Internal",
+        summary: "an internal compiler error",
+        explanation: "\
+The compiler reaches a state it does not expect. This is a bug in mini, please
+report it with the program that triggers it.",
+    },
+    ErrorCode {
+        code: "E0032",
+        name: "DuplicateParameter",
+        summary: "a function declares two parameters with the same name",
+        explanation: "\
+A function declares two parameters with the same name, so the second one would
+hide the first.
+
+    function add(a: number, a: number): number { // error: function `add` declares parameter `a` more than once
+        return a + a;
+    }
+
+Give each parameter a name of its own.",
+    },
+    ErrorCode {
+        code: "E0033",
+        name: "ParameterShadowsFunction",
+        summary: "a parameter has the name of its function",
+        explanation: "\
+A parameter has the name of the function declaring it, so the body could not
+call the function by its name.
+
+    function count(count: number): number { // error: parameter `count` has the name of its function
+        return count;
+    }
+
+Rename the parameter.",
+    },
+    ErrorCode {
+        code: "E0034",
+        name: "InvalidRestParameter",
+        summary: "a rest parameter is not the last parameter",
+        explanation: "\
+A rest parameter, `...name`, is followed by other parameters or a function
+declares more than one of them. The rest parameter takes the remaining
+arguments of a call, so only the last parameter can be one.
+
+    function log(...values: any[], level: number) { // error: rest parameter `values` of function `log` must be its last parameter
+        echo(level, values);
+    }
+
+Move the rest parameter to the end:
+
+    function log(level: number, ...values: any[]) {
+        echo(level, values);
+    }",
+    },
+];
+
+/// The code with the given name, which is case insensitive.
+pub fn find(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
@@ -0,0 +1,171 @@
+/// A part of a format string of the `format` builtin, parsed the same way as the
+/// std runtime parses it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder),
+}
+
+/// A `{index:<fill><align><0><width>.<precision>}` placeholder, with every part
+/// optional. Widths and precisions count characters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placeholder {
+    pub index: usize,
+    pub fill: char,
+    pub align: Option<char>,
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+fn parse_number(chars: &mut std::iter::Peekable<std::str::CharIndices>) -> Option<usize> {
+    let mut n = None;
+
+    while let Some((_, c)) = chars.peek() {
+        match c.to_digit(10) {
+            Some(digit) => {
+                n = Some(n.unwrap_or(0) * 10 + digit as usize);
+                chars.next();
+            }
+            None => break,
+        }
+    }
+
+    n
+}
+
+fn is_align(c: char) -> bool {
+    matches!(c, '<' | '>' | '^')
+}
+
+pub fn parse(fmt: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut chars = fmt.char_indices().peekable();
+    let mut next_index = 0;
+    let mut text_start = 0;
+
+    while let Some((start, c)) = chars.next() {
+        if c != '{' && c != '}' {
+            continue;
+        }
+
+        if start > text_start {
+            segments.push(Segment::Text(&fmt[text_start..start]));
+        }
+
+        if chars.peek().map(|(_, next)| *next) == Some(c) {
+            chars.next();
+            segments.push(Segment::Text(&fmt[start..start + 1]));
+            text_start = start + 2;
+            continue;
+        }
+
+        if c == '}' {
+            return Err("unmatched `}` in the format string".to_string());
+        }
+
+        let index = match parse_number(&mut chars) {
+            Some(index) => index,
+            None => {
+                next_index += 1;
+                next_index - 1
+            }
+        };
+
+        let mut placeholder = Placeholder {
+            index,
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+        };
+
+        if chars.next_if(|(_, c)| *c == ':').is_some() {
+            let mut lookahead = chars.clone();
+
+            match (lookahead.next(), lookahead.next()) {
+                (Some((_, fill)), Some((_, align))) if fill != '}' && is_align(align) => {
+                    placeholder.fill = fill;
+                    placeholder.align = Some(align);
+                    chars = lookahead;
+                }
+                (Some((_, align)), _) if is_align(align) => {
+                    placeholder.align = Some(align);
+                    chars.next();
+                }
+                _ => {}
+            }
+
+            placeholder.zero = chars.next_if(|(_, c)| *c == '0').is_some();
+            placeholder.width = parse_number(&mut chars).unwrap_or(0);
+
+            if chars.next_if(|(_, c)| *c == '.').is_some() {
+                match parse_number(&mut chars) {
+                    Some(precision) => placeholder.precision = Some(precision),
+                    None => {
+                        return Err(
+                            "expected a precision after `.` in the format string".to_string()
+                        )
+                    }
+                }
+            }
+        }
+
+        match chars.next() {
+            Some((end, '}')) => text_start = end + 1,
+            _ => return Err("unmatched `{` in the format string".to_string()),
+        }
+
+        segments.push(Segment::Placeholder(placeholder));
+    }
+
+    if fmt.len() > text_start {
+        segments.push(Segment::Text(&fmt[text_start..]));
+    }
+
+    Ok(segments)
+}
+
+/// The number of arguments the placeholders refer to.
+pub fn argument_count(segments: &[Segment]) -> usize {
+    segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Placeholder(placeholder) => Some(placeholder.index + 1),
+            Segment::Text(_) => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Pads the text to the width of the placeholder. Numbers are aligned to the right
+/// by default, and padded with zeros after their sign with the `0` flag.
+pub fn pad(text: &str, placeholder: &Placeholder, is_number: bool) -> String {
+    let padding = placeholder.width.saturating_sub(text.chars().count());
+
+    if is_number && placeholder.zero {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+
+        return format!("{}{}{}", sign, "0".repeat(padding), digits);
+    }
+
+    let before = match placeholder.align {
+        Some('>') => padding,
+        Some('^') => padding / 2,
+        None if is_number => padding,
+        _ => 0,
+    };
+
+    let fill = placeholder.fill.to_string();
+
+    format!(
+        "{}{}{}",
+        fill.repeat(before),
+        text,
+        fill.repeat(padding - before)
+    )
+}
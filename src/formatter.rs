@@ -0,0 +1,612 @@
+use std::collections::BTreeMap;
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::lexer::{self, Lexer, Token};
+use crate::pipeline;
+
+/// Prints the program in the canonical style: four space indentation, single
+/// quoted strings and at most one blank line between statements. Comments are
+/// printed on their own line before the statement following them, or after the
+/// line they end if they were written after code.
+pub fn format(content: &str) -> Result<String, CompilerError> {
+    let program = pipeline::parse_surface(content)?;
+
+    let mut braces = BTreeMap::new();
+    let mut open = Vec::new();
+
+    for (start, token, _) in Lexer::new(content).map_while(Result::ok) {
+        match token {
+            Token::Symbol("{") => open.push(start),
+            Token::Symbol("}") => {
+                if let Some(open) = open.pop() {
+                    braces.insert(open, start);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut comments = lexer::comments(content);
+    comments.reverse();
+
+    let mut formatter = Formatter {
+        content,
+        output: String::new(),
+        indent: 0,
+        comments,
+        braces,
+    };
+    formatter.statements(&program.statements);
+    formatter.comments_before(content.len(), program.statements.is_empty());
+
+    Ok(formatter.output)
+}
+
+fn precedence(expression: &ast::Expression) -> u8 {
+    match expression {
+        ast::Expression::AssignmentExpression { .. }
+        | ast::Expression::CompoundAssignmentExpression { .. }
+        | ast::Expression::ArrayExpression { .. }
+        | ast::Expression::ObjectExpression { .. } => 0,
+        ast::Expression::BinaryExpression { operator, .. } => match operator {
+            ast::BinaryOperator::Or => 1,
+            ast::BinaryOperator::And => 2,
+            ast::BinaryOperator::Equal
+            | ast::BinaryOperator::StrictEqual
+            | ast::BinaryOperator::NotEqual
+            | ast::BinaryOperator::StrictNotEqual => 3,
+            ast::BinaryOperator::Less
+            | ast::BinaryOperator::LessEqual
+            | ast::BinaryOperator::Greater
+            | ast::BinaryOperator::GreaterEqual => 4,
+            ast::BinaryOperator::Addition | ast::BinaryOperator::Subtraction => 6,
+            ast::BinaryOperator::Multiplication
+            | ast::BinaryOperator::Division
+            | ast::BinaryOperator::Mod => 7,
+        },
+        ast::Expression::RangeExpression { .. } => 5,
+        ast::Expression::UnaryExpression { .. } | ast::Expression::TypeOfExpression { .. } => 8,
+        _ => 9,
+    }
+}
+
+fn kind(kind: &ast::VariableKind) -> String {
+    match kind {
+        ast::VariableKind::Undefined => "void".to_string(),
+        ast::VariableKind::Object { fields } if fields.is_empty() => "{}".to_string(),
+        ast::VariableKind::Object { fields } => {
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    let readonly = if field.is_readonly { "readonly " } else { "" };
+
+                    format!("{}{}: {}", readonly, field.name, self::kind(&field.kind))
+                })
+                .collect::<Vec<_>>();
+
+            format!("{{ {} }}", fields.join(", "))
+        }
+        ast::VariableKind::Array { kind } => format!("{}[]", self::kind(kind)),
+        kind => kind.get_name().to_string(),
+    }
+}
+
+fn constant(value: &ast::Constant) -> String {
+    match value {
+        ast::Constant::Undefined => "undefined".to_string(),
+        ast::Constant::Null => "null".to_string(),
+        ast::Constant::Boolean(b) => b.to_string(),
+        ast::Constant::Integer(i) => i.to_string(),
+        ast::Constant::Float(f) if f.fract() == 0.0 => format!("{:.1}", f),
+        ast::Constant::Float(f) => f.to_string(),
+        ast::Constant::String(s) => {
+            let quote = if !s.contains('\'') {
+                '\''
+            } else if !s.contains('"') {
+                '"'
+            } else {
+                '`'
+            };
+
+            format!("{}{}{}", quote, s, quote)
+        }
+    }
+}
+
+/// Prints `let name: kind` or `const name: kind`, without the kind if it is not annotated.
+pub fn definition_header(definition: &ast::VariableDefinition) -> String {
+    let mut header = format!(
+        "{} {}",
+        if definition.is_writable {
+            "let"
+        } else {
+            "const"
+        },
+        definition.name
+    );
+
+    if definition.is_annotated {
+        header.push_str(&format!(": {}", kind(&definition.kind)));
+    }
+
+    header
+}
+
+/// Prints `function name(parameters): kind`, without the return kind if it is `any`.
+pub fn function_header(
+    definition: &ast::VariableDefinition,
+    parameters: &[ast::VariableDefinition],
+) -> String {
+    let (parameter_kinds, return_kind) = match &definition.kind {
+        ast::VariableKind::Function {
+            parameters,
+            return_kind,
+        } => (parameters, return_kind),
+        _ => unreachable!(),
+    };
+
+    let parameters = parameters
+        .iter()
+        .zip(parameter_kinds.iter())
+        .map(|(parameter, parameter_kind)| {
+            let mut s = String::new();
+
+            if parameter_kind.is_rest {
+                s.push_str("...");
+            }
+
+            s.push_str(parameter.name);
+
+            if parameter_kind.is_optional {
+                s.push('?');
+            }
+
+            if parameter.is_annotated {
+                s.push_str(&format!(": {}", kind(&parameter.kind)));
+            }
+
+            s
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = format!("function {}({})", definition.name, parameters.join(", "));
+
+    if **return_kind != ast::VariableKind::Any {
+        header.push_str(&format!(": {}", kind(return_kind)));
+    }
+
+    header
+}
+
+struct Formatter<'a> {
+    content: &'a str,
+    output: String,
+    indent: usize,
+    /// The comments left to print, the next one last.
+    comments: Vec<(usize, &'a str)>,
+    /// The offset of the matching `}` of each `{`.
+    braces: BTreeMap<usize, usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn line(&mut self, line: &str) {
+        self.output.push_str(&"    ".repeat(self.indent));
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    /// Returns whether the source has a blank line right before the given offset.
+    fn follows_blank_line(&self, offset: usize) -> bool {
+        self.content[..offset]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_whitespace())
+            .filter(|c| *c == '\n')
+            .count()
+            > 1
+    }
+
+    /// The offset of the `}` closing the first block starting after the offset.
+    fn block_end(&self, offset: usize) -> usize {
+        self.braces
+            .range(offset..)
+            .next()
+            .map_or(self.content.len(), |(_, end)| *end)
+    }
+
+    /// Prints the comments starting before the offset. Returns whether the block
+    /// is still empty, which it is when `is_first` is and no comment was printed.
+    fn comments_before(&mut self, offset: usize, mut is_first: bool) -> bool {
+        while let Some(&(start, comment)) = self.comments.last() {
+            if start >= offset {
+                break;
+            }
+
+            self.comments.pop();
+
+            let line_start = self.content[..start]
+                .rfind('\n')
+                .map_or(0, |index| index + 1);
+            let follows_code = !self.content[line_start..start].trim().is_empty();
+
+            if follows_code && self.output.ends_with('\n') {
+                self.output.pop();
+                self.output.push_str(&format!(" {}\n", comment));
+                continue;
+            }
+
+            if self.follows_blank_line(start) && !is_first {
+                self.output.push('\n');
+            }
+
+            self.line(comment);
+            is_first = false;
+        }
+
+        is_first
+    }
+
+    fn statements(&mut self, statements: &[ast::Statement]) {
+        let mut is_first = true;
+
+        for statement in statements.iter() {
+            if let ast::Statement::EmptyStatement = statement {
+                continue;
+            }
+
+            if let Some((start, _)) = statement.get_location() {
+                is_first = self.comments_before(start, is_first);
+            }
+
+            let has_blank_line = statement
+                .get_location()
+                .is_some_and(|(start, _)| self.follows_blank_line(start));
+
+            if has_blank_line && !is_first {
+                self.output.push('\n');
+            }
+
+            self.statement(statement);
+            is_first = false;
+        }
+    }
+
+    /// Prints a block, `end` being the offset of its `}`.
+    fn body(&mut self, header: String, statements: &[ast::Statement], end: usize) {
+        let is_empty = statements
+            .iter()
+            .all(|s| matches!(s, ast::Statement::EmptyStatement));
+
+        let has_comments = self.comments.last().is_some_and(|(start, _)| *start < end);
+
+        if is_empty && !has_comments {
+            self.line(&format!("{} {{}}", header));
+            return;
+        }
+
+        self.line(&format!("{} {{", header));
+        self.indent += 1;
+        self.statements(statements);
+        self.comments_before(end, is_empty);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn statement(&mut self, statement: &ast::Statement) {
+        match statement {
+            ast::Statement::ExpressionStatement { expression } => {
+                let line = format!("{};", self.expression(expression));
+                self.line(&line);
+            }
+            ast::Statement::DefinitionStatement {
+                definition,
+                expression,
+                ..
+            } => {
+                let mut line = definition_header(definition);
+
+                if let Some(expression) = expression {
+                    line.push_str(&format!(" = {}", self.expression(expression)));
+                }
+
+                line.push(';');
+                self.line(&line);
+            }
+            ast::Statement::FunctionStatement {
+                location,
+                definition,
+                parameters,
+                statements,
+            } => {
+                for decorator in definition.decorators.iter() {
+                    self.line(&format!("@{}", decorator));
+                }
+
+                let header = function_header(definition, parameters);
+
+                if definition.is_external {
+                    self.line(&format!("declare {};", header));
+                } else {
+                    self.body(header, statements, self.block_end(location.1));
+                }
+            }
+            ast::Statement::ReturnStatement { expression, .. } => {
+                let line = match expression {
+                    Some(expression) => format!("return {};", self.expression(expression)),
+                    None => "return;".to_string(),
+                };
+
+                self.line(&line);
+            }
+            // the statements are only the expression until they are lowered
+            ast::Statement::DeferStatement { statements, .. } => {
+                for statement in statements {
+                    if let ast::Statement::ExpressionStatement { expression } = statement {
+                        let line = format!("defer {};", self.expression(expression));
+                        self.line(&line);
+                    }
+                }
+            }
+            ast::Statement::IfStatement { .. } => self.if_statement(statement, String::new()),
+            ast::Statement::MacroStatement {
+                location,
+                name,
+                parameters,
+                statements,
+            } => {
+                let header = format!("macro {}({})", name, parameters.join(", "));
+                self.body(header, statements, self.block_end(location.1));
+            }
+            ast::Statement::MacroCallStatement {
+                name, arguments, ..
+            } => {
+                let line = format!("{}!({});", name, self.expressions(arguments));
+                self.line(&line);
+            }
+            ast::Statement::EmptyStatement => {}
+        }
+    }
+
+    /// Prints an if statement, `prefix` being `} else ` for an else-if chain.
+    fn if_statement(&mut self, statement: &ast::Statement, prefix: String) {
+        let (location, condition, statements, else_statements) = match statement {
+            ast::Statement::IfStatement {
+                location,
+                condition,
+                statements,
+                else_statements,
+            } => (location, condition, statements, else_statements),
+            _ => unreachable!(),
+        };
+
+        let header = format!("{}if ({})", prefix, self.expression(condition));
+        let end = self.block_end(location.1);
+
+        let else_statements = match else_statements {
+            Some(else_statements) => else_statements,
+            None => return self.body(header, statements, end),
+        };
+
+        self.line(&format!("{} {{", header));
+        self.indent += 1;
+        self.statements(statements);
+        self.comments_before(end, statements.is_empty());
+        self.indent -= 1;
+
+        match else_statements.as_slice() {
+            [else_if @ ast::Statement::IfStatement { .. }] => {
+                self.if_statement(else_if, "} else ".to_string())
+            }
+            _ => self.body(
+                "} else".to_string(),
+                else_statements,
+                self.block_end(end + 1),
+            ),
+        }
+    }
+
+    fn identifier(&self, identifier: &ast::VariableIdentifier) -> String {
+        match identifier {
+            ast::VariableIdentifier::Name { name, .. } => name.to_string(),
+            ast::VariableIdentifier::Property { base, property, .. } => {
+                format!("{}.{}", self.identifier(base), property)
+            }
+            ast::VariableIdentifier::Index { base, index, .. } => {
+                format!("{}[{}]", self.identifier(base), self.expression(index))
+            }
+        }
+    }
+
+    /// Prints the value a member is accessed on, parenthesized unless the
+    /// grammar accepts it as is.
+    fn member_base(&self, expression: &ast::Expression) -> String {
+        match expression {
+            ast::Expression::VariableExpression { .. }
+            | ast::Expression::CallExpression { .. }
+            | ast::Expression::ValidateExpression { .. }
+            | ast::Expression::MemberExpression { .. } => self.expression(expression),
+            _ => format!("({})", self.expression(expression)),
+        }
+    }
+
+    /// Prints the operand, parenthesized if it binds looser than `min_precedence`.
+    fn operand(&self, expression: &ast::Expression, min_precedence: u8) -> String {
+        if precedence(expression) < min_precedence {
+            format!("({})", self.expression(expression))
+        } else {
+            self.expression(expression)
+        }
+    }
+
+    fn pattern(&self, pattern: &ast::Pattern) -> String {
+        match pattern {
+            ast::Pattern::Constant(value) => constant(value),
+            ast::Pattern::Binding(name) => name.to_string(),
+            ast::Pattern::Symbol(name) => {
+                format!("Symbol({})", constant(&ast::Constant::String(name)))
+            }
+            ast::Pattern::Object(properties) if properties.is_empty() => "{}".to_string(),
+            ast::Pattern::Object(properties) => {
+                let properties = properties
+                    .iter()
+                    .map(|(name, pattern)| format!("{}: {}", name, self.pattern(pattern)))
+                    .collect::<Vec<_>>();
+
+                format!("{{ {} }}", properties.join(", "))
+            }
+        }
+    }
+
+    fn expressions(&self, expressions: &[ast::Expression]) -> String {
+        expressions
+            .iter()
+            .map(|expression| self.expression(expression))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn expression(&self, expression: &ast::Expression) -> String {
+        match expression {
+            ast::Expression::ConstantExpression { value, .. } => constant(value),
+            ast::Expression::ArrayExpression { items, .. } => {
+                format!("[{}]", self.expressions(items))
+            }
+            ast::Expression::ObjectExpression { properties, .. } if properties.is_empty() => {
+                "{}".to_string()
+            }
+            ast::Expression::ObjectExpression { properties, .. } => {
+                let properties = properties
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, self.expression(value)))
+                    .collect::<Vec<_>>();
+
+                format!("{{ {} }}", properties.join(", "))
+            }
+            ast::Expression::TypeOfExpression { expression, .. } => {
+                format!("typeof {}", self.operand(expression, 8))
+            }
+            ast::Expression::SymbolExpression { name, .. } => {
+                format!("Symbol({})", self.expression(name))
+            }
+            ast::Expression::ValidateExpression {
+                kind, expression, ..
+            } => format!(
+                "validate<{}>({})",
+                self::kind(kind),
+                self.expression(expression)
+            ),
+            ast::Expression::VariableExpression { identifier, .. } => self.identifier(identifier),
+            ast::Expression::CallExpression {
+                identifier,
+                arguments,
+                ..
+            } => format!(
+                "{}({})",
+                self.identifier(identifier),
+                self.expressions(arguments)
+            ),
+            ast::Expression::MemberExpression {
+                expression, member, ..
+            } => match member {
+                ast::Member::Property(property) => {
+                    format!("{}.{}", self.member_base(expression), property)
+                }
+                ast::Member::Index(index) => {
+                    format!(
+                        "{}[{}]",
+                        self.member_base(expression),
+                        self.expression(index)
+                    )
+                }
+                ast::Member::Slice { start, end } => {
+                    let bound = |bound: &Option<Box<ast::Expression>>| {
+                        bound
+                            .as_ref()
+                            .map_or(String::new(), |bound| self.expression(bound))
+                    };
+
+                    format!(
+                        "{}[{}:{}]",
+                        self.member_base(expression),
+                        bound(start),
+                        bound(end)
+                    )
+                }
+            },
+            ast::Expression::AssignmentExpression {
+                identifier,
+                expression,
+                ..
+            } => format!(
+                "{} = {}",
+                self.identifier(identifier),
+                self.expression(expression)
+            ),
+            ast::Expression::CompoundAssignmentExpression {
+                identifier,
+                operator,
+                expression,
+                ..
+            } => format!(
+                "{} {}= {}",
+                self.identifier(identifier),
+                operator.get_symbol(),
+                self.expression(expression)
+            ),
+            ast::Expression::MatchExpression { subject, arms, .. } if arms.is_empty() => {
+                format!("match ({}) {{}}", self.expression(subject))
+            }
+            ast::Expression::MatchExpression { subject, arms, .. } => {
+                let arms = arms
+                    .iter()
+                    .map(|arm| {
+                        format!(
+                            "{} => {}",
+                            self.pattern(&arm.pattern),
+                            self.expression(&arm.expression)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "match ({}) {{ {} }}",
+                    self.expression(subject),
+                    arms.join(", ")
+                )
+            }
+            ast::Expression::UnaryExpression {
+                operator,
+                expression,
+                ..
+            } => format!("{}{}", operator.get_symbol(), self.operand(expression, 8)),
+            ast::Expression::RangeExpression {
+                start,
+                end,
+                is_inclusive,
+                ..
+            } => format!(
+                "{}{}{}",
+                self.operand(start, 6),
+                if *is_inclusive { "..=" } else { ".." },
+                self.operand(end, 6)
+            ),
+            ast::Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let precedence = precedence(expression);
+
+                format!(
+                    "{} {} {}",
+                    self.operand(left, precedence),
+                    operator.get_symbol(),
+                    self.operand(right, precedence + 1)
+                )
+            }
+            ast::Expression::Empty => String::new(),
+        }
+    }
+}
@@ -1,26 +1,188 @@
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use by_address::ByAddress;
 use generational_arena::Index;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::{Linkage, Module};
-use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetTriple};
-use inkwell::types::{BasicType, BasicTypeEnum};
+use inkwell::passes::PassBuilderOptions;
+use inkwell::targets::{
+    FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+};
+use inkwell::types::{BasicType, BasicTypeEnum, StructType};
 use inkwell::values::{
-    AnyValue, BasicMetadataValueEnum, BasicValueEnum, FunctionValue, PointerValue,
+    BasicMetadataValueEnum, BasicValueEnum, FunctionValue, GlobalValue, IntValue, PointerValue,
 };
-use inkwell::{AddressSpace, OptimizationLevel};
+use inkwell::{AddressSpace, IntPredicate};
 
 use crate::ast;
+use crate::builtins;
+use crate::consteval;
 use crate::error::CompilerError;
+use crate::interp::Value;
+use crate::lifetime;
+use crate::options::{CompileOptions, Sanitizer};
+use crate::source::SourceFile;
 use crate::st;
+use crate::typeck::RuntimeCasts;
 
 const MAIN_FUNCTION_NAME: &str = "main";
+const STRING_TABLE_NAME: &str = "mini.strings";
+/// The words of the `undefined` and `null` of the runtime, see `std/defs.h`.
+const UNDEFINED_WORD: u64 = (3 << 3) | 0x2;
+const NULL_WORD: u64 = 0x2;
+/// The types of `val_t` the generated code tests, see `std/defs.h`.
+const VAL_FLOAT: u64 = 4;
+const VAL_F64_ARRAY: u64 = 14;
+const VAL_I64_ARRAY: u64 = 15;
+/// Functions of the program with at most this many instructions are inlined.
+const INLINE_THRESHOLD: usize = 40;
 const STD_LIBRARY_CODE: &'static [u8] = include_bytes!("../std.bc");
 
+fn load_module<'ctx>(context: &'ctx Context, code: &[u8]) -> Result<Module<'ctx>, CompilerError> {
+    context
+        .create_module_from_ir(MemoryBuffer::create_from_memory_range_copy(code, "std"))
+        .map_err(|err| CompilerError::CodeGenError(format!("Could not load std library: {}", err)))
+}
+
+/// Functions the built-in std library defines for the generated code.
+fn std_interface<'ctx>(module: &Module<'ctx>) -> Vec<FunctionValue<'ctx>> {
+    module
+        .get_functions()
+        .filter(|function| {
+            function.count_basic_blocks() > 0 && function.get_linkage() == Linkage::External
+        })
+        .collect()
+}
+
+/// The module the generated code is added to. A replacement std library must
+/// define every function of the built-in one with the same type, and without
+/// a std library the functions are only declared, to be linked from elsewhere.
+fn load_std_library<'ctx>(
+    context: &'ctx Context,
+    options: &CompileOptions,
+) -> Result<Module<'ctx>, CompilerError> {
+    let builtin = load_module(context, STD_LIBRARY_CODE)?;
+
+    if options.no_std {
+        let module = context.create_module("std");
+
+        for function in std_interface(&builtin) {
+            module.add_function(
+                &function.get_name().to_string_lossy(),
+                function.get_type(),
+                Some(Linkage::External),
+            );
+        }
+
+        return Ok(module);
+    }
+
+    let std_library = match &options.std_library {
+        Some(std_library) => std_library,
+        None => return Ok(builtin),
+    };
+
+    let module = load_module(context, std_library)?;
+
+    for expected in std_interface(&builtin) {
+        let name = expected.get_name().to_string_lossy();
+
+        match module.get_function(&name) {
+            Some(function) if function.get_type() == expected.get_type() => {}
+            Some(function) => {
+                return Err(CompilerError::CodeGenError(format!(
+                    "Incompatible std library: `{}` has type `{}`, expected `{}`",
+                    name,
+                    function.get_type().print_to_string().to_string_lossy(),
+                    expected.get_type().print_to_string().to_string_lossy()
+                )))
+            }
+            None => {
+                return Err(CompilerError::CodeGenError(format!(
+                    "Incompatible std library: `{}` is missing",
+                    name
+                )))
+            }
+        }
+    }
+
+    Ok(module)
+}
+
+/// Error of a translation given another kind of expression than the one it handles.
+fn unexpected_expression(expression: &ast::Expression) -> CompilerError {
+    CompilerError::Internal(
+        expression.get_location(),
+        "unexpected kind of expression".to_string(),
+    )
+}
+
+fn instruction_count(function: FunctionValue) -> usize {
+    function
+        .get_basic_blocks()
+        .iter()
+        .map(|block| {
+            let mut count = 0;
+            let mut instruction = block.get_first_instruction();
+
+            while let Some(current) = instruction {
+                count += 1;
+                instruction = current.get_next_instruction();
+            }
+
+            count
+        })
+        .sum()
+}
+
+/// Counts printed by `--stats`.
+#[derive(Clone, Debug, Default)]
+pub struct CodeGenStats {
+    /// Functions generated for the program, `main` included.
+    pub functions: usize,
+    /// IR instructions of the generated functions.
+    pub instructions: usize,
+    /// IR instructions of the std functions left in the module.
+    pub std_instructions: usize,
+    /// Calls to std functions emitted by name, the most frequent first.
+    pub builtin_calls: Vec<(String, usize)>,
+}
+
+/// Whether the expression is a non-empty array or object literal made only of
+/// constants, at any depth, which is built once and copied afterwards.
+fn is_constant_literal(expression: &ast::Expression) -> bool {
+    fn is_constant(expression: &ast::Expression) -> bool {
+        match expression {
+            ast::Expression::ConstantExpression { .. } => true,
+            ast::Expression::ArrayExpression { items, .. } => items.iter().all(is_constant),
+            ast::Expression::ObjectExpression { properties, .. } => {
+                properties.iter().all(|(_, e)| is_constant(e))
+            }
+            _ => false,
+        }
+    }
+
+    match expression {
+        ast::Expression::ArrayExpression { items, .. } => {
+            !items.is_empty() && is_constant(expression)
+        }
+        ast::Expression::ObjectExpression { properties, .. } => {
+            !properties.is_empty() && is_constant(expression)
+        }
+        _ => false,
+    }
+}
+
+/// Values are opaque words for the generated code: small integers, booleans and
+/// null are encoded in the word itself, so only the std functions may look into them.
 fn get_val_type<'ctx>(context: &'ctx Context) -> BasicTypeEnum<'ctx> {
     context
         .struct_type(&[context.i8_type().into()], true)
@@ -36,10 +198,118 @@ fn new_function_label() -> String {
     format!("@f{}", index)
 }
 
-pub struct IRGenerator<'input, 'ctx> {
-    pub optimize: bool,
+fn is_apple(triple: &target_lexicon::Triple) -> bool {
+    triple.vendor == target_lexicon::Vendor::Apple
+}
+
+fn link(
+    linker: &str,
+    libraries: &[String],
+    triple: &target_lexicon::Triple,
+    reloc_mode: RelocMode,
+    sanitizer: Option<Sanitizer>,
+    object_file: &Path,
+    out_file: &Path,
+) -> Result<(), CompilerError> {
+    let mut command = std::process::Command::new(linker);
+
+    if sanitizer == Some(Sanitizer::Address) {
+        command.arg("-fsanitize=address");
+    }
+
+    if reloc_mode == RelocMode::Static && !is_apple(triple) {
+        command.arg("-no-pie");
+    }
+
+    if is_apple(triple) {
+        let arch = match triple.architecture {
+            target_lexicon::Architecture::Aarch64(_) => "arm64".to_string(),
+            architecture => architecture.to_string(),
+        };
+
+        command.arg("-Wl,-ld_classic").arg("-arch").arg(arch);
+    } else {
+        // the std runtime starts threads with pthreads, a library of its own on older systems
+        command.arg("-pthread");
+    }
+
+    command
+        .arg("-o")
+        .arg(out_file)
+        .arg(object_file)
+        .args(libraries.iter().map(|library| format!("-l{}", library)));
+
+    run_tool(linker, command)
+}
+
+/// Runs a linking tool, failing with its error output if it does not succeed.
+fn run_tool(name: &str, mut command: std::process::Command) -> Result<(), CompilerError> {
+    let output = command
+        .output()
+        .map_err(|err| CompilerError::CodeGenError(format!("Could not run `{}`: {}", name, err)))?;
+
+    if !output.status.success() {
+        return Err(CompilerError::CodeGenError(format!(
+            "`{}` failed with {}:\n{}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+
+    Ok(())
+}
 
+/// Merges per-architecture macOS executables into a single universal binary.
+pub fn link_universal(
+    triples: &[target_lexicon::Triple],
+    executables: &[PathBuf],
+    out_file: &Path,
+) -> Result<(), CompilerError> {
+    if !triples.iter().all(is_apple) {
+        return Err(CompilerError::CodeGenError(
+            "Universal binaries can only be created for Apple targets".to_string(),
+        ));
+    }
+
+    let mut command = std::process::Command::new("lipo");
+    command
+        .arg("-create")
+        .arg("-output")
+        .arg(out_file)
+        .args(executables);
+
+    run_tool("lipo", command)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmitKind {
+    Object,
+    LlvmIr,
+    Bitcode,
+}
+
+#[derive(Default)]
+struct StringTable<'ctx> {
+    placeholder: Option<GlobalValue<'ctx>>,
+    data: Vec<u8>,
+    offsets: IndexMap<String, u64>,
+}
+
+/// What leaving a block undoes, in the reverse of the order it was recorded.
+#[derive(Clone, Copy)]
+enum Cleanup<'input> {
+    /// A counted variable, which is unlinked.
+    Variable(Index),
+    /// The statements of a `defer` statement, which are generated on every edge
+    /// leaving the block.
+    Defer(&'input [ast::Statement<'input>]),
+}
+
+pub struct IRGenerator<'input, 'ctx> {
+    options: &'input CompileOptions,
     symbol_table: &'input st::SymbolTable<'input>,
+    source: Option<&'input SourceFile<'input>>,
     val_type: BasicTypeEnum<'ctx>,
 
     context: &'ctx Context,
@@ -48,96 +318,547 @@ pub struct IRGenerator<'input, 'ctx> {
 
     functions: IndexMap<Index, FunctionValue<'ctx>>,
     variables: IndexMap<Index, PointerValue<'ctx>>,
+    /// Variables that may hold heap values, see `lifetime::counted_variables`.
+    counted_variables: IndexSet<Index>,
+    /// The block every return of the current function branches to, and the slot
+    /// holding the returned value.
+    exit: Option<(BasicBlock<'ctx>, PointerValue<'ctx>)>,
+    /// Whether the current block already ends with a branch, as after a `return`,
+    /// so that the statements left in it are unreachable.
+    terminated: bool,
+    /// The counted variables defined and the expressions deferred so far in each
+    /// block being generated, the body of the function first, run on every edge
+    /// leaving the block.
+    cleanups: Vec<Vec<Cleanup<'input>>>,
 
     current_function_index: Option<Index>,
+    current_location: Cell<Option<(usize, usize)>>,
+    source_file_name: Cell<Option<PointerValue<'ctx>>>,
+    string_table: RefCell<StringTable<'ctx>>,
+    /// Global of every distinct string constant, which all its uses share.
+    global_strings: RefCell<IndexMap<String, PointerValue<'ctx>>>,
+    /// The `property_key_t` of every distinct property name, which all its access
+    /// sites share so that the runtime interns it once per thread.
+    property_keys: RefCell<IndexMap<String, PointerValue<'ctx>>>,
+    /// Names of the std functions the generated code calls, with the number of
+    /// calls to each.
+    used_builtins: RefCell<IndexMap<String, usize>>,
+    /// The copy of the module `emit` last ran its passes on.
+    emitted_module: RefCell<Option<Module<'ctx>>>,
+    casts: RuntimeCasts<'input>,
 }
 
 impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
-    pub fn generate(
+    pub fn new(
         symbol_table: &'input st::SymbolTable<'input>,
+        source: Option<&'input SourceFile<'input>>,
         context: &'ctx Context,
-        triple: &TargetTriple,
-        optimize: bool,
-        out_file: PathBuf,
-    ) -> Result<(), CompilerError<'input>> {
-        let std_module_content =
-            MemoryBuffer::create_from_memory_range_copy(STD_LIBRARY_CODE, "std");
+        options: &'input CompileOptions,
+    ) -> Result<Self, CompilerError> {
+        let module = load_std_library(context, options)?;
 
-        let module = context.create_module_from_ir(std_module_content).unwrap();
-        let mut ir_generator = IRGenerator {
-            optimize,
+        Ok(IRGenerator {
+            options,
             symbol_table,
+            source,
             val_type: get_val_type(context),
             context,
             module,
             builder: context.create_builder(),
             functions: IndexMap::new(),
             variables: IndexMap::new(),
+            counted_variables: IndexSet::new(),
+            exit: None,
+            terminated: false,
+            cleanups: Vec::new(),
             current_function_index: None,
-        };
-        ir_generator.init()?;
-        ir_generator.compile()?;
-        ir_generator.write_to_file(triple, out_file)?;
+            current_location: Cell::new(None),
+            source_file_name: Cell::new(None),
+            string_table: RefCell::new(StringTable::default()),
+            global_strings: RefCell::new(IndexMap::new()),
+            property_keys: RefCell::new(IndexMap::new()),
+            used_builtins: RefCell::new(IndexMap::new()),
+            emitted_module: RefCell::new(None),
+            casts: RuntimeCasts::new(),
+        })
+    }
+
+    /// Arguments to check against their parameter kinds when the call happens.
+    pub fn set_casts(&mut self, casts: RuntimeCasts<'input>) {
+        self.casts = casts;
+    }
+
+    pub fn generate(&mut self) -> Result<(), CompilerError> {
+        self.counted_variables = lifetime::counted_variables(self.symbol_table);
+
+        self.init()?;
+        self.compile()?;
+        self.finalize_string_table();
+
+        if self.options.inline_functions {
+            self.mark_inline_functions()?;
+        }
 
         Ok(())
     }
 
-    fn write_to_file(
+    pub fn module(&self) -> &Module<'ctx> {
+        &self.module
+    }
+
+    /// The LLVM function generated for the function variable.
+    pub fn function(&self, function_id: &Index) -> Option<FunctionValue<'ctx>> {
+        self.functions.get(function_id).copied()
+    }
+
+    /// Defines a function taking no arguments that calls the function with none,
+    /// so that the JIT can call it through a single type. The function must take
+    /// no required parameters.
+    pub fn define_caller(
+        &mut self,
+        function_id: &Index,
+    ) -> Result<FunctionValue<'ctx>, CompilerError> {
+        let function = self.functions.get(function_id).unwrap();
+        let name = format!("{}.caller", function.get_name().to_string_lossy());
+
+        let caller = self
+            .module
+            .add_function(&name, self.val_type.fn_type(&[], false), None);
+        let block = self.context.append_basic_block(caller, "entry");
+        self.builder.position_at_end(block);
+
+        let v = self.call_without_arguments(function_id)?;
+        self.builder.build_return(Some(&v))?;
+
+        Ok(caller)
+    }
+
+    /// What the generator produced, after the passes `emit` ran on the module.
+    pub fn stats(&self) -> Result<CodeGenStats, CompilerError> {
+        let emitted_module = self.emitted_module.borrow();
+        let module = emitted_module.as_ref().unwrap_or(&self.module);
+
+        let generated_names = self.generated_function_names()?;
+        let generated = generated_names
+            .iter()
+            .filter_map(|name| module.get_function(name))
+            .collect::<Vec<_>>();
+
+        let std_instructions = module
+            .get_functions()
+            .filter(|function| !generated.contains(function))
+            .map(instruction_count)
+            .sum();
+
+        let mut builtin_calls = self
+            .used_builtins
+            .borrow()
+            .iter()
+            .map(|(name, count)| (name.clone(), *count))
+            .collect::<Vec<_>>();
+        builtin_calls.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+
+        Ok(CodeGenStats {
+            functions: generated_names.len(),
+            instructions: generated
+                .iter()
+                .map(|function| instruction_count(*function))
+                .sum(),
+            std_instructions,
+            builtin_calls,
+        })
+    }
+
+    fn create_target_machine(
         &self,
-        triple: &TargetTriple,
-        out_file: PathBuf,
-    ) -> Result<(), CompilerError<'input>> {
+        triple: &target_lexicon::Triple,
+    ) -> Result<TargetMachine, CompilerError> {
+        Target::initialize_all(&InitializationConfig::default());
+
+        let llvm_triple = TargetTriple::create(&triple.to_string());
+        let target = Target::from_triple(&llvm_triple).map_err(|err| {
+            CompilerError::CodeGenError(format!("Unsupported target `{}`: {}", triple, err))
+        })?;
+
+        target
+            .create_target_machine(
+                &llvm_triple,
+                "",
+                "",
+                self.options.opt_level,
+                self.options.get_reloc_mode(),
+                self.options.code_model,
+            )
+            .ok_or_else(|| {
+                CompilerError::CodeGenError("Could not create target machine".to_string())
+            })
+    }
+
+    /// Emits the module for the given target without linking it.
+    pub fn emit(
+        &self,
+        triple: &target_lexicon::Triple,
+        kind: EmitKind,
+    ) -> Result<Vec<u8>, CompilerError> {
         self.module.verify().map_err(|err| {
             CompilerError::CodeGenError(format!("Could not verify module: {}", err))
         })?;
 
-        Target::initialize_all(&InitializationConfig::default());
+        let target_machine = self.create_target_machine(triple)?;
 
-        let optimize_level = if self.optimize {
-            OptimizationLevel::Aggressive
-        } else {
-            OptimizationLevel::None
-        };
-        let target = Target::from_triple(&triple).unwrap();
-        let target_machine = target.create_target_machine(
-            &triple,
-            "",
-            "",
-            optimize_level,
-            RelocMode::Default,
-            CodeModel::Default,
-        );
+        // the passes run on a copy, so that every target starts from the generated code
+        let module = self.module.clone();
 
-        if let Some(target_machine) = target_machine {
-            // println!("{}", self.module.print_to_string().to_str().unwrap());
-            let std_tempfile = tempfile::NamedTempFile::new().unwrap();
+        module.set_triple(&target_machine.get_triple());
+        module.set_data_layout(&target_machine.get_target_data().get_data_layout());
 
-            target_machine
-                .write_to_file(
-                    &self.module,
-                    inkwell::targets::FileType::Object,
-                    std_tempfile.path(),
+        if !self.options.keep_all_std {
+            self.remove_unused_std(&module, &target_machine)?;
+        }
+
+        if self.options.inline_functions {
+            module
+                .run_passes(
+                    "always-inline",
+                    &target_machine,
+                    PassBuilderOptions::create(),
                 )
                 .map_err(|err| {
-                    CompilerError::CodeGenError(format!("Could not write object file: {}", err))
+                    CompilerError::CodeGenError(format!("Could not inline functions: {}", err))
                 })?;
+        }
 
-            std::process::Command::new("gcc")
-                .arg("-Wl,-ld_classic")
-                .arg("-o")
-                .arg(out_file)
-                .arg(std_tempfile.path())
-                .status()
-                .unwrap();
+        if self.options.lto {
+            self.run_lto(&module, &target_machine)?;
+        }
+
+        if let Some(sanitizer) = self.options.sanitizer {
+            self.run_sanitizer(&module, sanitizer, &target_machine)?;
+        }
+
+        let output = match kind {
+            EmitKind::Object => target_machine
+                .write_to_memory_buffer(&module, FileType::Object)
+                .map(|buffer| buffer.as_slice().to_vec())
+                .map_err(|err| {
+                    CompilerError::CodeGenError(format!("Could not emit object code: {}", err))
+                })?,
+            EmitKind::LlvmIr => module.print_to_string().to_bytes().to_vec(),
+            EmitKind::Bitcode => module.write_bitcode_to_memory().as_slice().to_vec(),
+        };
+
+        self.emitted_module.replace(Some(module));
+
+        Ok(output)
+    }
+
+    pub fn write_to_file(
+        &self,
+        triple: &target_lexicon::Triple,
+        out_file: PathBuf,
+    ) -> Result<(), CompilerError> {
+        let object = self.emit(triple, EmitKind::Object)?;
+
+        self.link_object(triple, &object, &out_file)
+    }
+
+    /// Links object code emitted for the given target into an executable.
+    pub fn link_object(
+        &self,
+        triple: &target_lexicon::Triple,
+        object: &[u8],
+        out_file: &Path,
+    ) -> Result<(), CompilerError> {
+        // writing next to the executable keeps the object on the same file system
+        let dir = match &self.options.temp_dir {
+            Some(temp_dir) => temp_dir.as_path(),
+            None => out_file
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new(".")),
+        };
+
+        let temp_path = if self.options.save_temps {
+            None
         } else {
-            return Err(CompilerError::CodeGenError(
-                "Could not create target machine".to_string(),
-            ));
+            let temp_file = tempfile::Builder::new()
+                .prefix(".mini-")
+                .suffix(".o")
+                .tempfile_in(dir)
+                .map_err(|err| {
+                    CompilerError::CodeGenError(format!("Could not create object file: {}", err))
+                })?;
+
+            Some(temp_file.into_temp_path())
+        };
+
+        let object_file = match &temp_path {
+            Some(temp_path) => temp_path.to_path_buf(),
+            None => {
+                let mut file_name = out_file.file_name().unwrap_or_default().to_os_string();
+                file_name.push(".o");
+
+                dir.join(file_name)
+            }
+        };
+
+        fs::write(&object_file, object).map_err(|err| {
+            CompilerError::CodeGenError(format!("Could not write object file: {}", err))
+        })?;
+
+        link(
+            &self.options.linker,
+            &self.options.libraries,
+            triple,
+            self.options.get_reloc_mode(),
+            self.options.sanitizer,
+            &object_file,
+            out_file,
+        )
+    }
+
+    /// Returns a pointer to a null terminated global string. In embedded mode every
+    /// string lives in one private table and is addressed by its offset into it, so
+    /// the object file needs no relocation per string constant.
+    fn global_string(&self, value: &str, name: &str) -> Result<PointerValue<'ctx>, CompilerError> {
+        if !self.options.embedded {
+            if let Some(s) = self.global_strings.borrow().get(value) {
+                return Ok(*s);
+            }
+
+            let s = self
+                .builder
+                .build_global_string_ptr(value, name)?
+                .as_pointer_value();
+            self.global_strings
+                .borrow_mut()
+                .insert(value.to_string(), s);
+
+            return Ok(s);
+        }
+
+        let mut string_table = self.string_table.borrow_mut();
+
+        let offset = match string_table.offsets.get(value) {
+            Some(offset) => *offset,
+            None => {
+                let offset = string_table.data.len() as u64;
+
+                string_table.data.extend_from_slice(value.as_bytes());
+                string_table.data.push(0);
+                string_table.offsets.insert(value.to_string(), offset);
+
+                offset
+            }
+        };
+
+        let placeholder = *string_table.placeholder.get_or_insert_with(|| {
+            self.module.add_global(
+                self.context.i8_type().array_type(0),
+                None,
+                STRING_TABLE_NAME,
+            )
+        });
+
+        let offset = self.context.i64_type().const_int(offset, false);
+        let ptr = unsafe {
+            placeholder
+                .as_pointer_value()
+                .const_gep(self.context.i8_type(), &[offset])
+        };
+
+        Ok(ptr)
+    }
+
+    fn finalize_string_table(&self) {
+        let string_table = self.string_table.borrow();
+
+        if let Some(placeholder) = string_table.placeholder {
+            let data = self.context.const_string(&string_table.data, false);
+
+            let table = self
+                .module
+                .add_global(data.get_type(), None, STRING_TABLE_NAME);
+            table.set_initializer(&data);
+            table.set_constant(true);
+            table.set_linkage(Linkage::Private);
+            table.set_unnamed_addr(true);
+
+            placeholder
+                .as_pointer_value()
+                .replace_all_uses_with(table.as_pointer_value());
+
+            unsafe {
+                placeholder.delete();
+            }
+        }
+    }
+
+    /// Hides the std functions the generated code does not call from the linker, so
+    /// that the ones nothing else calls either are removed with the unused globals.
+    fn remove_unused_std(
+        &self,
+        module: &Module<'ctx>,
+        target_machine: &TargetMachine,
+    ) -> Result<(), CompilerError> {
+        let used_builtins = self.used_builtins.borrow();
+        let generated = self.generated_function_names()?;
+
+        for function in module.get_functions() {
+            let name = function.get_name().to_string_lossy();
+
+            if function.count_basic_blocks() > 0
+                && !used_builtins.contains_key(name.as_ref())
+                && !generated.contains(name.as_ref())
+            {
+                function.set_linkage(Linkage::Internal);
+            }
+        }
+
+        for global in module.get_globals() {
+            if global.get_linkage() == Linkage::External && global.get_initializer().is_some() {
+                global.set_linkage(Linkage::Internal);
+            }
+        }
+
+        module
+            .run_passes("globaldce", target_machine, PassBuilderOptions::create())
+            .map_err(|err| {
+                CompilerError::CodeGenError(format!("Could not remove the unused std: {}", err))
+            })?;
+
+        Ok(())
+    }
+
+    /// Names of the functions generated for the program, which the copies of the
+    /// module `emit` runs its passes on share.
+    fn generated_function_names(&self) -> Result<IndexSet<String>, CompilerError> {
+        let mut names = IndexSet::new();
+
+        for (function_id, function) in &self.functions {
+            if !self.symbol_table.variable(function_id).is_external()? {
+                names.insert(function.get_name().to_string_lossy().into_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Marks the functions of the program the `always-inline` pass inlines: the ones
+    /// decorated with `@inline` and the small ones, unless decorated with
+    /// `@noinline` or calling themselves.
+    fn mark_inline_functions(&self) -> Result<(), CompilerError> {
+        let always_inline = Attribute::get_named_enum_kind_id("alwaysinline");
+        let no_inline = Attribute::get_named_enum_kind_id("noinline");
+
+        for (function_id, function) in &self.functions {
+            let variable = self.symbol_table.variable(function_id);
+
+            if variable.is_external()? || self.symbol_table.main_function == Some(*function_id) {
+                continue;
+            }
+
+            let decorators = variable.get_decorators()?;
+
+            let kind = if decorators.contains("noinline") {
+                no_inline
+            } else if decorators.contains("inline")
+                || (instruction_count(*function) <= INLINE_THRESHOLD
+                    && !self.is_recursive(function_id))
+            {
+                always_inline
+            } else {
+                continue;
+            };
+
+            function.add_attribute(
+                AttributeLoc::Function,
+                self.context.create_enum_attribute(kind, 0),
+            );
         }
 
         Ok(())
     }
 
+    /// Whether the function may call itself, directly or through other functions.
+    fn is_recursive(&self, function_id: &Index) -> bool {
+        let calls = self.symbol_table.calls();
+
+        let mut visited = IndexSet::new();
+        let mut worklist = vec![*function_id];
+
+        while let Some(caller) = worklist.pop() {
+            for callee in calls.get(&caller).into_iter().flatten() {
+                if callee == function_id {
+                    return true;
+                }
+
+                if visited.insert(*callee) {
+                    worklist.push(*callee);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// The user program and the std runtime already live in the same module, so link
+    /// time optimization only has to hide everything but `main` from the linker and
+    /// let the inliner fold the runtime helpers into the generated code.
+    fn run_lto(
+        &self,
+        module: &Module<'ctx>,
+        target_machine: &TargetMachine,
+    ) -> Result<(), CompilerError> {
+        for function in module.get_functions() {
+            if function.count_basic_blocks() > 0
+                && function.get_name().to_bytes() != MAIN_FUNCTION_NAME.as_bytes()
+            {
+                function.set_linkage(Linkage::Internal);
+            }
+        }
+
+        module
+            .run_passes("default<O3>", target_machine, PassBuilderOptions::create())
+            .map_err(|err| {
+                CompilerError::CodeGenError(format!("Could not run LTO passes: {}", err))
+            })?;
+
+        Ok(())
+    }
+
+    /// Instruments every function with a body, those of the std runtime included,
+    /// after the optimizations so that only the remaining memory accesses are checked.
+    fn run_sanitizer(
+        &self,
+        module: &Module<'ctx>,
+        sanitizer: Sanitizer,
+        target_machine: &TargetMachine,
+    ) -> Result<(), CompilerError> {
+        let (attribute, pass) = match sanitizer {
+            Sanitizer::Address => ("sanitize_address", "asan"),
+        };
+
+        let kind = Attribute::get_named_enum_kind_id(attribute);
+
+        for function in module.get_functions() {
+            if function.count_basic_blocks() > 0 {
+                function.add_attribute(
+                    AttributeLoc::Function,
+                    self.context.create_enum_attribute(kind, 0),
+                );
+            }
+        }
+
+        module
+            .run_passes(pass, target_machine, PassBuilderOptions::create())
+            .map_err(|err| {
+                CompilerError::CodeGenError(format!("Could not run sanitizer passes: {}", err))
+            })?;
+
+        Ok(())
+    }
+
     fn current_function(&self) -> (Index, &FunctionValue<'ctx>) {
         let function_id = self.current_function_index.unwrap();
 
@@ -156,48 +877,474 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
     fn get_value_for_variable(
         &self,
         variable_id: &Index,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         let variable = self.symbol_table.variable(variable_id);
 
         match variable {
+            // functions used as values are wrapped with their code
+            st::Variable::Static { .. } if variable.is_function() => {
+                let function = self.functions.get(variable_id).unwrap();
+
+                let code = self.builder.build_pointer_cast(
+                    function.as_global_value().as_pointer_value(),
+                    self.context.i8_type().ptr_type(AddressSpace::default()),
+                    "code",
+                )?;
+
+                self.call_builtin("new_function_val", &[code.into()])
+            }
             st::Variable::Static { .. } => {
                 let ptr = self.variables.get(variable_id).unwrap();
                 let v = self.builder.build_load(self.val_type, *ptr, "temp")?;
 
                 Ok(v)
             }
-            st::Variable::Property { base, property } => {
+            st::Variable::Property { base, property, .. } => {
                 let obj = self.get_value_for_variable(base)?;
 
-                let s = self.builder.build_global_string_ptr(property, "string")?;
-
-                let result_ptr = self
-                    .call_builtin("val_object_get", &[obj.into(), s.as_pointer_value().into()])?
-                    .into_pointer_value();
-
-                Ok(result_ptr.into())
+                self.get_property(obj, property)
             }
             st::Variable::Indexed {
                 base,
                 index: expression,
+                ..
             } => {
                 let obj = self.get_value_for_variable(base)?;
 
-                let i = self.translate_expression(expression)?.into_pointer_value();
+                match self.typed_array_kind(base) {
+                    Some(kind) => self.get_typed_array_item(obj, expression, kind),
+                    None => self.get_index(obj, expression),
+                }
+            }
+        }
+    }
 
-                let result_ptr = self
-                    .call_builtin("val_get", &[obj.into(), i.into()])?
-                    .into_pointer_value();
+    fn get_property(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        property: &str,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let key = self.property_key(property)?;
+        let cache = self.inline_cache();
+
+        let result_ptr = self
+            .call_builtin(
+                "val_object_get_cached",
+                &[obj.into(), key.into(), cache.into()],
+            )?
+            .into_pointer_value();
+
+        Ok(result_ptr.into())
+    }
+
+    fn set_property(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        property: &str,
+        v: BasicValueEnum<'ctx>,
+    ) -> Result<(), CompilerError> {
+        let key = self.property_key(property)?;
+        let cache = self.inline_cache();
+
+        self.call_builtin(
+            "val_object_set_cached",
+            &[obj.into(), key.into(), v.into(), cache.into()],
+        )?;
+
+        Ok(())
+    }
+
+    fn property_key(&self, property: &str) -> Result<PointerValue<'ctx>, CompilerError> {
+        if let Some(key) = self.property_keys.borrow().get(property) {
+            return Ok(*key);
+        }
+
+        let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+        let key_type = self
+            .context
+            .struct_type(&[ptr_type.into(), ptr_type.into()], false);
+        let name = self.global_string(property, "key")?;
+
+        let key = self.module.add_global(key_type, None, "property_key");
+        key.set_initializer(
+            &key_type.const_named_struct(&[name.into(), ptr_type.const_null().into()]),
+        );
+        key.set_linkage(Linkage::Private);
+
+        // keys are interned by the thread that uses them, like shapes
+        if !self.options.embedded {
+            key.set_thread_local(true);
+        }
+
+        let key = key.as_pointer_value();
+        self.property_keys
+            .borrow_mut()
+            .insert(property.to_string(), key);
+
+        Ok(key)
+    }
+
+    /// The `inline_cache_t` of a property access site, in which the runtime keeps
+    /// the shape of the objects the site accessed last.
+    fn inline_cache(&self) -> PointerValue<'ctx> {
+        let cache_type = self.context.i64_type().array_type(3);
+
+        let cache = self.module.add_global(cache_type, None, "cache");
+        cache.set_initializer(&cache_type.const_zero());
+        cache.set_linkage(Linkage::Private);
+
+        // shapes belong to the thread that created them, like the rest of its heap
+        if !self.options.embedded {
+            cache.set_thread_local(true);
+        }
+
+        cache.as_pointer_value()
+    }
+
+    fn get_index(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let i = self.translate_expression(expression)?.into_pointer_value();
+
+        let result_ptr = self
+            .call_builtin("val_get", &[obj.into(), i.into()])?
+            .into_pointer_value();
+
+        Ok(result_ptr.into())
+    }
+
+    fn get_slice(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        start: &'input Option<Box<ast::Expression<'input>>>,
+        end: &'input Option<Box<ast::Expression<'input>>>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        // a left out bound is undefined, which `val_slice` takes as the start or the end
+        let bound = |bound: &'input Option<Box<ast::Expression<'input>>>| match bound {
+            Some(bound) => self.translate_expression(bound),
+            None => Ok(self.undefined().into()),
+        };
+
+        let start = bound(start)?;
+        let end = bound(end)?;
+
+        self.call_builtin("val_slice", &[obj.into(), start.into(), end.into()])
+    }
+
+    /// The kind of the variable if it is declared a typed array, whose items the
+    /// generated code loads and stores in place.
+    fn typed_array_kind(&self, variable_id: &Index) -> Option<ast::VariableKind> {
+        let variable = self.symbol_table.variable(variable_id);
+
+        match variable {
+            st::Variable::Static { kind, .. } if !variable.is_function() => match kind {
+                ast::VariableKind::Float64Array | ast::VariableKind::Int64Array => {
+                    Some(kind.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The word of the value, whose lowest bit is set for integers.
+    fn word(&self, v: PointerValue<'ctx>) -> Result<IntValue<'ctx>, CompilerError> {
+        Ok(self
+            .builder
+            .build_ptr_to_int(v, self.context.i64_type(), "word")?)
+    }
+
+    fn is_int_word(&self, word: IntValue<'ctx>) -> Result<IntValue<'ctx>, CompilerError> {
+        let i64_type = self.context.i64_type();
+        let tag = self
+            .builder
+            .build_and(word, i64_type.const_int(1, false), "tag")?;
+
+        Ok(self.builder.build_int_compare(
+            IntPredicate::NE,
+            tag,
+            i64_type.const_zero(),
+            "is_int",
+        )?)
+    }
+
+    /// Whether the word points to a value on the heap, being neither tagged nor null.
+    fn is_heap_word(&self, word: IntValue<'ctx>) -> Result<IntValue<'ctx>, CompilerError> {
+        let i64_type = self.context.i64_type();
+        let tag = self
+            .builder
+            .build_and(word, i64_type.const_int(0x7, false), "tag")?;
+        let is_untagged = self.builder.build_int_compare(
+            IntPredicate::EQ,
+            tag,
+            i64_type.const_zero(),
+            "is_untagged",
+        )?;
+        let is_not_null = self.builder.build_int_compare(
+            IntPredicate::NE,
+            word,
+            i64_type.const_zero(),
+            "is_not_null",
+        )?;
+
+        Ok(self
+            .builder
+            .build_and(is_untagged, is_not_null, "is_heap")?)
+    }
+
+    /// The fields of a `val_t` on the heap: its type, its reference count, its
+    /// neighbours and the given fields of its union.
+    fn val_layout(&self, fields: &[BasicTypeEnum<'ctx>]) -> StructType<'ctx> {
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+
+        let mut layout = vec![
+            i32_type.into(),
+            i32_type.into(),
+            ptr_type.into(),
+            ptr_type.into(),
+        ];
+        layout.extend_from_slice(fields);
+
+        self.context.struct_type(&layout, false)
+    }
+
+    /// Loads a field of the heap value laid out as given.
+    fn load_field(
+        &self,
+        v: PointerValue<'ctx>,
+        layout: StructType<'ctx>,
+        index: u32,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let ptr = self.builder.build_pointer_cast(
+            v,
+            layout.ptr_type(AddressSpace::default()),
+            "layout",
+        )?;
+        let field_type = layout.get_field_type_at_index(index).unwrap();
+        let field = self.builder.build_struct_gep(layout, ptr, index, "field")?;
+
+        Ok(self.builder.build_load(field_type, field, "field")?)
+    }
+
+    fn has_type(
+        &self,
+        v: PointerValue<'ctx>,
+        layout: StructType<'ctx>,
+        val_type: u64,
+    ) -> Result<IntValue<'ctx>, CompilerError> {
+        let t = self.load_field(v, layout, 0)?.into_int_value();
+
+        Ok(self.builder.build_int_compare(
+            IntPredicate::EQ,
+            t,
+            self.context.i32_type().const_int(val_type, false),
+            "has_type",
+        )?)
+    }
 
-                Ok(result_ptr.into())
+    /// The layout of a typed array, with `typed_array_t` in its union, and the
+    /// type of its items.
+    fn typed_array_layout(
+        &self,
+        kind: &ast::VariableKind,
+    ) -> (StructType<'ctx>, BasicTypeEnum<'ctx>, u64) {
+        let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+        let layout = self.val_layout(&[self.context.i64_type().into(), ptr_type.into()]);
+
+        match kind {
+            ast::VariableKind::Float64Array => {
+                (layout, self.context.f64_type().into(), VAL_F64_ARRAY)
+            }
+            _ => (layout, self.context.i64_type().into(), VAL_I64_ARRAY),
+        }
+    }
+
+    /// Returns the pointer to the item at the index, positioning the builder in a
+    /// block reached when `is_valid` holds, the value is a typed array of the kind
+    /// and the index is an integer in its bounds. The other cases go to `else_block`.
+    fn typed_array_item_ptr(
+        &self,
+        obj: PointerValue<'ctx>,
+        i: PointerValue<'ctx>,
+        is_valid: IntValue<'ctx>,
+        kind: &ast::VariableKind,
+        else_block: BasicBlock<'ctx>,
+    ) -> Result<PointerValue<'ctx>, CompilerError> {
+        let function = *self.current_function().1;
+        let (layout, item_type, val_type) = self.typed_array_layout(kind);
+
+        let check_block = self
+            .context
+            .append_basic_block(function, "typed_array.check");
+        let item_block = self
+            .context
+            .append_basic_block(function, "typed_array.item");
+
+        // only a heap value can be read as a typed array
+        let index = self.word(i)?;
+        let is_heap = self.is_heap_word(self.word(obj)?)?;
+        let is_int = self.is_int_word(index)?;
+        let is_valid = self.builder.build_and(is_valid, is_heap, "is_valid")?;
+        let is_valid = self.builder.build_and(is_valid, is_int, "is_valid")?;
+        self.builder
+            .build_conditional_branch(is_valid, check_block, else_block)?;
+
+        // a negative index is out of bounds as an unsigned one
+        self.builder.position_at_end(check_block);
+        let has_type = self.has_type(obj, layout, val_type)?;
+        let n = self.builder.build_right_shift(
+            index,
+            self.context.i64_type().const_int(1, false),
+            true,
+            "index",
+        )?;
+        let len = self.load_field(obj, layout, 4)?.into_int_value();
+        let is_in_bounds =
+            self.builder
+                .build_int_compare(IntPredicate::ULT, n, len, "is_in_bounds")?;
+        let is_valid = self.builder.build_and(has_type, is_in_bounds, "is_valid")?;
+        self.builder
+            .build_conditional_branch(is_valid, item_block, else_block)?;
+
+        self.builder.position_at_end(item_block);
+        let data = self.load_field(obj, layout, 5)?.into_pointer_value();
+        let data = self.builder.build_pointer_cast(
+            data,
+            item_type.ptr_type(AddressSpace::default()),
+            "items",
+        )?;
+
+        Ok(unsafe {
+            self.builder
+                .build_in_bounds_gep(item_type, data, &[n], "item")?
+        })
+    }
+
+    /// `items[i]` of a typed array, boxing the item it loads.
+    fn get_typed_array_item(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        expression: &'input ast::Expression<'input>,
+        kind: ast::VariableKind,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let obj = obj.into_pointer_value();
+        let i = self.translate_expression(expression)?.into_pointer_value();
+
+        let function = *self.current_function().1;
+        let slow_block = self.context.append_basic_block(function, "typed_array.get");
+        let end_block = self.context.append_basic_block(function, "typed_array.end");
+
+        let is_valid = self.context.bool_type().const_int(1, false);
+        let item = self.typed_array_item_ptr(obj, i, is_valid, &kind, slow_block)?;
+
+        let (_, item_type, _) = self.typed_array_layout(&kind);
+        let item = self.builder.build_load(item_type, item, "item")?;
+        let loaded = match kind {
+            ast::VariableKind::Float64Array => {
+                self.call_builtin("new_float_val", &[item.into()])?
+            }
+            _ => self.call_builtin("new_int_val", &[item.into()])?,
+        };
+        let loaded_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+
+        // the runtime raises the errors of the other cases
+        self.builder.position_at_end(slow_block);
+        let got = self.call_builtin("val_get", &[obj.into(), i.into()])?;
+        let got_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+        let phi = self.builder.build_phi(self.val_type, "item")?;
+        phi.add_incoming(&[(&loaded, loaded_block), (&got, got_block)]);
+
+        Ok(phi.as_basic_value())
+    }
+
+    /// `items[i] = v` of a typed array, storing the number of the value in place.
+    fn set_typed_array_item(
+        &self,
+        obj: BasicValueEnum<'ctx>,
+        i: PointerValue<'ctx>,
+        v: BasicValueEnum<'ctx>,
+        kind: ast::VariableKind,
+    ) -> Result<(), CompilerError> {
+        let obj = obj.into_pointer_value();
+        let v_ptr = v.into_pointer_value();
+
+        let function = *self.current_function().1;
+        let slow_block = self.context.append_basic_block(function, "typed_array.set");
+        let end_block = self.context.append_basic_block(function, "typed_array.end");
+
+        let word = self.word(v_ptr)?;
+        let is_int = self.is_int_word(word)?;
+        let one = self.context.i64_type().const_int(1, false);
+
+        match kind {
+            ast::VariableKind::Int64Array => {
+                let item = self.typed_array_item_ptr(obj, i, is_int, &kind, slow_block)?;
+
+                let n = self.builder.build_right_shift(word, one, true, "n")?;
+                self.builder.build_store(item, n)?;
+            }
+            _ => {
+                // integers are converted, and floats are unboxed
+                let is_number =
+                    self.builder
+                        .build_or(is_int, self.is_heap_word(word)?, "is_number")?;
+                let item = self.typed_array_item_ptr(obj, i, is_number, &kind, slow_block)?;
+
+                let int_block = self.context.append_basic_block(function, "typed_array.int");
+                let float_block = self
+                    .context
+                    .append_basic_block(function, "typed_array.float");
+                let store_block = self
+                    .context
+                    .append_basic_block(function, "typed_array.store");
+                self.builder
+                    .build_conditional_branch(is_int, int_block, float_block)?;
+
+                self.builder.position_at_end(int_block);
+                let n = self.builder.build_right_shift(word, one, true, "n")?;
+                let converted = self.builder.build_signed_int_to_float(
+                    n,
+                    self.context.f64_type(),
+                    "converted",
+                )?;
+                self.builder.build_unconditional_branch(store_block)?;
+
+                self.builder.position_at_end(float_block);
+                let layout = self.val_layout(&[self.context.f64_type().into()]);
+                let unboxed = self.load_field(v_ptr, layout, 4)?;
+                let is_float = self.has_type(v_ptr, layout, VAL_FLOAT)?;
+                self.builder
+                    .build_conditional_branch(is_float, store_block, slow_block)?;
+
+                self.builder.position_at_end(store_block);
+                let phi = self.builder.build_phi(self.context.f64_type(), "f64")?;
+                phi.add_incoming(&[(&converted, int_block), (&unboxed, float_block)]);
+                self.builder.build_store(item, phi.as_basic_value())?;
             }
         }
+
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(slow_block);
+        self.call_builtin("val_set", &[obj.into(), i.into(), v.into()])?;
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+
+        Ok(())
     }
 
     fn get_value_for_identifier(
         &self,
         identifier: &'input ast::VariableIdentifier<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         let variable_id = self.symbol_table.identifier_ref(identifier);
 
         self.get_value_for_variable(variable_id)
@@ -207,43 +1354,46 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
         &self,
         variable_id: &Index,
         v: BasicValueEnum<'ctx>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         let variable = self.symbol_table.variable(variable_id);
 
         match variable {
             st::Variable::Static { .. } => {
                 let ptr = self.variables.get(variable_id).unwrap();
 
-                let old_value = self.builder.build_load(self.val_type, *ptr, "tmp")?;
-                self.call_builtin("unlink_val", &[old_value.into()])?;
+                if self.counted_variables.contains(variable_id) {
+                    let old_value = self.builder.build_load(self.val_type, *ptr, "tmp")?;
+                    self.call_builtin("unlink_val", &[old_value.into()])?;
 
-                self.call_builtin("link_val", &[v.into()])?;
+                    self.call_builtin("link_val", &[v.into()])?;
+                }
 
                 self.builder.build_store(*ptr, v)?;
 
                 Ok(v)
             }
-            st::Variable::Property { base, property } => {
+            st::Variable::Property { base, property, .. } => {
                 let obj = self.get_value_for_variable(base)?;
 
-                let s = self.builder.build_global_string_ptr(property, "string")?;
-
-                self.call_builtin(
-                    "val_object_set",
-                    &[obj.into(), s.as_pointer_value().into(), v.into()],
-                )?;
+                self.set_property(obj, property, v)?;
 
                 Ok(v)
             }
             st::Variable::Indexed {
                 base,
                 index: expression,
+                ..
             } => {
                 let obj = self.get_value_for_variable(base)?;
 
                 let i = self.translate_expression(expression)?.into_pointer_value();
 
-                self.call_builtin("val_set", &[obj.into(), i.into(), v.into()])?;
+                match self.typed_array_kind(base) {
+                    Some(kind) => self.set_typed_array_item(obj, i, v, kind)?,
+                    None => {
+                        self.call_builtin("val_set", &[obj.into(), i.into(), v.into()])?;
+                    }
+                }
 
                 Ok(v)
             }
@@ -254,13 +1404,13 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
         &self,
         identifier: &'input ast::VariableIdentifier<'input>,
         v: BasicValueEnum<'ctx>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         let variable_id = self.symbol_table.identifier_ref(identifier);
 
         self.set_value_for_variable(variable_id, v)
     }
 
-    fn init(&mut self) -> Result<(), CompilerError<'input>> {
+    fn init(&mut self) -> Result<(), CompilerError> {
         for variable_id in self.symbol_table.variables() {
             let variable = self.symbol_table.variable(&variable_id);
 
@@ -275,23 +1425,45 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
         Ok(())
     }
 
+    /// The symbol of a function, named after it and the functions it is nested in.
+    /// Functions of the same name in different blocks get a numbered suffix.
+    fn function_symbol(&self, function_id: &Index) -> Result<String, CompilerError> {
+        let name = format!(
+            "mini.{}",
+            self.symbol_table.function_path(function_id)?.join(".")
+        );
+
+        let mut symbol = name.clone();
+        let mut index = 1;
+
+        while self.module.get_function(&symbol).is_some() {
+            symbol = format!("{}.{}", name, index);
+            index += 1;
+        }
+
+        Ok(symbol)
+    }
+
     fn init_function(
         &self,
         function_variable_id: Index,
-    ) -> Result<FunctionValue<'ctx>, CompilerError<'input>> {
+    ) -> Result<FunctionValue<'ctx>, CompilerError> {
         let function = self.symbol_table.variable(&function_variable_id);
+        let is_external = function.is_external()?;
 
         let func_name = if self.symbol_table.main_function.unwrap() == function_variable_id {
             MAIN_FUNCTION_NAME.to_owned()
-        } else if function.is_external() {
-            function.get_name().to_owned()
-        } else {
+        } else if is_external {
+            function.get_name()?.to_owned()
+        } else if self.options.opaque_symbols {
             new_function_label()
+        } else {
+            self.function_symbol(&function_variable_id)?
         };
 
         let linkage = if self.symbol_table.main_function.unwrap() == function_variable_id {
             Linkage::External
-        } else if function.is_external() {
+        } else if is_external {
             Linkage::ExternalWeak
         } else {
             Linkage::External
@@ -301,24 +1473,44 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
             return Ok(self.module.get_function(&func_name).unwrap());
         }
 
-        if let ast::VariableKind::Function { parameters, .. } = function.get_kind() {
-            let parameters = vec![self.val_type.as_basic_type_enum()]
+        if let ast::VariableKind::Function {
+            parameters,
+            return_kind,
+        } = function.get_kind()?
+        {
+            let parameters = parameters
                 .iter()
-                .cycle()
-                .take(parameters.len())
-                .map(|t| (*t).into())
+                .map(|parameter| {
+                    if is_external && !parameter.is_rest && parameter.sub_kind.is_int32() {
+                        self.context.i32_type().into()
+                    } else {
+                        self.val_type.into()
+                    }
+                })
                 .collect::<Vec<_>>();
 
-            let fn_type = self.val_type.fn_type(parameters.as_slice(), false);
+            let is_main = self.symbol_table.main_function == Some(function_variable_id);
+
+            // `main` returns the exit status of the process
+            let fn_type = if (is_external && return_kind.is_int32()) || is_main {
+                self.context
+                    .i32_type()
+                    .fn_type(parameters.as_slice(), false)
+            } else {
+                self.val_type.fn_type(parameters.as_slice(), false)
+            };
             let fn_value = self.module.add_function(&func_name, fn_type, Some(linkage));
 
             Ok(fn_value)
         } else {
-            unreachable!()
+            Err(CompilerError::Internal(
+                Some(function.get_location()),
+                format!("`{}` is not a function", function.get_name()?),
+            ))
         }
     }
 
-    fn compile(&mut self) -> Result<(), CompilerError<'input>> {
+    fn compile(&mut self) -> Result<(), CompilerError> {
         let keys = self
             .functions
             .iter()
@@ -328,7 +1520,7 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
         for function_id in keys {
             let function_variable = self.symbol_table.variable(&function_id);
 
-            if !function_variable.is_external() {
+            if !function_variable.is_external()? {
                 self.visit_function(&function_id)?;
             }
         }
@@ -336,106 +1528,280 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
         Ok(())
     }
 
+    fn set_location(&self, location: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        self.current_location
+            .replace(location.or(self.current_location.get()))
+    }
+
+    fn put_source_location(&self) -> Result<(), CompilerError> {
+        let (source, (start, _)) = match (self.source, self.current_location.get()) {
+            (Some(source), Some(location)) => (source, location),
+            _ => return Ok(()),
+        };
+
+        let file_name = match self.source_file_name.get() {
+            Some(file_name) => file_name,
+            None => {
+                let file_name = self.global_string(source.name, "file")?;
+                self.source_file_name.set(Some(file_name));
+
+                file_name
+            }
+        };
+
+        let line = self
+            .context
+            .i64_type()
+            .const_int(source.line(start) as u64, false);
+
+        self.use_builtin("set_source_location");
+
+        let function = self.module.get_function("set_source_location").unwrap();
+        self.builder
+            .build_call(function, &[file_name.into(), line.into()], "tmp")?;
+
+        Ok(())
+    }
+
+    /// The value of a word of the runtime that is not a pointer, such as `null`.
+    fn immediate(&self, word: u64) -> PointerValue<'ctx> {
+        self.context
+            .i64_type()
+            .const_int(word, false)
+            .const_to_pointer(self.val_type.into_pointer_type())
+    }
+
+    fn undefined(&self) -> PointerValue<'ctx> {
+        self.immediate(UNDEFINED_WORD)
+    }
+
+    fn null(&self) -> PointerValue<'ctx> {
+        self.immediate(NULL_WORD)
+    }
+
+    fn use_builtin(&self, name: &str) {
+        *self
+            .used_builtins
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default() += 1;
+    }
+
     fn call_builtin(
         &self,
         name: &'input str,
         args: &[BasicMetadataValueEnum<'ctx>],
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        self.put_source_location()?;
+
+        if self.options.trace_runtime {
+            self.trace_call(name, args)?;
+        }
+
+        self.use_builtin(name);
+
         let function = self.module.get_function(name).unwrap();
 
         let v = self
             .builder
             .build_call(function, args, "tmp")?
-            .as_any_value_enum()
-            .into_pointer_value();
+            .try_as_basic_value()
+            .left()
+            .unwrap();
 
-        Ok(v.into())
+        Ok(v)
     }
 
-    fn visit_function(
-        &mut self,
-        function_variable_id: &Index,
-    ) -> Result<(), CompilerError<'input>> {
+    /// Logs the call with the types of its arguments, the values among them are
+    /// inspected by the runtime when the call happens.
+    fn trace_call(
+        &self,
+        name: &str,
+        args: &[BasicMetadataValueEnum<'ctx>],
+    ) -> Result<(), CompilerError> {
+        let mut kinds = String::new();
+        let mut trace_args = vec![
+            self.global_string(name, "trace")?.into(),
+            BasicMetadataValueEnum::from(self.val_type.const_zero()),
+        ];
+
+        for arg in args {
+            let kind = match arg {
+                BasicMetadataValueEnum::IntValue(v) if v.get_type().get_bit_width() == 1 => 'b',
+                BasicMetadataValueEnum::IntValue(_) => 'i',
+                BasicMetadataValueEnum::FloatValue(_) => 'f',
+                // the rest of the constants are strings, functions, caches and literal slots
+                BasicMetadataValueEnum::PointerValue(v)
+                    if !v.is_const() || *v == self.undefined() || *v == self.null() =>
+                {
+                    'v'
+                }
+                _ => 'p',
+            };
+
+            if kind == 'v' {
+                trace_args.push(*arg);
+            }
+
+            kinds.push(kind);
+        }
+
+        trace_args[1] = self.global_string(&kinds, "kinds")?.into();
+
+        self.use_builtin("trace_call");
+
+        let function = self.module.get_function("trace_call").unwrap();
+        self.builder.build_call(function, &trace_args, "tmp")?;
+
+        Ok(())
+    }
+
+    fn visit_function(&mut self, function_variable_id: &Index) -> Result<(), CompilerError> {
         self.current_function_index = Some(function_variable_id.to_owned());
 
         let scope = self.symbol_table.function_scope(function_variable_id);
-        let function = self.functions.get(function_variable_id).unwrap();
+        let function = *self.functions.get(function_variable_id).unwrap();
 
-        let basic_block = self.context.append_basic_block(*function, "entry");
+        let basic_block = self.context.append_basic_block(function, "entry");
         self.builder.position_at_end(basic_block);
 
         {
+            if self.options.debug_leaks
+                && self.symbol_table.main_function == Some(*function_variable_id)
+            {
+                self.call_builtin("debug_leaks", &[])?;
+            }
+
+            self.cleanups = vec![Vec::new()];
             self.define_variables()?;
 
+            let is_main = self.symbol_table.main_function == Some(*function_variable_id);
+            let return_type = if is_main {
+                self.context.i32_type().as_basic_type_enum()
+            } else {
+                self.val_type.as_basic_type_enum()
+            };
+
+            let return_slot = self.builder.build_alloca(return_type, "return")?;
+            let exit_block = self.context.append_basic_block(function, "exit");
+            self.exit = Some((exit_block, return_slot));
+            self.terminated = false;
+
             if let Some(statements) = scope.statements {
                 self.visit_statements(statements)?;
             }
 
-            self.put_return(None, true)?;
+            if !self.terminated {
+                match (
+                    &self.symbol_table.test_functions,
+                    self.symbol_table.entry_function,
+                ) {
+                    (Some(tests), _) if is_main => {
+                        let v = self.run_tests(tests)?;
+                        self.put_return_value(v)?;
+                    }
+                    (_, Some(entry_id)) if is_main => {
+                        let v = self.call_without_arguments(&entry_id)?;
+                        self.put_return_value(v)?;
+                    }
+                    _ => self.put_return(None)?,
+                }
+            }
+
+            // the returns ran the cleanups before branching here
+            self.builder.position_at_end(exit_block);
+
+            let v = self
+                .builder
+                .build_load(return_type, return_slot, "return")?;
+            self.builder.build_return(Some(&v))?;
+
+            self.exit = None;
+            self.cleanups.clear();
         }
 
         Ok(())
     }
 
-    fn define_variables(&mut self) -> Result<(), CompilerError<'input>> {
+    fn define_variables(&mut self) -> Result<(), CompilerError> {
         let (function_variable_id, _) = self.current_function();
 
-        let scope = self.symbol_table.function_scope(&function_variable_id);
-
         let mut parameter_index: u32 = 0;
 
-        for variable_id in scope.variables.values() {
-            let variable = self.symbol_table.variable(variable_id);
+        for variable_id in self.symbol_table.function_variables(&function_variable_id) {
+            let variable = self.symbol_table.variable(&variable_id);
 
             if variable.is_function() {
                 continue;
             }
 
             if !variable.is_static() {
-                unreachable!("Only static variables are supported")
+                return Err(CompilerError::Internal(
+                    Some(variable.get_location()),
+                    "function scope has a property or an index variable".to_string(),
+                ));
             }
 
             let alloca = self
                 .builder
-                .build_alloca(self.val_type, variable.get_name())?;
+                .build_alloca(self.val_type, variable.get_name()?)?;
 
-            self.variables.insert(*variable_id, alloca);
+            self.variables.insert(variable_id, alloca);
 
-            if variable.is_parameter() {
+            if variable.is_parameter()? {
                 let (_, function) = self.current_function();
 
                 let v = function.get_nth_param(parameter_index).unwrap();
                 self.builder.build_store(alloca, v)?;
 
-                self.call_builtin("link_val", &[v.into()])?;
+                if self.counted_variables.contains(&variable_id) {
+                    self.call_builtin("link_val", &[v.into()])?;
+                    self.cleanups
+                        .last_mut()
+                        .unwrap()
+                        .push(Cleanup::Variable(variable_id));
+                }
 
                 parameter_index += 1;
             } else {
-                let v = self.val_type.const_zero();
-
-                self.builder.build_store(alloca, v)?;
+                self.builder.build_store(alloca, self.undefined())?;
             }
         }
 
         Ok(())
     }
 
-    fn clear_variables(&mut self) -> Result<(), CompilerError<'input>> {
-        let (function_variable_id, _) = self.current_function();
+    /// Runs the cleanups of a block, the last recorded first, so that deferred
+    /// statements still see the variables defined before them.
+    fn run_cleanups(&mut self, cleanups: &[Cleanup<'input>]) -> Result<(), CompilerError> {
+        for cleanup in cleanups.iter().rev() {
+            match cleanup {
+                Cleanup::Variable(variable_id) => {
+                    let ptr = self.variables.get(variable_id).unwrap();
 
-        let scope = self.symbol_table.function_scope(&function_variable_id);
+                    let v = self.builder.build_load(self.val_type, *ptr, "tmp")?;
+                    self.call_builtin("unlink_val", &[v.into()])?;
+                }
+                Cleanup::Defer(statements) => self.visit_block(statements)?,
+            }
+        }
 
-        for variable_id in scope.variables.values() {
-            let variable = self.symbol_table.variable(variable_id);
+        Ok(())
+    }
 
-            if variable.is_function() {
-                continue;
-            }
+    /// Generates the statements of an `if` branch or a `defer`, running the cleanups
+    /// they record when falling through to the statements after them.
+    fn visit_block(
+        &mut self,
+        statements: &'input [ast::Statement<'input>],
+    ) -> Result<(), CompilerError> {
+        self.cleanups.push(Vec::new());
+        self.visit_statements(statements)?;
 
-            let ptr = self.variables.get(variable_id).unwrap();
+        let cleanups = self.cleanups.pop().unwrap();
 
-            let v = self.builder.build_load(self.val_type, *ptr, "tmp")?;
-            self.call_builtin("unlink_val", &[v.into()])?;
+        if !self.terminated {
+            self.run_cleanups(&cleanups)?;
         }
 
         Ok(())
@@ -444,8 +1810,13 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
     fn visit_statements(
         &mut self,
         statements: &'input [ast::Statement<'input>],
-    ) -> Result<(), CompilerError<'input>> {
+    ) -> Result<(), CompilerError> {
         for statement in statements.iter() {
+            // nothing runs after a return, so no block is started for it
+            if self.terminated {
+                break;
+            }
+
             self.visit_statement(statement)?;
         }
 
@@ -455,35 +1826,125 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
     fn visit_statement(
         &mut self,
         statement: &'input ast::Statement<'input>,
-    ) -> Result<(), CompilerError<'input>> {
+    ) -> Result<(), CompilerError> {
+        self.set_location(statement.get_location());
+
         match statement {
             ast::Statement::ReturnStatement { expression, .. } => {
-                self.put_return(expression.as_ref(), false)?;
+                self.put_return(expression.as_ref())?;
             }
 
             ast::Statement::ExpressionStatement { expression, .. } => {
                 self.translate_expression(expression)?;
             }
 
+            ast::Statement::DeferStatement { statements, .. } => {
+                self.cleanups
+                    .last_mut()
+                    .unwrap()
+                    .push(Cleanup::Defer(statements));
+            }
+
             ast::Statement::DefinitionStatement {
                 definition,
                 expression,
                 ..
             } => {
-                let ptr = self.get_pointer_for_definition(definition);
+                let ptr = *self.get_pointer_for_definition(definition);
                 let v = if let Some(expression) = expression {
                     self.translate_expression(expression)?
                 } else {
-                    self.val_type.const_zero()
+                    self.undefined().into()
                 };
 
-                self.call_builtin("link_val", &[v.into()])?;
+                let variable_id = self.symbol_table.definition_ref(definition);
 
-                self.builder.build_store(*ptr, v)?;
+                if self.counted_variables.contains(variable_id) {
+                    self.call_builtin("link_val", &[v.into()])?;
+                    self.cleanups
+                        .last_mut()
+                        .unwrap()
+                        .push(Cleanup::Variable(*variable_id));
+                }
+
+                self.builder.build_store(ptr, v)?;
+            }
+
+            ast::Statement::IfStatement {
+                condition,
+                statements,
+                else_statements,
+                ..
+            } => {
+                // a constant condition only emits the branch it takes
+                if let Some(value) = consteval::evaluate(self.symbol_table, condition) {
+                    if value.is_truthy() {
+                        self.visit_block(statements)?;
+                    } else if let Some(else_statements) = else_statements {
+                        self.visit_block(else_statements)?;
+                    }
+
+                    return Ok(());
+                }
+
+                let function = *self.current_function().1;
+
+                let v = self.translate_expression(condition)?;
+                let condition = self
+                    .call_builtin("val_to_bool", &[v.into()])?
+                    .into_int_value();
+
+                let then_block = self.context.append_basic_block(function, "then");
+                let else_block = self.context.append_basic_block(function, "else");
+
+                self.builder
+                    .build_conditional_branch(condition, then_block, else_block)?;
+
+                // the blocks the branches end in, unless they return
+                let mut open_blocks = Vec::new();
+
+                self.builder.position_at_end(then_block);
+                self.visit_block(statements)?;
+                if !self.terminated {
+                    open_blocks.push(self.builder.get_insert_block().unwrap());
+                }
+
+                self.terminated = false;
+                self.builder.position_at_end(else_block);
+                if let Some(else_statements) = else_statements {
+                    self.visit_block(else_statements)?;
+                }
+                if !self.terminated {
+                    open_blocks.push(self.builder.get_insert_block().unwrap());
+                }
+
+                // the statements after the `if` are only reached through a branch
+                // that does not return
+                if open_blocks.is_empty() {
+                    return Ok(());
+                }
+
+                let end_block = self.context.append_basic_block(function, "end");
+
+                for block in open_blocks {
+                    self.builder.position_at_end(block);
+                    self.builder.build_unconditional_branch(end_block)?;
+                }
+
+                self.terminated = false;
+                self.builder.position_at_end(end_block);
             }
 
             ast::Statement::FunctionStatement { .. } => {} // functions are handled in visit_function
 
+            ast::Statement::MacroStatement { location, .. }
+            | ast::Statement::MacroCallStatement { location, .. } => {
+                return Err(CompilerError::Internal(
+                    Some(*location),
+                    "macro reached, it should have been expanded".to_string(),
+                ));
+            }
+
             ast::Statement::EmptyStatement => {}
         }
 
@@ -493,7 +1954,7 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
     fn translate_binary_expression(
         &self,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         if let ast::Expression::BinaryExpression {
             operator,
             left,
@@ -528,14 +1989,14 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
 
             Ok(result.into())
         } else {
-            unreachable!()
+            Err(unexpected_expression(expression))
         }
     }
 
     fn translate_unary_expression(
         &self,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         if let ast::Expression::UnaryExpression {
             operator,
             expression: e,
@@ -556,50 +2017,234 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
 
             Ok(result.into())
         } else {
-            unreachable!()
+            Err(unexpected_expression(expression))
         }
     }
 
-    fn translate_object_expression(
+    /// Builds the array or object literal, translating its items with the given
+    /// function.
+    fn translate_literal(
         &self,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
-        if let ast::Expression::ObjectExpression { properties, .. } = expression {
-            let result = self
-                .call_builtin("new_object_val", &[])?
-                .into_pointer_value();
+        translate_item: fn(
+            &Self,
+            &'input ast::Expression<'input>,
+        ) -> Result<BasicValueEnum<'ctx>, CompilerError>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        match expression {
+            ast::Expression::ArrayExpression { items, .. } => {
+                let array_size = self.context.i64_type().const_int(items.len() as u64, false);
+
+                let array = self
+                    .call_builtin("new_array_val", &[array_size.into()])?
+                    .into_pointer_value();
 
-            for (key, e) in properties.iter() {
-                let k = self.builder.build_global_string_ptr(key, "key")?;
+                for v in items.iter() {
+                    let v = translate_item(self, v)?;
+                    self.call_builtin("val_array_push", &[array.into(), v.into()])?;
+                }
 
-                let v = self.translate_expression(e)?;
+                Ok(array.into())
+            }
 
-                self.call_builtin(
-                    "val_object_set",
-                    &[result.into(), k.as_pointer_value().into(), v.into()],
-                )?;
+            ast::Expression::ObjectExpression { properties, .. } => {
+                let result = self
+                    .call_builtin("new_object_val", &[])?
+                    .into_pointer_value();
+
+                for (key, e) in properties.iter() {
+                    let v = translate_item(self, e)?;
+
+                    self.set_property(result.into(), key, v)?;
+                }
+
+                Ok(result.into())
             }
 
-            Ok(result.into())
-        } else {
-            unreachable!()
+            _ => Err(unexpected_expression(expression)),
+        }
+    }
+
+    /// A copy of the constant literal, which the first evaluation in each thread
+    /// builds into a slot the later ones copy from, instead of building it anew.
+    fn translate_pooled_literal(
+        &self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        // the nested literals are part of the pooled one
+        fn translate_constant<'input, 'ctx>(
+            generator: &IRGenerator<'input, 'ctx>,
+            expression: &'input ast::Expression<'input>,
+        ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+            match expression {
+                ast::Expression::ArrayExpression { .. }
+                | ast::Expression::ObjectExpression { .. } => {
+                    generator.translate_literal(expression, translate_constant)
+                }
+                _ => generator.translate_expression(expression),
+            }
+        }
+
+        let slot = self.module.add_global(self.val_type, None, "literal");
+        slot.set_initializer(&self.val_type.const_zero());
+        slot.set_linkage(Linkage::Private);
+
+        // values belong to the heap of the thread that created them
+        if !self.options.embedded {
+            slot.set_thread_local(true);
+        }
+
+        let slot = slot.as_pointer_value();
+        let function = *self.current_function().1;
+
+        let pooled = self
+            .call_builtin("val_pooled", &[slot.into()])?
+            .into_pointer_value();
+        let pooled_block = self.builder.get_insert_block().unwrap();
+
+        let build_block = self.context.append_basic_block(function, "literal");
+        let end_block = self.context.append_basic_block(function, "pooled");
+
+        let is_built = self.builder.build_is_not_null(pooled, "is_built")?;
+        self.builder
+            .build_conditional_branch(is_built, end_block, build_block)?;
+
+        self.builder.position_at_end(build_block);
+        let v = translate_constant(self, expression)?;
+        let built = self
+            .call_builtin("val_pool", &[slot.into(), v.into()])?
+            .into_pointer_value();
+        let built_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+
+        self.builder.position_at_end(end_block);
+        let phi = self.builder.build_phi(self.val_type, "literal")?;
+        phi.add_incoming(&[(&pooled, pooled_block), (&built, built_block)]);
+
+        Ok(phi.as_basic_value())
+    }
+
+    fn translate_argument(
+        &self,
+        argument: &'input ast::Expression<'input>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let v = self.translate_expression(argument)?;
+
+        match self.casts.get(&ByAddress(argument)) {
+            Some(cast) => {
+                let schema = self.global_string(&cast.kind.get_schema(), "schema")?;
+                let path = self.global_string(&cast.path, "string")?;
+
+                self.call_builtin("val_assert_type", &[v.into(), schema.into(), path.into()])
+            }
+            None => Ok(v),
+        }
+    }
+
+    /// Links the body of an `__llvm_ir` call as a function of its operands and
+    /// calls it.
+    fn translate_inline_ir(
+        &self,
+        location: (usize, usize),
+        arguments: &'input [ast::Expression<'input>],
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let body = match arguments.first() {
+            Some(ast::Expression::ConstantExpression {
+                value: ast::Constant::String(body),
+                ..
+            }) => *body,
+            _ => {
+                return Err(CompilerError::InvalidInlineIr(
+                    location,
+                    "`__llvm_ir` expects a literal body".to_string(),
+                ))
+            }
+        };
+
+        let operands = arguments[1..]
+            .iter()
+            .map(|argument| Ok(self.translate_argument(argument)?.into()))
+            .collect::<Result<Vec<BasicMetadataValueEnum<'ctx>>, CompilerError>>()?;
+
+        let mut index = 0;
+        while self
+            .module
+            .get_function(&format!("mini.ir.{}", index))
+            .is_some()
+        {
+            index += 1;
         }
+        let name = format!("mini.ir.{}", index);
+
+        let val_type = self.val_type.print_to_string().to_string();
+        let parameters = (0..operands.len())
+            .map(|index| format!("{} %{}", val_type, index))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let code = format!(
+            "declare {v} @new_bool_val(i1)\n\
+             declare {v} @new_int_val(i64)\n\
+             declare {v} @new_float_val(double)\n\
+             declare i1 @val_unbox_bool({v})\n\
+             declare i64 @val_unbox_int({v})\n\
+             declare double @val_unbox_float({v})\n\
+             define {v} @{name}({parameters}) alwaysinline {{\n{body}\n}}\n",
+            v = val_type,
+        );
+
+        let snippet = self
+            .context
+            .create_module_from_ir(MemoryBuffer::create_from_memory_range_copy(
+                code.as_bytes(),
+                &name,
+            ))
+            .map_err(|err| CompilerError::InvalidInlineIr(location, err.to_string()))?;
+
+        self.module
+            .link_in_module(snippet)
+            .map_err(|err| CompilerError::InvalidInlineIr(location, err.to_string()))?;
+
+        // the linker drops the internal functions nothing refers to yet
+        let function = self.module.get_function(&name).unwrap();
+        function.set_linkage(Linkage::Internal);
+
+        let v = self
+            .builder
+            .build_call(function, &operands, "tmp")?
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+
+        Ok(v)
     }
 
     fn translate_call_expression(
         &self,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
         if let ast::Expression::CallExpression {
+            location,
             identifier,
             arguments,
-            ..
         } = expression
         {
             let function_variable_id = self.symbol_table.identifier_ref(identifier);
             let function = self.symbol_table.variable(function_variable_id);
+            let is_external = function.is_external()?;
+
+            if is_external && function.get_name()? == "__llvm_ir" {
+                return self.translate_inline_ir(*location, arguments);
+            }
+
+            if is_external && function.get_name()? == "embed" {
+                let content = builtins::read_embedded_file(self.source, *location, arguments)?;
+                let s = self.global_string(&content, "embed")?;
+
+                return self.call_builtin("new_str_val", &[s.into()]);
+            }
 
-            let parameters = function.get_parameters();
+            let parameters = function.get_parameters()?;
 
             let mut argument_values: Vec<BasicMetadataValueEnum<'ctx>> = Vec::new();
             let mut rest_values: Vec<BasicMetadataValueEnum<'ctx>> = Vec::new();
@@ -608,26 +2253,41 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
 
             let max_index = std::cmp::max(arguments.len(), parameters.len());
 
+            // the symbol table checked the number of arguments, so only the optional
+            // parameters are missing values here
             for index in 0..max_index {
                 let param = parameters.get(index);
                 let exp = arguments.get(index);
 
-                let v = if exp.is_some() {
-                    self.translate_expression(arguments.get(index).unwrap())?
+                if has_switched_to_rest || (param.is_some() && param.unwrap().is_rest) {
+                    has_switched_to_rest = true;
+
+                    if let Some(exp) = exp {
+                        rest_values.push(self.translate_argument(exp)?.into());
+                    }
+
+                    continue;
+                }
+
+                let v = if let Some(exp) = exp {
+                    self.translate_argument(exp)?
                 } else {
-                    self.val_type.const_zero()
+                    self.undefined().into()
                 };
 
-                if has_switched_to_rest || (param.is_some() && param.unwrap().is_rest) {
-                    has_switched_to_rest = true;
+                // the parameter is an `i32` of the external function, not a `val_t *`
+                if is_external && param.is_some_and(|p| p.sub_kind.is_int32()) {
+                    let v = self
+                        .call_builtin("val_to_int32", &[v.into()])?
+                        .into_int_value();
 
-                    rest_values.push(v.into())
+                    argument_values.push(v.into())
                 } else {
                     argument_values.push(v.into())
                 }
             }
 
-            if !rest_values.is_empty() {
+            if has_switched_to_rest {
                 let array_size = self
                     .context
                     .i64_type()
@@ -644,6 +2304,15 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
                 argument_values.push(array.into());
             }
 
+            if is_external {
+                if self.options.trace_runtime {
+                    self.put_source_location()?;
+                    self.trace_call(function.get_name()?, &argument_values)?;
+                }
+
+                self.use_builtin(function.get_name()?);
+            }
+
             let fn_value = self.functions.get(function_variable_id).unwrap();
 
             let v = self
@@ -653,91 +2322,139 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
                 .left()
                 .unwrap();
 
+            if let ast::VariableKind::Function { return_kind, .. } = function.get_kind()? {
+                if is_external && return_kind.is_int32() {
+                    let v = self.builder.build_int_s_extend(
+                        v.into_int_value(),
+                        self.context.i64_type(),
+                        "tmp",
+                    )?;
+
+                    return self.call_builtin("new_int_val", &[v.into()]);
+                }
+            }
+
             Ok(v)
         } else {
-            unreachable!()
+            Err(unexpected_expression(expression))
         }
     }
 
-    fn translate_expression(
-        &self,
-        expression: &'input ast::Expression<'input>,
-    ) -> Result<BasicValueEnum<'ctx>, CompilerError<'input>> {
-        match expression {
-            ast::Expression::ConstantExpression { value, .. } => match value {
-                ast::Constant::Undefined => {
-                    let v = self.val_type.const_zero();
+    fn translate_value(&self, value: &Value) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        match value {
+            Value::Undefined => Ok(self.undefined().into()),
 
-                    Ok(v.into())
-                }
+            Value::Null => Ok(self.null().into()),
+
+            Value::Boolean(data) => {
+                let v = self
+                    .context
+                    .bool_type()
+                    .const_int(if *data { 1 } else { 0 }, false);
 
-                ast::Constant::Null => {
-                    let v = self.call_builtin("new_null_val", &[])?;
+                let v = self.call_builtin("new_bool_val", &[v.into()])?;
 
-                    Ok(v.into())
-                }
+                Ok(v.into())
+            }
 
-                ast::Constant::Boolean(data) => {
-                    let v = self
-                        .context
-                        .bool_type()
-                        .const_int(if *data { 1 } else { 0 }, false);
+            Value::Integer(data) => {
+                let v = self.context.i64_type().const_int(*data as u64, true);
 
-                    let v = self.call_builtin("new_bool_val", &[v.into()])?;
+                let v = self.call_builtin("new_int_val", &[v.into()])?;
 
-                    Ok(v.into())
-                }
+                Ok(v.into())
+            }
 
-                ast::Constant::Integer(data) => {
-                    let v = self.context.i64_type().const_int(*data, true);
+            Value::Float(data) => {
+                let v = self.context.f64_type().const_float(*data);
 
-                    let v = self.call_builtin("new_int_val", &[v.into()])?;
+                let v = self.call_builtin("new_float_val", &[v.into()])?;
 
-                    Ok(v.into())
-                }
+                Ok(v.into())
+            }
 
-                ast::Constant::Float(data) => {
-                    let v = self.context.f64_type().const_float(*data);
+            Value::String(data) => {
+                let s = self.global_string(data, "string")?;
 
-                    let v = self.call_builtin("new_float_val", &[v.into()])?;
+                let v = self.call_builtin("new_str_val", &[s.into()])?;
 
-                    Ok(v.into())
-                }
+                Ok(v.into())
+            }
+
+            Value::Array(_)
+            | Value::Object(_)
+            | Value::Range(..)
+            | Value::Float64Array(_)
+            | Value::Int64Array(_)
+            | Value::Symbol(_) => Err(CompilerError::Internal(
+                None,
+                "only primitive values are constants".to_string(),
+            )),
+        }
+    }
 
-                ast::Constant::String(data) => {
-                    let s = self.builder.build_global_string_ptr(data, "string")?;
+    fn translate_expression(
+        &self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let previous_location = self.set_location(expression.get_location());
+        let result = self.translate_expression_at_location(expression);
+        self.current_location.set(previous_location);
 
-                    let v = self.call_builtin("new_str_val", &[s.as_pointer_value().into()])?;
+        result
+    }
 
-                    Ok(v.into())
-                }
-            },
+    fn translate_expression_at_location(
+        &self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        if let ast::Expression::VariableExpression { .. }
+        | ast::Expression::UnaryExpression { .. }
+        | ast::Expression::BinaryExpression { .. } = expression
+        {
+            if let Some(value) = consteval::evaluate(self.symbol_table, expression) {
+                return self.translate_value(&value);
+            }
+        }
+
+        match expression {
+            ast::Expression::ConstantExpression { value, .. } => {
+                self.translate_value(&Value::from(value))
+            }
 
             ast::Expression::BinaryExpression { .. } => {
                 self.translate_binary_expression(expression)
             }
 
-            ast::Expression::UnaryExpression { .. } => self.translate_unary_expression(expression),
-
-            ast::Expression::CallExpression { .. } => self.translate_call_expression(expression),
+            ast::Expression::RangeExpression {
+                start,
+                end,
+                is_inclusive,
+                ..
+            } => {
+                let start = self.translate_expression(start)?;
+                let end = self.translate_expression(end)?;
+                let is_inclusive = self
+                    .context
+                    .bool_type()
+                    .const_int(*is_inclusive as u64, false);
 
-            ast::Expression::ObjectExpression { .. } => {
-                self.translate_object_expression(expression)
+                self.call_builtin(
+                    "val_op_range",
+                    &[start.into(), end.into(), is_inclusive.into()],
+                )
             }
 
-            ast::Expression::ArrayExpression { items, .. } => {
-                let array_size = self.context.i64_type().const_int(items.len() as u64, false);
+            ast::Expression::UnaryExpression { .. } => self.translate_unary_expression(expression),
 
-                let array = self
-                    .call_builtin("new_array_val", &[array_size.into()])?
-                    .into_pointer_value();
+            ast::Expression::CallExpression { .. } => self.translate_call_expression(expression),
 
-                for v in items.iter() {
-                    let v = self.translate_expression(v)?;
-                    self.call_builtin("val_array_push", &[array.into(), v.into()])?;
+            ast::Expression::ArrayExpression { .. } | ast::Expression::ObjectExpression { .. } => {
+                if is_constant_literal(expression) {
+                    self.translate_pooled_literal(expression)
+                } else {
+                    self.translate_literal(expression, Self::translate_expression)
                 }
-
-                Ok(array.into())
             }
 
             ast::Expression::TypeOfExpression { expression, .. } => {
@@ -750,12 +2467,51 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
                 Ok(v.into())
             }
 
+            ast::Expression::SymbolExpression { name, .. } => match name.as_ref() {
+                // literal names are interned at compile time, like property keys
+                ast::Expression::ConstantExpression {
+                    value: ast::Constant::String(name),
+                    ..
+                } => {
+                    let key = self.property_key(name)?;
+
+                    self.call_builtin("val_symbol_cached", &[key.into()])
+                }
+                name => {
+                    let v = self.translate_expression(name)?;
+
+                    self.call_builtin("val_symbol", &[v.into()])
+                }
+            },
+
+            ast::Expression::ValidateExpression {
+                kind, expression, ..
+            } => {
+                let v = self.translate_expression(expression)?;
+
+                let schema = self.global_string(&kind.get_schema(), "schema")?;
+
+                self.call_builtin("val_validate", &[v.into(), schema.into()])
+            }
+
             ast::Expression::VariableExpression { identifier, .. } => {
                 let v = self.get_value_for_identifier(identifier)?;
 
                 Ok(v)
             }
 
+            ast::Expression::MemberExpression {
+                expression, member, ..
+            } => {
+                let obj = self.translate_expression(expression)?;
+
+                match member {
+                    ast::Member::Property(property) => self.get_property(obj, property),
+                    ast::Member::Index(index) => self.get_index(obj, index),
+                    ast::Member::Slice { start, end } => self.get_slice(obj, start, end),
+                }
+            }
+
             ast::Expression::AssignmentExpression {
                 identifier,
                 expression,
@@ -766,32 +2522,135 @@ impl<'input, 'ctx> IRGenerator<'input, 'ctx> {
                 self.set_value_for_identifier(identifier, v)
             }
 
-            ast::Expression::Empty => unreachable!("Empty expression"),
+            ast::Expression::Empty => Err(CompilerError::Internal(
+                None,
+                "empty expression reached".to_string(),
+            )),
+
+            ast::Expression::CompoundAssignmentExpression { location, .. } => {
+                Err(CompilerError::Internal(
+                    Some(*location),
+                    "compound assignment reached, it should have been lowered".to_string(),
+                ))
+            }
+
+            ast::Expression::MatchExpression { location, .. } => Err(CompilerError::Internal(
+                Some(*location),
+                "match reached, it should have been lowered".to_string(),
+            )),
         }
     }
 
     fn put_return(
         &mut self,
         expression: Option<&'input ast::Expression<'input>>,
-        terminate: bool,
-    ) -> Result<(), CompilerError<'input>> {
+    ) -> Result<(), CompilerError> {
         let v = if let Some(expression) = expression {
             self.translate_expression(expression)?
         } else {
-            self.val_type.const_zero()
+            self.undefined().into()
         };
 
-        self.clear_variables()?;
+        self.put_return_value(v)
+    }
 
-        self.builder.build_return(Some(&v))?;
+    /// Calls the function without arguments, with `undefined` for its optional
+    /// parameters and no rest items, like `main` calls the entry function.
+    fn call_without_arguments(
+        &self,
+        function_id: &Index,
+    ) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let mut arguments: Vec<BasicMetadataValueEnum<'ctx>> = Vec::new();
+
+        if let ast::VariableKind::Function { parameters, .. } =
+            self.symbol_table.variable(function_id).get_kind()?
+        {
+            for parameter in parameters {
+                if parameter.is_rest {
+                    let size = self.context.i64_type().const_zero();
+                    arguments.push(self.call_builtin("new_array_val", &[size.into()])?.into());
+                } else {
+                    arguments.push(self.undefined().into());
+                }
+            }
+        }
+
+        let function = self.functions.get(function_id).unwrap();
+
+        let v = self
+            .builder
+            .build_call(*function, &arguments, "tmp")?
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+
+        Ok(v)
+    }
+
+    /// Runs the tests of the harness one after the other through their callers,
+    /// returning the exit status of the program.
+    fn run_tests(&mut self, tests: &[Index]) -> Result<BasicValueEnum<'ctx>, CompilerError> {
+        let count = self.context.i64_type().const_int(tests.len() as u64, false);
+        self.call_builtin("start_tests", &[count.into()])?;
+
+        for test_id in tests {
+            let block = self.builder.get_insert_block().unwrap();
+            let caller = self.define_caller(test_id)?;
+            self.builder.position_at_end(block);
+
+            let name = self.symbol_table.variable(test_id).get_name()?;
+            let name = self.global_string(name, "test")?;
+            let caller = self.builder.build_pointer_cast(
+                caller.as_global_value().as_pointer_value(),
+                self.context.i8_type().ptr_type(AddressSpace::default()),
+                "code",
+            )?;
+
+            self.call_builtin("run_test", &[name.into(), caller.into()])?;
+        }
+
+        self.call_builtin("finish_tests", &[])
+    }
+
+    fn put_return_value(&mut self, v: BasicValueEnum<'ctx>) -> Result<(), CompilerError> {
+        let is_main = self.symbol_table.main_function == self.current_function_index;
+        let (exit_block, return_slot) = self.exit.unwrap();
+
+        // the timeouts run once the program finished, while its variables are alive
+        if is_main {
+            self.call_builtin("run_timers", &[])?;
+        }
+
+        // the value may be held by one of the variables, so it is kept alive while
+        // they are unlinked and handed to the caller like a new value
+        let has_cleanups = self.cleanups.iter().any(|cleanups| !cleanups.is_empty());
+
+        if has_cleanups {
+            self.call_builtin("link_val", &[v.into()])?;
+        }
+
+        // every block the return leaves, the innermost first
+        for cleanups in self.cleanups.clone().iter().rev() {
+            self.run_cleanups(cleanups)?;
+        }
+
+        if has_cleanups {
+            self.call_builtin("release_val", &[v.into()])?;
+        }
+
+        // the status is read before the pooled literals, which the value may be, are released
+        if is_main {
+            let status = self.call_builtin("val_exit_status", &[v.into()])?;
+            self.builder.build_store(return_slot, status)?;
 
-        if !terminate {
-            let ret_block = self
-                .context
-                .append_basic_block(*(self.current_function().1), "next");
-            self.builder.position_at_end(ret_block);
+            self.call_builtin("release_pooled_vals", &[])?;
+        } else {
+            self.builder.build_store(return_slot, v)?;
         }
 
+        self.builder.build_unconditional_branch(exit_block)?;
+        self.terminated = true;
+
         Ok(())
     }
 }
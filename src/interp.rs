@@ -0,0 +1,1759 @@
+use by_address::ByAddress;
+use generational_arena::Index;
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::ast;
+use crate::builtins;
+use crate::cli;
+use crate::consteval;
+use crate::error::CompilerError;
+use crate::format_string;
+use crate::source::SourceFile;
+use crate::st;
+use crate::typeck::RuntimeCasts;
+
+/// Setting an item past the end fills the array up to it, so the index of a set is
+/// bounded like in the runtime, see `MAX_ARRAY_LEN` in std/array.h.
+const MAX_ARRAY_LEN: usize = 1 << 26;
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(Rc<str>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Object(Rc<RefCell<IndexMap<String, Value>>>),
+    /// `start..end`, whose end is not in it.
+    Range(i64, i64),
+    Float64Array(Rc<RefCell<Vec<f64>>>),
+    Int64Array(Rc<RefCell<Vec<i64>>>),
+    /// `Symbol(name)`, equal to the symbols of the same name.
+    Symbol(Rc<str>),
+}
+
+impl Value {
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            Value::Undefined => "undefined",
+            Value::Null => "null",
+            Value::Boolean(_) => "boolean",
+            Value::Integer(_) => "number",
+            Value::Float(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Range(..) => "range",
+            Value::Float64Array(_) => "Float64Array",
+            Value::Int64Array(_) => "Int64Array",
+            Value::Symbol(_) => "symbol",
+        }
+    }
+
+    /// Whether conditions and logical operators take the value as true. `false`,
+    /// `0`, `NaN`, the empty string, `null` and `undefined` are false, every other
+    /// value is true, empty arrays and objects included.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Undefined | Value::Null => false,
+            Value::Boolean(b) => *b,
+            Value::Integer(i) => *i != 0,
+            Value::Float(f) => *f != 0.0 && !f.is_nan(),
+            Value::String(s) => !s.is_empty(),
+            Value::Array(_)
+            | Value::Object(_)
+            | Value::Range(..)
+            | Value::Float64Array(_)
+            | Value::Int64Array(_)
+            | Value::Symbol(_) => true,
+        }
+    }
+
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    fn fmt_colored(
+        f: &mut fmt::Formatter<'_>,
+        color: &str,
+        text: fmt::Arguments<'_>,
+        colored: bool,
+    ) -> fmt::Result {
+        if colored {
+            write!(f, "{}{}\x1B[0m", color, text)
+        } else {
+            write!(f, "{}", text)
+        }
+    }
+
+    fn fmt_float(value: f64) -> String {
+        let s = format!("{:.6}", value);
+
+        if !s.contains('.') {
+            return s;
+        }
+
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+
+    pub fn inspect(&self) -> Inspect<'_> {
+        Inspect(self)
+    }
+
+    /// The text of the value as the `val_to_str` builtin of the std runtime gives it.
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::String(s) => s.to_string(),
+            _ => Text(self).to_string(),
+        }
+    }
+
+    /// Formats the value the same way the std runtime does, colored for `echo`.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>, colored: bool) -> fmt::Result {
+        match self {
+            Value::Undefined => {
+                Value::fmt_colored(f, "\x1B[2m", format_args!("undefined"), colored)
+            }
+            Value::Null => Value::fmt_colored(f, "\x1B[1m", format_args!("null"), colored),
+            Value::Boolean(b) => {
+                Value::fmt_colored(f, "\x1B[0;33m", format_args!("{}", b), colored)
+            }
+            Value::Integer(i) => {
+                Value::fmt_colored(f, "\x1B[0;33m", format_args!("{}", i), colored)
+            }
+            Value::Float(value) => Value::fmt_colored(
+                f,
+                "\x1B[0;33m",
+                format_args!("{}", Value::fmt_float(*value)),
+                colored,
+            ),
+            Value::String(s) => {
+                Value::fmt_colored(f, "\x1B[0;32m", format_args!("'{}'", s), colored)
+            }
+            Value::Range(start, end) => {
+                Value::fmt_colored(f, "\x1B[0;33m", format_args!("{}..{}", start, end), colored)
+            }
+            Value::Symbol(name) => {
+                Value::fmt_colored(f, "\x1B[0;32m", format_args!("Symbol({})", name), colored)
+            }
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_nested(f, colored)?;
+                }
+                write!(f, "]")
+            }
+            Value::Float64Array(items) => {
+                write!(f, "Float64Array [")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    Value::fmt_colored(
+                        f,
+                        "\x1B[0;33m",
+                        format_args!("{}", Value::fmt_float(*item)),
+                        colored,
+                    )?;
+                }
+                write!(f, "]")
+            }
+            Value::Int64Array(items) => {
+                write!(f, "Int64Array [")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    Value::fmt_colored(f, "\x1B[0;33m", format_args!("{}", item), colored)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(properties) => {
+                let properties = properties.borrow();
+
+                if properties.is_empty() {
+                    return write!(f, "{{}}");
+                }
+
+                write!(f, "{{ ")?;
+                for (index, (key, value)) in properties.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_nested(f, colored)?;
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+/// Formats a value without colors, with the strings inside it quoted.
+struct Text<'a>(&'a Value);
+
+impl<'a> fmt::Display for Text<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_nested(f, false)
+    }
+}
+
+/// Displays a value the way it appears inside an array, with strings quoted.
+pub struct Inspect<'a>(&'a Value);
+
+impl<'a> fmt::Display for Inspect<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_nested(f, true)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            _ => self.fmt_nested(f, true),
+        }
+    }
+}
+
+impl<'input> From<&ast::Constant<'input>> for Value {
+    fn from(constant: &ast::Constant<'input>) -> Self {
+        match constant {
+            ast::Constant::Undefined => Value::Undefined,
+            ast::Constant::Null => Value::Null,
+            ast::Constant::Boolean(b) => Value::Boolean(*b),
+            ast::Constant::Integer(i) => Value::Integer(*i as i64),
+            ast::Constant::Float(f) => Value::Float(*f),
+            ast::Constant::String(s) => Value::String(Rc::from(*s)),
+        }
+    }
+}
+
+/// Runs a program directly over its AST, using the symbol table to resolve names.
+/// It follows the semantics of the std runtime, so its output can be compared with
+/// the output of the LLVM backend.
+pub struct Interpreter<'input> {
+    symbol_table: &'input st::SymbolTable<'input>,
+    source: Option<&'input SourceFile<'input>>,
+
+    frames: Vec<IndexMap<Index, Value>>,
+    current_location: Option<(usize, usize)>,
+    casts: RuntimeCasts<'input>,
+    output: Rc<RefCell<dyn Write>>,
+    last_value: Value,
+}
+
+impl<'input> Interpreter<'input> {
+    pub fn new(
+        symbol_table: &'input st::SymbolTable<'input>,
+        source: Option<&'input SourceFile<'input>>,
+    ) -> Self {
+        Interpreter {
+            symbol_table,
+            source,
+            frames: Vec::new(),
+            current_location: None,
+            casts: RuntimeCasts::new(),
+            output: Rc::new(RefCell::new(std::io::stdout())),
+            last_value: Value::Undefined,
+        }
+    }
+
+    /// Where `echo` writes, the standard output by default.
+    pub fn set_output(&mut self, output: Rc<RefCell<dyn Write>>) {
+        self.output = output;
+    }
+
+    /// Value of the last expression statement run at the top level.
+    pub fn last_value(&self) -> &Value {
+        &self.last_value
+    }
+
+    /// Arguments to check against their parameter kinds when the call happens.
+    pub fn set_casts(&mut self, casts: RuntimeCasts<'input>) {
+        self.casts = casts;
+    }
+
+    pub fn run(&mut self) -> Result<Value, CompilerError> {
+        let main_function = self.symbol_table.main_function.unwrap();
+
+        self.call_function(&main_function, Vec::new())
+    }
+
+    /// Exit status of the process for the value `run` returns, like `val_exit_status`
+    /// of the std runtime.
+    pub fn exit_status(&self, value: &Value) -> Result<i32, CompilerError> {
+        match value {
+            Value::Undefined | Value::Null => Ok(0),
+            Value::Integer(n) => Ok(*n as i32),
+            Value::Float(f) if *f > -9.2e18 && *f < 9.2e18 => Ok(*f as i64 as i32),
+            Value::Float(_) => Ok(0),
+            v => self.runtime_error(format!(
+                "the exit status must be a number, got {}",
+                v.get_type_name()
+            )),
+        }
+    }
+
+    /// Calls the entry function selected with `--entry` at the end of `main`, with
+    /// its optional parameters undefined and its rest parameter empty.
+    fn call_entry(&mut self, entry_id: &Index) -> Result<Value, CompilerError> {
+        let arguments = self
+            .symbol_table
+            .variable(entry_id)
+            .get_parameters()?
+            .iter()
+            .map(|parameter| {
+                if parameter.is_rest {
+                    Value::Array(Rc::new(RefCell::new(Vec::new())))
+                } else {
+                    Value::Undefined
+                }
+            })
+            .collect();
+
+        self.call_function(entry_id, arguments)
+    }
+
+    /// Runs the tests of the harness one after the other like `run_test` of the std
+    /// runtime, returning the exit status of the program. The error of a failing
+    /// test is printed and the next test runs.
+    fn run_tests(&mut self, tests: &[Index]) -> Result<Value, CompilerError> {
+        let mut failed = 0;
+
+        self.write_line(format_args!("running {} tests", tests.len()))?;
+
+        for test_id in tests {
+            let name = self.symbol_table.variable(test_id).get_name()?;
+
+            match self.call_entry(test_id) {
+                Ok(_) => self.write_line(format_args!("test {} ... \x1B[0;32mok\x1B[0m", name))?,
+                Err(err) => {
+                    eprintln!("{}", err.message());
+                    failed += 1;
+
+                    self.write_line(format_args!("test {} ... \x1B[0;31mFAILED\x1B[0m", name))?;
+                }
+            }
+        }
+
+        let result = if failed == 0 {
+            "\x1B[0;32mok"
+        } else {
+            "\x1B[0;31mFAILED"
+        };
+
+        self.write_line(format_args!(
+            "\ntest result: {}\x1B[0m. {} passed; {} failed",
+            result,
+            tests.len() - failed,
+            failed
+        ))?;
+
+        let status = if failed == 0 {
+            0
+        } else {
+            cli::EXIT_RUNTIME_ERROR
+        };
+
+        Ok(Value::Integer(status as i64))
+    }
+
+    fn write_line(&self, line: fmt::Arguments<'_>) -> Result<(), CompilerError> {
+        match writeln!(self.output.borrow_mut(), "{}", line) {
+            Ok(()) => Ok(()),
+            Err(err) => self.runtime_error(format!("test: {}", err)),
+        }
+    }
+
+    fn runtime_error<T>(&self, message: String) -> Result<T, CompilerError> {
+        let location = self.source.and(self.current_location);
+
+        Err(CompilerError::RuntimeError(location, message))
+    }
+
+    fn internal_error<T>(&self, message: &str) -> Result<T, CompilerError> {
+        Err(CompilerError::Internal(
+            self.current_location,
+            message.to_string(),
+        ))
+    }
+
+    fn call_function(
+        &mut self,
+        function_id: &Index,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        let function = self.symbol_table.variable(function_id);
+
+        if function.is_external()? {
+            return self.call_builtin(function.get_name()?, arguments);
+        }
+
+        let scope = self.symbol_table.function_scope(function_id);
+
+        let mut frame = IndexMap::new();
+        let mut arguments = arguments.into_iter();
+
+        for variable_id in self.symbol_table.function_variables(function_id) {
+            let variable = self.symbol_table.variable(&variable_id);
+
+            if variable.is_function() {
+                continue;
+            }
+
+            let value = if variable.is_parameter()? {
+                arguments.next().unwrap_or(Value::Undefined)
+            } else {
+                Value::Undefined
+            };
+
+            frame.insert(variable_id, value);
+        }
+
+        self.frames.push(frame);
+
+        let result = match scope.statements {
+            Some(statements) => self.execute_statements(statements),
+            None => Ok(None),
+        };
+
+        let is_main = self.symbol_table.main_function == Some(*function_id);
+
+        let result = match (result, &self.symbol_table.test_functions) {
+            (Ok(None), Some(tests)) if is_main => self.run_tests(tests).map(Some),
+            (Ok(None), None) if is_main => match self.symbol_table.entry_function {
+                Some(entry_id) => self.call_entry(&entry_id).map(Some),
+                None => Ok(None),
+            },
+            (result, _) => result,
+        };
+
+        self.frames.pop();
+
+        Ok(result?.unwrap_or(Value::Undefined))
+    }
+
+    fn call_builtin(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, CompilerError> {
+        match name {
+            "echo" => {
+                let items = match arguments.into_iter().next() {
+                    Some(Value::Array(items)) => items,
+                    _ => return self.runtime_error("echo: expected an array".to_string()),
+                };
+
+                let line = items
+                    .borrow()
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                if let Err(err) = writeln!(self.output.borrow_mut(), "{}", line) {
+                    return self.runtime_error(format!("echo: {}", err));
+                }
+
+                Ok(Value::Undefined)
+            }
+            "assert" => {
+                let mut arguments = arguments.into_iter();
+
+                match (arguments.next(), arguments.next()) {
+                    (Some(Value::Boolean(true)), _) => Ok(Value::Undefined),
+                    (Some(Value::Boolean(false)), Some(Value::String(message))) => {
+                        self.runtime_error(format!("assertion failed: {}", message))
+                    }
+                    (Some(Value::Boolean(false)), _) => {
+                        self.runtime_error("assertion failed".to_string())
+                    }
+                    (v, _) => self.runtime_error(format!(
+                        "`assert` expects a boolean, got {}",
+                        v.unwrap_or(Value::Undefined).get_type_name()
+                    )),
+                }
+            }
+            name if name.starts_with("val_array_") => self.call_array_builtin(name, arguments),
+            "exec" | "spawn" => self.call_process_builtin(name, arguments),
+            "sleep_ms" => {
+                let ms = match arguments.into_iter().next() {
+                    Some(Value::Integer(ms)) => ms as f64,
+                    Some(Value::Float(ms)) => ms,
+                    v => {
+                        return self.runtime_error(format!(
+                            "`sleep_ms` expects a number of milliseconds, got {}",
+                            v.unwrap_or(Value::Undefined).get_type_name()
+                        ))
+                    }
+                };
+
+                std::thread::sleep(std::time::Duration::from_millis(ms.max(0.0) as u64));
+
+                Ok(Value::Undefined)
+            }
+            name if name.starts_with("fs_") => self.call_fs_builtin(name, arguments),
+            name if name.starts_with("path_") => self.call_path_builtin(name, arguments),
+            "format" => self.call_format_builtin(arguments),
+            "val_str_slice" => self.call_str_slice(arguments),
+            "float64_array" | "int64_array" | "typed_array_len" => {
+                self.call_typed_array_builtin(name, arguments)
+            }
+            "array_add" | "array_scale" | "dot" => self.call_array_math_builtin(name, arguments),
+            "val_to_str" => Ok(Value::String(Rc::from(
+                arguments
+                    .into_iter()
+                    .next()
+                    .unwrap_or(Value::Undefined)
+                    .to_text(),
+            ))),
+            _ => self.runtime_error(format!(
+                "function `{}` is not supported by the interpreter",
+                name
+            )),
+        }
+    }
+
+    fn call_array_builtin(
+        &mut self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        let mut arguments = arguments.into_iter();
+
+        let items = match arguments.next() {
+            Some(Value::Array(items)) => items,
+            v => {
+                return self.runtime_error(format!(
+                    "`{}` expects an array, got {}",
+                    name,
+                    v.unwrap_or(Value::Undefined).get_type_name()
+                ))
+            }
+        };
+
+        let argument = arguments.next().unwrap_or(Value::Undefined);
+
+        match name {
+            "val_array_len" => Ok(Value::Integer(items.borrow().len() as i64)),
+            "val_array_pop" => Ok(items.borrow_mut().pop().unwrap_or(Value::Undefined)),
+            "val_array_shift" => {
+                let mut items = items.borrow_mut();
+
+                if items.is_empty() {
+                    Ok(Value::Undefined)
+                } else {
+                    Ok(items.remove(0))
+                }
+            }
+            "val_array_unshift" => {
+                items.borrow_mut().insert(0, argument);
+
+                Ok(Value::Undefined)
+            }
+            "val_array_slice" => {
+                let len = items.borrow().len() as i64;
+
+                let from = self.slice_index(name, argument, len)?;
+                let to = match arguments.next().unwrap_or(Value::Undefined) {
+                    Value::Undefined => len,
+                    end => self.slice_index(name, end, len)?,
+                };
+
+                let slice = items
+                    .borrow()
+                    .iter()
+                    .skip(from as usize)
+                    .take((to - from).max(0) as usize)
+                    .cloned()
+                    .collect();
+
+                Ok(Value::Array(Rc::new(RefCell::new(slice))))
+            }
+            "val_array_concat" => match argument {
+                Value::Array(others) => {
+                    let mut result = items.borrow().clone();
+                    result.extend(others.borrow().iter().cloned());
+
+                    Ok(Value::Array(Rc::new(RefCell::new(result))))
+                }
+                v => self.runtime_error(format!(
+                    "`{}` expects an array, got {}",
+                    name,
+                    v.get_type_name()
+                )),
+            },
+            "val_array_join" => {
+                let separator = match argument {
+                    Value::String(separator) => separator,
+                    v => {
+                        return self.runtime_error(format!(
+                            "`{}` expects a string separator, got {}",
+                            name,
+                            v.get_type_name()
+                        ))
+                    }
+                };
+
+                let mut parts = Vec::new();
+
+                for item in items.borrow().iter() {
+                    // undefined and null are joined as empty strings, like in JavaScript
+                    parts.push(match item {
+                        Value::Undefined | Value::Null => String::new(),
+                        v => v.to_text(),
+                    });
+                }
+
+                Ok(Value::String(Rc::from(parts.join(&separator))))
+            }
+            "val_array_index_of" => Ok(Value::Integer(
+                items
+                    .borrow()
+                    .iter()
+                    .position(|item| Self::strict_equals(item, &argument))
+                    .map_or(-1, |i| i as i64),
+            )),
+            "val_array_sort" => {
+                let mut error = None;
+
+                items
+                    .borrow_mut()
+                    .sort_by(|l, r| match consteval::compare(l, r) {
+                        Ok(ordering) => ordering.cmp(&0),
+                        Err(err) => {
+                            error.get_or_insert(err);
+                            std::cmp::Ordering::Equal
+                        }
+                    });
+
+                match error {
+                    Some(message) => self.runtime_error(message),
+                    None => Ok(Value::Undefined),
+                }
+            }
+            "val_array_reverse" => {
+                items.borrow_mut().reverse();
+
+                Ok(Value::Undefined)
+            }
+            _ => self.runtime_error(format!(
+                "function `{}` is not supported by the interpreter",
+                name
+            )),
+        }
+    }
+
+    fn call_process_builtin(
+        &mut self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        let mut arguments = arguments.into_iter();
+
+        let cmd = match arguments.next() {
+            Some(Value::String(cmd)) => cmd,
+            v => {
+                return self.runtime_error(format!(
+                    "`{}` expects a command string, got {}",
+                    name,
+                    v.unwrap_or(Value::Undefined).get_type_name()
+                ))
+            }
+        };
+
+        let args = match arguments.next() {
+            Some(Value::Array(args)) => args,
+            v => {
+                return self.runtime_error(format!(
+                    "`{}` expects an array of arguments, got {}",
+                    name,
+                    v.unwrap_or(Value::Undefined).get_type_name()
+                ))
+            }
+        };
+
+        let mut command = std::process::Command::new(&*cmd);
+
+        for arg in args.borrow().iter() {
+            match arg {
+                Value::String(arg) => command.arg(&**arg),
+                v => {
+                    return self.runtime_error(format!(
+                        "`{}` expects string arguments, got {}",
+                        name,
+                        v.get_type_name()
+                    ))
+                }
+            };
+        }
+
+        if name == "spawn" {
+            if let Err(err) = command.spawn() {
+                return self.runtime_error(format!("`spawn` could not start `{}`: {}", cmd, err));
+            }
+
+            return Ok(Value::Undefined);
+        }
+
+        // like the std runtime, only stdout is captured and commands that cannot
+        // run exit with the status shells use for them
+        let (code, output) = match command.stderr(std::process::Stdio::inherit()).output() {
+            Ok(output) => (
+                output.status.code().unwrap_or(-1) as i64,
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            ),
+            Err(_) => (127, String::new()),
+        };
+
+        let mut result = IndexMap::new();
+        result.insert("code".to_string(), Value::Integer(code));
+        result.insert("output".to_string(), Value::String(Rc::from(output)));
+
+        Ok(Value::Object(Rc::new(RefCell::new(result))))
+    }
+
+    fn call_fs_builtin(
+        &mut self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        let mut paths = Vec::new();
+
+        for argument in arguments {
+            match argument {
+                Value::String(path) => paths.push(path),
+                v => {
+                    return self.runtime_error(format!(
+                        "`{}` expects a path string, got {}",
+                        name,
+                        v.get_type_name()
+                    ))
+                }
+            }
+        }
+
+        let path = match paths.first() {
+            Some(path) => std::path::Path::new(&**path),
+            None => return self.runtime_error(format!("`{}` expects a path string", name)),
+        };
+
+        let result = match name {
+            "fs_list_dir" => std::fs::read_dir(path).and_then(|entries| {
+                let mut names = entries
+                    .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                names.sort();
+
+                Ok(Value::Array(Rc::new(RefCell::new(
+                    names
+                        .into_iter()
+                        .map(|name| Value::String(Rc::from(name)))
+                        .collect(),
+                ))))
+            }),
+            "fs_stat" => std::fs::metadata(path).map(|metadata| {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |duration| duration.as_secs() as i64);
+
+                let mut result = IndexMap::new();
+                result.insert("size".to_string(), Value::Integer(metadata.len() as i64));
+                result.insert("is_file".to_string(), Value::Boolean(metadata.is_file()));
+                result.insert("is_dir".to_string(), Value::Boolean(metadata.is_dir()));
+                result.insert("modified".to_string(), Value::Integer(modified));
+
+                Value::Object(Rc::new(RefCell::new(result)))
+            }),
+            "fs_mkdir" => std::fs::create_dir(path).map(|_| Value::Undefined),
+            "fs_remove" => match std::fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.is_dir() => std::fs::remove_dir(path),
+                _ => std::fs::remove_file(path),
+            }
+            .map(|_| Value::Undefined),
+            "fs_exists" => Ok(Value::Boolean(path.exists())),
+            "fs_copy" => match paths.get(1) {
+                Some(to) => std::fs::copy(path, &**to).map(|_| Value::Undefined),
+                None => return self.runtime_error(format!("`{}` expects a path string", name)),
+            },
+            _ => {
+                return self.runtime_error(format!(
+                    "function `{}` is not supported by the interpreter",
+                    name
+                ))
+            }
+        };
+
+        result.or_else(|err| {
+            self.runtime_error(format!(
+                "`{}` failed for `{}`: {}",
+                name,
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    /// Handles paths the same way as the std runtime, like the POSIX functions of
+    /// the `path` module of Node.js.
+    fn call_path_builtin(
+        &mut self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        // rest parameters are passed as an array
+        let arguments = match (name, arguments.into_iter().next()) {
+            ("path_join", Some(Value::Array(parts))) => parts.borrow().clone(),
+            (_, argument) => vec![argument.unwrap_or(Value::Undefined)],
+        };
+
+        let mut paths = Vec::new();
+
+        for argument in arguments {
+            match argument {
+                Value::String(path) => paths.push(path),
+                v => {
+                    return self.runtime_error(format!(
+                        "`{}` expects a path string, got {}",
+                        name,
+                        v.get_type_name()
+                    ))
+                }
+            }
+        }
+
+        if name == "path_join" {
+            let mut result = String::new();
+
+            for part in paths.iter().filter(|part| !part.is_empty()) {
+                if !result.is_empty() && !result.ends_with('/') {
+                    result.push('/');
+                }
+
+                for c in part.chars() {
+                    if c != '/' || !result.ends_with('/') {
+                        result.push(c);
+                    }
+                }
+            }
+
+            if result.is_empty() {
+                result.push('.');
+            }
+
+            return Ok(Value::String(Rc::from(result)));
+        }
+
+        let path = &*paths[0];
+
+        // without its trailing slashes, keeping a root slash
+        let trimmed = match path.trim_end_matches('/') {
+            "" if path.starts_with('/') => "/",
+            trimmed => trimmed,
+        };
+
+        let start = trimmed.rfind('/').map_or(0, |i| i + 1);
+        let basename = &trimmed[start..];
+
+        let result = match name {
+            "path_dirname" => match &trimmed[..start] {
+                "" if path.starts_with('/') => "/",
+                "" => ".",
+                dirname => match dirname.trim_end_matches('/') {
+                    "" => "/",
+                    dirname => dirname,
+                },
+            },
+            "path_basename" => basename,
+            "path_ext" => match basename.rfind('.') {
+                Some(dot) if dot > 0 => &basename[dot..],
+                _ => "",
+            },
+            _ => {
+                return self.runtime_error(format!(
+                    "function `{}` is not supported by the interpreter",
+                    name
+                ))
+            }
+        };
+
+        Ok(Value::String(Rc::from(result)))
+    }
+
+    fn call_format_builtin(&mut self, arguments: Vec<Value>) -> Result<Value, CompilerError> {
+        let mut arguments = arguments.into_iter();
+
+        let fmt = match arguments.next() {
+            Some(Value::String(fmt)) => fmt,
+            v => {
+                return self.runtime_error(format!(
+                    "`format` expects a format string, got {}",
+                    v.unwrap_or(Value::Undefined).get_type_name()
+                ))
+            }
+        };
+
+        // rest parameters are passed as an array
+        let args = match arguments.next() {
+            Some(Value::Array(args)) => args.borrow().clone(),
+            _ => Vec::new(),
+        };
+
+        let segments = match format_string::parse(&fmt) {
+            Ok(segments) => segments,
+            Err(reason) => return self.runtime_error(format!("`format`: {}", reason)),
+        };
+
+        let mut result = String::new();
+
+        for segment in segments {
+            let placeholder = match segment {
+                format_string::Segment::Text(text) => {
+                    result.push_str(text);
+                    continue;
+                }
+                format_string::Segment::Placeholder(placeholder) => placeholder,
+            };
+
+            let arg = match args.get(placeholder.index) {
+                Some(arg) => arg,
+                None => {
+                    return self.runtime_error(format!(
+                        "`format`: no argument for the placeholder {}, got {} arguments",
+                        placeholder.index,
+                        args.len()
+                    ))
+                }
+            };
+
+            let text = match (arg, placeholder.precision) {
+                (Value::Integer(i), Some(precision)) => format!("{:.*}", precision, *i as f64),
+                (Value::Float(f), Some(precision)) => format!("{:.*}", precision, f),
+                (Value::String(s), Some(precision)) => s.chars().take(precision).collect(),
+                (v, _) => v.to_text(),
+            };
+
+            let is_number = matches!(arg, Value::Integer(_) | Value::Float(_));
+            result.push_str(&format_string::pad(&text, &placeholder, is_number));
+        }
+
+        Ok(Value::String(Rc::from(result)))
+    }
+
+    /// Indices count characters, like in `std/ops.h`.
+    fn call_str_slice(&self, arguments: Vec<Value>) -> Result<Value, CompilerError> {
+        let mut arguments = arguments.into_iter();
+
+        let s = match arguments.next() {
+            Some(Value::String(s)) => s,
+            v => {
+                return self.runtime_error(format!(
+                    "`val_str_slice` expects a string, got {}",
+                    v.unwrap_or(Value::Undefined).get_type_name()
+                ))
+            }
+        };
+
+        let len = s.chars().count() as i64;
+
+        let start = arguments.next().unwrap_or(Value::Undefined);
+        let from = self.slice_index("val_str_slice", start, len)?;
+        let to = match arguments.next().unwrap_or(Value::Undefined) {
+            Value::Undefined => len,
+            end => self.slice_index("val_str_slice", end, len)?,
+        };
+
+        let slice: String = s
+            .chars()
+            .skip(from as usize)
+            .take((to - from).max(0) as usize)
+            .collect();
+
+        Ok(Value::String(Rc::from(slice)))
+    }
+
+    fn call_typed_array_builtin(
+        &self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        let argument = arguments.into_iter().next().unwrap_or(Value::Undefined);
+
+        match (name, argument) {
+            ("typed_array_len", Value::Float64Array(items)) => {
+                Ok(Value::Integer(items.borrow().len() as i64))
+            }
+            ("typed_array_len", Value::Int64Array(items)) => {
+                Ok(Value::Integer(items.borrow().len() as i64))
+            }
+            ("typed_array_len", v) => self.runtime_error(format!(
+                "`typed_array_len` expects a typed array, got {}",
+                v.get_type_name()
+            )),
+            ("float64_array", Value::Integer(len)) if len >= 0 => {
+                Ok(Value::Float64Array(Rc::new(RefCell::new(vec![
+                    0.0;
+                    len as usize
+                ]))))
+            }
+            ("int64_array", Value::Integer(len)) if len >= 0 => {
+                Ok(Value::Int64Array(Rc::new(RefCell::new(vec![
+                    0;
+                    len as usize
+                ]))))
+            }
+            (name, v) => self.runtime_error(format!(
+                "`{}` expects a non-negative integer length, got {}",
+                name,
+                v.get_type_name()
+            )),
+        }
+    }
+
+    /// The bulk operations of `std/typed.h`, whose integers wrap around and whose
+    /// dot product of floats sums the items in the same lanes.
+    fn call_array_math_builtin(
+        &self,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, CompilerError> {
+        const DOT_LANES: usize = 4;
+
+        let mut arguments = arguments.into_iter();
+        let a = arguments.next().unwrap_or(Value::Undefined);
+        let b = arguments.next().unwrap_or(Value::Undefined);
+
+        if let ("array_add" | "dot", Some((l, r))) = (name, Self::typed_array_lens(&a, &b)) {
+            if l != r && std::mem::discriminant(&a) == std::mem::discriminant(&b) {
+                return self.runtime_error(format!(
+                    "`{}` expects arrays of the same length, got {} and {} items",
+                    name, l, r
+                ));
+            }
+        }
+
+        match (name, &a, &b) {
+            ("array_add", Value::Float64Array(x), Value::Float64Array(y)) => {
+                let items = x
+                    .borrow()
+                    .iter()
+                    .zip(y.borrow().iter())
+                    .map(|(x, y)| x + y)
+                    .collect();
+
+                Ok(Value::Float64Array(Rc::new(RefCell::new(items))))
+            }
+            ("array_add", Value::Int64Array(x), Value::Int64Array(y)) => {
+                let items = x
+                    .borrow()
+                    .iter()
+                    .zip(y.borrow().iter())
+                    .map(|(x, y)| x.wrapping_add(*y))
+                    .collect();
+
+                Ok(Value::Int64Array(Rc::new(RefCell::new(items))))
+            }
+            ("array_scale", Value::Float64Array(x), k) if k.as_float().is_some() => {
+                let k = k.as_float().unwrap();
+                let items = x.borrow().iter().map(|x| x * k).collect();
+
+                Ok(Value::Float64Array(Rc::new(RefCell::new(items))))
+            }
+            ("array_scale", Value::Int64Array(x), Value::Integer(k)) => {
+                let items = x.borrow().iter().map(|x| x.wrapping_mul(*k)).collect();
+
+                Ok(Value::Int64Array(Rc::new(RefCell::new(items))))
+            }
+            ("array_scale", Value::Float64Array(_), k) => self.runtime_error(format!(
+                "`array_scale` expects a number, got {}",
+                k.get_type_name()
+            )),
+            ("array_scale", Value::Int64Array(_), k) => self.runtime_error(format!(
+                "`array_scale` expects an integer for an Int64Array, got {}",
+                k.get_type_name()
+            )),
+            ("dot", Value::Float64Array(x), Value::Float64Array(y)) => {
+                let mut sums = [0.0; DOT_LANES];
+
+                for (i, (x, y)) in x.borrow().iter().zip(y.borrow().iter()).enumerate() {
+                    sums[i % DOT_LANES] += x * y;
+                }
+
+                Ok(Value::Float((sums[0] + sums[1]) + (sums[2] + sums[3])))
+            }
+            ("dot", Value::Int64Array(x), Value::Int64Array(y)) => Ok(Value::Integer(
+                x.borrow()
+                    .iter()
+                    .zip(y.borrow().iter())
+                    .fold(0i64, |sum, (x, y)| sum.wrapping_add(x.wrapping_mul(*y))),
+            )),
+            (name, a, b) if Self::typed_array_lens(a, b).is_some() => self.runtime_error(format!(
+                "`{}` expects arrays of the same type, got {} and {}",
+                name,
+                a.get_type_name(),
+                b.get_type_name()
+            )),
+            (name, a, b) => {
+                let v = match a {
+                    Value::Float64Array(_) | Value::Int64Array(_) => b,
+                    _ => a,
+                };
+
+                self.runtime_error(format!(
+                    "`{}` expects a typed array, got {}",
+                    name,
+                    v.get_type_name()
+                ))
+            }
+        }
+    }
+
+    /// The lengths of the values if both are typed arrays.
+    fn typed_array_lens(a: &Value, b: &Value) -> Option<(usize, usize)> {
+        let len = |v: &Value| match v {
+            Value::Float64Array(items) => Some(items.borrow().len()),
+            Value::Int64Array(items) => Some(items.borrow().len()),
+            _ => None,
+        };
+
+        Some((len(a)?, len(b)?))
+    }
+
+    /// Clamps an index of a slice to the array or string, negative indices counting from the end.
+    fn slice_index(&self, name: &str, index: Value, len: i64) -> Result<i64, CompilerError> {
+        match index {
+            Value::Integer(i) if i < 0 => Ok((i + len).max(0)),
+            Value::Integer(i) => Ok(i.min(len)),
+            v => self.runtime_error(format!(
+                "`{}` expects an integer index, got {}",
+                name,
+                v.get_type_name()
+            )),
+        }
+    }
+
+    /// Arrays and objects are only equal to themselves, other values are compared like `===`.
+    fn strict_equals(left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::String(l), Value::String(r)) | (Value::Symbol(l), Value::Symbol(r)) => l == r,
+            (Value::Array(l), Value::Array(r)) => Rc::ptr_eq(l, r),
+            (Value::Object(l), Value::Object(r)) => Rc::ptr_eq(l, r),
+            (Value::Float64Array(l), Value::Float64Array(r)) => Rc::ptr_eq(l, r),
+            (Value::Int64Array(l), Value::Int64Array(r)) => Rc::ptr_eq(l, r),
+            (Value::Range(ls, le), Value::Range(rs, re)) => ls == rs && le == re,
+            (l, r) => match (l.as_float(), r.as_float()) {
+                (Some(l), Some(r)) => l == r,
+                _ => false,
+            },
+        }
+    }
+
+    /// Returns `Some` with the returned value once a return statement has been executed.
+    /// The statements deferred in them are executed when they are left, the last
+    /// deferred first.
+    fn execute_statements(
+        &mut self,
+        statements: &'input [ast::Statement<'input>],
+    ) -> Result<Option<Value>, CompilerError> {
+        let mut deferred = Vec::new();
+        let mut result = None;
+
+        for statement in statements.iter() {
+            self.current_location = statement.get_location().or(self.current_location);
+
+            match statement {
+                ast::Statement::ExpressionStatement { expression } => {
+                    let value = self.evaluate(expression)?;
+
+                    if self.frames.len() == 1 {
+                        self.last_value = value;
+                    }
+                }
+
+                ast::Statement::DefinitionStatement {
+                    definition,
+                    expression,
+                    ..
+                } => {
+                    let value = match expression {
+                        Some(expression) => self.evaluate(expression)?,
+                        None => Value::Undefined,
+                    };
+
+                    let variable_id = self.symbol_table.definition_ref(definition);
+                    self.set_variable(variable_id, value)?;
+                }
+
+                ast::Statement::ReturnStatement { expression, .. } => {
+                    let value = match expression {
+                        Some(expression) => self.evaluate(expression)?,
+                        None => Value::Undefined,
+                    };
+
+                    result = Some(value);
+                    break;
+                }
+
+                ast::Statement::DeferStatement { statements, .. } => deferred.push(statements),
+
+                ast::Statement::IfStatement {
+                    condition,
+                    statements,
+                    else_statements,
+                    ..
+                } => {
+                    let branch = if self.evaluate(condition)?.is_truthy() {
+                        Some(statements)
+                    } else {
+                        else_statements.as_ref()
+                    };
+
+                    if let Some(branch) = branch {
+                        if let Some(value) = self.execute_statements(branch)? {
+                            result = Some(value);
+                            break;
+                        }
+                    }
+                }
+
+                ast::Statement::FunctionStatement { .. } => {}
+
+                ast::Statement::MacroStatement { location, .. }
+                | ast::Statement::MacroCallStatement { location, .. } => {
+                    return Err(CompilerError::Internal(
+                        Some(*location),
+                        "macro reached, it should have been expanded".to_string(),
+                    ));
+                }
+
+                ast::Statement::EmptyStatement => {}
+            }
+        }
+
+        for statements in deferred.into_iter().rev() {
+            self.execute_statements(statements)?;
+        }
+
+        Ok(result)
+    }
+
+    fn frame_of(&mut self, variable_id: &Index) -> Option<&mut IndexMap<Index, Value>> {
+        self.frames
+            .iter_mut()
+            .rev()
+            .find(|frame| frame.contains_key(variable_id))
+    }
+
+    fn get_variable(&mut self, variable_id: &Index) -> Result<Value, CompilerError> {
+        match self.symbol_table.variable(variable_id) {
+            st::Variable::Static { definition, .. } => match self.frame_of(variable_id) {
+                Some(frame) => Ok(frame.get(variable_id).unwrap().clone()),
+                None => {
+                    self.runtime_error(format!("`{}` cannot be used as a value", definition.name))
+                }
+            },
+            st::Variable::Property { base, property, .. } => {
+                let base = self.get_variable(base)?;
+
+                self.get_property(base, property)
+            }
+            st::Variable::Indexed { base, index, .. } => {
+                let base = self.get_variable(base)?;
+                let index = self.evaluate(index)?;
+
+                self.get_index(base, index)
+            }
+        }
+    }
+
+    fn get_property(&self, base: Value, property: &str) -> Result<Value, CompilerError> {
+        match base {
+            Value::Object(properties) => Ok(properties
+                .borrow()
+                .get(property)
+                .cloned()
+                .unwrap_or(Value::Undefined)),
+            v => self.runtime_error(format!("expected an object, got {}", v.get_type_name())),
+        }
+    }
+
+    fn get_index(&self, base: Value, index: Value) -> Result<Value, CompilerError> {
+        match (base, index) {
+            // a range slices the array, its bounds clamped to it
+            (Value::Array(items), Value::Range(start, end)) => {
+                let items = items.borrow();
+                let clamp = |i: i64| i.clamp(0, items.len() as i64) as usize;
+                let slice = items[clamp(start)..clamp(end).max(clamp(start))].to_vec();
+
+                Ok(Value::Array(Rc::new(RefCell::new(slice))))
+            }
+            (Value::Array(items), Value::Integer(i)) => Ok(usize::try_from(i)
+                .ok()
+                .and_then(|i| items.borrow().get(i).cloned())
+                .unwrap_or(Value::Undefined)),
+            (Value::Array(_), i) => self.runtime_error(format!(
+                "array index must be a number, got {}",
+                i.get_type_name()
+            )),
+            (base @ Value::Float64Array(_), index) | (base @ Value::Int64Array(_), index) => {
+                let i = self.typed_array_index(&base, index)?;
+
+                match base {
+                    Value::Float64Array(items) => Ok(Value::Float(items.borrow()[i])),
+                    Value::Int64Array(items) => Ok(Value::Integer(items.borrow()[i])),
+                    _ => self.internal_error("expected a typed array"),
+                }
+            }
+            (Value::Object(properties), key) => match Self::object_key(&key) {
+                Some(key) => Ok(properties
+                    .borrow()
+                    .get(key.as_ref())
+                    .cloned()
+                    .unwrap_or(Value::Undefined)),
+                None => self.runtime_error(format!(
+                    "object key must be a string or a symbol, got {}",
+                    key.get_type_name()
+                )),
+            },
+            (v, _) => self.runtime_error(format!("expected an object, got {}", v.get_type_name())),
+        }
+    }
+
+    /// The property name of a string or symbol key, a symbol being stored as `[Symbol(name)]`.
+    fn object_key(key: &Value) -> Option<Cow<'_, str>> {
+        match key {
+            Value::String(key) => Some(Cow::Borrowed(key)),
+            Value::Symbol(name) => Some(Cow::Owned(format!("[Symbol({})]", name))),
+            _ => None,
+        }
+    }
+
+    /// The index of an item of a typed array, which must be in bounds.
+    fn typed_array_index(&self, base: &Value, index: Value) -> Result<usize, CompilerError> {
+        let len = match base {
+            Value::Float64Array(items) => items.borrow().len(),
+            Value::Int64Array(items) => items.borrow().len(),
+            _ => return self.internal_error("expected a typed array"),
+        };
+
+        match index {
+            Value::Integer(i) if i >= 0 && (i as usize) < len => Ok(i as usize),
+            Value::Integer(i) => self.runtime_error(format!(
+                "index {} is out of bounds, the {} has {} items",
+                i,
+                base.get_type_name(),
+                len
+            )),
+            i => self.runtime_error(format!(
+                "array index must be a number, got {}",
+                i.get_type_name()
+            )),
+        }
+    }
+
+    fn set_variable(&mut self, variable_id: &Index, value: Value) -> Result<(), CompilerError> {
+        match self.symbol_table.variable(variable_id) {
+            st::Variable::Static { .. } => {
+                let frame = self.frame_of(variable_id).unwrap();
+                frame.insert(*variable_id, value);
+
+                Ok(())
+            }
+            st::Variable::Property { base, property, .. } => match self.get_variable(base)? {
+                Value::Object(properties) => {
+                    properties.borrow_mut().insert(property.to_string(), value);
+
+                    Ok(())
+                }
+                v => self.runtime_error(format!("expected an object, got {}", v.get_type_name())),
+            },
+            st::Variable::Indexed { base, index, .. } => {
+                let base = self.get_variable(base)?;
+                let index = self.evaluate(index)?;
+
+                match (base, index) {
+                    (Value::Array(items), Value::Integer(i)) => {
+                        let i = match usize::try_from(i) {
+                            Ok(index) if index < MAX_ARRAY_LEN => index,
+                            _ => {
+                                return self
+                                    .runtime_error(format!("array index {} out of bounds", i))
+                            }
+                        };
+
+                        let mut items = items.borrow_mut();
+                        if i >= items.len() {
+                            items.resize(i + 1, Value::Undefined);
+                        }
+                        items[i] = value;
+
+                        Ok(())
+                    }
+                    (Value::Array(_), i) => self.runtime_error(format!(
+                        "array index must be a number, got {}",
+                        i.get_type_name()
+                    )),
+                    (base @ Value::Float64Array(_), index)
+                    | (base @ Value::Int64Array(_), index) => {
+                        let i = self.typed_array_index(&base, index)?;
+
+                        // the item is a copy of the number
+                        match (base, value) {
+                            (Value::Int64Array(items), Value::Integer(n)) => {
+                                items.borrow_mut()[i] = n
+                            }
+                            (Value::Int64Array(_), v) => {
+                                return self.runtime_error(format!(
+                                    "the items of an Int64Array are integers, got {}",
+                                    v.get_type_name()
+                                ))
+                            }
+                            (Value::Float64Array(items), v) => match v.as_float() {
+                                Some(n) => items.borrow_mut()[i] = n,
+                                None => {
+                                    return self.runtime_error(format!(
+                                        "the items of a Float64Array are numbers, got {}",
+                                        v.get_type_name()
+                                    ))
+                                }
+                            },
+                            _ => return self.internal_error("expected a typed array"),
+                        }
+
+                        Ok(())
+                    }
+                    (Value::Object(properties), key) => match Self::object_key(&key) {
+                        Some(key) => {
+                            properties.borrow_mut().insert(key.into_owned(), value);
+
+                            Ok(())
+                        }
+                        None => self.runtime_error(format!(
+                            "object key must be a string or a symbol, got {}",
+                            key.get_type_name()
+                        )),
+                    },
+                    (v, _) => {
+                        self.runtime_error(format!("expected an object, got {}", v.get_type_name()))
+                    }
+                }
+            }
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<Value, CompilerError> {
+        let previous_location = self.current_location;
+        self.current_location = expression.get_location().or(previous_location);
+
+        let result = self.evaluate_at_location(expression);
+
+        self.current_location = previous_location;
+
+        result
+    }
+
+    fn evaluate_at_location(
+        &mut self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<Value, CompilerError> {
+        match expression {
+            ast::Expression::ConstantExpression { value, .. } => Ok(Value::from(value)),
+
+            ast::Expression::ArrayExpression { items, .. } => {
+                let items = items
+                    .iter()
+                    .map(|item| self.evaluate(item))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Value::Array(Rc::new(RefCell::new(items))))
+            }
+
+            ast::Expression::ObjectExpression { properties, .. } => {
+                let mut result = IndexMap::new();
+
+                for (key, e) in properties.iter() {
+                    let value = self.evaluate(e)?;
+                    result.insert(key.to_string(), value);
+                }
+
+                Ok(Value::Object(Rc::new(RefCell::new(result))))
+            }
+
+            ast::Expression::SymbolExpression { name, .. } => match self.evaluate(name)? {
+                Value::String(name) => Ok(Value::Symbol(name)),
+                v => self.runtime_error(format!(
+                    "`Symbol` expects a string name, got {}",
+                    v.get_type_name()
+                )),
+            },
+
+            ast::Expression::TypeOfExpression { expression, .. } => {
+                let type_name = match self.evaluate(expression)? {
+                    Value::Boolean(_) => "boolean",
+                    Value::Integer(_) | Value::Float(_) => "number",
+                    Value::String(_) => "string",
+                    Value::Symbol(_) => "symbol",
+                    Value::Undefined => "undefined",
+                    _ => "object",
+                };
+
+                Ok(Value::String(Rc::from(type_name)))
+            }
+
+            ast::Expression::ValidateExpression {
+                kind, expression, ..
+            } => {
+                let value = self.evaluate(expression)?;
+
+                self.validate(&value, kind, "$", "validation failed")?;
+
+                Ok(value)
+            }
+
+            ast::Expression::VariableExpression { identifier, .. } => {
+                let variable_id = self.symbol_table.identifier_ref(identifier);
+
+                self.get_variable(variable_id)
+            }
+
+            ast::Expression::MemberExpression {
+                expression, member, ..
+            } => {
+                let base = self.evaluate(expression)?;
+
+                match member {
+                    ast::Member::Property(property) => self.get_property(base, property),
+                    ast::Member::Index(index) => {
+                        let index = self.evaluate(index)?;
+
+                        self.get_index(base, index)
+                    }
+                    ast::Member::Slice { start, end } => {
+                        let start = match start {
+                            Some(start) => self.evaluate(start)?,
+                            None => Value::Integer(0),
+                        };
+                        let end = match end {
+                            Some(end) => self.evaluate(end)?,
+                            None => Value::Undefined,
+                        };
+
+                        let name = match base {
+                            Value::String(_) => "val_str_slice",
+                            Value::Array(_) => "val_array_slice",
+                            v => {
+                                return self.runtime_error(format!(
+                                    "expected an array or a string, got {}",
+                                    v.get_type_name()
+                                ))
+                            }
+                        };
+
+                        self.call_builtin(name, vec![base, start, end])
+                    }
+                }
+            }
+
+            ast::Expression::AssignmentExpression {
+                identifier,
+                expression,
+                ..
+            } => {
+                let value = self.evaluate(expression)?;
+
+                let variable_id = self.symbol_table.identifier_ref(identifier);
+                self.set_variable(variable_id, value.clone())?;
+
+                Ok(value)
+            }
+
+            ast::Expression::CallExpression {
+                location,
+                identifier,
+                arguments,
+            } => {
+                let function_id = self.symbol_table.identifier_ref(identifier);
+                let function = self.symbol_table.variable(function_id);
+
+                if function.is_external()? && function.get_name()? == "embed" {
+                    let content = builtins::read_embedded_file(self.source, *location, arguments)?;
+
+                    return Ok(Value::String(Rc::from(content)));
+                }
+
+                let parameters = function.get_parameters()?;
+
+                let mut argument_values = arguments
+                    .iter()
+                    .map(|argument| self.evaluate_argument(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if let Some(rest_index) = parameters.iter().position(|p| p.is_rest) {
+                    let rest_values = if argument_values.len() > rest_index {
+                        argument_values.split_off(rest_index)
+                    } else {
+                        argument_values.resize(rest_index, Value::Undefined);
+                        Vec::new()
+                    };
+
+                    argument_values.push(Value::Array(Rc::new(RefCell::new(rest_values))));
+                }
+
+                self.call_function(function_id, argument_values)
+            }
+
+            ast::Expression::UnaryExpression {
+                operator,
+                expression,
+                ..
+            } => {
+                let value = self.evaluate(expression)?;
+
+                consteval::unary_operation(operator, value)
+                    .or_else(|message| self.runtime_error(message))
+            }
+
+            ast::Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+
+                consteval::binary_operation(operator, left, right)
+                    .or_else(|message| self.runtime_error(message))
+            }
+
+            ast::Expression::RangeExpression {
+                start,
+                end,
+                is_inclusive,
+                ..
+            } => match (self.evaluate(start)?, self.evaluate(end)?) {
+                (Value::Integer(start), Value::Integer(end)) => Ok(Value::Range(
+                    start,
+                    if *is_inclusive { end + 1 } else { end },
+                )),
+                (start, end) => self.runtime_error(format!(
+                    "range bounds must be integers, got {} and {}",
+                    start.get_type_name(),
+                    end.get_type_name()
+                )),
+            },
+
+            ast::Expression::Empty => Err(CompilerError::Internal(
+                None,
+                "empty expression reached".to_string(),
+            )),
+
+            ast::Expression::CompoundAssignmentExpression { location, .. } => {
+                Err(CompilerError::Internal(
+                    Some(*location),
+                    "compound assignment reached, it should have been lowered".to_string(),
+                ))
+            }
+
+            ast::Expression::MatchExpression { location, .. } => Err(CompilerError::Internal(
+                Some(*location),
+                "match reached, it should have been lowered".to_string(),
+            )),
+        }
+    }
+
+    fn evaluate_argument(
+        &mut self,
+        argument: &'input ast::Expression<'input>,
+    ) -> Result<Value, CompilerError> {
+        let value = self.evaluate(argument)?;
+
+        if let Some(cast) = self.casts.get(&ByAddress(argument)) {
+            self.validate(&value, &cast.kind, &cast.path, "type assertion failed")?;
+        }
+
+        Ok(value)
+    }
+
+    /// Checks the value against the kind, failing with a message starting with `failure`.
+    fn validate(
+        &self,
+        value: &Value,
+        kind: &ast::VariableKind,
+        path: &str,
+        failure: &str,
+    ) -> Result<(), CompilerError> {
+        let is_valid = match (kind, value) {
+            (ast::VariableKind::Any, _) => true,
+            (ast::VariableKind::Function { .. }, _) => true,
+            (ast::VariableKind::Undefined, Value::Undefined) => true,
+            (ast::VariableKind::Null, Value::Null) => true,
+            (ast::VariableKind::Boolean, Value::Boolean(_)) => true,
+            (ast::VariableKind::String, Value::String(_)) => true,
+            (ast::VariableKind::Number, Value::Integer(_) | Value::Float(_)) => true,
+            (ast::VariableKind::Int32, Value::Integer(i)) => i32::try_from(*i).is_ok(),
+            (ast::VariableKind::Float64Array, Value::Float64Array(_)) => true,
+            (ast::VariableKind::Int64Array, Value::Int64Array(_)) => true,
+            (ast::VariableKind::Symbol, Value::Symbol(_)) => true,
+            (ast::VariableKind::Object { fields }, Value::Object(properties)) => {
+                for field in fields {
+                    let value = properties
+                        .borrow()
+                        .get(field.name.as_str())
+                        .cloned()
+                        .unwrap_or(Value::Undefined);
+
+                    let path = format!("{}.{}", path, field.name);
+
+                    self.validate(&value, &field.kind, &path, failure)?;
+                }
+
+                true
+            }
+            (ast::VariableKind::Array { kind }, Value::Array(items)) => {
+                for (index, item) in items.borrow().iter().enumerate() {
+                    self.validate(item, kind, &format!("{}[{}]", path, index), failure)?;
+                }
+
+                true
+            }
+            _ => false,
+        };
+
+        if is_valid {
+            return Ok(());
+        }
+
+        let expected = match kind {
+            ast::VariableKind::Array { .. } => "array",
+            kind => kind.get_name(),
+        };
+
+        self.runtime_error(format!(
+            "{} at {}: expected {}, got {}",
+            failure,
+            path,
+            expected,
+            value.get_type_name()
+        ))
+    }
+}
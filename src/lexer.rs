@@ -0,0 +1,248 @@
+use std::fmt;
+
+pub const KEYWORDS: [&str; 29] = [
+    "let",
+    "const",
+    "readonly",
+    "any",
+    "string",
+    "number",
+    "int32",
+    "symbol",
+    "Float64Array",
+    "Int64Array",
+    "true",
+    "false",
+    "null",
+    "undefined",
+    "typeof",
+    "Symbol",
+    "validate",
+    "return",
+    "defer",
+    "if",
+    "else",
+    "void",
+    "declare",
+    "function",
+    "macro",
+    "match",
+    "export",
+    "import",
+    "from",
+];
+
+/// Longer symbols come first, so that the longest one matching is taken.
+pub const SYMBOLS: [&str; 37] = [
+    "===", "!==", "...", "..=", "..", "==", "!=", "<=", ">=", "=>", "&&", "||", "+=", "-=", "*=",
+    "/=", "%=", ",", ".", ";", ":", "=", "+", "-", "!", "*", "/", "%", "<", ">", "{", "}", "(",
+    ")", "[", "]", "?",
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Token<'input> {
+    Keyword(&'static str),
+    Symbol(&'static str),
+    Identifier(&'input str),
+    /// `@name`, with the `@`.
+    Decorator(&'input str),
+    Integer(&'input str),
+    Float(&'input str),
+    /// A string literal, with its quotes.
+    String(&'input str),
+    /// `/// comment`, with the slashes.
+    DocComment(&'input str),
+    /// `// comment` or `/* comment */`, only produced by `Lexer::with_trivia`.
+    Comment(&'input str),
+    /// Only produced by `Lexer::with_trivia`.
+    Whitespace(&'input str),
+}
+
+impl<'input> Token<'input> {
+    /// The source text of the token.
+    pub fn text(&self) -> &'input str {
+        match self {
+            Token::Keyword(text) | Token::Symbol(text) => text,
+            Token::Identifier(text)
+            | Token::Decorator(text)
+            | Token::Integer(text)
+            | Token::Float(text)
+            | Token::String(text)
+            | Token::DocComment(text)
+            | Token::Comment(text)
+            | Token::Whitespace(text) => text,
+        }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Token::Comment(_) | Token::Whitespace(_))
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+/// No token starts at `location`, e.g. an unknown character or an unterminated
+/// string or comment.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LexicalError {
+    pub location: usize,
+}
+
+impl fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid token at {}", self.location)
+    }
+}
+
+pub type Spanned<'input> = Result<(usize, Token<'input>, usize), LexicalError>;
+
+/// Splits the source into tokens with their byte ranges. The parser reads it
+/// without trivia, tools such as highlighters can keep comments and whitespace
+/// with `with_trivia`. It stops after the first error.
+#[derive(Clone, Debug)]
+pub struct Lexer<'input> {
+    content: &'input str,
+    position: usize,
+    trivia: bool,
+    failed: bool,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(content: &'input str) -> Self {
+        Lexer {
+            content,
+            position: 0,
+            trivia: false,
+            failed: false,
+        }
+    }
+
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    fn take_while(&self, start: usize, predicate: impl Fn(char) -> bool) -> usize {
+        let rest = &self.content[start..];
+
+        start + rest.find(|c| !predicate(c)).unwrap_or(rest.len())
+    }
+
+    /// The token starting at the current position and its end.
+    fn token(&self) -> Result<(Token<'input>, usize), LexicalError> {
+        let start = self.position;
+        let rest = &self.content[start..];
+        let error = LexicalError { location: start };
+
+        let first = match rest.chars().next() {
+            Some(first) => first,
+            None => return Err(error),
+        };
+
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+        let end = if first.is_whitespace() {
+            self.take_while(start, char::is_whitespace)
+        } else if rest.starts_with("//") {
+            self.take_while(start, |c| c != '\n' && c != '\r')
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(index) => start + index + 4,
+                None => return Err(error),
+            }
+        } else if first.is_ascii_digit() {
+            self.take_while(start, |c| c.is_ascii_digit())
+        } else if first.is_ascii_alphabetic() || first == '_' {
+            self.take_while(start, is_word)
+        } else if first == '@'
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        {
+            self.take_while(start + 1, is_word)
+        } else if matches!(first, '\'' | '"' | '`') {
+            let length = rest[1..]
+                .find([first, '\n', '\r'])
+                .filter(|&index| rest[1 + index..].starts_with(first))
+                .ok_or(error)?;
+
+            start + length + 2
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or(error)?;
+
+            return Ok((Token::Symbol(symbol), start + symbol.len()));
+        };
+
+        let text = &self.content[start..end];
+
+        let token = if first.is_whitespace() {
+            Token::Whitespace(text)
+        } else if text.starts_with("///") {
+            Token::DocComment(text)
+        } else if text.starts_with("//") || text.starts_with("/*") {
+            Token::Comment(text)
+        } else if first.is_ascii_digit() {
+            let rest = &self.content[end..];
+
+            // a fraction needs digits after the dot, `1.` is an integer and a `.`
+            if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                let end = self.take_while(end + 1, |c| c.is_ascii_digit());
+                return Ok((Token::Float(&self.content[start..end]), end));
+            }
+
+            Token::Integer(text)
+        } else if first == '@' {
+            Token::Decorator(text)
+        } else if matches!(first, '\'' | '"' | '`') {
+            Token::String(text)
+        } else {
+            match KEYWORDS.iter().find(|keyword| **keyword == text) {
+                Some(keyword) => Token::Keyword(keyword),
+                None => Token::Identifier(text),
+            }
+        };
+
+        Ok((token, end))
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Spanned<'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.content.len() && !self.failed {
+            let start = self.position;
+
+            match self.token() {
+                Ok((token, end)) => {
+                    self.position = end;
+
+                    if self.trivia || !token.is_trivia() {
+                        return Some(Ok((start, token, end)));
+                    }
+                }
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// The comments of the source, doc comments included, with their offsets.
+pub fn comments(content: &str) -> Vec<(usize, &str)> {
+    Lexer::new(content)
+        .with_trivia()
+        .map_while(Result::ok)
+        .filter(|(_, token, _)| matches!(token, Token::Comment(_) | Token::DocComment(_)))
+        .map(|(start, token, _)| (start, token.text()))
+        .collect()
+}
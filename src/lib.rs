@@ -1,10 +1,42 @@
 use lalrpop_util::lalrpop_mod;
 
 pub mod ast;
+pub mod bench;
+pub mod builtins;
 pub mod cli;
+pub mod consteval;
+pub mod doc;
+pub mod document;
 pub mod error;
+pub mod explain;
+pub mod format_string;
+pub mod formatter;
 pub mod gen;
+pub mod interp;
+pub mod lexer;
+pub mod lifetime;
+pub mod lower;
+pub mod lsp;
+pub mod manifest;
+pub mod nesting;
+pub mod options;
+pub mod pipeline;
+pub mod repl;
+pub mod source;
 pub mod st;
+pub mod syntax;
+pub mod testing;
+pub mod typeck;
 pub mod value;
+pub mod visit;
+pub mod warning;
 
 lalrpop_mod!(pub parser);
+
+pub use document::Document;
+pub use error::{CompilerError, Diagnostic, DiagnosticCollector, DiagnosticEmitter};
+pub use gen::EmitKind;
+pub use manifest::Manifest;
+pub use options::{CompileOptions, Sanitizer};
+pub use pipeline::{compile_source, compile_source_with_passes, parse, Pass, Passes};
+pub use source::SourceFile;
@@ -0,0 +1,120 @@
+use generational_arena::Index;
+use indexmap::IndexSet;
+
+use crate::ast;
+use crate::st;
+use crate::visit::{self, Visitor};
+
+/// The largest integer the runtime encodes in the value word on every target.
+const IMMEDIATE_INT_MAX: u64 = (1 << 30) - 1;
+
+/// Whether the expression always evaluates to undefined, null, a boolean or a
+/// small integer, which live in the value word instead of the heap.
+pub fn is_immediate(expression: &ast::Expression) -> bool {
+    match expression {
+        ast::Expression::ConstantExpression { value, .. } => match value {
+            ast::Constant::Undefined | ast::Constant::Null | ast::Constant::Boolean(_) => true,
+            ast::Constant::Integer(n) => *n <= IMMEDIATE_INT_MAX,
+            _ => false,
+        },
+        ast::Expression::UnaryExpression {
+            operator,
+            expression,
+            ..
+        } => match operator {
+            ast::UnaryOperator::Not => true,
+            _ => matches!(
+                expression.as_ref(),
+                ast::Expression::ConstantExpression {
+                    value: ast::Constant::Integer(n),
+                    ..
+                } if *n <= IMMEDIATE_INT_MAX
+            ),
+        },
+        ast::Expression::BinaryExpression { operator, .. } => !matches!(
+            operator,
+            ast::BinaryOperator::Addition
+                | ast::BinaryOperator::Subtraction
+                | ast::BinaryOperator::Multiplication
+                | ast::BinaryOperator::Division
+                | ast::BinaryOperator::Mod
+        ),
+        ast::Expression::AssignmentExpression { expression, .. } => is_immediate(expression),
+        _ => false,
+    }
+}
+
+/// Finds the variables that may hold a value allocated on the heap, the only ones
+/// whose references are counted. Parameters may be given any value by the caller,
+/// other variables only hold what they are defined or assigned with.
+pub fn counted_variables(symbol_table: &st::SymbolTable) -> IndexSet<Index> {
+    let mut analysis = Analysis {
+        symbol_table,
+        counted: IndexSet::new(),
+    };
+
+    let main_scope = symbol_table.function_scope(&symbol_table.main_function.unwrap());
+
+    if let Some(statements) = main_scope.statements {
+        analysis.visit_statements(statements);
+    }
+
+    analysis.counted
+}
+
+struct Analysis<'a, 'input> {
+    symbol_table: &'a st::SymbolTable<'input>,
+    counted: IndexSet<Index>,
+}
+
+impl<'a, 'input> Visitor<'input> for Analysis<'a, 'input> {
+    fn visit_statement(&mut self, statement: &'input ast::Statement<'input>) {
+        match statement {
+            ast::Statement::DefinitionStatement {
+                definition,
+                expression: Some(expression),
+                ..
+            } if !is_immediate(expression) => {
+                self.counted
+                    .insert(*self.symbol_table.definition_ref(definition));
+            }
+
+            ast::Statement::FunctionStatement {
+                definition,
+                parameters,
+                ..
+            } => {
+                // external functions have no body to walk
+                if definition.is_external {
+                    return;
+                }
+
+                for parameter in parameters {
+                    self.counted
+                        .insert(*self.symbol_table.definition_ref(parameter));
+                }
+            }
+
+            _ => {}
+        }
+
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &'input ast::Expression<'input>) {
+        if let ast::Expression::AssignmentExpression {
+            identifier,
+            expression,
+            ..
+        } = expression
+        {
+            let variable_id = self.symbol_table.identifier_ref(identifier);
+
+            if self.symbol_table.variable(variable_id).is_static() && !is_immediate(expression) {
+                self.counted.insert(*variable_id);
+            }
+        }
+
+        visit::walk_expression(self, expression);
+    }
+}
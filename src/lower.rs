@@ -0,0 +1,755 @@
+use indexmap::{IndexMap, IndexSet};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::visit::{self, Folder};
+
+/// How deep macro calls may expand into further macro calls.
+const MAX_MACRO_DEPTH: usize = 64;
+
+/// Rewrites the surface syntax of the program into the core the symbol table,
+/// the type checker and the backends handle, e.g. `x += e` into `x = x + e` and
+/// `match` expressions into `if` statements, and expands the calls of the macros
+/// defined at its top level.
+pub fn lower(program: ast::Program) -> Result<ast::Program, CompilerError> {
+    let mut lowering = Lowering {
+        macros: IndexMap::new(),
+        depth: 0,
+        expansions: 0,
+        matches: 0,
+        temporaries: 0,
+        evaluated: IndexSet::new(),
+        hoisted: Vec::new(),
+        error: None,
+    };
+
+    let mut statements = Vec::new();
+
+    for statement in program.statements {
+        match statement {
+            ast::Statement::MacroStatement {
+                location,
+                name,
+                parameters,
+                statements,
+            } => lowering.define_macro(location, name, parameters, statements),
+            statement => statements.push(statement),
+        }
+    }
+
+    let statements = lowering.fold_statements(statements);
+
+    match lowering.error {
+        Some(err) => Err(err),
+        None => Ok(ast::Program { statements }),
+    }
+}
+
+struct Macro<'input> {
+    parameters: Vec<&'input str>,
+    statements: Vec<ast::Statement<'input>>,
+}
+
+struct Lowering<'input> {
+    macros: IndexMap<&'input str, Macro<'input>>,
+    /// The number of macro calls the statements being lowered are expanded from.
+    depth: usize,
+    expansions: usize,
+    matches: usize,
+    temporaries: usize,
+    /// The variables holding the results of `match` expressions and the values of
+    /// the temporaries, which nothing assigns once they are set.
+    evaluated: IndexSet<&'input str>,
+    /// The statements lowered `match` expressions run before the statement they
+    /// are in.
+    hoisted: Vec<ast::Statement<'input>>,
+    /// The first construct that could not be lowered, folding goes on past it.
+    error: Option<CompilerError>,
+}
+
+impl<'input> Lowering<'input> {
+    fn fail(&mut self, location: (usize, usize), reason: String) {
+        self.error
+            .get_or_insert(CompilerError::InvalidMacro(location, reason));
+    }
+
+    fn define_macro(
+        &mut self,
+        location: (usize, usize),
+        name: &'input str,
+        parameters: Vec<&'input str>,
+        statements: Vec<ast::Statement<'input>>,
+    ) {
+        let mut names = IndexSet::new();
+        bindings(&statements, &mut names);
+
+        if let Some(parameter) = parameters.iter().find(|p| names.contains(*p)) {
+            let reason = format!("macro `{}` defines its parameter `{}`", name, parameter);
+            self.fail(location, reason);
+        }
+
+        let definition = Macro {
+            parameters,
+            statements,
+        };
+
+        if self.macros.insert(name, definition).is_some() {
+            self.fail(location, format!("macro `{}` is already defined", name));
+        }
+    }
+
+    /// The statements of the macro, with the arguments in place of its parameters
+    /// and fresh names for the variables and functions it defines, so that they
+    /// neither clash with nor capture the ones of the call site.
+    fn expand(
+        &mut self,
+        location: (usize, usize),
+        name: &'input str,
+        arguments: Vec<ast::Expression<'input>>,
+    ) -> Vec<ast::Statement<'input>> {
+        let definition = match self.macros.get(name) {
+            Some(definition) => definition,
+            None => {
+                self.fail(location, format!("macro `{}` is not defined", name));
+                return Vec::new();
+            }
+        };
+
+        if definition.parameters.len() != arguments.len() {
+            let reason = format!(
+                "macro `{}` takes {} arguments, but got {}",
+                name,
+                definition.parameters.len(),
+                arguments.len()
+            );
+            self.fail(location, reason);
+            return Vec::new();
+        }
+
+        if self.depth >= MAX_MACRO_DEPTH {
+            let reason = format!("macro `{}` expands into too many nested macro calls", name);
+            self.fail(location, reason);
+            return Vec::new();
+        }
+
+        let mut names = IndexSet::new();
+        bindings(&definition.statements, &mut names);
+
+        self.expansions += 1;
+
+        let mut expansion = Expansion {
+            location,
+            name,
+            arguments: definition
+                .parameters
+                .iter()
+                .copied()
+                .zip(arguments)
+                .collect(),
+            renames: names
+                .into_iter()
+                .map(|binding| (binding, intern(format!("{}#{}", binding, self.expansions))))
+                .collect(),
+            error: None,
+        };
+
+        let statements = expansion.fold_statements(definition.statements.clone());
+
+        if let Some(err) = expansion.error {
+            self.error.get_or_insert(err);
+        }
+
+        statements
+    }
+}
+
+impl<'input> Lowering<'input> {
+    /// Lowers `match (x) { ... }` into statements that put the value of the first
+    /// arm matching `x` into a variable, which replaces the expression. The value
+    /// is `undefined` if no arm matches.
+    fn lower_match(
+        &mut self,
+        location: (usize, usize),
+        subject: ast::Expression<'input>,
+        arms: Vec<ast::MatchArm<'input>>,
+    ) -> ast::Expression<'input> {
+        self.matches += 1;
+
+        // the leading `_` keeps them out of the unused variable warnings
+        let name = intern(format!("_match#{}", self.matches));
+        let result = intern(format!("_match#{}#result", self.matches));
+        let done = intern(format!("_match#{}#done", self.matches));
+        self.evaluated.insert(result);
+
+        let mut statements = vec![
+            definition(location, name, Some(subject)),
+            definition(location, result, None),
+            definition(location, done, Some(boolean(location, false))),
+        ];
+
+        for (index, arm) in arms.into_iter().enumerate() {
+            let location = arm.location;
+
+            let mut tests = Vec::new();
+            let mut bindings = Vec::new();
+            pattern_tests(
+                &arm.pattern,
+                variable(location, name),
+                &mut tests,
+                &mut bindings,
+            );
+
+            let mut body: Vec<_> = bindings
+                .into_iter()
+                .map(|(binding, identifier)| {
+                    let value = ast::Expression::VariableExpression {
+                        location,
+                        identifier,
+                    };
+
+                    definition(location, binding, Some(value))
+                })
+                .collect();
+
+            body.push(assignment(location, done, boolean(location, true)));
+            body.push(assignment(location, result, arm.expression));
+
+            // `&&` evaluates both operands, so the tests of an object pattern are
+            // nested to only read the properties of objects
+            for test in tests.into_iter().rev() {
+                body = vec![if_statement(location, test, body)];
+            }
+
+            if index > 0 {
+                let test = ast::Expression::UnaryExpression {
+                    location,
+                    operator: ast::UnaryOperator::Not,
+                    expression: Box::new(ast::Expression::VariableExpression {
+                        location,
+                        identifier: variable(location, done),
+                    }),
+                };
+
+                body = vec![if_statement(location, test, body)];
+            }
+
+            statements.extend(body);
+        }
+
+        let statements = self.fold_statements(statements);
+        self.hoisted.extend(statements);
+
+        ast::Expression::VariableExpression {
+            location,
+            identifier: variable(location, result),
+        }
+    }
+
+    /// Folds expressions evaluated from left to right, like the arguments of a
+    /// call. The ones before an expression hoisting the statements of a `match`
+    /// are put into variables ahead of those statements, so they still run first.
+    fn fold_in_order(
+        &mut self,
+        expressions: Vec<ast::Expression<'input>>,
+    ) -> Vec<ast::Expression<'input>> {
+        let mut folded: Vec<ast::Expression<'input>> = Vec::new();
+
+        for expression in expressions {
+            let start = self.hoisted.len();
+            let expression = self.fold_expression(expression);
+
+            if self.hoisted.len() > start {
+                let hoisted = self.hoisted.split_off(start);
+
+                for earlier in folded.iter_mut() {
+                    if self.is_evaluated(earlier) {
+                        continue;
+                    }
+
+                    self.temporaries += 1;
+
+                    let location = earlier.get_location().unwrap_or((0, 0));
+                    let name = intern(format!("_temp#{}", self.temporaries));
+                    self.evaluated.insert(name);
+                    let value = std::mem::replace(
+                        earlier,
+                        ast::Expression::VariableExpression {
+                            location,
+                            identifier: variable(location, name),
+                        },
+                    );
+
+                    self.hoisted.push(definition(location, name, Some(value)));
+                }
+
+                self.hoisted.extend(hoisted);
+            }
+
+            folded.push(expression);
+        }
+
+        folded
+    }
+
+    /// Whether the expression is a constant or a variable introduced by the lowering,
+    /// which the statements of a `match` cannot change.
+    fn is_evaluated(&self, expression: &ast::Expression) -> bool {
+        match expression {
+            ast::Expression::ConstantExpression { .. } => true,
+            ast::Expression::VariableExpression {
+                identifier: ast::VariableIdentifier::Name { name, .. },
+                ..
+            } => self.evaluated.contains(name),
+            _ => false,
+        }
+    }
+}
+
+/// The tests a value must pass to match the pattern, and the names it binds to
+/// the parts of the value.
+fn pattern_tests<'input>(
+    pattern: &ast::Pattern<'input>,
+    identifier: ast::VariableIdentifier<'input>,
+    tests: &mut Vec<ast::Expression<'input>>,
+    bindings: &mut Vec<(&'input str, ast::VariableIdentifier<'input>)>,
+) {
+    let location = identifier.get_location();
+    let value = || ast::Expression::VariableExpression {
+        location,
+        identifier: identifier.clone(),
+    };
+    let compare = |operator, right| ast::Expression::BinaryExpression {
+        location,
+        operator,
+        left: Box::new(value()),
+        right: Box::new(ast::Expression::ConstantExpression {
+            location,
+            value: right,
+        }),
+    };
+
+    match pattern {
+        ast::Pattern::Constant(constant) => {
+            tests.push(compare(ast::BinaryOperator::StrictEqual, constant.clone()));
+        }
+
+        ast::Pattern::Symbol(name) => tests.push(ast::Expression::BinaryExpression {
+            location,
+            operator: ast::BinaryOperator::StrictEqual,
+            left: Box::new(value()),
+            right: Box::new(ast::Expression::SymbolExpression {
+                location,
+                name: Box::new(ast::Expression::ConstantExpression {
+                    location,
+                    value: ast::Constant::String(name),
+                }),
+            }),
+        }),
+
+        ast::Pattern::Binding("_") => {}
+
+        ast::Pattern::Binding(name) => bindings.push((name, identifier)),
+
+        ast::Pattern::Object(properties) => {
+            tests.push(ast::Expression::BinaryExpression {
+                location,
+                operator: ast::BinaryOperator::StrictEqual,
+                left: Box::new(ast::Expression::TypeOfExpression {
+                    location,
+                    expression: Box::new(value()),
+                }),
+                right: Box::new(ast::Expression::ConstantExpression {
+                    location,
+                    value: ast::Constant::String("object"),
+                }),
+            });
+            tests.push(compare(
+                ast::BinaryOperator::StrictNotEqual,
+                ast::Constant::Null,
+            ));
+
+            for (property, pattern) in properties {
+                let identifier = ast::VariableIdentifier::Property {
+                    location,
+                    base: Box::new(identifier.clone()),
+                    property,
+                };
+
+                pattern_tests(pattern, identifier, tests, bindings);
+            }
+        }
+    }
+}
+
+fn variable(location: (usize, usize), name: &str) -> ast::VariableIdentifier<'_> {
+    ast::VariableIdentifier::Name { location, name }
+}
+
+fn boolean<'input>(location: (usize, usize), value: bool) -> ast::Expression<'input> {
+    ast::Expression::ConstantExpression {
+        location,
+        value: ast::Constant::Boolean(value),
+    }
+}
+
+fn definition<'input>(
+    location: (usize, usize),
+    name: &'input str,
+    expression: Option<ast::Expression<'input>>,
+) -> ast::Statement<'input> {
+    ast::Statement::DefinitionStatement {
+        location,
+        definition: ast::VariableDefinition {
+            location,
+            name,
+            kind: ast::VariableKind::Any,
+            is_annotated: false,
+            is_writable: true,
+            is_external: false,
+            decorators: IndexSet::new(),
+            docs: Vec::new(),
+        },
+        expression,
+    }
+}
+
+fn assignment<'input>(
+    location: (usize, usize),
+    name: &'input str,
+    expression: ast::Expression<'input>,
+) -> ast::Statement<'input> {
+    ast::Statement::ExpressionStatement {
+        expression: ast::Expression::AssignmentExpression {
+            location,
+            identifier: variable(location, name),
+            expression: Box::new(expression),
+        },
+    }
+}
+
+fn if_statement<'input>(
+    location: (usize, usize),
+    condition: ast::Expression<'input>,
+    statements: Vec<ast::Statement<'input>>,
+) -> ast::Statement<'input> {
+    ast::Statement::IfStatement {
+        location,
+        condition,
+        statements,
+        else_statements: None,
+    }
+}
+
+/// Whether reading the identifier twice has the same result as reading it once.
+fn is_repeatable(identifier: &ast::VariableIdentifier) -> bool {
+    match identifier {
+        ast::VariableIdentifier::Name { .. } => true,
+        ast::VariableIdentifier::Property { base, .. } => is_repeatable(base),
+        ast::VariableIdentifier::Index { base, index, .. } => {
+            is_repeatable(base)
+                && matches!(
+                    index.as_ref(),
+                    ast::Expression::ConstantExpression { .. }
+                        | ast::Expression::VariableExpression {
+                            identifier: ast::VariableIdentifier::Name { .. },
+                            ..
+                        }
+                )
+        }
+    }
+}
+
+impl<'input> Folder<'input> for Lowering<'input> {
+    fn fold_statements(
+        &mut self,
+        statements: Vec<ast::Statement<'input>>,
+    ) -> Vec<ast::Statement<'input>> {
+        let mut lowered = Vec::new();
+
+        // the statements of the blocks within a statement are hoisted within them
+        let outer = std::mem::take(&mut self.hoisted);
+
+        for statement in statements {
+            match statement {
+                ast::Statement::MacroCallStatement {
+                    location,
+                    name,
+                    arguments,
+                } => {
+                    let statements = self.expand(location, name, arguments);
+
+                    self.depth += 1;
+                    lowered.extend(self.fold_statements(statements));
+                    self.depth -= 1;
+                }
+
+                ast::Statement::MacroStatement { location, .. } => {
+                    let reason = "macros can only be defined at the top level".to_string();
+                    self.fail(location, reason);
+                }
+
+                statement => {
+                    let statement = self.fold_statement(statement);
+
+                    lowered.append(&mut self.hoisted);
+                    lowered.push(statement);
+                }
+            }
+        }
+
+        self.hoisted = outer;
+
+        lowered
+    }
+
+    fn fold_expression(&mut self, expression: ast::Expression<'input>) -> ast::Expression<'input> {
+        // the arms are lowered on their own, as only the one matching is evaluated
+        if let ast::Expression::MatchExpression {
+            location,
+            subject,
+            arms,
+        } = expression
+        {
+            return self.lower_match(location, *subject, arms);
+        }
+
+        let expression = match expression {
+            ast::Expression::CallExpression {
+                location,
+                identifier,
+                arguments,
+            } => ast::Expression::CallExpression {
+                location,
+                identifier: self.fold_identifier(identifier),
+                arguments: self.fold_in_order(arguments),
+            },
+
+            ast::Expression::ArrayExpression { location, items } => {
+                ast::Expression::ArrayExpression {
+                    location,
+                    items: self.fold_in_order(items),
+                }
+            }
+
+            ast::Expression::ObjectExpression {
+                location,
+                properties,
+            } => {
+                let (names, values): (Vec<_>, Vec<_>) = properties.into_iter().unzip();
+
+                ast::Expression::ObjectExpression {
+                    location,
+                    properties: names.into_iter().zip(self.fold_in_order(values)).collect(),
+                }
+            }
+
+            ast::Expression::RangeExpression {
+                location,
+                start,
+                end,
+                is_inclusive,
+            } => {
+                let mut bounds = self.fold_in_order(vec![*start, *end]).into_iter();
+
+                ast::Expression::RangeExpression {
+                    location,
+                    start: Box::new(bounds.next().unwrap()),
+                    end: Box::new(bounds.next().unwrap()),
+                    is_inclusive,
+                }
+            }
+
+            ast::Expression::BinaryExpression {
+                location,
+                operator,
+                left,
+                right,
+            } => {
+                let mut operands = self.fold_in_order(vec![*left, *right]).into_iter();
+
+                ast::Expression::BinaryExpression {
+                    location,
+                    operator,
+                    left: Box::new(operands.next().unwrap()),
+                    right: Box::new(operands.next().unwrap()),
+                }
+            }
+
+            expression => visit::fold_expression(self, expression),
+        };
+
+        match expression {
+            ast::Expression::CompoundAssignmentExpression {
+                location,
+                identifier,
+                operator,
+                expression,
+            } => {
+                if !is_repeatable(&identifier) {
+                    self.error
+                        .get_or_insert(CompilerError::InvalidCompoundAssignment(location));
+                }
+
+                ast::Expression::AssignmentExpression {
+                    location,
+                    identifier: identifier.clone(),
+                    expression: Box::new(ast::Expression::BinaryExpression {
+                        location,
+                        operator,
+                        left: Box::new(ast::Expression::VariableExpression {
+                            location: identifier.get_location(),
+                            identifier,
+                        }),
+                        right: expression,
+                    }),
+                }
+            }
+
+            expression => expression,
+        }
+    }
+}
+
+/// The names of the variables and functions the statements define, in any of
+/// their blocks.
+fn bindings<'input>(statements: &[ast::Statement<'input>], names: &mut IndexSet<&'input str>) {
+    for statement in statements {
+        match statement {
+            ast::Statement::DefinitionStatement { definition, .. } => {
+                names.insert(definition.name);
+            }
+
+            ast::Statement::FunctionStatement {
+                definition,
+                parameters,
+                statements,
+                ..
+            } => {
+                names.insert(definition.name);
+                names.extend(parameters.iter().map(|parameter| parameter.name));
+                bindings(statements, names);
+            }
+
+            ast::Statement::IfStatement {
+                statements,
+                else_statements,
+                ..
+            } => {
+                bindings(statements, names);
+
+                if let Some(else_statements) = else_statements {
+                    bindings(else_statements, names);
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Names made up for the bindings of macro expansions. They live as long as the
+/// compiler and are shared, so compiling a program again does not add more.
+fn intern(name: String) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut names = NAMES.lock().unwrap();
+
+    if let Some(name) = names.get(name.as_str()) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(name.into_boxed_str());
+    names.insert(name);
+
+    name
+}
+
+/// Substitutes the arguments of a macro call and renames the bindings of its body.
+struct Expansion<'input> {
+    location: (usize, usize),
+    name: &'input str,
+    arguments: IndexMap<&'input str, ast::Expression<'input>>,
+    renames: IndexMap<&'input str, &'static str>,
+    error: Option<CompilerError>,
+}
+
+impl<'input> Expansion<'input> {
+    fn rename(&self, definition: &mut ast::VariableDefinition<'input>) {
+        if let Some(renamed) = self.renames.get(definition.name) {
+            definition.name = renamed;
+        }
+    }
+}
+
+impl<'input> Folder<'input> for Expansion<'input> {
+    fn fold_statement(&mut self, statement: ast::Statement<'input>) -> ast::Statement<'input> {
+        let mut statement = visit::fold_statement(self, statement);
+
+        match &mut statement {
+            ast::Statement::DefinitionStatement { definition, .. } => self.rename(definition),
+            ast::Statement::FunctionStatement {
+                definition,
+                parameters,
+                ..
+            } => {
+                self.rename(definition);
+
+                for parameter in parameters.iter_mut() {
+                    self.rename(parameter);
+                }
+            }
+            _ => {}
+        }
+
+        statement
+    }
+
+    fn fold_expression(&mut self, expression: ast::Expression<'input>) -> ast::Expression<'input> {
+        if let ast::Expression::VariableExpression {
+            identifier: ast::VariableIdentifier::Name { name, .. },
+            ..
+        } = &expression
+        {
+            // the arguments belong to the call site, so they are left as they are
+            if let Some(argument) = self.arguments.get(name) {
+                return argument.clone();
+            }
+        }
+
+        visit::fold_expression(self, expression)
+    }
+
+    fn fold_identifier(
+        &mut self,
+        identifier: ast::VariableIdentifier<'input>,
+    ) -> ast::VariableIdentifier<'input> {
+        let (location, name) = match identifier {
+            ast::VariableIdentifier::Name { location, name } => (location, name),
+            identifier => return visit::fold_identifier(self, identifier),
+        };
+
+        if let Some(renamed) = self.renames.get(name) {
+            return ast::VariableIdentifier::Name {
+                location,
+                name: renamed,
+            };
+        }
+
+        match self.arguments.get(name) {
+            Some(ast::Expression::VariableExpression { identifier, .. }) => identifier.clone(),
+            Some(_) => {
+                let reason = format!(
+                    "macro `{}` assigns or calls `{}`, so its argument must be a variable",
+                    self.name, name
+                );
+                self.error
+                    .get_or_insert(CompilerError::InvalidMacro(self.location, reason));
+
+                ast::VariableIdentifier::Name { location, name }
+            }
+            None => ast::VariableIdentifier::Name { location, name },
+        }
+    }
+}
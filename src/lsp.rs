@@ -0,0 +1,357 @@
+use indexmap::IndexMap;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationKind, PublishDiagnostics,
+};
+use lsp_types::request::{GotoDefinition, HoverRequest, References, Request as RequestKind};
+use lsp_types::{
+    DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, Position, PublishDiagnosticsParams, Range, ReferenceParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+use crate::document::Document;
+use crate::error::{CompilerError, Diagnostic, Severity};
+use crate::typeck;
+use crate::warning::{Level, Suppressions, WarningLevels};
+
+fn server_error(err: impl std::fmt::Display) -> CompilerError {
+    CompilerError::CliError(format!("Language server failed: {}", err))
+}
+
+/// Converts a byte offset into a position counting UTF-16 code units, like LSP clients do.
+fn position(content: &str, offset: usize) -> Position {
+    let offset = offset.min(content.len());
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    Position::new(
+        before.matches('\n').count() as u32,
+        content[line_start..offset].encode_utf16().count() as u32,
+    )
+}
+
+fn offset(content: &str, position: Position) -> usize {
+    let line_start = content
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = content[line_start..].split('\n').next().unwrap_or("");
+
+    let mut character = 0;
+
+    for (index, c) in line.char_indices() {
+        if character >= position.character {
+            return line_start + index;
+        }
+
+        character += c.len_utf16() as u32;
+    }
+
+    line_start + line.len()
+}
+
+fn range(content: &str, (start, end): (usize, usize)) -> Range {
+    Range::new(position(content, start), position(content, end))
+}
+
+/// Everything the editor should show for the document: the first error of the
+/// compilation, if any, and the warnings that are not allowed by default.
+fn diagnostics(document: &Document) -> Vec<Diagnostic> {
+    let levels = WarningLevels::default();
+    let suppressions = Suppressions::new(document.content());
+
+    let result = document.with_symbol_table(|symbol_table| {
+        let mut diagnostics = symbol_table
+            .warnings
+            .iter()
+            .filter(|warning| levels.level(warning.kind()) != Level::Allow)
+            .filter(|warning| !suppressions.is_suppressed(warning))
+            .map(Diagnostic::from)
+            .collect::<Vec<_>>();
+
+        if let Err(err) = typeck::TypeChecker::new(symbol_table).check() {
+            diagnostics.push(Diagnostic::from(&err));
+        }
+
+        diagnostics
+    });
+
+    result.unwrap_or_else(|err| vec![Diagnostic::from(&err)])
+}
+
+/// Answers hover and go-to-definition requests about the name at the offset,
+/// returning the range of its definition, its description and its doc comments.
+fn lookup(document: &Document, offset: usize) -> Option<((usize, usize), String, String)> {
+    let result = document.with_symbol_table(|symbol_table| {
+        let definition = symbol_table.definition_at(offset)?;
+        let kind = symbol_table
+            .variable(symbol_table.definition_ref(definition))
+            .get_kind()
+            .ok()?;
+
+        Some((
+            definition.location,
+            format!("{}: {}", definition.name, kind),
+            definition.docs.join("\n"),
+        ))
+    });
+
+    result.ok()?
+}
+
+/// Ranges of the uses of the name at the offset, preceded by its definition if asked.
+fn references(
+    document: &Document,
+    offset: usize,
+    include_declaration: bool,
+) -> Vec<(usize, usize)> {
+    let result = document.with_symbol_table(|symbol_table| {
+        let definition = match symbol_table.definition_at(offset) {
+            Some(definition) => definition,
+            None => return Vec::new(),
+        };
+
+        let mut locations = Vec::new();
+
+        if include_declaration {
+            locations.push(definition.location);
+        }
+
+        locations.extend(symbol_table.references_of(symbol_table.definition_ref(definition)));
+        locations
+    });
+
+    result.unwrap_or_default()
+}
+
+/// Serves a single client over the standard input and output. Documents are
+/// analyzed from scratch whenever they change, and kept parsed for the requests
+/// about them while they have no syntax errors.
+struct Server {
+    connection: Connection,
+    documents: IndexMap<Url, Document>,
+}
+
+impl Server {
+    fn send(&self, message: Message) -> Result<(), CompilerError> {
+        self.connection.sender.send(message).map_err(server_error)
+    }
+
+    /// Parses the new content of the document and publishes its diagnostics.
+    fn update(&mut self, uri: Url, content: String) -> Result<(), CompilerError> {
+        let diagnostics = match Document::parse(content.clone()) {
+            Ok(document) => {
+                let diagnostics = diagnostics(&document);
+                self.documents.insert(uri.clone(), document);
+
+                diagnostics
+            }
+            Err(err) => {
+                self.documents.shift_remove(&uri);
+
+                vec![Diagnostic::from(&err)]
+            }
+        };
+
+        self.publish_diagnostics(&uri, &content, diagnostics)
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: &Url,
+        content: &str,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Result<(), CompilerError> {
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| lsp_types::Diagnostic {
+                range: range(content, diagnostic.location.unwrap_or((0, 0))),
+                severity: Some(match diagnostic.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                code: diagnostic
+                    .code
+                    .map(|code| NumberOrString::String(code.to_string())),
+                source: Some("mini".to_string()),
+                message: diagnostic.message,
+                ..Default::default()
+            })
+            .collect();
+
+        let params = PublishDiagnosticsParams::new(uri.clone(), diagnostics, None);
+
+        self.send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), CompilerError> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).map_err(server_error)?;
+
+                self.update(params.text_document.uri, params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).map_err(server_error)?;
+
+                // the server asks for full document synchronization
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(params.text_document.uri, change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).map_err(server_error)?;
+                let uri = params.text_document.uri;
+
+                self.documents.shift_remove(&uri);
+                self.publish_diagnostics(&uri, "", Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn respond<T: serde::Serialize>(&self, id: RequestId, result: T) -> Result<(), CompilerError> {
+        self.send(Message::Response(Response::new_ok(id, result)))
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<(), CompilerError> {
+        match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let (id, params) = request
+                    .extract::<HoverParams>(HoverRequest::METHOD)
+                    .map_err(server_error)?;
+                let position = params.text_document_position_params;
+
+                let hover = self
+                    .documents
+                    .get(&position.text_document.uri)
+                    .and_then(|document| {
+                        let (_, description, docs) =
+                            lookup(document, offset(document.content(), position.position))?;
+
+                        let mut value = format!("```ts\n{}\n```", description);
+
+                        if !docs.is_empty() {
+                            value.push_str(&format!("\n\n{}", docs));
+                        }
+
+                        Some(Hover {
+                            contents: HoverContents::Markup(MarkupContent {
+                                kind: MarkupKind::Markdown,
+                                value,
+                            }),
+                            range: None,
+                        })
+                    });
+
+                self.respond(id, hover)
+            }
+            GotoDefinition::METHOD => {
+                let (id, params) = request
+                    .extract::<GotoDefinitionParams>(GotoDefinition::METHOD)
+                    .map_err(server_error)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+
+                let definition = self.documents.get(&uri).and_then(|document| {
+                    let content = document.content();
+                    let (location, ..) = lookup(document, offset(content, position.position))?;
+
+                    Some(GotoDefinitionResponse::Scalar(Location::new(
+                        uri.clone(),
+                        range(content, location),
+                    )))
+                });
+
+                self.respond(id, definition)
+            }
+            References::METHOD => {
+                let (id, params) = request
+                    .extract::<ReferenceParams>(References::METHOD)
+                    .map_err(server_error)?;
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+
+                let locations = self.documents.get(&uri).map(|document| {
+                    let content = document.content();
+
+                    references(
+                        document,
+                        offset(content, position.position),
+                        params.context.include_declaration,
+                    )
+                    .into_iter()
+                    .map(|location| Location::new(uri.clone(), range(content, location)))
+                    .collect::<Vec<_>>()
+                });
+
+                self.respond(id, locations)
+            }
+            _ => self.send(Message::Response(Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request: {}", request.method),
+            ))),
+        }
+    }
+
+    fn main_loop(&mut self) -> Result<(), CompilerError> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self
+                        .connection
+                        .handle_shutdown(&request)
+                        .map_err(server_error)?
+                    {
+                        return Ok(());
+                    }
+
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the language server until the client shuts it down.
+pub fn run() -> Result<(), CompilerError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+
+    connection
+        .initialize(serde_json::to_value(capabilities).map_err(server_error)?)
+        .map_err(server_error)?;
+
+    let mut server = Server {
+        connection,
+        documents: IndexMap::new(),
+    };
+    server.main_loop()?;
+
+    // the writer thread only finishes once the connection is dropped
+    drop(server);
+    io_threads.join().map_err(server_error)
+}
@@ -0,0 +1,152 @@
+use inkwell::OptimizationLevel;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::CompilerError;
+use crate::options::CompileOptions;
+use crate::pipeline;
+
+fn default_output() -> PathBuf {
+    PathBuf::from("foo")
+}
+
+/// Project settings read from a `mini.toml` file by `mini build`. Paths are
+/// relative to the directory of the manifest.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Manifest {
+    pub entry: PathBuf,
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+    pub target: Option<String>,
+    #[serde(default)]
+    pub opt_level: u8,
+    /// Passed to the linker as `-l<name>`.
+    #[serde(default)]
+    pub libraries: Vec<String>,
+    /// Globs of files compiled together with the entry, placed before it in glob order.
+    #[serde(default)]
+    pub sources: Vec<String>,
+
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+impl Manifest {
+    pub const FILE_NAME: &'static str = "mini.toml";
+
+    pub fn load(path: &Path) -> Result<Manifest, CompilerError> {
+        let content = fs::read_to_string(path)
+            .map_err(|_| CompilerError::CliError(format!("File not found: {}", path.display())))?;
+
+        let root = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Manifest::parse(&content, root).map_err(|err| {
+            CompilerError::CliError(format!("Invalid manifest {}: {}", path.display(), err))
+        })
+    }
+
+    pub fn parse(content: &str, root: &Path) -> Result<Manifest, String> {
+        let mut manifest: Manifest =
+            toml::from_str(content).map_err(|err| err.message().to_string())?;
+        manifest.root = root.to_path_buf();
+
+        if manifest.opt_level > 3 {
+            return Err(format!(
+                "`opt-level` must be between 0 and 3, got {}",
+                manifest.opt_level
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn entry_path(&self) -> PathBuf {
+        self.root.join(&self.entry)
+    }
+
+    /// Files of the program in compilation order, the entry being the last one.
+    pub fn source_files(&self) -> Result<Vec<PathBuf>, CompilerError> {
+        let entry = self.entry_path();
+        let mut files = Vec::new();
+
+        for pattern in self.sources.iter() {
+            let pattern = self.root.join(pattern);
+            let pattern = pattern.to_string_lossy();
+
+            let paths = glob::glob(&pattern).map_err(|err| {
+                CompilerError::CliError(format!("Invalid source glob `{}`: {}", pattern, err))
+            })?;
+
+            for path in paths.filter_map(Result::ok) {
+                if path != entry && !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.push(entry);
+
+        Ok(files)
+    }
+
+    /// Reads and joins the source files into a single program.
+    pub fn read_source(&self) -> Result<String, CompilerError> {
+        let mut content = String::new();
+
+        for file in self.source_files()? {
+            let file_content = fs::read_to_string(&file).map_err(|_| {
+                CompilerError::CliError(format!("File not found: {}", file.display()))
+            })?;
+
+            content.push_str(&file_content);
+
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+        }
+
+        Ok(content)
+    }
+
+    pub fn get_opt_level(&self) -> OptimizationLevel {
+        match self.opt_level {
+            0 => OptimizationLevel::None,
+            1 => OptimizationLevel::Less,
+            2 => OptimizationLevel::Default,
+            _ => OptimizationLevel::Aggressive,
+        }
+    }
+
+    /// Applies the manifest on top of the given compile options.
+    pub fn options(
+        &self,
+        compile: CompileOptions,
+        backend: pipeline::Backend,
+    ) -> Result<pipeline::Options, CompilerError> {
+        let target = match &self.target {
+            Some(target) => target_lexicon::Triple::from_str(target).map_err(|err| {
+                CompilerError::CliError(format!("Invalid target `{}`: {}", target, err))
+            })?,
+            None => target_lexicon::Triple::host(),
+        };
+
+        let mut compile = compile
+            .file_name(&self.entry_path().to_string_lossy())
+            .opt_level(self.get_opt_level());
+
+        for library in self.libraries.iter() {
+            compile = compile.library(library);
+        }
+
+        Ok(pipeline::Options {
+            compile,
+            backend,
+            targets: vec![target],
+            universal: false,
+            out_file: self.root.join(&self.output),
+        })
+    }
+}
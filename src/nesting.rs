@@ -0,0 +1,172 @@
+use crate::ast;
+use crate::error::CompilerError;
+
+/// The default of `CompileOptions::max_nesting`, deep enough for any program
+/// written by hand while leaving the recursive phases of the compiler plenty of
+/// stack.
+pub const DEFAULT_MAX_NESTING: usize = 256;
+
+enum Node<'a, 'input> {
+    Statement(&'a ast::Statement<'input>),
+    Expression(&'a ast::Expression<'input>),
+    Identifier(&'a ast::VariableIdentifier<'input>),
+}
+
+/// Fails when statements, expressions or identifiers are nested more than
+/// `max_nesting` levels deep. The symbol table, the type checker and the backends
+/// walk the AST recursively and would run out of stack on such a program, so the
+/// walk here keeps its own worklist instead.
+pub fn check_nesting(program: &ast::Program, max_nesting: usize) -> Result<(), CompilerError> {
+    let mut worklist: Vec<(Node, usize)> = program
+        .statements
+        .iter()
+        .map(|statement| (Node::Statement(statement), 1))
+        .collect();
+
+    while let Some((node, depth)) = worklist.pop() {
+        if depth > max_nesting {
+            let location = match node {
+                Node::Statement(statement) => statement.get_location(),
+                Node::Expression(expression) => expression.get_location(),
+                Node::Identifier(identifier) => Some(identifier.get_location()),
+            };
+
+            return Err(CompilerError::TooDeeplyNested(location, max_nesting));
+        }
+
+        let statement_node = |statement| (Node::Statement(statement), depth + 1);
+        let expression_node = |expression| (Node::Expression(expression), depth + 1);
+        let identifier_node = |identifier| (Node::Identifier(identifier), depth + 1);
+
+        match node {
+            Node::Statement(statement) => match statement {
+                ast::Statement::ExpressionStatement { expression: e } => {
+                    worklist.push(expression_node(e));
+                }
+
+                ast::Statement::DefinitionStatement { expression: e, .. }
+                | ast::Statement::ReturnStatement { expression: e, .. } => {
+                    worklist.extend(e.iter().map(expression_node));
+                }
+
+                ast::Statement::FunctionStatement { statements: s, .. }
+                | ast::Statement::DeferStatement { statements: s, .. }
+                | ast::Statement::MacroStatement { statements: s, .. } => {
+                    worklist.extend(s.iter().map(statement_node));
+                }
+
+                ast::Statement::MacroCallStatement { arguments, .. } => {
+                    worklist.extend(arguments.iter().map(expression_node));
+                }
+
+                ast::Statement::IfStatement {
+                    condition,
+                    statements: s,
+                    else_statements,
+                    ..
+                } => {
+                    worklist.push(expression_node(condition));
+                    worklist.extend(s.iter().map(statement_node));
+
+                    if let Some(else_statements) = else_statements {
+                        worklist.extend(else_statements.iter().map(statement_node));
+                    }
+                }
+
+                ast::Statement::EmptyStatement => {}
+            },
+
+            Node::Expression(e) => match e {
+                ast::Expression::ConstantExpression { .. } | ast::Expression::Empty => {}
+
+                ast::Expression::ArrayExpression { items, .. } => {
+                    worklist.extend(items.iter().map(expression_node));
+                }
+
+                ast::Expression::ObjectExpression { properties, .. } => {
+                    worklist.extend(properties.iter().map(|(_, e)| expression_node(e)));
+                }
+
+                ast::Expression::SymbolExpression { name: e, .. }
+                | ast::Expression::TypeOfExpression { expression: e, .. }
+                | ast::Expression::ValidateExpression { expression: e, .. }
+                | ast::Expression::UnaryExpression { expression: e, .. } => {
+                    worklist.push(expression_node(e));
+                }
+
+                ast::Expression::MemberExpression {
+                    expression: e,
+                    member,
+                    ..
+                } => {
+                    worklist.push(expression_node(e));
+
+                    match member {
+                        ast::Member::Property(_) => {}
+                        ast::Member::Index(index) => worklist.push(expression_node(index)),
+                        ast::Member::Slice { start, end } => {
+                            for bound in [start, end].into_iter().flatten() {
+                                worklist.push(expression_node(bound));
+                            }
+                        }
+                    }
+                }
+
+                ast::Expression::VariableExpression { identifier: i, .. } => {
+                    worklist.push(identifier_node(i));
+                }
+
+                ast::Expression::CallExpression {
+                    identifier: i,
+                    arguments,
+                    ..
+                } => {
+                    worklist.push(identifier_node(i));
+                    worklist.extend(arguments.iter().map(expression_node));
+                }
+
+                ast::Expression::AssignmentExpression {
+                    identifier: i,
+                    expression: e,
+                    ..
+                }
+                | ast::Expression::CompoundAssignmentExpression {
+                    identifier: i,
+                    expression: e,
+                    ..
+                } => {
+                    worklist.push(identifier_node(i));
+                    worklist.push(expression_node(e));
+                }
+
+                ast::Expression::RangeExpression { start, end, .. } => {
+                    worklist.push(expression_node(start));
+                    worklist.push(expression_node(end));
+                }
+
+                ast::Expression::MatchExpression { subject, arms, .. } => {
+                    worklist.push(expression_node(subject));
+                    worklist.extend(arms.iter().map(|arm| expression_node(&arm.expression)));
+                }
+
+                ast::Expression::BinaryExpression { left, right, .. } => {
+                    worklist.push(expression_node(left));
+                    worklist.push(expression_node(right));
+                }
+            },
+
+            Node::Identifier(i) => match i {
+                ast::VariableIdentifier::Name { .. } => {}
+                ast::VariableIdentifier::Property { base, .. } => {
+                    worklist.push(identifier_node(base));
+                }
+                ast::VariableIdentifier::Index { base, index, .. } => {
+                    worklist.push(identifier_node(base));
+                    worklist.push(expression_node(index));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use inkwell::targets::{CodeModel, RelocMode};
+use inkwell::OptimizationLevel;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::{CompilerError, DiagnosticEmitter};
+use crate::gen::EmitKind;
+use crate::nesting;
+use crate::warning::{Level, Suppressions, Warning, WarningKind, WarningLevels};
+
+/// Receives every error reported by a compilation before it is returned.
+pub type DiagnosticSink = Rc<RefCell<dyn DiagnosticEmitter>>;
+
+/// Runtime checks compiled into the program to catch memory bugs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Sanitizer {
+    Address,
+}
+
+/// Settings of a compilation, built with chained setters starting from `CompileOptions::new()`.
+#[derive(Clone)]
+pub struct CompileOptions {
+    pub(crate) file_name: String,
+    pub(crate) source_info: bool,
+    pub(crate) target: target_lexicon::Triple,
+    pub(crate) opt_level: OptimizationLevel,
+    pub(crate) emit: EmitKind,
+    pub(crate) linker: String,
+    pub(crate) libraries: Vec<String>,
+    pub(crate) std_library: Option<Vec<u8>>,
+    pub(crate) no_std: bool,
+    pub(crate) save_temps: bool,
+    pub(crate) debug_leaks: bool,
+    pub(crate) trace_runtime: bool,
+    pub(crate) opaque_symbols: bool,
+    pub(crate) keep_all_std: bool,
+    pub(crate) entry: Option<String>,
+    pub(crate) test_harness: bool,
+    pub(crate) plugins: Vec<PathBuf>,
+    pub(crate) max_nesting: usize,
+    pub(crate) temp_dir: Option<PathBuf>,
+    pub(crate) diagnostics: Option<DiagnosticSink>,
+    pub(crate) warnings: WarningLevels,
+    pub(crate) strict: bool,
+    pub(crate) time_passes: bool,
+    pub(crate) stats: bool,
+    pub(crate) lto: bool,
+    pub(crate) inline_functions: bool,
+    pub(crate) embedded: bool,
+    pub(crate) reloc_mode: RelocMode,
+    pub(crate) code_model: CodeModel,
+    pub(crate) sanitizer: Option<Sanitizer>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            file_name: "main.ts".to_string(),
+            source_info: true,
+            target: target_lexicon::Triple::host(),
+            opt_level: OptimizationLevel::None,
+            emit: EmitKind::Object,
+            linker: "gcc".to_string(),
+            libraries: Vec::new(),
+            std_library: None,
+            no_std: false,
+            save_temps: false,
+            debug_leaks: false,
+            trace_runtime: false,
+            opaque_symbols: false,
+            keep_all_std: false,
+            entry: None,
+            test_harness: false,
+            plugins: Vec::new(),
+            max_nesting: nesting::DEFAULT_MAX_NESTING,
+            temp_dir: None,
+            diagnostics: None,
+            warnings: WarningLevels::default(),
+            strict: false,
+            time_passes: false,
+            stats: false,
+            lto: false,
+            inline_functions: true,
+            embedded: false,
+            reloc_mode: RelocMode::Default,
+            code_model: CodeModel::Default,
+            sanitizer: None,
+        }
+    }
+}
+
+impl CompileOptions {
+    pub fn new() -> Self {
+        CompileOptions::default()
+    }
+
+    /// Name of the compiled file, used in runtime error messages.
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = file_name.to_string();
+        self
+    }
+
+    pub fn source_info(mut self, source_info: bool) -> Self {
+        self.source_info = source_info;
+        self
+    }
+
+    pub fn target(mut self, target: target_lexicon::Triple) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn opt_level(mut self, opt_level: OptimizationLevel) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    pub fn emit(mut self, emit: EmitKind) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// Command used to link executables, invoked like `gcc`.
+    pub fn linker(mut self, linker: &str) -> Self {
+        self.linker = linker.to_string();
+        self
+    }
+
+    /// Library linked into executables, passed to the linker as `-l<name>`.
+    pub fn library(mut self, library: &str) -> Self {
+        self.libraries.push(library.to_string());
+        self
+    }
+
+    /// LLVM bitcode of the runtime, replacing the std library built into the compiler.
+    pub fn std_library(mut self, std_library: Vec<u8>) -> Self {
+        self.std_library = Some(std_library);
+        self
+    }
+
+    /// Only declares the std library functions, leaving their definitions to
+    /// the libraries linked with the program.
+    pub fn no_std(mut self, no_std: bool) -> Self {
+        self.no_std = no_std;
+        self
+    }
+
+    /// Keeps the object file of every executable, named after it with an `.o` suffix.
+    pub fn save_temps(mut self, save_temps: bool) -> Self {
+        self.save_temps = save_temps;
+        self
+    }
+
+    /// Makes the program print the values still alive when it exits.
+    pub fn debug_leaks(mut self, debug_leaks: bool) -> Self {
+        self.debug_leaks = debug_leaks;
+        self
+    }
+
+    /// Makes the program log every call into the std runtime to stderr, with the
+    /// types of the arguments and the source line of the call.
+    pub fn trace_runtime(mut self, trace_runtime: bool) -> Self {
+        self.trace_runtime = trace_runtime;
+        self
+    }
+
+    /// Names the generated functions with numbered labels instead of their source
+    /// names, which differ between compilations in the same process.
+    pub fn opaque_symbols(mut self, opaque_symbols: bool) -> Self {
+        self.opaque_symbols = opaque_symbols;
+        self
+    }
+
+    /// Keeps every std function in the emitted code, instead of only the ones the
+    /// program calls.
+    pub fn keep_all_std(mut self, keep_all_std: bool) -> Self {
+        self.keep_all_std = keep_all_std;
+        self
+    }
+
+    /// Function of the top level called once the top level statements ran, as the
+    /// entry of the program.
+    pub fn entry(mut self, entry: &str) -> Self {
+        self.entry = Some(entry.to_string());
+        self
+    }
+
+    /// Builds the harness of `mini test`: `main` runs every test function once the
+    /// top level statements have run and reports each of them.
+    pub fn test_harness(mut self, test_harness: bool) -> Self {
+        self.test_harness = test_harness;
+        self
+    }
+
+    /// Dynamic library registering passes with its `mini_register_passes` function,
+    /// see `pipeline::Passes::load_plugin`.
+    pub fn plugin(mut self, plugin: &Path) -> Self {
+        self.plugins.push(plugin.to_path_buf());
+        self
+    }
+
+    /// How many levels deep statements and expressions may be nested, deeper
+    /// programs fail to compile rather than overflowing the stack of the compiler.
+    pub fn max_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    /// Directory of the intermediate files, the directory of the executable by default.
+    pub fn temp_dir(mut self, temp_dir: &Path) -> Self {
+        self.temp_dir = Some(temp_dir.to_path_buf());
+        self
+    }
+
+    pub fn diagnostics(mut self, diagnostics: DiagnosticSink) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    pub fn warning_level(mut self, kind: WarningKind, level: Level) -> Self {
+        self.warnings.set(kind, level);
+        self
+    }
+
+    pub fn deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.warnings.deny_warnings(deny_warnings);
+        self
+    }
+
+    /// Runs the type checker before code generation.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Prints how long each pass of the compilation takes to the standard error.
+    pub fn time_passes(mut self, time_passes: bool) -> Self {
+        self.time_passes = time_passes;
+        self
+    }
+
+    /// Prints counts of the generated functions, variables, IR instructions and
+    /// std calls, and the size of the output, to the standard error.
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn lto(mut self, lto: bool) -> Self {
+        self.lto = lto;
+        self
+    }
+
+    /// Inlines the small functions of the program, and the ones decorated with
+    /// `@inline`, into their callers. `@noinline` keeps a function out of line.
+    pub fn inline_functions(mut self, inline_functions: bool) -> Self {
+        self.inline_functions = inline_functions;
+        self
+    }
+
+    /// Embedded builds default to static relocation.
+    pub fn embedded(mut self, embedded: bool) -> Self {
+        self.embedded = embedded;
+        self
+    }
+
+    pub fn reloc_mode(mut self, reloc_mode: RelocMode) -> Self {
+        self.reloc_mode = reloc_mode;
+        self
+    }
+
+    pub fn code_model(mut self, code_model: CodeModel) -> Self {
+        self.code_model = code_model;
+        self
+    }
+
+    /// Instruments the program and the std runtime, and links the executable with
+    /// the runtime library of the sanitizer.
+    pub fn sanitizer(mut self, sanitizer: Option<Sanitizer>) -> Self {
+        self.sanitizer = sanitizer;
+        self
+    }
+
+    pub(crate) fn get_reloc_mode(&self) -> RelocMode {
+        if self.embedded && self.reloc_mode == RelocMode::Default {
+            RelocMode::Static
+        } else {
+            self.reloc_mode
+        }
+    }
+
+    /// Hands the error to the diagnostics sink, if any, and gives it back.
+    pub(crate) fn report(&self, error: CompilerError) -> CompilerError {
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.borrow_mut().emit(&error);
+        }
+
+        error
+    }
+
+    /// Hands the warnings to the diagnostics sink according to their levels,
+    /// failing on the first denied one. The ones the `mini-ignore` comments of the
+    /// content silence are left out.
+    pub(crate) fn check_warnings(
+        &self,
+        content: &str,
+        warnings: &[Warning],
+    ) -> Result<(), CompilerError> {
+        let suppressions = Suppressions::new(content);
+
+        for warning in warnings {
+            if suppressions.is_suppressed(warning) {
+                continue;
+            }
+
+            match self.warnings.level(warning.kind()) {
+                Level::Allow => {}
+                Level::Warn => {
+                    if let Some(diagnostics) = &self.diagnostics {
+                        diagnostics.borrow_mut().warn(warning);
+                    }
+                }
+                Level::Deny => return Err(CompilerError::DeniedWarning(warning.clone())),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,645 @@
+use indexmap::IndexSet;
+use inkwell::context::Context;
+use inkwell::module::Module;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::gen;
+use crate::interp;
+use crate::lexer::Lexer;
+use crate::lower;
+use crate::nesting;
+use crate::options::CompileOptions;
+use crate::parser;
+use crate::source::SourceFile;
+use crate::st;
+use crate::syntax;
+use crate::typeck;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Phase {
+    Parse,
+    SymbolTable,
+    Check,
+    CodeGen,
+}
+
+impl Phase {
+    pub const NAMES: [&'static str; 4] = ["parse", "st", "check", "codegen"];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Phase::Parse => "parse",
+            Phase::SymbolTable => "st",
+            Phase::Check => "check",
+            Phase::CodeGen => "codegen",
+        }
+    }
+}
+
+impl FromStr for Phase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "parse" => Ok(Phase::Parse),
+            "st" => Ok(Phase::SymbolTable),
+            "check" => Ok(Phase::Check),
+            "codegen" => Ok(Phase::CodeGen),
+            _ => Err(format!("Unknown compilation phase: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    Llvm,
+    Interp,
+}
+
+impl Backend {
+    pub const NAMES: [&'static str; 2] = ["llvm", "interp"];
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "llvm" => Ok(Backend::Llvm),
+            "interp" => Ok(Backend::Interp),
+            _ => Err(format!("Unknown backend: {}", s)),
+        }
+    }
+}
+
+/// What the compiler should do once a hook has inspected the result of a phase.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HookAction {
+    Continue,
+    Stop,
+    Veto(String),
+}
+
+/// Callbacks invoked after each compilation phase. Every method defaults to
+/// `HookAction::Continue`, so embedders only override the phases they care about.
+pub trait Hooks {
+    fn after_parse(&mut self, _program: &ast::Program) -> HookAction {
+        HookAction::Continue
+    }
+
+    fn after_symbol_table(&mut self, _symbol_table: &st::SymbolTable) -> HookAction {
+        HookAction::Continue
+    }
+
+    fn after_check(&mut self, _symbol_table: &st::SymbolTable) -> HookAction {
+        HookAction::Continue
+    }
+
+    fn after_codegen(&mut self, _module: &Module) -> HookAction {
+        HookAction::Continue
+    }
+}
+
+/// Hooks that stop the pipeline once the given phase has finished.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StopAfter(pub Option<Phase>);
+
+impl StopAfter {
+    fn action(&self, phase: Phase) -> HookAction {
+        if self.0 == Some(phase) {
+            HookAction::Stop
+        } else {
+            HookAction::Continue
+        }
+    }
+}
+
+impl Hooks for StopAfter {
+    fn after_parse(&mut self, _program: &ast::Program) -> HookAction {
+        self.action(Phase::Parse)
+    }
+
+    fn after_symbol_table(&mut self, _symbol_table: &st::SymbolTable) -> HookAction {
+        self.action(Phase::SymbolTable)
+    }
+
+    fn after_check(&mut self, _symbol_table: &st::SymbolTable) -> HookAction {
+        self.action(Phase::Check)
+    }
+
+    fn after_codegen(&mut self, _module: &Module) -> HookAction {
+        self.action(Phase::CodeGen)
+    }
+}
+
+/// A transformation the pipeline runs on the parsed program, before it is lowered
+/// and the symbol table is built, or on the generated module, before it is emitted. Both leave
+/// their input unchanged by default, see `visit::Folder` to rewrite programs.
+pub trait Pass {
+    fn name(&self) -> &str;
+
+    fn run_on_program<'input>(
+        &mut self,
+        program: ast::Program<'input>,
+    ) -> Result<ast::Program<'input>, String> {
+        Ok(program)
+    }
+
+    fn run_on_module(&mut self, _module: &Module) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The signature of the `mini_register_passes` function of a plugin.
+pub type RegisterPasses = fn(&mut Passes);
+
+/// The passes of a compilation, run in the order they were registered.
+#[derive(Default)]
+pub struct Passes {
+    passes: Vec<Box<dyn Pass>>,
+    // dropped after the passes, whose code they hold
+    plugins: Vec<libloading::Library>,
+}
+
+impl Passes {
+    pub fn new() -> Self {
+        Passes::default()
+    }
+
+    pub fn register(&mut self, pass: impl Pass + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    /// Loads a dynamic library and calls its `mini_register_passes` function, a
+    /// `RegisterPasses` the library must be built for with the same compiler and
+    /// version of mini.
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), CompilerError> {
+        let error = |err: libloading::Error| {
+            CompilerError::CliError(format!("Could not load plugin {}: {}", path.display(), err))
+        };
+
+        unsafe {
+            let library = libloading::Library::new(path).map_err(error)?;
+            let register = *library
+                .get::<RegisterPasses>(b"mini_register_passes")
+                .map_err(error)?;
+
+            self.plugins.push(library);
+            register(self);
+        }
+
+        Ok(())
+    }
+
+    fn run_on_program<'input>(
+        &mut self,
+        mut program: ast::Program<'input>,
+        timer: &PassTimer,
+    ) -> Result<ast::Program<'input>, CompilerError> {
+        for pass in self.passes.iter_mut() {
+            let name = pass.name().to_string();
+
+            program = timer
+                .time(&format!("pass {}", name), || pass.run_on_program(program))
+                .map_err(|reason| CompilerError::PassFailed(name, reason))?;
+        }
+
+        Ok(program)
+    }
+
+    fn run_on_module(&mut self, module: &Module, timer: &PassTimer) -> Result<(), CompilerError> {
+        for pass in self.passes.iter_mut() {
+            let name = pass.name().to_string();
+
+            timer
+                .time(&format!("pass {}", name), || pass.run_on_module(module))
+                .map_err(|reason| CompilerError::PassFailed(name, reason))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The passes registered by the plugins of the options, followed by the given ones.
+fn with_plugins(options: &CompileOptions, passes: Passes) -> Result<Passes, CompilerError> {
+    if options.plugins.is_empty() {
+        return Ok(passes);
+    }
+
+    let mut all = Passes::new();
+
+    for plugin in options.plugins.iter() {
+        all.load_plugin(plugin)?;
+    }
+
+    all.passes.extend(passes.passes);
+    all.plugins.extend(passes.plugins);
+
+    Ok(all)
+}
+
+pub struct Options {
+    pub compile: CompileOptions,
+    pub backend: Backend,
+    pub targets: Vec<target_lexicon::Triple>,
+    pub universal: bool,
+    pub out_file: PathBuf,
+}
+
+impl Options {
+    /// Output path for the given target. With more than one target, every
+    /// executable gets the target triple appended to its name.
+    fn target_out_file(&self, triple: &target_lexicon::Triple) -> PathBuf {
+        if self.targets.len() == 1 {
+            return self.out_file.clone();
+        }
+
+        let mut file_name = self.out_file.clone().into_os_string();
+        file_name.push(format!("-{}", triple));
+
+        PathBuf::from(file_name)
+    }
+}
+
+/// Parses a program borrowing from `content`. The program is lowered to the core
+/// syntax the symbol table is built from.
+pub fn parse(content: &str) -> Result<ast::Program<'_>, CompilerError> {
+    let program = lower::lower(parse_surface(content)?)?;
+    limit_nesting(program, nesting::DEFAULT_MAX_NESTING)
+}
+
+/// Parses a program keeping its surface syntax, as written.
+pub fn parse_surface(content: &str) -> Result<ast::Program<'_>, CompilerError> {
+    parse_surface_limited(content, nesting::DEFAULT_MAX_NESTING)
+}
+
+/// Like `parse_surface`, but fails when the program is nested more than
+/// `max_nesting` levels deep instead of the default.
+pub fn parse_surface_limited(
+    content: &str,
+    max_nesting: usize,
+) -> Result<ast::Program<'_>, CompilerError> {
+    let program = parser::ProgramParser::new()
+        .parse(Lexer::new(content))
+        .map_err(|err| {
+            let err = err.map_token(|token| token.to_string());
+            let (message, help) = syntax::explain(&err, content);
+            CompilerError::ParserError(err, message, help)
+        })?;
+
+    limit_nesting(program, max_nesting)
+}
+
+fn limit_nesting(
+    program: ast::Program<'_>,
+    max_nesting: usize,
+) -> Result<ast::Program<'_>, CompilerError> {
+    match nesting::check_nesting(&program, max_nesting) {
+        Ok(()) => Ok(program),
+        Err(err) => {
+            // dropping the program recurses as deep as it is nested
+            std::mem::forget(program);
+            Err(err)
+        }
+    }
+}
+
+/// Definition of the implicit function wrapping the top level statements.
+pub fn main_definition(content: &str) -> ast::VariableDefinition<'static> {
+    ast::VariableDefinition {
+        location: (0, content.len()),
+        name: "main",
+        kind: ast::VariableKind::Function {
+            parameters: Vec::new(),
+            return_kind: Box::new(ast::VariableKind::Number),
+        },
+        is_annotated: true,
+        is_writable: false,
+        is_external: false,
+        decorators: IndexSet::new(),
+        docs: Vec::new(),
+    }
+}
+
+/// Measures the passes of a compilation, printing their durations when
+/// `--time-passes` is given.
+struct PassTimer {
+    enabled: bool,
+}
+
+impl PassTimer {
+    fn time<T>(&self, name: &str, pass: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = pass();
+
+        if self.enabled {
+            let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+            eprintln!("time: {:>10.3}ms\t{}", elapsed, name);
+        }
+
+        result
+    }
+}
+
+/// Returns `Ok(true)` if the pipeline should go on with the next phase.
+fn handle_action(phase: Phase, action: HookAction) -> Result<bool, CompilerError> {
+    match action {
+        HookAction::Continue => Ok(true),
+        HookAction::Stop => Ok(false),
+        HookAction::Veto(reason) => Err(CompilerError::CompilationVetoed(phase, reason)),
+    }
+}
+
+/// Returns the exit status of the program when the interpreter runs it, 0 when
+/// it is compiled.
+pub fn compile(
+    content: &str,
+    options: &Options,
+    hooks: &mut dyn Hooks,
+) -> Result<i32, CompilerError> {
+    compile_with_passes(content, options, hooks, Passes::new())
+}
+
+/// Compiles the program like `compile`, running the passes after the ones of
+/// the plugins in the options.
+pub fn compile_with_passes(
+    content: &str,
+    options: &Options,
+    hooks: &mut dyn Hooks,
+    passes: Passes,
+) -> Result<i32, CompilerError> {
+    run(content, options, hooks, passes).map_err(|err| options.compile.report(err))
+}
+
+fn run(
+    content: &str,
+    options: &Options,
+    hooks: &mut dyn Hooks,
+    passes: Passes,
+) -> Result<i32, CompilerError> {
+    let timer = PassTimer {
+        enabled: options.compile.time_passes,
+    };
+
+    let mut passes = with_plugins(&options.compile, passes)?;
+
+    timer.time("total", || {
+        run_passes(content, options, hooks, &mut passes, &timer)
+    })
+}
+
+/// The lowered program and the definition of `main`, which the symbol table of
+/// `Checked` borrows.
+#[derive(Default)]
+struct Parsed<'input> {
+    main_def: Option<ast::VariableDefinition<'static>>,
+    program: Option<ast::Program<'input>>,
+}
+
+/// The program once the front end is done with it, which the back ends start from.
+struct Checked<'a> {
+    symbol_table: st::SymbolTable<'a>,
+    casts: typeck::RuntimeCasts<'a>,
+    source: Option<SourceFile<'a>>,
+}
+
+/// Parses, lowers and checks the program, running the passes on it before it is
+/// lowered. Returns `None` if one of the hooks stopped the pipeline.
+fn front_end<'a, 'input: 'a>(
+    content: &'input str,
+    options: &'a CompileOptions,
+    hooks: &mut dyn Hooks,
+    passes: &mut Passes,
+    timer: &PassTimer,
+    parsed: &'a mut Parsed<'input>,
+) -> Result<Option<Checked<'a>>, CompilerError> {
+    let max_nesting = options.max_nesting;
+    let program = timer.time("parse", || parse_surface_limited(content, max_nesting))?;
+    let program = passes.run_on_program(program, timer)?;
+    let program = timer.time("lower", || {
+        limit_nesting(lower::lower(program)?, max_nesting)
+    })?;
+
+    if !handle_action(Phase::Parse, hooks.after_parse(&program))? {
+        return Ok(None);
+    }
+
+    let main_def = parsed.main_def.insert(main_definition(content));
+    let program = parsed.program.insert(program);
+
+    let symbol_table = timer.time("symbol table", || {
+        if options.test_harness {
+            st::SymbolTable::test_harness(main_def, program)
+        } else {
+            st::SymbolTable::with_entry(main_def, program, options.entry.as_deref())
+        }
+    })?;
+    options.check_warnings(content, &symbol_table.warnings)?;
+
+    if !handle_action(Phase::SymbolTable, hooks.after_symbol_table(&symbol_table))? {
+        return Ok(None);
+    }
+
+    let casts = if options.strict {
+        timer.time("type check", || {
+            typeck::TypeChecker::new(&symbol_table).check()
+        })?
+    } else {
+        typeck::RuntimeCasts::new()
+    };
+
+    if !handle_action(Phase::Check, hooks.after_check(&symbol_table))? {
+        return Ok(None);
+    }
+
+    let source = if options.source_info {
+        Some(SourceFile::new(&options.file_name, content))
+    } else {
+        None
+    };
+
+    Ok(Some(Checked {
+        symbol_table,
+        casts,
+        source,
+    }))
+}
+
+fn run_passes(
+    content: &str,
+    options: &Options,
+    hooks: &mut dyn Hooks,
+    passes: &mut Passes,
+    timer: &PassTimer,
+) -> Result<i32, CompilerError> {
+    let mut parsed = Parsed::default();
+
+    let checked = match front_end(content, &options.compile, hooks, passes, timer, &mut parsed)? {
+        Some(checked) => checked,
+        None => return Ok(0),
+    };
+    let symbol_table = &checked.symbol_table;
+
+    if options.backend == Backend::Interp {
+        let mut interpreter = interp::Interpreter::new(symbol_table, checked.source.as_ref());
+        interpreter.set_casts(checked.casts);
+
+        return timer.time("interpret", || {
+            let value = interpreter.run()?;
+            interpreter.exit_status(&value)
+        });
+    }
+
+    let ir_context = Context::create();
+    let mut ir_generator = gen::IRGenerator::new(
+        symbol_table,
+        checked.source.as_ref(),
+        &ir_context,
+        &options.compile,
+    )?;
+    ir_generator.set_casts(checked.casts);
+    timer.time("codegen", || ir_generator.generate())?;
+    passes.run_on_module(ir_generator.module(), timer)?;
+
+    if !handle_action(Phase::CodeGen, hooks.after_codegen(ir_generator.module()))? {
+        return Ok(0);
+    }
+
+    let mut executables = Vec::new();
+    let mut sizes = Vec::new();
+
+    for triple in options.targets.iter() {
+        let out_file = options.target_out_file(triple);
+
+        let object = timer.time(&format!("emit {}", triple), || {
+            ir_generator.emit(triple, gen::EmitKind::Object)
+        })?;
+        timer.time(&format!("link {}", triple), || {
+            ir_generator.link_object(triple, &object, &out_file)
+        })?;
+
+        sizes.push((format!("object {}", triple), object.len() as u64));
+        sizes.push((format!("executable {}", triple), file_size(&out_file)));
+        executables.push(out_file);
+    }
+
+    if options.universal {
+        timer.time("link universal", || {
+            gen::link_universal(&options.targets, &executables, &options.out_file)
+        })?;
+
+        sizes.push(("universal".to_string(), file_size(&options.out_file)));
+    }
+
+    if options.compile.stats {
+        print_stats(symbol_table, &ir_generator.stats()?, &sizes);
+    }
+
+    Ok(0)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+/// Prints the counts of `--stats`, with the sizes in bytes of the outputs.
+fn print_stats(symbol_table: &st::SymbolTable, stats: &gen::CodeGenStats, sizes: &[(String, u64)]) {
+    let variables = symbol_table
+        .variables()
+        .iter()
+        .filter(|id| !symbol_table.variable(id).is_function())
+        .count();
+    let builtin_calls: usize = stats.builtin_calls.iter().map(|(_, count)| count).sum();
+
+    eprintln!("stats: {:>10}\tfunctions", stats.functions);
+    eprintln!("stats: {:>10}\tvariables", variables);
+    eprintln!("stats: {:>10}\tIR instructions", stats.instructions);
+    eprintln!(
+        "stats: {:>10}\tIR instructions of std",
+        stats.std_instructions
+    );
+    eprintln!("stats: {:>10}\tstd calls", builtin_calls);
+
+    for (name, count) in &stats.builtin_calls {
+        eprintln!("stats: {:>10}\t  {}", count, name);
+    }
+
+    for (name, size) in sizes {
+        eprintln!("stats: {:>10}\tbytes of {}", size, name);
+    }
+}
+
+/// Compiles a program without touching the file system or invoking the linker,
+/// returning the object code, LLVM IR or bitcode selected by the options.
+pub fn compile_source(content: &str, options: &CompileOptions) -> Result<Vec<u8>, CompilerError> {
+    compile_source_with_passes(content, options, Passes::new())
+}
+
+/// Compiles the program like `compile_source`, running the passes after the ones
+/// of the plugins in the options.
+pub fn compile_source_with_passes(
+    content: &str,
+    options: &CompileOptions,
+    passes: Passes,
+) -> Result<Vec<u8>, CompilerError> {
+    generate(content, options, passes).map_err(|err| options.report(err))
+}
+
+fn generate(
+    content: &str,
+    options: &CompileOptions,
+    passes: Passes,
+) -> Result<Vec<u8>, CompilerError> {
+    let timer = PassTimer {
+        enabled: options.time_passes,
+    };
+
+    let mut passes = with_plugins(options, passes)?;
+    let mut parsed = Parsed::default();
+
+    // no hooks stop the pipeline, so the front end always returns the program
+    let checked = front_end(
+        content,
+        options,
+        &mut StopAfter(None),
+        &mut passes,
+        &timer,
+        &mut parsed,
+    )?
+    .unwrap();
+
+    let ir_context = Context::create();
+    let mut ir_generator = gen::IRGenerator::new(
+        &checked.symbol_table,
+        checked.source.as_ref(),
+        &ir_context,
+        options,
+    )?;
+    ir_generator.set_casts(checked.casts);
+    timer.time("codegen", || ir_generator.generate())?;
+    passes.run_on_module(ir_generator.module(), &timer)?;
+
+    let output = timer.time(&format!("emit {}", options.target), || {
+        ir_generator.emit(&options.target, options.emit)
+    })?;
+
+    if options.stats {
+        let size = (format!("output {}", options.target), output.len() as u64);
+
+        print_stats(&checked.symbol_table, &ir_generator.stats()?, &[size]);
+    }
+
+    Ok(output)
+}
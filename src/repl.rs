@@ -0,0 +1,176 @@
+use lalrpop_util::ParseError;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use crate::ast;
+use crate::builtins;
+use crate::document::Document;
+use crate::error::CompilerError;
+use crate::interp::{Interpreter, Value};
+use crate::pipeline;
+use crate::source::SourceFile;
+
+/// Result of running one input of the REPL.
+pub struct Evaluation {
+    /// What the input printed, even if it failed afterwards.
+    pub output: String,
+    /// Value of the input if it ends with an expression statement.
+    pub result: Result<Option<Value>, CompilerError>,
+}
+
+/// Runs the inputs with the interpreter. The interpreter cannot keep its state
+/// across programs, so every input runs again after all the previous ones, and
+/// only the output produced by the new input is shown.
+pub struct Repl {
+    history: Document,
+    printed: usize,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        let declarations = builtins::BUILTINS
+            .iter()
+            .map(|builtin| format!("{}\n", builtin.declaration))
+            .collect();
+
+        Repl {
+            history: Document::parse(declarations).unwrap(),
+            printed: 0,
+        }
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl::default()
+    }
+
+    /// The program of the inputs that ran so far, after the declarations of the builtins.
+    pub fn history(&self) -> &Document {
+        &self.history
+    }
+
+    /// The previous inputs followed by the given one.
+    pub fn source(&self, input: &str) -> String {
+        format!("{}{}\n", self.history.content(), input)
+    }
+
+    /// Runs the input after the previous ones. Inputs failing to compile or run are forgotten.
+    pub fn eval(&mut self, input: &str) -> Evaluation {
+        let output = Rc::new(RefCell::new(Vec::new()));
+
+        let result = Document::parse(self.source(input)).and_then(|document| {
+            let value = self.run_document(&document, output.clone())?;
+
+            Ok((document, value))
+        });
+
+        let output = output.borrow();
+        let printed = String::from_utf8_lossy(&output[self.printed.min(output.len())..]);
+
+        let result = result.map(|(document, value)| {
+            self.history = document;
+            self.printed = output.len();
+
+            value
+        });
+
+        Evaluation {
+            output: printed.to_string(),
+            result,
+        }
+    }
+
+    fn run_document(
+        &self,
+        document: &Document,
+        output: Rc<RefCell<Vec<u8>>>,
+    ) -> Result<Option<Value>, CompilerError> {
+        let source_file = SourceFile::new("<repl>", document.content());
+
+        let last_value = document.with_symbol_table(|symbol_table| {
+            let mut interpreter = Interpreter::new(symbol_table, Some(&source_file));
+            interpreter.set_output(output);
+            interpreter.run().map(|_| interpreter.last_value().clone())
+        })??;
+
+        match document.program().statements.last() {
+            Some(ast::Statement::ExpressionStatement { expression })
+                if expression
+                    .get_location()
+                    .is_some_and(|(start, _)| start >= self.history.content().len()) =>
+            {
+                Ok(Some(last_value))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Completes the input with a missing `;`, returns `None` if more lines are needed.
+    fn complete(&self, input: &str) -> Result<Option<String>, CompilerError> {
+        let with_semicolon = format!("{};", input.trim_end());
+
+        for candidate in [input, &with_semicolon] {
+            if pipeline::parse(&self.source(candidate)).is_ok() {
+                return Ok(Some(candidate.to_string()));
+            }
+        }
+
+        match pipeline::parse(&self.source(input)) {
+            Err(CompilerError::ParserError(ParseError::UnrecognizedEof { .. }, ..)) => Ok(None),
+            Err(err) => Err(err),
+            Ok(_) => unreachable!(),
+        }
+    }
+
+    /// Reads inputs from the standard input until it is closed. Lines are joined
+    /// until they form a complete program.
+    pub fn run(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut input = String::new();
+
+        loop {
+            print!("{}", if input.is_empty() { "> " } else { "... " });
+            io::stdout().flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+
+            input.push_str(&line);
+            input.push('\n');
+
+            if input.trim().is_empty() {
+                input.clear();
+                continue;
+            }
+
+            let complete = match self.complete(&input) {
+                Ok(Some(complete)) => complete,
+                Ok(None) => continue,
+                Err(err) => {
+                    let source = self.source(&input);
+                    println!("{}", SourceFile::new("<repl>", &source).render(&err));
+                    input.clear();
+                    continue;
+                }
+            };
+
+            let source = self.source(&complete);
+            let evaluation = self.eval(&complete);
+
+            print!("{}", evaluation.output);
+
+            match evaluation.result {
+                Ok(Some(Value::Undefined)) | Ok(None) => {}
+                Ok(Some(value)) => println!("{}", value.inspect()),
+                Err(err) => println!("{}", SourceFile::new("<repl>", &source).render(&err)),
+            }
+
+            input.clear();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use colored::{ColoredString, Colorize};
+
+use crate::error::CompilerError;
+use crate::warning::Warning;
+
+#[derive(Clone, Debug)]
+pub struct SourceFile<'input> {
+    pub name: &'input str,
+    pub content: &'input str,
+
+    line_starts: Vec<usize>,
+}
+
+impl<'input> SourceFile<'input> {
+    pub fn new(name: &'input str, content: &'input str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(index, _)| index + 1))
+            .collect::<Vec<_>>();
+
+        SourceFile {
+            name,
+            content,
+            line_starts,
+        }
+    }
+
+    /// Converts a byte offset into a 1-based line number.
+    pub fn line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        }
+    }
+
+    /// Converts a byte offset into a 1-based line and column, counting columns in characters.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len());
+        let line = self.line(offset);
+        let column = self.content[self.line_starts[line - 1]..offset]
+            .chars()
+            .count()
+            + 1;
+
+        (line, column)
+    }
+
+    /// Byte range of the given line, without its line break.
+    fn line_range(&self, line: usize) -> (usize, usize) {
+        let start = self.line_starts[line - 1];
+        let end = match self.line_starts.get(line) {
+            Some(next_start) => next_start - 1,
+            None => self.content.len(),
+        };
+
+        let text = &self.content[start..end];
+
+        (start, start + text.trim_end_matches('\r').len())
+    }
+
+    /// Renders the error like rustc does: the message, the file position, the
+    /// offending line and a caret underline below the erroneous part.
+    pub fn render(&self, error: &CompilerError) -> String {
+        let location = match error.location() {
+            Some(location) => location,
+            None => return error.to_string(),
+        };
+
+        let snippet = self.render_snippet(
+            format!("{} {}", error.header(), error.highlighted_message()),
+            location,
+            |s| s.red(),
+        );
+
+        match error.help() {
+            Some(help) => {
+                let gutter = " ".repeat(self.line(location.0).to_string().len());
+                format!("{}\n{} {} {}", snippet, gutter, "= help:".blue(), help)
+            }
+            None => snippet,
+        }
+    }
+
+    pub fn render_warning(&self, warning: &Warning) -> String {
+        self.render_snippet(
+            format!("{} {}", "warning:".yellow(), warning.highlighted_message()),
+            warning.location(),
+            |s| s.yellow(),
+        )
+    }
+
+    fn render_snippet(
+        &self,
+        header: String,
+        location: (usize, usize),
+        color: fn(&str) -> ColoredString,
+    ) -> String {
+        let start = location.0.min(self.content.len());
+        let end = location.1.min(self.content.len());
+
+        let (line, column) = self.line_column(start);
+        let (line_start, line_end) = self.line_range(line);
+
+        let underline_end = end.clamp(start, line_end.max(start));
+        let underline = "^".repeat(self.content[start..underline_end].chars().count().max(1));
+        let padding = " ".repeat(self.content[line_start..start].chars().count());
+
+        let gutter = " ".repeat(line.to_string().len());
+
+        format!(
+            "{}\n{}{} {}:{}:{}\n{} {}\n{} {} {}\n{} {} {}{}",
+            header,
+            gutter,
+            "-->".blue(),
+            self.name,
+            line,
+            column,
+            gutter,
+            "|".blue(),
+            line.to_string().blue(),
+            "|".blue(),
+            &self.content[line_start..line_end],
+            gutter,
+            "|".blue(),
+            padding,
+            color(&underline),
+        )
+    }
+}
@@ -1,9 +1,13 @@
 use by_address::ByAddress;
 use generational_arena::{Arena, Index};
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
+use serde::Serialize;
 
 use crate::ast;
 use crate::error::CompilerError;
+use crate::source::SourceFile;
+use crate::testing;
+use crate::warning::Warning;
 
 #[derive(Clone, Debug)]
 pub struct Scope<'input> {
@@ -12,36 +16,67 @@ pub struct Scope<'input> {
     pub statements: Option<&'input Vec<ast::Statement<'input>>>,
 
     pub variables: IndexMap<&'input str, Index>,
+
+    blocks: Vec<Index>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Variable<'input> {
     Static {
         definition: &'input ast::VariableDefinition<'input>,
+        kind: ast::VariableKind,
         is_parameter: bool,
     },
     Property {
+        location: (usize, usize),
         base: Index,
         property: &'input str,
     },
     Indexed {
+        location: (usize, usize),
         base: Index,
         index: &'input ast::Expression<'input>,
     },
 }
 
 impl<'input> Variable<'input> {
-    pub fn get_name(&self) -> &'input str {
+    /// Where the variable is defined or, for the property and indexed variables,
+    /// where it is used.
+    pub fn get_location(&self) -> (usize, usize) {
+        match &self {
+            Variable::Static { definition, .. } => definition.location,
+            Variable::Property { location, .. } | Variable::Indexed { location, .. } => *location,
+        }
+    }
+
+    /// The error of asking a property or an indexed variable for what only the
+    /// static variables have, which is a bug in the compiler.
+    fn not_static(&self, what: &str) -> CompilerError {
+        CompilerError::Internal(
+            Some(self.get_location()),
+            format!("a property or an indexed variable has no {}", what),
+        )
+    }
+
+    pub fn get_definition(&self) -> Result<&'input ast::VariableDefinition<'input>, CompilerError> {
+        match &self {
+            Variable::Static { definition, .. } => Ok(definition),
+            _ => Err(self.not_static("definition")),
+        }
+    }
+
+    pub fn get_name(&self) -> Result<&'input str, CompilerError> {
         match &self {
-            Variable::Static { definition, .. } => definition.name,
-            _ => unreachable!(),
+            Variable::Static { definition, .. } => Ok(definition.name),
+            _ => Err(self.not_static("name")),
         }
     }
 
-    pub fn get_kind(&self) -> &'input ast::VariableKind {
+    /// The annotated kind of the variable, or the kind infered from its initializer.
+    pub fn get_kind(&self) -> Result<&ast::VariableKind, CompilerError> {
         match &self {
-            Variable::Static { definition, .. } => &definition.kind,
-            _ => unreachable!(),
+            Variable::Static { kind, .. } => Ok(kind),
+            _ => Err(self.not_static("kind")),
         }
     }
 
@@ -52,17 +87,24 @@ impl<'input> Variable<'input> {
         }
     }
 
-    pub fn is_parameter(&self) -> bool {
+    pub fn is_parameter(&self) -> Result<bool, CompilerError> {
+        match &self {
+            Variable::Static { is_parameter, .. } => Ok(*is_parameter),
+            _ => Err(self.not_static("definition")),
+        }
+    }
+
+    pub fn is_external(&self) -> Result<bool, CompilerError> {
         match &self {
-            Variable::Static { is_parameter, .. } => *is_parameter,
-            _ => unreachable!(),
+            Variable::Static { definition, .. } => Ok(definition.is_external),
+            _ => Err(self.not_static("definition")),
         }
     }
 
-    pub fn is_external(&self) -> bool {
+    pub fn get_decorators(&self) -> Result<&IndexSet<&'input str>, CompilerError> {
         match &self {
-            Variable::Static { definition, .. } => definition.is_external,
-            _ => unreachable!(),
+            Variable::Static { definition, .. } => Ok(&definition.decorators),
+            _ => Err(self.not_static("decorators")),
         }
     }
 
@@ -76,20 +118,79 @@ impl<'input> Variable<'input> {
         }
     }
 
-    pub fn get_parameters(&self) -> &Vec<ast::ParameterKind> {
+    /// The least and the most number of arguments a call to the function accepts,
+    /// the latter being unbounded for functions with a rest parameter.
+    pub fn get_arity(&self) -> Result<(usize, Option<usize>), CompilerError> {
+        let parameters = self.get_parameters()?;
+
+        let required = parameters
+            .iter()
+            .take_while(|p| !p.is_optional && !p.is_rest)
+            .count();
+
+        if parameters.iter().any(|p| p.is_rest) {
+            Ok((required, None))
+        } else {
+            Ok((required, Some(parameters.len())))
+        }
+    }
+
+    pub fn get_parameters(&self) -> Result<&Vec<ast::ParameterKind>, CompilerError> {
         match &self {
             Variable::Static { definition, .. } => match &definition.kind {
-                ast::VariableKind::Function { parameters, .. } => parameters,
-                _ => unreachable!(),
+                ast::VariableKind::Function { parameters, .. } => Ok(parameters),
+                _ => Err(CompilerError::Internal(
+                    Some(definition.location),
+                    format!("`{}` is not a function", definition.name),
+                )),
             },
-            _ => unreachable!(),
+            _ => Err(self.not_static("parameters")),
         }
     }
 }
 
+/// A serializable view of a symbol table, identifying scopes and variables by
+/// the index of their arena slot, see `SymbolTable::snapshot`.
+#[derive(Clone, Debug, Serialize)]
+pub struct SymbolTableSnapshot<'a> {
+    pub main_function: Option<usize>,
+    pub scopes: Vec<ScopeSnapshot<'a>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScopeSnapshot<'a> {
+    pub id: usize,
+    pub parent: Option<usize>,
+    /// The function the scope is the body of, if it is not a block.
+    pub function: Option<usize>,
+    pub variables: Vec<VariableSnapshot<'a>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VariableSnapshot<'a> {
+    pub id: usize,
+    pub name: &'a str,
+    pub kind: &'a ast::VariableKind,
+    pub location: (usize, usize),
+    pub is_parameter: bool,
+    pub is_writable: bool,
+    pub is_external: bool,
+    pub docs: &'a [&'a str],
+    pub references: &'a [(usize, usize)],
+    /// The functions the variable calls, if it is a function.
+    pub calls: Vec<usize>,
+}
+
 #[derive(Clone, Debug)]
 pub struct SymbolTable<'input> {
     pub main_function: Option<Index>,
+    /// The top level function `main` calls once the top level statements have run,
+    /// whose result is the exit status of the program.
+    pub entry_function: Option<Index>,
+    /// The test functions `main` runs one after the other once the top level
+    /// statements have run, in the harness `mini test` builds.
+    pub test_functions: Option<Vec<Index>>,
+    pub warnings: Vec<Warning>,
 
     scope_arena: Arena<Scope<'input>>,
     variable_arena: Arena<Variable<'input>>,
@@ -98,20 +199,58 @@ pub struct SymbolTable<'input> {
 
     definition_ref_map: IndexMap<ByAddress<&'input ast::VariableDefinition<'input>>, Index>,
     identifier_ref_map: IndexMap<ByAddress<&'input ast::VariableIdentifier<'input>>, Index>,
+
+    references: IndexMap<Index, Vec<(usize, usize)>>,
+    calls: IndexMap<Index, IndexSet<Index>>,
+    const_initializers: IndexMap<Index, &'input ast::Expression<'input>>,
 }
 
 impl<'input> SymbolTable<'input> {
     pub fn from(
         main_def: &'input ast::VariableDefinition<'input>,
         program: &'input ast::Program<'input>,
-    ) -> Result<SymbolTable<'input>, CompilerError<'input>> {
+    ) -> Result<SymbolTable<'input>, CompilerError> {
+        SymbolTable::with_entry(main_def, program, None)
+    }
+
+    /// Like `from`, with the top level function named `entry` as the entry of
+    /// the program.
+    pub fn with_entry(
+        main_def: &'input ast::VariableDefinition<'input>,
+        program: &'input ast::Program<'input>,
+        entry: Option<&str>,
+    ) -> Result<SymbolTable<'input>, CompilerError> {
+        SymbolTable::build(main_def, program, entry, false)
+    }
+
+    /// Like `from`, with `main` running every test function of the program, see
+    /// `testing::is_test`.
+    pub fn test_harness(
+        main_def: &'input ast::VariableDefinition<'input>,
+        program: &'input ast::Program<'input>,
+    ) -> Result<SymbolTable<'input>, CompilerError> {
+        SymbolTable::build(main_def, program, None, true)
+    }
+
+    fn build(
+        main_def: &'input ast::VariableDefinition<'input>,
+        program: &'input ast::Program<'input>,
+        entry: Option<&str>,
+        test_harness: bool,
+    ) -> Result<SymbolTable<'input>, CompilerError> {
         let mut symbol_table = SymbolTable {
             main_function: None,
+            entry_function: None,
+            test_functions: None,
+            warnings: Vec::new(),
             scope_arena: Arena::new(),
             variable_arena: Arena::new(),
             function_scope_map: IndexMap::new(),
             definition_ref_map: IndexMap::new(),
             identifier_ref_map: IndexMap::new(),
+            references: IndexMap::new(),
+            calls: IndexMap::new(),
+            const_initializers: IndexMap::new(),
         };
 
         let (main_function, global_scope) =
@@ -122,6 +261,16 @@ impl<'input> SymbolTable<'input> {
 
         symbol_table.visit_scopes()?;
 
+        if let Some(entry) = entry {
+            symbol_table.resolve_entry(entry)?;
+        }
+
+        if test_harness {
+            symbol_table.resolve_tests()?;
+        }
+
+        symbol_table.check_unused_variables();
+
         Ok(symbol_table)
     }
 
@@ -154,6 +303,132 @@ impl<'input> SymbolTable<'input> {
         self.scope(scope_id)
     }
 
+    /// Variables of the function scope followed by the ones of the blocks nested in it.
+    pub fn function_variables(&self, function_id: &Index) -> Vec<Index> {
+        let mut variables = Vec::new();
+        let mut scopes = vec![*self.function_scope_map.get(function_id).unwrap()];
+
+        while let Some(scope_id) = scopes.pop() {
+            let scope = self.scope(&scope_id);
+
+            variables.extend(scope.variables.values());
+            scopes.extend(scope.blocks.iter().rev());
+        }
+
+        variables
+    }
+
+    /// The static variable a property or an index belongs to, or the variable itself.
+    pub fn static_variable(&self, variable_id: &Index) -> Index {
+        let mut variable_id = *variable_id;
+
+        while let Variable::Property { base, .. } | Variable::Indexed { base, .. } =
+            self.variable(&variable_id)
+        {
+            variable_id = *base;
+        }
+
+        variable_id
+    }
+
+    /// The static variable named at the byte offset, by one of its uses or by its
+    /// definition. The innermost name wins when they are nested.
+    pub fn variable_at(&self, offset: usize) -> Option<Index> {
+        let identifiers = self
+            .identifier_ref_map
+            .iter()
+            .map(|(identifier, variable_id)| (identifier.get_location(), *variable_id));
+
+        let definitions = self
+            .definition_ref_map
+            .iter()
+            .filter(|(_, variable_id)| Some(**variable_id) != self.main_function)
+            .map(|(definition, variable_id)| (definition.location, *variable_id));
+
+        identifiers
+            .chain(definitions)
+            .filter(|((start, end), _)| *start <= offset && offset <= *end)
+            .min_by_key(|((start, end), _)| end - start)
+            .map(|(_, variable_id)| self.static_variable(&variable_id))
+    }
+
+    /// The definition of the variable named at the byte offset.
+    pub fn definition_at(&self, offset: usize) -> Option<&'input ast::VariableDefinition<'input>> {
+        match self.variable(&self.variable_at(offset)?) {
+            Variable::Static { definition, .. } => Some(definition),
+            _ => None,
+        }
+    }
+
+    /// Locations of the identifiers reading or writing the static variable, or
+    /// one of its properties and indexes, in the order they appear.
+    pub fn references_of(&self, variable_id: &Index) -> Vec<(usize, usize)> {
+        let mut references = self
+            .identifier_ref_map
+            .iter()
+            .filter(|(_, id)| self.static_variable(id) == *variable_id)
+            .map(|(identifier, _)| identifier.get_location())
+            .collect::<Vec<_>>();
+
+        references.sort();
+        references
+    }
+
+    /// The function whose body contains the scope, directly or through blocks.
+    pub fn enclosing_function(&self, scope_id: &Index) -> Index {
+        let mut scope_id = *scope_id;
+
+        loop {
+            if let Some((function_id, _)) = self
+                .function_scope_map
+                .iter()
+                .find(|(_, function_scope_id)| **function_scope_id == scope_id)
+            {
+                return *function_id;
+            }
+
+            scope_id = self.scope(&scope_id).parent_scope.unwrap();
+        }
+    }
+
+    /// The names of the functions the function is nested in, outermost first and
+    /// without the main function, followed by its own name.
+    pub fn function_path(&self, function_id: &Index) -> Result<Vec<&'input str>, CompilerError> {
+        let mut path = vec![self.variable(function_id).get_name()?];
+        let mut function_id = *function_id;
+
+        while Some(function_id) != self.main_function {
+            let scope_id = self
+                .scope_arena
+                .iter()
+                .find(|(_, scope)| scope.variables.values().any(|id| *id == function_id))
+                .map(|(scope_id, _)| scope_id)
+                .unwrap();
+
+            function_id = self.enclosing_function(&scope_id);
+
+            if Some(function_id) != self.main_function {
+                path.push(self.variable(&function_id).get_name()?);
+            }
+        }
+
+        path.reverse();
+        Ok(path)
+    }
+
+    /// The functions called by each function, in the order of their first call.
+    pub fn calls(&self) -> &IndexMap<Index, IndexSet<Index>> {
+        &self.calls
+    }
+
+    /// The initializer of the `const` variable.
+    pub fn const_initializer(
+        &self,
+        variable_id: &Index,
+    ) -> Option<&'input ast::Expression<'input>> {
+        self.const_initializers.get(variable_id).copied()
+    }
+
     fn set_function_scope(&mut self, function_id: &Index, scope_id: &Index) {
         self.function_scope_map.insert(*function_id, *scope_id);
     }
@@ -191,15 +466,27 @@ impl<'input> SymbolTable<'input> {
         scope_id: &Index,
         definition: &'input ast::VariableDefinition<'input>,
         is_parameter: bool,
-    ) -> Result<Index, CompilerError<'input>> {
+    ) -> Result<Index, CompilerError> {
         let scope = self.scope(scope_id);
 
         if scope.variables.contains_key(definition.name) {
-            return Err(CompilerError::VariableAlreadyDefined(definition.name));
+            return Err(CompilerError::VariableAlreadyDefined(
+                definition.location,
+                definition.name.to_string(),
+            ));
+        }
+
+        // definitions are reported once their kind could not be infered
+        if is_parameter && !definition.is_annotated {
+            self.warnings.push(Warning::ImplicitAny(
+                definition.location,
+                definition.name.to_string(),
+            ));
         }
 
         let variable_id = self.variable_arena.insert(Variable::Static {
             definition,
+            kind: definition.kind.clone(),
             is_parameter,
         });
         self.set_definition_ref(definition, &variable_id);
@@ -215,11 +502,12 @@ impl<'input> SymbolTable<'input> {
         scope_id: Option<&Index>,
         definition: &'input ast::VariableDefinition<'input>,
         statements: &'input Vec<ast::Statement<'input>>,
-    ) -> Result<(Index, Index), CompilerError<'input>> {
+    ) -> Result<(Index, Index), CompilerError> {
         let function_scope_id = self.scope_arena.insert(Scope {
             parent_scope: scope_id.map(|s| s.to_owned()),
             statements: Some(statements),
             variables: IndexMap::new(),
+            blocks: Vec::new(),
         });
 
         let variable_scope_id = scope_id.unwrap_or(&function_scope_id);
@@ -230,12 +518,31 @@ impl<'input> SymbolTable<'input> {
         Ok((variable_id, function_scope_id.to_owned()))
     }
 
+    fn create_block(
+        &mut self,
+        scope_id: &Index,
+        statements: &'input Vec<ast::Statement<'input>>,
+    ) -> Result<(), CompilerError> {
+        let block_scope_id = self.scope_arena.insert(Scope {
+            parent_scope: Some(*scope_id),
+            statements: Some(statements),
+            variables: IndexMap::new(),
+            blocks: Vec::new(),
+        });
+
+        self.scope_mut(scope_id).blocks.push(block_scope_id);
+
+        self.build_scope(&block_scope_id)
+    }
+
     fn create_property_variable(
         &mut self,
+        location: (usize, usize),
         base_variable_id: &Index,
         property: &'input str,
-    ) -> Result<Index, CompilerError<'input>> {
+    ) -> Result<Index, CompilerError> {
         let variable_id = self.variable_arena.insert(Variable::Property {
+            location,
             base: base_variable_id.to_owned(),
             property,
         });
@@ -245,10 +552,12 @@ impl<'input> SymbolTable<'input> {
 
     fn create_indexed_variable(
         &mut self,
+        location: (usize, usize),
         base_variable_id: &Index,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<Index, CompilerError<'input>> {
+    ) -> Result<Index, CompilerError> {
         let variable_id = self.variable_arena.insert(Variable::Indexed {
+            location,
             base: base_variable_id.to_owned(),
             index: expression,
         });
@@ -256,7 +565,7 @@ impl<'input> SymbolTable<'input> {
         Ok(variable_id)
     }
 
-    fn build_scope(&mut self, scope_id: &Index) -> Result<(), CompilerError<'input>> {
+    fn build_scope(&mut self, scope_id: &Index) -> Result<(), CompilerError> {
         let scope = self.scope(scope_id);
 
         if let Some(statements) = scope.statements {
@@ -268,6 +577,8 @@ impl<'input> SymbolTable<'input> {
                         statements,
                         ..
                     } => {
+                        check_parameters(definition, parameters)?;
+
                         let (_, function_scope_id) =
                             self.create_function(Some(scope_id), definition, statements)?;
 
@@ -288,6 +599,30 @@ impl<'input> SymbolTable<'input> {
 
                     ast::Statement::ReturnStatement { .. } => {}
 
+                    ast::Statement::DeferStatement { statements, .. } => {
+                        self.create_block(scope_id, statements)?;
+                    }
+
+                    ast::Statement::IfStatement {
+                        statements,
+                        else_statements,
+                        ..
+                    } => {
+                        self.create_block(scope_id, statements)?;
+
+                        if let Some(else_statements) = else_statements {
+                            self.create_block(scope_id, else_statements)?;
+                        }
+                    }
+
+                    ast::Statement::MacroStatement { location, .. }
+                    | ast::Statement::MacroCallStatement { location, .. } => {
+                        return Err(CompilerError::Internal(
+                            Some(*location),
+                            "macro reached, it should have been expanded".to_string(),
+                        ));
+                    }
+
                     ast::Statement::EmptyStatement => {}
                 }
             }
@@ -297,12 +632,73 @@ impl<'input> SymbolTable<'input> {
     }
 }
 
+/// Rejects parameters sharing a name with each other or with their function, one
+/// of which would otherwise hide the other in the body, and rest parameters
+/// before the last one, which calls could not pack the arguments for.
+fn check_parameters(
+    definition: &ast::VariableDefinition,
+    parameters: &[ast::VariableDefinition],
+) -> Result<(), CompilerError> {
+    for (index, parameter) in parameters.iter().enumerate() {
+        if parameter.name == definition.name {
+            return Err(CompilerError::ParameterShadowsFunction(
+                parameter.location,
+                parameter.name.to_string(),
+            ));
+        }
+
+        if parameters[..index].iter().any(|p| p.name == parameter.name) {
+            return Err(CompilerError::DuplicateParameter(
+                parameter.location,
+                definition.name.to_string(),
+                parameter.name.to_string(),
+            ));
+        }
+    }
+
+    let kinds = match &definition.kind {
+        ast::VariableKind::Function { parameters, .. } => parameters,
+        _ => return Ok(()),
+    };
+
+    let rests = parameters
+        .iter()
+        .zip(kinds)
+        .filter(|(_, kind)| kind.is_rest)
+        .map(|(parameter, _)| parameter)
+        .collect::<Vec<_>>();
+
+    if let [_, repeated, ..] = rests[..] {
+        return Err(CompilerError::InvalidRestParameter(
+            repeated.location,
+            format!(
+                "function `{}` declares more than one rest parameter",
+                definition.name
+            ),
+        ));
+    }
+
+    match (rests.first(), parameters.last()) {
+        (Some(rest), Some(last)) if rest.location != last.location => {
+            Err(CompilerError::InvalidRestParameter(
+                rest.location,
+                format!(
+                    "rest parameter `{}` of function `{}` must be its last parameter",
+                    rest.name, definition.name
+                ),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
 impl<'input> SymbolTable<'input> {
     fn fetch_variable_by_name(
         &mut self,
         scope_id: &Index,
+        location: (usize, usize),
         name: &'input str,
-    ) -> Result<Index, CompilerError<'input>> {
+    ) -> Result<Index, CompilerError> {
         let scope = self.scope(scope_id);
 
         if let Some(variable_id) = scope.variables.get(name) {
@@ -311,30 +707,43 @@ impl<'input> SymbolTable<'input> {
 
         if let Some(parent) = scope.parent_scope.as_ref() {
             let parent = parent.to_owned();
-            return self.fetch_variable_by_name(&parent, name);
+            return self.fetch_variable_by_name(&parent, location, name);
         }
 
-        Err(CompilerError::VariableNotDefined(name))
+        Err(CompilerError::VariableNotDefined(
+            location,
+            name.to_string(),
+        ))
     }
 
     fn fetch_variable_by_identifier(
         &mut self,
         scope_id: &Index,
         identifier: &'input ast::VariableIdentifier<'input>,
-    ) -> Result<Index, CompilerError<'input>> {
+    ) -> Result<Index, CompilerError> {
         match identifier {
-            ast::VariableIdentifier::Name { name, .. } => {
-                self.fetch_variable_by_name(scope_id, name)
+            ast::VariableIdentifier::Name { location, name } => {
+                self.fetch_variable_by_name(scope_id, *location, name)
             }
-            ast::VariableIdentifier::Property { base, property, .. } => {
+            ast::VariableIdentifier::Property {
+                location,
+                base,
+                property,
+            } => {
                 let base_variable_id = self.fetch_variable_by_identifier(scope_id, base)?;
 
-                self.create_property_variable(&base_variable_id, property)
+                self.create_property_variable(*location, &base_variable_id, property)
             }
-            ast::VariableIdentifier::Index { base, index, .. } => {
+            ast::VariableIdentifier::Index {
+                location,
+                base,
+                index,
+            } => {
+                self.visit_expression(scope_id, index)?;
+
                 let base_variable_id = self.fetch_variable_by_identifier(scope_id, base)?;
 
-                self.create_indexed_variable(&base_variable_id, index)
+                self.create_indexed_variable(*location, &base_variable_id, index)
             }
         }
     }
@@ -343,19 +752,40 @@ impl<'input> SymbolTable<'input> {
         &mut self,
         scope_id: &Index,
         expression: &'input ast::Expression<'input>,
-    ) -> Result<(), CompilerError<'input>> {
+    ) -> Result<(), CompilerError> {
         match expression {
             ast::Expression::ConstantExpression { .. } => {}
 
-            ast::Expression::VariableExpression { identifier, .. } => {
+            ast::Expression::VariableExpression {
+                location,
+                identifier,
+            } => {
                 let variable_id = self.fetch_variable_by_identifier(scope_id, identifier)?;
 
+                self.add_reference(&variable_id, *location);
                 self.set_identifier_ref(identifier, &variable_id);
+
+                // a function used as a value may be called from wherever it is given to
+                if self.variable(&variable_id).is_function() {
+                    let caller_id = self.enclosing_function(scope_id);
+                    self.calls.entry(caller_id).or_default().insert(variable_id);
+                }
             }
 
-            ast::Expression::AssignmentExpression { identifier, .. } => {
+            ast::Expression::AssignmentExpression {
+                location,
+                identifier,
+                expression: e,
+            } => {
+                self.visit_expression(scope_id, e)?;
+
                 let variable_id = self.fetch_variable_by_identifier(scope_id, identifier)?;
 
+                // assigning to a property or an index reads the base variable
+                if !self.variable(&variable_id).is_static() {
+                    self.add_reference(&variable_id, *location);
+                }
+
                 self.set_identifier_ref(identifier, &variable_id);
             }
 
@@ -364,14 +794,24 @@ impl<'input> SymbolTable<'input> {
                 self.visit_expression(scope_id, right)?;
             }
 
+            ast::Expression::RangeExpression { start, end, .. } => {
+                self.visit_expression(scope_id, start)?;
+                self.visit_expression(scope_id, end)?;
+            }
+
             ast::Expression::UnaryExpression { expression: e, .. } => {
                 self.visit_expression(scope_id, &e)?;
             }
 
-            ast::Expression::TypeOfExpression { expression: e, .. } => {
+            ast::Expression::SymbolExpression { name: e, .. }
+            | ast::Expression::TypeOfExpression { expression: e, .. } => {
                 self.visit_expression(scope_id, &e)?;
             }
 
+            ast::Expression::ValidateExpression { expression: e, .. } => {
+                self.visit_expression(scope_id, e)?;
+            }
+
             ast::Expression::ObjectExpression { properties, .. } => {
                 for (_, e) in properties {
                     self.visit_expression(scope_id, e)?;
@@ -385,9 +825,9 @@ impl<'input> SymbolTable<'input> {
             }
 
             ast::Expression::CallExpression {
+                location,
                 identifier,
                 arguments,
-                ..
             } => {
                 for argument in arguments {
                     self.visit_expression(scope_id, argument)?;
@@ -399,15 +839,78 @@ impl<'input> SymbolTable<'input> {
                 match &variable {
                     Variable::Static { definition, .. } => match &definition.kind {
                         ast::VariableKind::Function { .. } => {
+                            let (min, max) = variable.get_arity()?;
+
+                            if arguments.len() < min || max.is_some_and(|max| arguments.len() > max)
+                            {
+                                return Err(CompilerError::InvalidNumberOfArguments(
+                                    *location,
+                                    definition.name.to_string(),
+                                    (min, max),
+                                    arguments.len(),
+                                ));
+                            }
+
+                            self.add_reference(&variable_id, *location);
                             self.set_identifier_ref(identifier, &variable_id);
+
+                            let caller_id = self.enclosing_function(scope_id);
+                            self.calls.entry(caller_id).or_default().insert(variable_id);
+                        }
+                        _ => {
+                            return Err(CompilerError::InvalidFunctionCall(
+                                *location,
+                                definition.name.to_string(),
+                            ))
                         }
-                        _ => return Err(CompilerError::InvalidFunctionCall(definition.name)),
                     },
-                    _ => unreachable!("Invalid function call"),
+                    _ => {
+                        return Err(CompilerError::InvalidFunctionCall(
+                            *location,
+                            identifier.get_name(),
+                        ))
+                    }
                 }
             }
 
-            ast::Expression::Empty => unreachable!("Empty expression"),
+            ast::Expression::MemberExpression {
+                expression: e,
+                member,
+                ..
+            } => {
+                self.visit_expression(scope_id, e)?;
+
+                match member {
+                    ast::Member::Property(_) => {}
+                    ast::Member::Index(index) => self.visit_expression(scope_id, index)?,
+                    ast::Member::Slice { start, end } => {
+                        for bound in [start, end].into_iter().flatten() {
+                            self.visit_expression(scope_id, bound)?;
+                        }
+                    }
+                }
+            }
+
+            ast::Expression::Empty => {
+                return Err(CompilerError::Internal(
+                    None,
+                    "empty expression reached".to_string(),
+                ))
+            }
+
+            ast::Expression::CompoundAssignmentExpression { location, .. } => {
+                return Err(CompilerError::Internal(
+                    Some(*location),
+                    "compound assignment reached, it should have been lowered".to_string(),
+                ))
+            }
+
+            ast::Expression::MatchExpression { location, .. } => {
+                return Err(CompilerError::Internal(
+                    Some(*location),
+                    "match reached, it should have been lowered".to_string(),
+                ))
+            }
         }
 
         Ok(())
@@ -417,7 +920,7 @@ impl<'input> SymbolTable<'input> {
         &mut self,
         scope_id: &Index,
         statement: &'input ast::Statement<'input>,
-    ) -> Result<(), CompilerError<'input>> {
+    ) -> Result<(), CompilerError> {
         match statement {
             ast::Statement::ExpressionStatement { expression } => {
                 self.visit_expression(scope_id, expression)?;
@@ -429,13 +932,39 @@ impl<'input> SymbolTable<'input> {
                 }
             }
 
-            ast::Statement::DefinitionStatement { expression, .. } => {
+            ast::Statement::DefinitionStatement {
+                definition,
+                expression,
+                ..
+            } => {
                 if let Some(expression) = expression {
                     self.visit_expression(scope_id, expression)?;
+
+                    if !definition.is_writable && !definition.is_external {
+                        let variable_id = *self.definition_ref(definition);
+                        self.const_initializers.insert(variable_id, expression);
+                    }
+                }
+
+                if !definition.is_annotated {
+                    self.infer_definition_kind(definition, expression.as_ref());
                 }
             }
 
-            ast::Statement::FunctionStatement { .. } => {} // the function statements will be visited by visit_scopes
+            ast::Statement::IfStatement { condition, .. } => {
+                self.visit_expression(scope_id, condition)?;
+            }
+
+            // the function and block statements will be visited by visit_scopes
+            ast::Statement::FunctionStatement { .. } | ast::Statement::DeferStatement { .. } => {}
+
+            ast::Statement::MacroStatement { location, .. }
+            | ast::Statement::MacroCallStatement { location, .. } => {
+                return Err(CompilerError::Internal(
+                    Some(*location),
+                    "macro reached, it should have been expanded".to_string(),
+                ));
+            }
 
             ast::Statement::EmptyStatement => {}
         }
@@ -443,19 +972,306 @@ impl<'input> SymbolTable<'input> {
         Ok(())
     }
 
-    fn visit_scope(&mut self, scope_id: &Index) -> Result<(), CompilerError<'input>> {
+    fn visit_scope(&mut self, scope_id: &Index) -> Result<(), CompilerError> {
         let scope = self.scope_mut(scope_id);
 
         if let Some(statements) = scope.statements {
             for statement in statements {
                 self.visit_statement(scope_id, statement)?;
             }
+
+            let unreachable_statement = statements
+                .iter()
+                .skip_while(|s| !matches!(s, ast::Statement::ReturnStatement { .. }))
+                .skip(1)
+                .find(|s| {
+                    !matches!(
+                        s,
+                        ast::Statement::FunctionStatement { .. } | ast::Statement::EmptyStatement
+                    )
+                });
+
+            if let Some(location) = unreachable_statement.and_then(|s| s.get_location()) {
+                self.warnings.push(Warning::UnreachableCode(location));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn infer_definition_kind(
+        &mut self,
+        definition: &'input ast::VariableDefinition<'input>,
+        expression: Option<&'input ast::Expression<'input>>,
+    ) {
+        let infered_kind = expression
+            .map(|e| self.infer_kind(e))
+            .unwrap_or(ast::VariableKind::Any);
+
+        if infered_kind == ast::VariableKind::Any {
+            self.warnings.push(Warning::ImplicitAny(
+                definition.location,
+                definition.name.to_string(),
+            ));
+        }
+
+        let variable_id = *self.definition_ref(definition);
+
+        if let Variable::Static { kind, .. } = self.variable_mut(&variable_id) {
+            *kind = infered_kind;
+        }
+    }
+
+    /// Best effort guess of the kind of an expression, falling back to `any`.
+    fn infer_kind(&self, expression: &'input ast::Expression<'input>) -> ast::VariableKind {
+        match expression {
+            ast::Expression::ConstantExpression { value, .. } => match value.get_kind() {
+                // `let x = null;` is meant to be assigned later
+                ast::VariableKind::Null | ast::VariableKind::Undefined => ast::VariableKind::Any,
+                kind => kind,
+            },
+
+            ast::Expression::ArrayExpression { items, .. } => {
+                let mut kinds = items.iter().map(|e| self.infer_kind(e));
+
+                let kind = match kinds.next() {
+                    Some(first) if kinds.all(|kind| kind == first) => first,
+                    _ => ast::VariableKind::Any,
+                };
+
+                ast::VariableKind::Array {
+                    kind: Box::new(kind),
+                }
+            }
+
+            ast::Expression::ObjectExpression { properties, .. } => ast::VariableKind::Object {
+                fields: properties
+                    .iter()
+                    .map(|(name, e)| ast::FieldKind {
+                        name: name.to_string(),
+                        kind: self.infer_kind(e),
+                        is_readonly: false,
+                    })
+                    .collect(),
+            },
+
+            ast::Expression::TypeOfExpression { .. } => ast::VariableKind::String,
+
+            ast::Expression::SymbolExpression { .. } => ast::VariableKind::Symbol,
+
+            ast::Expression::ValidateExpression { kind, .. } => kind.clone(),
+
+            ast::Expression::VariableExpression { identifier, .. } => {
+                match self.variable(self.identifier_ref(identifier)) {
+                    // function values cannot be called through variables
+                    variable if variable.is_function() => ast::VariableKind::Any,
+                    Variable::Static { kind, .. } => kind.clone(),
+                    _ => ast::VariableKind::Any,
+                }
+            }
+
+            ast::Expression::AssignmentExpression { expression, .. } => self.infer_kind(expression),
+
+            ast::Expression::CallExpression { identifier, .. } => {
+                // the functions called through properties have no known kind
+                match self.variable(self.identifier_ref(identifier)).get_kind() {
+                    Ok(ast::VariableKind::Function { return_kind, .. }) => *return_kind.clone(),
+                    _ => ast::VariableKind::Any,
+                }
+            }
+
+            ast::Expression::MemberExpression {
+                expression, member, ..
+            } => match (self.infer_kind(expression), member) {
+                (kind @ ast::VariableKind::Object { .. }, ast::Member::Property(property)) => kind
+                    .get_field(property)
+                    .map_or(ast::VariableKind::Any, |field| field.kind.clone()),
+                (ast::VariableKind::Array { kind }, ast::Member::Index(_)) => *kind,
+                (
+                    ast::VariableKind::Float64Array | ast::VariableKind::Int64Array,
+                    ast::Member::Index(_),
+                ) => ast::VariableKind::Number,
+                (kind @ ast::VariableKind::Array { .. }, ast::Member::Slice { .. })
+                | (kind @ ast::VariableKind::String, ast::Member::Slice { .. }) => kind,
+                _ => ast::VariableKind::Any,
+            },
+
+            ast::Expression::UnaryExpression { operator, .. } => match operator {
+                ast::UnaryOperator::Not => ast::VariableKind::Boolean,
+                _ => ast::VariableKind::Number,
+            },
+
+            ast::Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => match operator {
+                ast::BinaryOperator::Addition => {
+                    let left = self.infer_kind(left);
+                    let right = self.infer_kind(right);
+
+                    if left == ast::VariableKind::Any || right == ast::VariableKind::Any {
+                        ast::VariableKind::Any
+                    } else {
+                        left.operation_result(&right)
+                    }
+                }
+                ast::BinaryOperator::Subtraction
+                | ast::BinaryOperator::Multiplication
+                | ast::BinaryOperator::Division
+                | ast::BinaryOperator::Mod => ast::VariableKind::Number,
+                ast::BinaryOperator::And | ast::BinaryOperator::Or => {
+                    match (self.infer_kind(left), self.infer_kind(right)) {
+                        (ast::VariableKind::Boolean, ast::VariableKind::Boolean) => {
+                            ast::VariableKind::Boolean
+                        }
+                        _ => ast::VariableKind::Any,
+                    }
+                }
+                _ => ast::VariableKind::Boolean,
+            },
+
+            ast::Expression::RangeExpression { .. } => ast::VariableKind::Any,
+
+            ast::Expression::CompoundAssignmentExpression { .. }
+            | ast::Expression::MatchExpression { .. }
+            | ast::Expression::Empty => ast::VariableKind::Any,
+        }
+    }
+
+    /// Records a read of the variable, or of the static variable a property or
+    /// an index belongs to.
+    fn add_reference(&mut self, variable_id: &Index, location: (usize, usize)) {
+        let variable_id = self.static_variable(variable_id);
+
+        self.references
+            .entry(variable_id)
+            .or_default()
+            .push(location);
+    }
+
+    /// Makes the top level function the entry of the program, called by `main` and so
+    /// used and reachable. It must take no required parameters.
+    fn resolve_entry(&mut self, name: &str) -> Result<(), CompilerError> {
+        let main_function = self.main_function.unwrap();
+
+        let entry = self
+            .function_scope(&main_function)
+            .variables
+            .get(name)
+            .copied()
+            .filter(|variable_id| {
+                let variable = self.variable(variable_id);
+
+                variable.is_function() && matches!(variable.is_external(), Ok(false))
+            })
+            .ok_or_else(|| {
+                CompilerError::CliError(format!(
+                    "Entry function `{}` is not defined at the top level",
+                    name
+                ))
+            })?;
+
+        if let ast::VariableKind::Function { parameters, .. } = self.variable(&entry).get_kind()? {
+            if parameters.iter().any(|p| !p.is_optional && !p.is_rest) {
+                return Err(CompilerError::CliError(format!(
+                    "Entry function `{}` cannot take required parameters",
+                    name
+                )));
+            }
         }
 
+        self.references.entry(entry).or_default();
+        self.calls.entry(main_function).or_default().insert(entry);
+        self.entry_function = Some(entry);
+
+        Ok(())
+    }
+
+    /// Makes the top level test functions called by `main`, in the order they are
+    /// defined. Like the entry, they must take no required parameters.
+    fn resolve_tests(&mut self) -> Result<(), CompilerError> {
+        let main_function = self.main_function.unwrap();
+
+        let candidates = self
+            .function_scope(&main_function)
+            .variables
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut tests = Vec::new();
+
+        for variable_id in candidates {
+            let definition = match self.variable(&variable_id).get_definition() {
+                Ok(definition) if testing::is_test(definition) => definition,
+                _ => continue,
+            };
+
+            if let ast::VariableKind::Function { parameters, .. } =
+                self.variable(&variable_id).get_kind()?
+            {
+                if parameters.iter().any(|p| !p.is_optional && !p.is_rest) {
+                    return Err(CompilerError::CliError(format!(
+                        "Test function `{}` cannot take required parameters",
+                        definition.name
+                    )));
+                }
+            }
+
+            self.references.entry(variable_id).or_default();
+            self.calls
+                .entry(main_function)
+                .or_default()
+                .insert(variable_id);
+            tests.push(variable_id);
+        }
+
+        self.test_functions = Some(tests);
+
         Ok(())
     }
 
-    fn visit_scopes(&mut self) -> Result<(), CompilerError<'input>> {
+    fn check_unused_variables(&mut self) {
+        for (variable_id, variable) in self.variable_arena.iter() {
+            let definition = match variable {
+                Variable::Static {
+                    definition,
+                    is_parameter: false,
+                    ..
+                } => definition,
+                _ => continue,
+            };
+
+            if self.references.contains_key(&variable_id)
+                || Some(variable_id) == self.main_function
+                || definition.is_external
+                || definition.name.starts_with('_')
+            {
+                continue;
+            }
+
+            let name = definition.name.to_string();
+
+            if variable.is_function() {
+                // exported functions are used from outside of the program, test
+                // and bench functions by `mini test` and `mini bench`
+                if !definition.decorators.contains("export")
+                    && !testing::is_test(definition)
+                    && !testing::is_bench(definition)
+                {
+                    self.warnings
+                        .push(Warning::UnusedFunction(definition.location, name));
+                }
+            } else {
+                self.warnings
+                    .push(Warning::UnusedVariable(definition.location, name));
+            }
+        }
+    }
+
+    fn visit_scopes(&mut self) -> Result<(), CompilerError> {
         let scopes = self.scope_arena.iter().map(|(i, _)| i).collect::<Vec<_>>();
 
         for scope_id in scopes {
@@ -465,3 +1281,286 @@ impl<'input> SymbolTable<'input> {
         Ok(())
     }
 }
+
+impl<'input> SymbolTable<'input> {
+    /// Describes the scope: the function owning it, or `block` for if/else bodies.
+    fn scope_label(&self, scope_id: &Index) -> Result<String, CompilerError> {
+        match self
+            .function_scope_map
+            .iter()
+            .find(|(_, function_scope_id)| *function_scope_id == scope_id)
+        {
+            Some((function_id, _)) => Ok(format!(
+                "function {}",
+                self.variable(function_id).get_name()?
+            )),
+            None => Ok("block".to_string()),
+        }
+    }
+
+    fn child_scopes(&self, scope_id: &Index) -> Vec<Index> {
+        self.scope_arena
+            .iter()
+            .filter(|(_, scope)| scope.parent_scope == Some(*scope_id))
+            .map(|(child_id, _)| child_id)
+            .collect()
+    }
+
+    fn describe_variable(
+        &self,
+        variable_id: &Index,
+        source: &SourceFile,
+    ) -> Result<String, CompilerError> {
+        let variable = self.variable(variable_id);
+        let definition = variable.get_definition()?;
+        let kind = variable.get_kind()?;
+
+        let mut flags = Vec::new();
+
+        if variable.is_parameter()? {
+            flags.push("parameter");
+        } else if !definition.is_writable && !variable.is_function() {
+            flags.push("const");
+        }
+
+        if definition.is_external {
+            flags.push("external");
+        }
+
+        if !self.references.contains_key(variable_id)
+            && Some(*variable_id) != self.main_function
+            && !definition.is_external
+        {
+            flags.push("unused");
+        }
+
+        let (line, column) = source.line_column(definition.location.0);
+        let mut description = format!("{}: {} ({}:{})", definition.name, kind, line, column);
+
+        if !flags.is_empty() {
+            description.push_str(&format!(" [{}]", flags.join(", ")));
+        }
+
+        Ok(description)
+    }
+
+    /// Lists every scope with its variables, their kinds and where they are
+    /// defined, nesting scopes under their parents.
+    pub fn dump(&self, source: &SourceFile) -> Result<String, CompilerError> {
+        let mut output = String::new();
+        let main_scope_id = self.function_scope_map[&self.main_function.unwrap()];
+        let mut scopes = vec![(main_scope_id, 0)];
+
+        while let Some((scope_id, depth)) = scopes.pop() {
+            let indent = "    ".repeat(depth);
+
+            output.push_str(&format!(
+                "{}scope #{} ({})\n",
+                indent,
+                scope_id.into_raw_parts().0,
+                self.scope_label(&scope_id)?
+            ));
+
+            for variable_id in self.scope(&scope_id).variables.values() {
+                output.push_str(&format!(
+                    "{}    {}\n",
+                    indent,
+                    self.describe_variable(variable_id, source)?
+                ));
+            }
+
+            scopes.extend(
+                self.child_scopes(&scope_id)
+                    .into_iter()
+                    .rev()
+                    .map(|child_id| (child_id, depth + 1)),
+            );
+        }
+
+        Ok(output)
+    }
+
+    /// The scopes as a Graphviz graph, with an edge from each scope to its parent.
+    pub fn dump_dot(&self, source: &SourceFile) -> Result<String, CompilerError> {
+        let escape = |s: String| s.replace('\\', "\\\\").replace('"', "\\\"");
+
+        let mut output = String::from("digraph symbols {\n    node [shape=box];\n");
+
+        for (scope_id, scope) in self.scope_arena.iter() {
+            let mut label = format!(
+                "scope #{} ({})\\l",
+                scope_id.into_raw_parts().0,
+                self.scope_label(&scope_id)?
+            );
+
+            for variable_id in scope.variables.values() {
+                label.push_str(&escape(self.describe_variable(variable_id, source)?));
+                label.push_str("\\l");
+            }
+
+            output.push_str(&format!(
+                "    s{} [label=\"{}\"];\n",
+                scope_id.into_raw_parts().0,
+                label
+            ));
+
+            if let Some(parent_id) = scope.parent_scope {
+                output.push_str(&format!(
+                    "    s{} -> s{};\n",
+                    scope_id.into_raw_parts().0,
+                    parent_id.into_raw_parts().0
+                ));
+            }
+        }
+
+        output.push_str("}\n");
+
+        Ok(output)
+    }
+
+    /// Functions that can be called starting from `main`.
+    fn reachable_functions(&self) -> IndexSet<Index> {
+        let mut reachable = IndexSet::new();
+        let mut pending = vec![self.main_function.unwrap()];
+
+        while let Some(function_id) = pending.pop() {
+            if reachable.insert(function_id) {
+                pending.extend(self.calls.get(&function_id).into_iter().flatten());
+            }
+        }
+
+        reachable
+    }
+
+    /// Lists the functions each function calls, marking the ones never reached from `main`.
+    pub fn dump_call_graph(&self) -> Result<String, CompilerError> {
+        let reachable = self.reachable_functions();
+        let mut output = String::new();
+
+        for function_id in self.function_scope_map.keys() {
+            let callees = self
+                .calls
+                .get(function_id)
+                .into_iter()
+                .flatten()
+                .map(|callee_id| self.variable(callee_id).get_name())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            output.push_str(&format!(
+                "{} -> [{}]",
+                self.variable(function_id).get_name()?,
+                callees.join(", ")
+            ));
+
+            if !reachable.contains(function_id) {
+                output.push_str(" (unreachable)");
+            }
+
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+
+    /// The call graph in Graphviz format. External functions are dashed and the
+    /// ones never reached from `main` are gray.
+    pub fn dump_call_graph_dot(&self) -> Result<String, CompilerError> {
+        let reachable = self.reachable_functions();
+        let node = |function_id: &Index| format!("f{}", function_id.into_raw_parts().0);
+
+        let mut output = String::from("digraph callgraph {\n");
+
+        for function_id in self.function_scope_map.keys() {
+            let variable = self.variable(function_id);
+            let mut attributes = vec![format!("label=\"{}\"", variable.get_name()?)];
+
+            if variable.is_external()? {
+                attributes.push("style=dashed".to_string());
+            }
+
+            if !reachable.contains(function_id) {
+                attributes.push("color=gray".to_string());
+                attributes.push("fontcolor=gray".to_string());
+            }
+
+            output.push_str(&format!(
+                "    {} [{}];\n",
+                node(function_id),
+                attributes.join(", ")
+            ));
+        }
+
+        for (caller_id, callees) in self.calls.iter() {
+            for callee_id in callees {
+                output.push_str(&format!(
+                    "    {} -> {};\n",
+                    node(caller_id),
+                    node(callee_id)
+                ));
+            }
+        }
+
+        output.push_str("}\n");
+
+        Ok(output)
+    }
+
+    /// The scopes and their variables in a form that can be serialized, e.g. to
+    /// JSON for tools analyzing programs.
+    pub fn snapshot(&self) -> Result<SymbolTableSnapshot<'_>, CompilerError> {
+        let id = |index: &Index| index.into_raw_parts().0;
+
+        let variable = |variable_id: &Index| {
+            let variable = self.variable(variable_id);
+            let definition = variable.get_definition()?;
+
+            Ok(VariableSnapshot {
+                id: id(variable_id),
+                name: definition.name,
+                kind: variable.get_kind()?,
+                location: definition.location,
+                is_parameter: variable.is_parameter()?,
+                is_writable: definition.is_writable,
+                is_external: definition.is_external,
+                docs: &definition.docs,
+                references: self
+                    .references
+                    .get(variable_id)
+                    .map_or(&[], |references| references.as_slice()),
+                calls: self
+                    .calls
+                    .get(variable_id)
+                    .into_iter()
+                    .flatten()
+                    .map(id)
+                    .collect(),
+            })
+        };
+
+        let scopes = self
+            .scope_arena
+            .iter()
+            .map(|(scope_id, scope)| {
+                Ok(ScopeSnapshot {
+                    id: id(&scope_id),
+                    parent: scope.parent_scope.as_ref().map(id),
+                    function: self
+                        .function_scope_map
+                        .iter()
+                        .find(|(_, function_scope_id)| **function_scope_id == scope_id)
+                        .map(|(function_id, _)| id(function_id)),
+                    variables: scope
+                        .variables
+                        .values()
+                        .map(variable)
+                        .collect::<Result<_, CompilerError>>()?,
+                })
+            })
+            .collect::<Result<_, CompilerError>>()?;
+
+        Ok(SymbolTableSnapshot {
+            main_function: self.main_function.as_ref().map(id),
+            scopes,
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use lalrpop_util::ParseError;
+
+use crate::lexer::{Lexer, LexicalError, Token};
+
+/// A syntax error as the parser reports it, with tokens as their source text.
+pub type RawParseError = ParseError<usize, String, LexicalError>;
+
+/// Describes the syntax error in terms of the constructs of the language, e.g.
+/// "expected expression after `=`, found `;`", rather than the grammar terminals
+/// the parser lists. Returns the message and, when there is a likely fix, a hint.
+pub fn explain(err: &RawParseError, content: &str) -> (String, Option<String>) {
+    match err {
+        ParseError::InvalidToken { location }
+        | ParseError::User {
+            error: LexicalError { location },
+        } => match content[*location..].chars().next() {
+            Some(quote @ ('\'' | '"' | '`')) => (
+                "unterminated string literal".to_string(),
+                Some(format!(
+                    "strings cannot span lines, close it with a `{}` on the same line",
+                    quote
+                )),
+            ),
+            Some('/') => ("unterminated block comment".to_string(), None),
+            Some(c) => (format!("unexpected character `{}`", c), None),
+            None => ("unexpected end of file".to_string(), None),
+        },
+
+        ParseError::UnrecognizedToken {
+            token: (start, token, _),
+            expected,
+        } => {
+            let message = expectation(content, *start, expected, &format!("`{}`", token));
+
+            let help = if expects(expected, ";") && !expects(expected, ")") {
+                previous_token(content, *start)
+                    .map(|previous| format!("add a `;` after `{}` to end the statement", previous))
+            } else {
+                None
+            };
+
+            (message, help)
+        }
+
+        ParseError::UnrecognizedEof { location, expected } => {
+            let message = expectation(content, *location, expected, "end of file");
+
+            let help = if expects(expected, "}") {
+                unclosed_brace(content)
+                    .map(|line| format!("the `{{` on line {} is never closed, add a `}}`", line))
+            } else if expects(expected, ";") {
+                Some("the last statement is missing a `;`".to_string())
+            } else {
+                None
+            };
+
+            (message, help)
+        }
+
+        ParseError::ExtraToken {
+            token: (_, token, _),
+        } => (format!("unexpected `{}`", token), None),
+    }
+}
+
+fn expects(expected: &[String], token: &str) -> bool {
+    expected.iter().any(|e| e == &format!("\"{}\"", token))
+}
+
+/// "expected <construct> [after `<previous>`], found <found>"
+fn expectation(content: &str, location: usize, expected: &[String], found: &str) -> String {
+    let construct = if expected.iter().any(|e| terminal_name(e) == "number") {
+        "expression".to_string()
+    } else if expects(expected, ";") {
+        "`;`".to_string()
+    } else {
+        let mut names: Vec<String> = expected.iter().map(|e| terminal_name(e)).collect();
+        names.dedup();
+
+        match names.len() {
+            0 => "nothing".to_string(),
+            1 => names.remove(0),
+            n if n <= 4 => {
+                let last = names.pop().unwrap();
+                format!("{} or {}", names.join(", "), last)
+            }
+            _ => format!("one of {} and more", names[..4].join(", ")),
+        }
+    };
+
+    match previous_token(content, location) {
+        Some(previous) => format!(
+            "expected {} after `{}`, found {}",
+            construct, previous, found
+        ),
+        None => format!("expected {}, found {}", construct, found),
+    }
+}
+
+/// Names the terminal of the grammar, its literal text for keywords and symbols.
+fn terminal_name(terminal: &str) -> String {
+    match terminal.trim_matches('"') {
+        "Identifier" => "name".to_string(),
+        "Decorator" => "decorator".to_string(),
+        "Integer" | "Float" => "number".to_string(),
+        "String" => "string".to_string(),
+        "DocComment" => "doc comment".to_string(),
+        literal => format!("`{}`", literal),
+    }
+}
+
+/// The text of the last token ending before `location`.
+fn previous_token(content: &str, location: usize) -> Option<&str> {
+    Lexer::new(&content[..location.min(content.len())])
+        .map_while(Result::ok)
+        .last()
+        .map(|(_, token, _)| token.text())
+}
+
+/// The line of the last `{` left open at the end of the content.
+fn unclosed_brace(content: &str) -> Option<usize> {
+    let mut open = Vec::new();
+
+    for (start, token, _) in Lexer::new(content).map_while(Result::ok) {
+        match token {
+            Token::Symbol("{") => open.push(start),
+            Token::Symbol("}") => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    open.pop()
+        .map(|start| content[..start].matches('\n').count() + 1)
+}
@@ -0,0 +1,16 @@
+use crate::ast;
+
+/// Returns whether the function is run by `mini test`, being named `test_*` or
+/// decorated with `@test`.
+pub fn is_test(definition: &ast::VariableDefinition) -> bool {
+    matches!(definition.kind, ast::VariableKind::Function { .. })
+        && !definition.is_external
+        && (definition.name.starts_with("test_") || definition.decorators.contains("test"))
+}
+
+/// Returns whether the function is measured by `mini bench`, being decorated with `@bench`.
+pub fn is_bench(definition: &ast::VariableDefinition) -> bool {
+    matches!(definition.kind, ast::VariableKind::Function { .. })
+        && !definition.is_external
+        && definition.decorators.contains("bench")
+}
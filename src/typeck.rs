@@ -0,0 +1,778 @@
+use by_address::ByAddress;
+use generational_arena::Index;
+use indexmap::IndexMap;
+
+use crate::ast;
+use crate::error::CompilerError;
+use crate::format_string;
+use crate::st;
+
+/// A value of kind `any` passed to a typed parameter, whose kind is asserted when
+/// the call happens.
+#[derive(Clone, Debug)]
+pub struct RuntimeCast {
+    pub kind: ast::VariableKind,
+    /// Describes the parameter in the error raised when the assertion fails.
+    pub path: String,
+}
+
+pub type RuntimeCasts<'input> = IndexMap<ByAddress<&'input ast::Expression<'input>>, RuntimeCast>;
+
+/// Checks the types of a program against its annotations before code generation.
+/// Values of kind `any` are compatible with everything, so unannotated code passes.
+pub struct TypeChecker<'st, 'input> {
+    symbol_table: &'st st::SymbolTable<'input>,
+
+    current_function: Option<Index>,
+
+    /// Kinds of variables narrowed down by the `typeof` guards of the enclosing `if`s.
+    narrowed: IndexMap<Index, ast::VariableKind>,
+
+    casts: RuntimeCasts<'input>,
+}
+
+impl<'st, 'input> TypeChecker<'st, 'input> {
+    pub fn new(symbol_table: &'st st::SymbolTable<'input>) -> Self {
+        TypeChecker {
+            symbol_table,
+            current_function: None,
+            narrowed: IndexMap::new(),
+            casts: IndexMap::new(),
+        }
+    }
+
+    /// Returns the arguments whose kinds have to be asserted at runtime.
+    pub fn check(mut self) -> Result<RuntimeCasts<'input>, CompilerError> {
+        let main_function = self.symbol_table.main_function.unwrap();
+
+        self.check_function(&main_function)?;
+
+        Ok(self.casts)
+    }
+
+    fn check_function(&mut self, function_id: &Index) -> Result<(), CompilerError> {
+        let scope = self.symbol_table.function_scope(function_id);
+
+        let previous_function = self.current_function.replace(*function_id);
+        // the function may be called after the narrowed variables change
+        let previous_narrowed = std::mem::take(&mut self.narrowed);
+
+        if let Some(statements) = scope.statements {
+            self.check_statements(statements)?;
+        }
+
+        self.current_function = previous_function;
+        self.narrowed = previous_narrowed;
+
+        Ok(())
+    }
+
+    fn check_statements(
+        &mut self,
+        statements: &'input [ast::Statement<'input>],
+    ) -> Result<(), CompilerError> {
+        for statement in statements {
+            self.check_statement(statement)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the statements of an `if` branch with the given variable narrowed down.
+    fn check_branch(
+        &mut self,
+        statements: &'input [ast::Statement<'input>],
+        narrowing: Option<(Index, ast::VariableKind)>,
+    ) -> Result<(), CompilerError> {
+        let previous_narrowed = self.narrowed.clone();
+
+        if let Some((variable_id, kind)) = narrowing {
+            self.narrowed.insert(variable_id, kind);
+        }
+
+        let result = self.check_statements(statements);
+
+        self.narrowed = previous_narrowed;
+
+        result
+    }
+
+    /// Matches `typeof x === "kind"` and its negated forms, returning the variable,
+    /// the kind it has when the condition holds, and whether the condition is negated.
+    fn typeof_guard(
+        &self,
+        condition: &'input ast::Expression<'input>,
+    ) -> Option<(Index, ast::VariableKind, bool)> {
+        let (operator, left, right) = match condition {
+            ast::Expression::BinaryExpression {
+                operator,
+                left,
+                right,
+                ..
+            } => (operator, left.as_ref(), right.as_ref()),
+            _ => return None,
+        };
+
+        let is_negated = match operator {
+            ast::BinaryOperator::Equal | ast::BinaryOperator::StrictEqual => false,
+            ast::BinaryOperator::NotEqual | ast::BinaryOperator::StrictNotEqual => true,
+            _ => return None,
+        };
+
+        let (identifier, type_name) = match (left, right) {
+            (
+                ast::Expression::TypeOfExpression { expression, .. },
+                ast::Expression::ConstantExpression {
+                    value: ast::Constant::String(type_name),
+                    ..
+                },
+            )
+            | (
+                ast::Expression::ConstantExpression {
+                    value: ast::Constant::String(type_name),
+                    ..
+                },
+                ast::Expression::TypeOfExpression { expression, .. },
+            ) => match expression.as_ref() {
+                ast::Expression::VariableExpression { identifier, .. } => (identifier, type_name),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let kind = match *type_name {
+            "number" => ast::VariableKind::Number,
+            "string" => ast::VariableKind::String,
+            "boolean" => ast::VariableKind::Boolean,
+            "symbol" => ast::VariableKind::Symbol,
+            _ => return None,
+        };
+
+        let variable_id = *self.symbol_table.identifier_ref(identifier);
+
+        match self.symbol_table.variable(&variable_id) {
+            st::Variable::Static { kind: declared, .. } if declared.is_assignable_from(&kind) => {
+                Some((variable_id, kind, is_negated))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.current_function == self.symbol_table.main_function
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &'input ast::Statement<'input>,
+    ) -> Result<(), CompilerError> {
+        match statement {
+            ast::Statement::ExpressionStatement { expression } => {
+                self.check_expression(expression)?;
+            }
+
+            ast::Statement::DeferStatement { statements, .. } => {
+                self.check_statements(statements)?;
+            }
+
+            ast::Statement::DefinitionStatement {
+                definition,
+                expression,
+                ..
+            } => {
+                if let Some(expression) = expression {
+                    let kind = self.check_expression(expression)?;
+
+                    if !definition.kind.is_assignable_from(&kind) {
+                        return Err(CompilerError::InvalidAssignment(
+                            definition.location,
+                            definition.name.to_string(),
+                            definition.kind.clone(),
+                            kind,
+                        ));
+                    }
+                }
+            }
+
+            ast::Statement::FunctionStatement { definition, .. } => {
+                if !definition.is_external {
+                    let function_id = self.symbol_table.definition_ref(definition);
+
+                    self.check_function(function_id)?;
+                }
+            }
+
+            ast::Statement::ReturnStatement {
+                location,
+                expression,
+            } => {
+                if self.is_global_scope() {
+                    return Err(CompilerError::CannotReturnFromGlobalScope(*location));
+                }
+
+                let kind = match expression {
+                    Some(expression) => self.check_expression(expression)?,
+                    None => ast::VariableKind::Undefined,
+                };
+
+                let function = self.symbol_table.variable(&self.current_function.unwrap());
+
+                let return_kind = match function.get_kind()? {
+                    ast::VariableKind::Function { return_kind, .. } => return_kind,
+                    _ => {
+                        return Err(CompilerError::Internal(
+                            Some(*location),
+                            "return outside of a function".to_string(),
+                        ))
+                    }
+                };
+
+                if !return_kind.is_assignable_from(&kind) {
+                    return Err(CompilerError::InvalidReturnType(
+                        *location,
+                        function.get_name()?.to_string(),
+                        *return_kind.clone(),
+                        kind,
+                    ));
+                }
+            }
+
+            ast::Statement::IfStatement {
+                condition,
+                statements,
+                else_statements,
+                ..
+            } => {
+                // any value can be a condition, it is taken as its truthiness
+                self.check_expression(condition)?;
+
+                let (then_narrowing, else_narrowing) = match self.typeof_guard(condition) {
+                    Some((variable_id, kind, false)) => (Some((variable_id, kind)), None),
+                    Some((variable_id, kind, true)) => (None, Some((variable_id, kind))),
+                    None => (None, None),
+                };
+
+                self.check_branch(statements, then_narrowing)?;
+
+                if let Some(else_statements) = else_statements {
+                    self.check_branch(else_statements, else_narrowing)?;
+                }
+            }
+
+            ast::Statement::MacroStatement { location, .. }
+            | ast::Statement::MacroCallStatement { location, .. } => {
+                return Err(CompilerError::Internal(
+                    Some(*location),
+                    "macro reached, it should have been expanded".to_string(),
+                ));
+            }
+
+            ast::Statement::EmptyStatement => {}
+        }
+
+        Ok(())
+    }
+
+    /// Returns the kind of the value the expression evaluates to.
+    fn check_expression(
+        &mut self,
+        expression: &'input ast::Expression<'input>,
+    ) -> Result<ast::VariableKind, CompilerError> {
+        match expression {
+            ast::Expression::ConstantExpression { value, .. } => Ok(value.get_kind()),
+
+            ast::Expression::ArrayExpression { items, .. } => {
+                let mut item_kind: Option<ast::VariableKind> = None;
+
+                for item in items {
+                    let kind = self.check_expression(item)?;
+
+                    item_kind = match item_kind {
+                        None => Some(kind),
+                        Some(item_kind) if item_kind == kind => Some(item_kind),
+                        Some(_) => Some(ast::VariableKind::Any),
+                    };
+                }
+
+                Ok(ast::VariableKind::Array {
+                    kind: Box::new(item_kind.unwrap_or(ast::VariableKind::Any)),
+                })
+            }
+
+            ast::Expression::ObjectExpression { properties, .. } => {
+                let mut fields = Vec::new();
+
+                for (name, e) in properties {
+                    fields.push(ast::FieldKind {
+                        name: name.to_string(),
+                        kind: self.check_expression(e)?,
+                        is_readonly: false,
+                    });
+                }
+
+                Ok(ast::VariableKind::Object { fields })
+            }
+
+            ast::Expression::TypeOfExpression { expression, .. } => {
+                self.check_expression(expression)?;
+
+                Ok(ast::VariableKind::String)
+            }
+
+            ast::Expression::SymbolExpression { location, name } => {
+                let kind = self.check_expression(name)?;
+
+                if !ast::VariableKind::String.is_assignable_from(&kind) {
+                    return Err(CompilerError::InvalidArgumentType(
+                        name.get_location().unwrap_or(*location),
+                        "Symbol".to_string(),
+                        ast::VariableKind::String,
+                        kind,
+                    ));
+                }
+
+                Ok(ast::VariableKind::Symbol)
+            }
+
+            ast::Expression::ValidateExpression {
+                kind, expression, ..
+            } => {
+                self.check_expression(expression)?;
+
+                Ok(kind.clone())
+            }
+
+            ast::Expression::VariableExpression {
+                location,
+                identifier,
+            } => self.identifier_kind(identifier, *location),
+
+            ast::Expression::AssignmentExpression {
+                location,
+                identifier,
+                expression,
+            } => {
+                let kind = self.check_expression(expression)?;
+
+                let variable_id = self.symbol_table.identifier_ref(identifier);
+
+                // the assigned value is checked against the declared kind from now on
+                self.narrowed.shift_remove(variable_id);
+
+                match self.symbol_table.variable(variable_id) {
+                    st::Variable::Static { definition, .. } if !definition.is_writable => {
+                        return Err(CompilerError::CannotAssignConstVariable(
+                            *location,
+                            definition.name.to_string(),
+                        ));
+                    }
+                    st::Variable::Property { base, property, .. } => {
+                        let base_kind = self.variable_kind(base, *location)?;
+
+                        if base_kind.get_field(property).is_some_and(|f| f.is_readonly) {
+                            return Err(CompilerError::CannotAssignReadonlyProperty(
+                                *location,
+                                property.to_string(),
+                                base_kind,
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+
+                let variable_kind = self.variable_kind(variable_id, *location)?;
+
+                if !variable_kind.is_assignable_from(&kind) {
+                    return Err(CompilerError::InvalidAssignment(
+                        *location,
+                        identifier.get_name(),
+                        variable_kind,
+                        kind,
+                    ));
+                }
+
+                Ok(kind)
+            }
+
+            ast::Expression::CallExpression {
+                location,
+                identifier,
+                arguments,
+            } => self.check_call_expression(*location, identifier, arguments),
+
+            ast::Expression::MemberExpression {
+                location,
+                expression,
+                member,
+            } => {
+                let base_kind = self.check_expression(expression)?;
+
+                match member {
+                    ast::Member::Property(property) => {
+                        property_kind(base_kind, property, *location)
+                    }
+                    ast::Member::Index(index) => {
+                        self.check_expression(index)?;
+
+                        Ok(index_kind(base_kind, index))
+                    }
+                    ast::Member::Slice { start, end } => {
+                        for bound in [start, end].into_iter().flatten() {
+                            self.check_expression(bound)?;
+                        }
+
+                        Ok(match base_kind {
+                            kind
+                            @ (ast::VariableKind::Array { .. } | ast::VariableKind::String) => kind,
+                            _ => ast::VariableKind::Any,
+                        })
+                    }
+                }
+            }
+
+            ast::Expression::UnaryExpression {
+                location,
+                operator,
+                expression,
+            } => {
+                let kind = self.check_expression(expression)?;
+
+                let result = match (operator, &kind) {
+                    (_, ast::VariableKind::Any) => Some(ast::VariableKind::Any),
+                    (ast::UnaryOperator::Positive, ast::VariableKind::Boolean) => {
+                        Some(ast::VariableKind::Number)
+                    }
+                    (
+                        ast::UnaryOperator::Positive | ast::UnaryOperator::Negative,
+                        ast::VariableKind::Number | ast::VariableKind::Int32,
+                    ) => Some(ast::VariableKind::Number),
+                    (ast::UnaryOperator::Not, ast::VariableKind::Boolean) => {
+                        Some(ast::VariableKind::Boolean)
+                    }
+                    _ => None,
+                };
+
+                result.ok_or_else(|| {
+                    CompilerError::InvalidUnaryOperand(*location, operator.get_symbol(), kind)
+                })
+            }
+
+            ast::Expression::RangeExpression {
+                location,
+                start,
+                end,
+                is_inclusive,
+            } => {
+                let start = self.check_expression(start)?;
+                let end = self.check_expression(end)?;
+
+                let is_number = |kind: &ast::VariableKind| {
+                    matches!(
+                        kind,
+                        ast::VariableKind::Number
+                            | ast::VariableKind::Int32
+                            | ast::VariableKind::Any
+                    )
+                };
+
+                if is_number(&start) && is_number(&end) {
+                    Ok(ast::VariableKind::Any)
+                } else {
+                    let symbol = if *is_inclusive { "..=" } else { ".." };
+
+                    Err(CompilerError::InvalidBinaryOperands(
+                        *location, symbol, start, end,
+                    ))
+                }
+            }
+
+            ast::Expression::BinaryExpression {
+                location,
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.check_expression(left)?;
+                let right = self.check_expression(right)?;
+
+                binary_operation_kind(operator, &left, &right).ok_or_else(|| {
+                    CompilerError::InvalidBinaryOperands(
+                        *location,
+                        operator.get_symbol(),
+                        left,
+                        right,
+                    )
+                })
+            }
+
+            ast::Expression::Empty => Err(CompilerError::Internal(
+                None,
+                "empty expression reached".to_string(),
+            )),
+
+            ast::Expression::CompoundAssignmentExpression { location, .. } => {
+                Err(CompilerError::Internal(
+                    Some(*location),
+                    "compound assignment reached, it should have been lowered".to_string(),
+                ))
+            }
+
+            ast::Expression::MatchExpression { location, .. } => Err(CompilerError::Internal(
+                Some(*location),
+                "match reached, it should have been lowered".to_string(),
+            )),
+        }
+    }
+
+    fn check_call_expression(
+        &mut self,
+        location: (usize, usize),
+        identifier: &'input ast::VariableIdentifier<'input>,
+        arguments: &'input [ast::Expression<'input>],
+    ) -> Result<ast::VariableKind, CompilerError> {
+        let function_id = self.symbol_table.identifier_ref(identifier);
+        let function = self.symbol_table.variable(function_id);
+
+        let (parameters, return_kind) = match function.get_kind()? {
+            ast::VariableKind::Function {
+                parameters,
+                return_kind,
+            } => (parameters, return_kind),
+            _ => {
+                return Err(CompilerError::Internal(
+                    Some(identifier.get_location()),
+                    format!("`{}` is not a function", identifier.get_name()),
+                ))
+            }
+        };
+
+        let is_external = function.is_external()?;
+        let function_name = function.get_name()?;
+
+        // declared functions have no scope to take the parameter names from
+        let mut parameter_names = Vec::new();
+
+        if !is_external {
+            for variable_id in self.symbol_table.function_variables(function_id) {
+                let variable = self.symbol_table.variable(&variable_id);
+
+                if variable.is_parameter()? {
+                    parameter_names.push(variable.get_name()?);
+                }
+            }
+        }
+
+        for (index, argument) in arguments.iter().enumerate() {
+            let kind = self.check_expression(argument)?;
+
+            let expected = match parameters.get(index.min(parameters.len() - 1)) {
+                Some(parameter) if parameter.is_rest => match &parameter.sub_kind {
+                    ast::VariableKind::Array { kind } => kind.as_ref(),
+                    kind => kind,
+                },
+                Some(parameter) => &parameter.sub_kind,
+                None => continue,
+            };
+
+            // external functions truncate numbers to their int32 parameters
+            let is_marshalled =
+                is_external && expected.is_int32() && kind == ast::VariableKind::Number;
+
+            if !expected.is_assignable_from(&kind) && !is_marshalled {
+                return Err(CompilerError::InvalidArgumentType(
+                    argument.get_location().unwrap_or(location),
+                    function_name.to_string(),
+                    expected.clone(),
+                    kind,
+                ));
+            }
+
+            if kind == ast::VariableKind::Any && *expected != ast::VariableKind::Any {
+                let parameter = match parameter_names.get(index.min(parameters.len() - 1)) {
+                    Some(name) => name.to_string(),
+                    None => format!("#{}", index + 1),
+                };
+
+                self.casts.insert(
+                    ByAddress(argument),
+                    RuntimeCast {
+                        kind: expected.clone(),
+                        path: format!("{}({})", function_name, parameter),
+                    },
+                );
+            }
+        }
+
+        if is_external && identifier.get_name() == "format" {
+            check_format_arguments(location, arguments)?;
+        }
+
+        Ok(*return_kind.clone())
+    }
+
+    fn identifier_kind(
+        &mut self,
+        identifier: &'input ast::VariableIdentifier<'input>,
+        location: (usize, usize),
+    ) -> Result<ast::VariableKind, CompilerError> {
+        let variable_id = self.symbol_table.identifier_ref(identifier);
+
+        self.variable_kind(variable_id, location)
+    }
+
+    fn variable_kind(
+        &mut self,
+        variable_id: &Index,
+        location: (usize, usize),
+    ) -> Result<ast::VariableKind, CompilerError> {
+        if let Some(kind) = self.narrowed.get(variable_id) {
+            return Ok(kind.clone());
+        }
+
+        match self.symbol_table.variable(variable_id) {
+            st::Variable::Static { kind, .. } => Ok(kind.clone()),
+            st::Variable::Property { base, property, .. } => {
+                let base_kind = self.variable_kind(base, location)?;
+
+                property_kind(base_kind, property, location)
+            }
+            st::Variable::Indexed { base, index, .. } => {
+                self.check_expression(index)?;
+
+                Ok(index_kind(self.variable_kind(base, location)?, index))
+            }
+        }
+    }
+}
+
+/// The kind of a property of a value of the given kind. Objects with no known
+/// fields may have any property.
+fn property_kind(
+    base_kind: ast::VariableKind,
+    property: &str,
+    location: (usize, usize),
+) -> Result<ast::VariableKind, CompilerError> {
+    match &base_kind {
+        ast::VariableKind::Object { fields } if !fields.is_empty() => {
+            match base_kind.get_field(property) {
+                Some(field) => Ok(field.kind.clone()),
+                None => Err(CompilerError::UnknownProperty(
+                    location,
+                    property.to_string(),
+                    base_kind,
+                )),
+            }
+        }
+        _ => Ok(ast::VariableKind::Any),
+    }
+}
+
+// literal format strings are parsed at compile time, so that mistakes in them do
+// not wait for the call to run
+fn check_format_arguments(
+    location: (usize, usize),
+    arguments: &[ast::Expression],
+) -> Result<(), CompilerError> {
+    let (fmt, fmt_location) = match arguments.first() {
+        Some(ast::Expression::ConstantExpression {
+            location,
+            value: ast::Constant::String(fmt),
+        }) => (*fmt, *location),
+        _ => return Ok(()),
+    };
+
+    let segments = format_string::parse(fmt)
+        .map_err(|reason| CompilerError::InvalidFormatString(fmt_location, reason))?;
+    let expected = format_string::argument_count(&segments);
+
+    if expected != arguments.len() - 1 {
+        return Err(CompilerError::InvalidNumberOfFormatArguments(
+            location,
+            expected,
+            arguments.len() - 1,
+        ));
+    }
+
+    Ok(())
+}
+
+fn index_kind(base_kind: ast::VariableKind, index: &ast::Expression) -> ast::VariableKind {
+    match base_kind {
+        // a range index slices the array
+        kind @ ast::VariableKind::Array { .. }
+            if matches!(index, ast::Expression::RangeExpression { .. }) =>
+        {
+            kind
+        }
+        ast::VariableKind::Array { kind } => *kind,
+        ast::VariableKind::Float64Array | ast::VariableKind::Int64Array => {
+            ast::VariableKind::Number
+        }
+        _ => ast::VariableKind::Any,
+    }
+}
+
+fn binary_operation_kind(
+    operator: &ast::BinaryOperator,
+    left: &ast::VariableKind,
+    right: &ast::VariableKind,
+) -> Option<ast::VariableKind> {
+    let is_number = |kind: &ast::VariableKind| {
+        matches!(
+            kind,
+            ast::VariableKind::Number | ast::VariableKind::Int32 | ast::VariableKind::Any
+        )
+    };
+    let is_boolean = |kind: &ast::VariableKind| {
+        matches!(kind, ast::VariableKind::Boolean | ast::VariableKind::Any)
+    };
+    // objects may overload the arithmetic operators with `__add` and the like
+    let is_object = |kind: &ast::VariableKind| matches!(kind, ast::VariableKind::Object { .. });
+
+    match operator {
+        ast::BinaryOperator::Addition => match (left, right) {
+            (ast::VariableKind::Any, _) | (_, ast::VariableKind::Any) => {
+                Some(ast::VariableKind::Any)
+            }
+            _ if is_object(left) || is_object(right) => Some(ast::VariableKind::Any),
+            (ast::VariableKind::String, ast::VariableKind::String) => {
+                Some(ast::VariableKind::String)
+            }
+            _ if is_number(left) && is_number(right) => Some(ast::VariableKind::Number),
+            _ => None,
+        },
+        ast::BinaryOperator::Subtraction
+        | ast::BinaryOperator::Multiplication
+        | ast::BinaryOperator::Division
+        | ast::BinaryOperator::Mod => {
+            if is_number(left) && is_number(right) {
+                Some(ast::VariableKind::Number)
+            } else if is_object(left) || is_object(right) {
+                Some(ast::VariableKind::Any)
+            } else {
+                None
+            }
+        }
+        ast::BinaryOperator::Less
+        | ast::BinaryOperator::LessEqual
+        | ast::BinaryOperator::Greater
+        | ast::BinaryOperator::GreaterEqual => {
+            if is_number(left) && is_number(right) {
+                Some(ast::VariableKind::Boolean)
+            } else {
+                None
+            }
+        }
+        ast::BinaryOperator::Equal
+        | ast::BinaryOperator::NotEqual
+        | ast::BinaryOperator::StrictEqual
+        | ast::BinaryOperator::StrictNotEqual => Some(ast::VariableKind::Boolean),
+        ast::BinaryOperator::And | ast::BinaryOperator::Or => {
+            if is_boolean(left) && is_boolean(right) {
+                Some(ast::VariableKind::Boolean)
+            } else {
+                None
+            }
+        }
+    }
+}
@@ -1,11 +1,21 @@
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
 pub struct ParameterKind {
     pub sub_kind: VariableKind,
     pub is_rest: bool,
     pub is_optional: bool,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+pub struct FieldKind {
+    pub name: String,
+    pub kind: VariableKind,
+    pub is_readonly: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
 pub enum VariableKind {
     Undefined,
     Null,
@@ -13,7 +23,16 @@ pub enum VariableKind {
     Boolean,
     String,
     Number,
-    Object,
+    Int32,
+    Symbol,
+    /// An array of unboxed floats, see `std/typed.h`.
+    Float64Array,
+    /// An array of unboxed integers, see `std/typed.h`.
+    Int64Array,
+    /// An object with at least the given fields, any object when there are none.
+    Object {
+        fields: Vec<FieldKind>,
+    },
     Function {
         parameters: Vec<ParameterKind>,
         return_kind: Box<VariableKind>,
@@ -23,7 +42,7 @@ pub enum VariableKind {
     },
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum Constant<'input> {
     Undefined,
     Null,
@@ -42,19 +61,89 @@ impl VariableKind {
             VariableKind::Boolean => "boolean",
             VariableKind::String => "string",
             VariableKind::Number { .. } => "number",
+            VariableKind::Int32 => "int32",
+            VariableKind::Symbol => "symbol",
+            VariableKind::Float64Array => "Float64Array",
+            VariableKind::Int64Array => "Int64Array",
             VariableKind::Object { .. } => "object",
             VariableKind::Function { .. } => "function",
             VariableKind::Array { .. } => "object",
         }
     }
 
+    /// Encodes the kind as the type descriptor understood by the `val_validate` builtin.
+    pub fn get_schema(&self) -> String {
+        match self {
+            VariableKind::Undefined => "u".to_string(),
+            VariableKind::Null => "z".to_string(),
+            VariableKind::Any => "a".to_string(),
+            VariableKind::Boolean => "b".to_string(),
+            VariableKind::String => "s".to_string(),
+            VariableKind::Number => "n".to_string(),
+            VariableKind::Int32 => "i".to_string(),
+            VariableKind::Symbol => "y".to_string(),
+            VariableKind::Float64Array => "F".to_string(),
+            VariableKind::Int64Array => "I".to_string(),
+            VariableKind::Object { fields } if fields.is_empty() => "o".to_string(),
+            VariableKind::Object { fields } => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|field| format!("{}:{}", field.name, field.kind.get_schema()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            VariableKind::Function { .. } => "a".to_string(),
+            VariableKind::Array { kind } => format!("[{}", kind.get_schema()),
+        }
+    }
+
+    /// The given field of a structural object type.
+    pub fn get_field(&self, name: &str) -> Option<&FieldKind> {
+        match self {
+            VariableKind::Object { fields } => fields.iter().find(|field| field.name == name),
+            _ => None,
+        }
+    }
+
     fn is_number(&self) -> bool {
         match self {
             VariableKind::Number => true,
+            VariableKind::Int32 => true,
             _ => false,
         }
     }
 
+    pub fn is_int32(&self) -> bool {
+        *self == VariableKind::Int32
+    }
+
+    /// Returns whether a value of kind `other` can be stored in a binding of this kind.
+    /// `int32` widens implicitly to `number`; the other direction only happens at FFI
+    /// boundaries, where the marshalling layer truncates the value to 32 bits.
+    pub fn is_assignable_from(&self, other: &VariableKind) -> bool {
+        match (self, other) {
+            (VariableKind::Any, _) | (_, VariableKind::Any) => true,
+            (VariableKind::Number, VariableKind::Int32) => true,
+            (VariableKind::Array { kind }, VariableKind::Array { kind: other_kind }) => {
+                kind.is_assignable_from(other_kind)
+            }
+            // objects are typed structurally, extra fields are allowed
+            (
+                VariableKind::Object { fields },
+                VariableKind::Object {
+                    fields: other_fields,
+                },
+            ) => fields.iter().all(|field| {
+                other_fields
+                    .iter()
+                    .find(|other_field| other_field.name == field.name)
+                    .is_some_and(|other_field| field.kind.is_assignable_from(&other_field.kind))
+            }),
+            _ => self == other,
+        }
+    }
+
     pub fn operation_result(&self, other: &VariableKind) -> VariableKind {
         if other == self {
             return self.clone();
@@ -84,3 +173,57 @@ impl<'input> Constant<'input> {
         }
     }
 }
+
+impl fmt::Display for VariableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableKind::Object { fields } if !fields.is_empty() => {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            VariableKind::Array { kind } => write!(f, "{}[]", kind),
+            VariableKind::Function {
+                parameters,
+                return_kind,
+            } => {
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "({}) => {}", parameters.join(", "), return_kind)
+            }
+            kind => write!(f, "{}", kind.get_name()),
+        }
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_readonly {
+            write!(f, "readonly ")?;
+        }
+
+        write!(f, "{}: {}", self.name, self.kind)
+    }
+}
+
+impl fmt::Display for ParameterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_rest {
+            write!(f, "...")?;
+        }
+
+        write!(f, "{}", self.sub_kind)?;
+
+        if self.is_optional {
+            write!(f, "?")?;
+        }
+
+        Ok(())
+    }
+}
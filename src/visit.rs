@@ -0,0 +1,507 @@
+use crate::ast;
+
+/// Walks the AST by reference. Every method visits the children of its node by
+/// default, so an implementation only overrides the nodes it is interested in and
+/// calls the matching `walk_*` function to keep descending.
+pub trait Visitor<'input> {
+    fn visit_statements(&mut self, statements: &'input [ast::Statement<'input>]) {
+        walk_statements(self, statements);
+    }
+
+    fn visit_statement(&mut self, statement: &'input ast::Statement<'input>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &'input ast::Expression<'input>) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_identifier(&mut self, identifier: &'input ast::VariableIdentifier<'input>) {
+        walk_identifier(self, identifier);
+    }
+}
+
+pub fn walk_statements<'input, V: Visitor<'input> + ?Sized>(
+    visitor: &mut V,
+    statements: &'input [ast::Statement<'input>],
+) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<'input, V: Visitor<'input> + ?Sized>(
+    visitor: &mut V,
+    statement: &'input ast::Statement<'input>,
+) {
+    match statement {
+        ast::Statement::ExpressionStatement { expression } => {
+            visitor.visit_expression(expression);
+        }
+
+        ast::Statement::DefinitionStatement { expression, .. }
+        | ast::Statement::ReturnStatement { expression, .. } => {
+            if let Some(expression) = expression {
+                visitor.visit_expression(expression);
+            }
+        }
+
+        ast::Statement::FunctionStatement { statements, .. }
+        | ast::Statement::DeferStatement { statements, .. }
+        | ast::Statement::MacroStatement { statements, .. } => {
+            visitor.visit_statements(statements);
+        }
+
+        ast::Statement::MacroCallStatement { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+
+        ast::Statement::IfStatement {
+            condition,
+            statements,
+            else_statements,
+            ..
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_statements(statements);
+
+            if let Some(else_statements) = else_statements {
+                visitor.visit_statements(else_statements);
+            }
+        }
+
+        ast::Statement::EmptyStatement => {}
+    }
+}
+
+pub fn walk_expression<'input, V: Visitor<'input> + ?Sized>(
+    visitor: &mut V,
+    expression: &'input ast::Expression<'input>,
+) {
+    match expression {
+        ast::Expression::ConstantExpression { .. } | ast::Expression::Empty => {}
+
+        ast::Expression::ArrayExpression { items, .. } => {
+            for item in items {
+                visitor.visit_expression(item);
+            }
+        }
+
+        ast::Expression::ObjectExpression { properties, .. } => {
+            for (_, e) in properties {
+                visitor.visit_expression(e);
+            }
+        }
+
+        ast::Expression::SymbolExpression {
+            name: expression, ..
+        }
+        | ast::Expression::TypeOfExpression { expression, .. }
+        | ast::Expression::ValidateExpression { expression, .. }
+        | ast::Expression::UnaryExpression { expression, .. } => {
+            visitor.visit_expression(expression);
+        }
+
+        ast::Expression::MemberExpression {
+            expression, member, ..
+        } => {
+            visitor.visit_expression(expression);
+
+            match member {
+                ast::Member::Property(_) => {}
+                ast::Member::Index(index) => visitor.visit_expression(index),
+                ast::Member::Slice { start, end } => {
+                    for bound in [start, end].into_iter().flatten() {
+                        visitor.visit_expression(bound);
+                    }
+                }
+            }
+        }
+
+        ast::Expression::VariableExpression { identifier, .. } => {
+            visitor.visit_identifier(identifier);
+        }
+
+        ast::Expression::CallExpression {
+            identifier,
+            arguments,
+            ..
+        } => {
+            visitor.visit_identifier(identifier);
+
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+
+        ast::Expression::AssignmentExpression {
+            identifier,
+            expression,
+            ..
+        }
+        | ast::Expression::CompoundAssignmentExpression {
+            identifier,
+            expression,
+            ..
+        } => {
+            visitor.visit_identifier(identifier);
+            visitor.visit_expression(expression);
+        }
+
+        ast::Expression::MatchExpression { subject, arms, .. } => {
+            visitor.visit_expression(subject);
+
+            for arm in arms {
+                visitor.visit_expression(&arm.expression);
+            }
+        }
+
+        ast::Expression::RangeExpression { start, end, .. } => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+        }
+
+        ast::Expression::BinaryExpression { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+    }
+}
+
+pub fn walk_identifier<'input, V: Visitor<'input> + ?Sized>(
+    visitor: &mut V,
+    identifier: &'input ast::VariableIdentifier<'input>,
+) {
+    match identifier {
+        ast::VariableIdentifier::Name { .. } => {}
+        ast::VariableIdentifier::Property { base, .. } => visitor.visit_identifier(base),
+        ast::VariableIdentifier::Index { base, index, .. } => {
+            visitor.visit_identifier(base);
+            visitor.visit_expression(index);
+        }
+    }
+}
+
+/// Rewrites the AST by value. Like `Visitor`, every method rebuilds its node from
+/// its folded children by default, through the matching `fold_*` function.
+pub trait Folder<'input> {
+    fn fold_statements(
+        &mut self,
+        statements: Vec<ast::Statement<'input>>,
+    ) -> Vec<ast::Statement<'input>> {
+        fold_statements(self, statements)
+    }
+
+    fn fold_statement(&mut self, statement: ast::Statement<'input>) -> ast::Statement<'input> {
+        fold_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: ast::Expression<'input>) -> ast::Expression<'input> {
+        fold_expression(self, expression)
+    }
+
+    fn fold_identifier(
+        &mut self,
+        identifier: ast::VariableIdentifier<'input>,
+    ) -> ast::VariableIdentifier<'input> {
+        fold_identifier(self, identifier)
+    }
+}
+
+pub fn fold_statements<'input, F: Folder<'input> + ?Sized>(
+    folder: &mut F,
+    statements: Vec<ast::Statement<'input>>,
+) -> Vec<ast::Statement<'input>> {
+    statements
+        .into_iter()
+        .map(|statement| folder.fold_statement(statement))
+        .collect()
+}
+
+pub fn fold_statement<'input, F: Folder<'input> + ?Sized>(
+    folder: &mut F,
+    statement: ast::Statement<'input>,
+) -> ast::Statement<'input> {
+    match statement {
+        ast::Statement::ExpressionStatement { expression } => ast::Statement::ExpressionStatement {
+            expression: folder.fold_expression(expression),
+        },
+
+        ast::Statement::DefinitionStatement {
+            location,
+            definition,
+            expression,
+        } => ast::Statement::DefinitionStatement {
+            location,
+            definition,
+            expression: expression.map(|expression| folder.fold_expression(expression)),
+        },
+
+        ast::Statement::FunctionStatement {
+            location,
+            definition,
+            parameters,
+            statements,
+        } => ast::Statement::FunctionStatement {
+            location,
+            definition,
+            parameters,
+            statements: folder.fold_statements(statements),
+        },
+
+        ast::Statement::ReturnStatement {
+            location,
+            expression,
+        } => ast::Statement::ReturnStatement {
+            location,
+            expression: expression.map(|expression| folder.fold_expression(expression)),
+        },
+
+        ast::Statement::DeferStatement {
+            location,
+            statements,
+        } => ast::Statement::DeferStatement {
+            location,
+            statements: folder.fold_statements(statements),
+        },
+
+        ast::Statement::IfStatement {
+            location,
+            condition,
+            statements,
+            else_statements,
+        } => ast::Statement::IfStatement {
+            location,
+            condition: folder.fold_expression(condition),
+            statements: folder.fold_statements(statements),
+            else_statements: else_statements
+                .map(|else_statements| folder.fold_statements(else_statements)),
+        },
+
+        ast::Statement::MacroStatement {
+            location,
+            name,
+            parameters,
+            statements,
+        } => ast::Statement::MacroStatement {
+            location,
+            name,
+            parameters,
+            statements: folder.fold_statements(statements),
+        },
+
+        ast::Statement::MacroCallStatement {
+            location,
+            name,
+            arguments,
+        } => ast::Statement::MacroCallStatement {
+            location,
+            name,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+        },
+
+        ast::Statement::EmptyStatement => ast::Statement::EmptyStatement,
+    }
+}
+
+pub fn fold_expression<'input, F: Folder<'input> + ?Sized>(
+    folder: &mut F,
+    expression: ast::Expression<'input>,
+) -> ast::Expression<'input> {
+    match expression {
+        ast::Expression::ConstantExpression { .. } | ast::Expression::Empty => expression,
+
+        ast::Expression::ArrayExpression { location, items } => ast::Expression::ArrayExpression {
+            location,
+            items: items
+                .into_iter()
+                .map(|item| folder.fold_expression(item))
+                .collect(),
+        },
+
+        ast::Expression::ObjectExpression {
+            location,
+            properties,
+        } => ast::Expression::ObjectExpression {
+            location,
+            properties: properties
+                .into_iter()
+                .map(|(name, e)| (name, folder.fold_expression(e)))
+                .collect(),
+        },
+
+        ast::Expression::SymbolExpression { location, name } => ast::Expression::SymbolExpression {
+            location,
+            name: fold_boxed(folder, name),
+        },
+
+        ast::Expression::TypeOfExpression {
+            location,
+            expression,
+        } => ast::Expression::TypeOfExpression {
+            location,
+            expression: fold_boxed(folder, expression),
+        },
+
+        ast::Expression::ValidateExpression {
+            location,
+            kind,
+            expression,
+        } => ast::Expression::ValidateExpression {
+            location,
+            kind,
+            expression: fold_boxed(folder, expression),
+        },
+
+        ast::Expression::UnaryExpression {
+            location,
+            operator,
+            expression,
+        } => ast::Expression::UnaryExpression {
+            location,
+            operator,
+            expression: fold_boxed(folder, expression),
+        },
+
+        ast::Expression::MemberExpression {
+            location,
+            expression,
+            member,
+        } => ast::Expression::MemberExpression {
+            location,
+            expression: fold_boxed(folder, expression),
+            member: match member {
+                ast::Member::Index(index) => ast::Member::Index(fold_boxed(folder, index)),
+                ast::Member::Slice { start, end } => ast::Member::Slice {
+                    start: start.map(|start| fold_boxed(folder, start)),
+                    end: end.map(|end| fold_boxed(folder, end)),
+                },
+                member => member,
+            },
+        },
+
+        ast::Expression::VariableExpression {
+            location,
+            identifier,
+        } => ast::Expression::VariableExpression {
+            location,
+            identifier: folder.fold_identifier(identifier),
+        },
+
+        ast::Expression::CallExpression {
+            location,
+            identifier,
+            arguments,
+        } => ast::Expression::CallExpression {
+            location,
+            identifier: folder.fold_identifier(identifier),
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+        },
+
+        ast::Expression::AssignmentExpression {
+            location,
+            identifier,
+            expression,
+        } => ast::Expression::AssignmentExpression {
+            location,
+            identifier: folder.fold_identifier(identifier),
+            expression: fold_boxed(folder, expression),
+        },
+
+        ast::Expression::CompoundAssignmentExpression {
+            location,
+            identifier,
+            operator,
+            expression,
+        } => ast::Expression::CompoundAssignmentExpression {
+            location,
+            identifier: folder.fold_identifier(identifier),
+            operator,
+            expression: fold_boxed(folder, expression),
+        },
+
+        ast::Expression::MatchExpression {
+            location,
+            subject,
+            arms,
+        } => ast::Expression::MatchExpression {
+            location,
+            subject: fold_boxed(folder, subject),
+            arms: arms
+                .into_iter()
+                .map(|arm| ast::MatchArm {
+                    expression: folder.fold_expression(arm.expression),
+                    ..arm
+                })
+                .collect(),
+        },
+
+        ast::Expression::RangeExpression {
+            location,
+            start,
+            end,
+            is_inclusive,
+        } => ast::Expression::RangeExpression {
+            location,
+            start: fold_boxed(folder, start),
+            end: fold_boxed(folder, end),
+            is_inclusive,
+        },
+
+        ast::Expression::BinaryExpression {
+            location,
+            operator,
+            left,
+            right,
+        } => ast::Expression::BinaryExpression {
+            location,
+            operator,
+            left: fold_boxed(folder, left),
+            right: fold_boxed(folder, right),
+        },
+    }
+}
+
+pub fn fold_identifier<'input, F: Folder<'input> + ?Sized>(
+    folder: &mut F,
+    identifier: ast::VariableIdentifier<'input>,
+) -> ast::VariableIdentifier<'input> {
+    match identifier {
+        ast::VariableIdentifier::Name { .. } => identifier,
+        ast::VariableIdentifier::Property {
+            location,
+            base,
+            property,
+        } => ast::VariableIdentifier::Property {
+            location,
+            base: Box::new(folder.fold_identifier(*base)),
+            property,
+        },
+        ast::VariableIdentifier::Index {
+            location,
+            base,
+            index,
+        } => ast::VariableIdentifier::Index {
+            location,
+            base: Box::new(folder.fold_identifier(*base)),
+            index: Box::new(folder.fold_expression(*index)),
+        },
+    }
+}
+
+fn fold_boxed<'input, F: Folder<'input> + ?Sized>(
+    folder: &mut F,
+    mut expression: Box<ast::Expression<'input>>,
+) -> Box<ast::Expression<'input>> {
+    *expression =
+        folder.fold_expression(std::mem::replace(&mut expression, ast::Expression::Empty));
+    expression
+}
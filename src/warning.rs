@@ -0,0 +1,223 @@
+use colored::Colorize;
+use indexmap::IndexMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::lexer;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum WarningKind {
+    UnusedVariable,
+    UnusedFunction,
+    UnreachableCode,
+    ImplicitAny,
+}
+
+impl WarningKind {
+    pub const NAMES: [&'static str; 4] = [
+        "unused-variable",
+        "unused-function",
+        "unreachable-code",
+        "implicit-any",
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            WarningKind::UnusedVariable => "unused-variable",
+            WarningKind::UnusedFunction => "unused-function",
+            WarningKind::UnreachableCode => "unreachable-code",
+            WarningKind::ImplicitAny => "implicit-any",
+        }
+    }
+
+    fn default_level(&self) -> Level {
+        match self {
+            WarningKind::ImplicitAny => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl FromStr for WarningKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unused-variable" => Ok(WarningKind::UnusedVariable),
+            "unused-function" => Ok(WarningKind::UnusedFunction),
+            "unreachable-code" => Ok(WarningKind::UnreachableCode),
+            "implicit-any" => Ok(WarningKind::ImplicitAny),
+            _ => Err(format!("Unknown warning: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Warning {
+    UnusedVariable((usize, usize), String),
+    UnusedFunction((usize, usize), String),
+    UnreachableCode((usize, usize)),
+    ImplicitAny((usize, usize), String),
+}
+
+impl Warning {
+    pub fn kind(&self) -> WarningKind {
+        match self {
+            Warning::UnusedVariable(..) => WarningKind::UnusedVariable,
+            Warning::UnusedFunction(..) => WarningKind::UnusedFunction,
+            Warning::UnreachableCode(..) => WarningKind::UnreachableCode,
+            Warning::ImplicitAny(..) => WarningKind::ImplicitAny,
+        }
+    }
+
+    pub fn location(&self) -> (usize, usize) {
+        match self {
+            Warning::UnusedVariable(location, _)
+            | Warning::UnusedFunction(location, _)
+            | Warning::UnreachableCode(location)
+            | Warning::ImplicitAny(location, _) => *location,
+        }
+    }
+
+    /// The warning message without the `warning:` prefix and terminal colors.
+    pub fn message(&self) -> String {
+        self.describe(|s| s.to_string())
+    }
+
+    pub(crate) fn highlighted_message(&self) -> String {
+        self.describe(|s| s.yellow().to_string())
+    }
+
+    pub(crate) fn describe(&self, highlight: fn(&str) -> String) -> String {
+        let message = match self {
+            Warning::UnusedVariable(_, v) => format!("variable `{}` is never used", highlight(v)),
+            Warning::UnusedFunction(_, v) => format!("function `{}` is never used", highlight(v)),
+            Warning::UnreachableCode(_) => "unreachable code".to_string(),
+            Warning::ImplicitAny(_, v) => {
+                format!(
+                    "variable `{}` implicitly has type `{}`",
+                    highlight(v),
+                    highlight("any")
+                )
+            }
+        };
+
+        format!("{} [{}]", message, self.kind())
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", "warning:".yellow(), self.highlighted_message())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Decides what happens with each kind of warning, set from `-W`, `-A` and
+/// `--deny-warnings` on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct WarningLevels {
+    levels: IndexMap<WarningKind, Level>,
+    deny_warnings: bool,
+}
+
+impl WarningLevels {
+    pub fn set(&mut self, kind: WarningKind, level: Level) {
+        self.levels.insert(kind, level);
+    }
+
+    /// Turns every enabled warning into an error.
+    pub fn deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    pub fn level(&self, kind: WarningKind) -> Level {
+        let level = self
+            .levels
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.default_level());
+
+        match level {
+            Level::Warn if self.deny_warnings => Level::Deny,
+            level => level,
+        }
+    }
+}
+
+/// The warnings silenced by `// mini-ignore: unused-variable, ...` comments. A
+/// comment after code silences its own line, a comment on a line of its own the
+/// line below, and one without names every warning there.
+#[derive(Clone, Debug, Default)]
+pub struct Suppressions {
+    /// The byte range of each silenced line, with the kinds silenced on it.
+    lines: Vec<((usize, usize), Vec<WarningKind>)>,
+}
+
+impl Suppressions {
+    pub const PREFIX: &'static str = "mini-ignore";
+
+    pub fn new(content: &str) -> Self {
+        let mut lines = Vec::new();
+
+        for (offset, comment) in lexer::comments(content) {
+            let text = match comment.strip_prefix("//") {
+                Some(text) if !text.starts_with('/') => text.trim(),
+                _ => continue,
+            };
+
+            let kinds = match text.strip_prefix(Self::PREFIX).map(str::trim_start) {
+                Some("") => WarningKind::NAMES
+                    .iter()
+                    .filter_map(|name| name.parse().ok())
+                    .collect(),
+                Some(names) => match names.strip_prefix(':') {
+                    Some(names) => names
+                        .split(',')
+                        .filter_map(|name| name.trim().parse().ok())
+                        .collect(),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            let line_start = content[..offset].rfind('\n').map_or(0, |index| index + 1);
+            let line_end = |start: usize| {
+                content[start..]
+                    .find('\n')
+                    .map_or(content.len(), |index| start + index)
+            };
+
+            let range = if content[line_start..offset].trim().is_empty() {
+                let start = (line_end(offset) + 1).min(content.len());
+                (start, line_end(start))
+            } else {
+                (line_start, line_end(offset))
+            };
+
+            lines.push((range, kinds));
+        }
+
+        Suppressions { lines }
+    }
+
+    pub fn is_suppressed(&self, warning: &Warning) -> bool {
+        let offset = warning.location().0;
+
+        self.lines.iter().any(|((start, end), kinds)| {
+            (*start..=*end).contains(&offset) && kinds.contains(&warning.kind())
+        })
+    }
+}
@@ -3,10 +3,40 @@
 
 #include "defs.h"
 
+// Setting an item past the end fills the array up to it, so the index of a set is
+// bounded, the interpreter using the same bound.
+#define MAX_ARRAY_LEN (1LL << 26)
+
 void *link_val(val_t *val);
 void *unlink_val(val_t *val);
 
+// Defined when the program is linked with AddressSanitizer, which is then told the
+// part of the capacity of every array in use, to catch accesses past their end.
+#ifndef __APPLE__
+void __sanitizer_annotate_contiguous_container(const void *beg, const void *end, const void *old_mid, const void *new_mid) __attribute__((weak));
+#endif
+
+static void array_annotate(array_t *a, uint64_t old_len, uint64_t new_len) {
+#ifndef __APPLE__
+    if (__sanitizer_annotate_contiguous_container != NULL && a->capacity > 0) {
+        __sanitizer_annotate_contiguous_container(a->data, a->data + a->capacity, a->data + old_len, a->data + new_len);
+    }
+#endif
+}
+
+// grows the capacity, the whole buffer is in use while it is reallocated
+static void array_grow(array_t *a, uint64_t capacity) {
+    array_annotate(a, a->len, a->capacity);
+
+    a->data = realloc(a->data, capacity * sizeof(void *));
+    a->capacity = capacity;
+
+    array_annotate(a, a->capacity, a->len);
+}
+
 static void free_array(array_t *s) {
+    array_annotate(s, s->len, s->capacity);
+
     free(s->data);
 }
 
@@ -16,14 +46,23 @@ static void new_array(array_t *result, uint64_t capacity) {
     result->capacity = capacity;
     result->len = 0;
     result->data = data;
+
+    array_annotate(result, capacity, 0);
+}
+
+static void array_truncate(array_t *result, uint64_t len) {
+    array_annotate(result, result->len, len);
+
+    result->len = len;
 }
 
 static void array_push(array_t *result, void *v) {
     if (result->len == result->capacity) {
-        result->capacity *= 2;
-        result->data = realloc(result->data, result->capacity * sizeof(void *));
+        array_grow(result, result->capacity > 0 ? result->capacity * 2 : 1);
     }
 
+    array_annotate(result, result->len, result->len + 1);
+
     result->data[result->len] = v;
     result->len++;
 
@@ -31,20 +70,35 @@ static void array_push(array_t *result, void *v) {
 }
 
 static void array_insert(array_t *result, size_t index, void *v) {
-    while (index >= result->capacity) {
-        result->capacity *= 2;
-        result->data = realloc(result->data, result->capacity * sizeof(void *));
+    uint64_t capacity = result->capacity;
+
+    while (index >= capacity) {
+        capacity = capacity > 0 ? capacity * 2 : 1;
+    }
+
+    if (capacity > result->capacity) {
+        array_grow(result, capacity);
+    }
+
+    if (index >= result->len) {
+        array_annotate(result, result->len, index + 1);
+
+        // the items skipped over are holes, which read as undefined
+        for (size_t i = result->len; i < index; i++) {
+            result->data[i] = IMM_UNDEFINED;
+        }
+
+        result->len = index + 1;
     }
 
     result->data[index] = v;
-    result->len = result->len > index + 1 ? result->len : index + 1;
 
     DEBUG("ARRAY: insert: %p, %p", result, v);
 }
 
 static void *array_get(array_t *result, size_t index) {
     if (index >= result->len) {
-        return NULL;
+        return IMM_UNDEFINED;
     }
 
     return result->data[index];
@@ -0,0 +1,145 @@
+#ifndef MINI_STD_BUFFER_H
+#define MINI_STD_BUFFER_H
+
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "gc.h"
+#include "val.h"
+
+static void expect_buffer(val_t *buffer, const char *function) {
+    if (val_type(buffer) != VAL_BUFFER) {
+        RUNTIME_ERROR("`%s` expects a buffer, got %s", function, val_type_name(buffer));
+    }
+}
+
+static int64_t expect_int(val_t *n, const char *function) {
+    if (val_type(n) != VAL_INT) {
+        RUNTIME_ERROR("`%s` expects an integer, got %s", function, val_type_name(n));
+    }
+
+    return val_i64(n);
+}
+
+static void expect_buffer_index(val_t *buffer, int64_t index, const char *function) {
+    if (index < 0 || (uint64_t) index >= buffer->buffer.len) {
+        RUNTIME_ERROR("`%s`: index %lld is out of bounds for a buffer of %llu bytes", function, index, buffer->buffer.len);
+    }
+}
+
+static val_t *new_buffer_val(const uint8_t *data, uint64_t len) {
+    val_t *result = new_val(VAL_BUFFER);
+    result->buffer.len = len;
+    result->buffer.data = calloc(len > 0 ? len : 1, 1);
+
+    if (data != NULL) {
+        memcpy(result->buffer.data, data, len);
+    }
+
+    DEBUG("new buffer: %llu, %p", len, result);
+
+    return result;
+}
+
+val_t *buffer_alloc(val_t *size) {
+    int64_t len = expect_int(size, "buffer_alloc");
+
+    if (len < 0) {
+        RUNTIME_ERROR("`buffer_alloc` expects a non-negative size, got %lld", len);
+    }
+
+    return new_buffer_val(NULL, len);
+}
+
+val_t *buffer_length(val_t *buffer) {
+    expect_buffer(buffer, "buffer_length");
+
+    val_t *result = new_int_val(buffer->buffer.len);
+
+    free_val_if_ok(buffer);
+
+    return result;
+}
+
+val_t *buffer_read_u8(val_t *buffer, val_t *index) {
+    expect_buffer(buffer, "buffer_read_u8");
+
+    int64_t i = expect_int(index, "buffer_read_u8");
+    expect_buffer_index(buffer, i, "buffer_read_u8");
+
+    val_t *result = new_int_val(buffer->buffer.data[i]);
+
+    free_val_if_ok(buffer);
+
+    return result;
+}
+
+void *buffer_write_u8(val_t *buffer, val_t *index, val_t *byte) {
+    expect_buffer(buffer, "buffer_write_u8");
+
+    int64_t i = expect_int(index, "buffer_write_u8");
+    expect_buffer_index(buffer, i, "buffer_write_u8");
+
+    int64_t b = expect_int(byte, "buffer_write_u8");
+
+    if (b < 0 || b > 255) {
+        RUNTIME_ERROR("`buffer_write_u8` expects a byte, got %lld", b);
+    }
+
+    buffer->buffer.data[i] = (uint8_t) b;
+
+    free_val_if_ok(buffer);
+
+    return IMM_UNDEFINED;
+}
+
+// copies the bytes from start up to end, both clamped to the buffer like in JavaScript
+val_t *buffer_slice(val_t *buffer, val_t *start, val_t *end) {
+    expect_buffer(buffer, "buffer_slice");
+
+    int64_t len = buffer->buffer.len;
+    int64_t from = expect_int(start, "buffer_slice");
+    int64_t to = val_type(end) == VAL_UNDEFINED ? len : expect_int(end, "buffer_slice");
+
+    from = from < 0 ? (from + len < 0 ? 0 : from + len) : (from > len ? len : from);
+    to = to < 0 ? (to + len < 0 ? 0 : to + len) : (to > len ? len : to);
+
+    val_t *result = new_buffer_val(buffer->buffer.data + from, to > from ? to - from : 0);
+
+    free_val_if_ok(buffer);
+
+    return result;
+}
+
+val_t *buffer_from_string(val_t *s) {
+    if (val_type(s) != VAL_STR) {
+        RUNTIME_ERROR("`buffer_from_string` expects a string, got %s", val_type_name(s));
+    }
+
+    val_t *result = new_buffer_val((uint8_t *) s->str.data, s->str.len);
+
+    free_val_if_ok(s);
+
+    return result;
+}
+
+// the bytes are taken as they are, up to the first null byte if there is one
+val_t *buffer_to_string(val_t *buffer) {
+    expect_buffer(buffer, "buffer_to_string");
+
+    val_t *result = new_val(VAL_STR);
+    result->str.len = strnlen((char *) buffer->buffer.data, buffer->buffer.len);
+    result->str.capacity = result->str.len;
+    result->str.data = malloc(result->str.len + 1);
+    memcpy(result->str.data, buffer->buffer.data, result->str.len);
+    result->str.data[result->str.len] = 0;
+
+    free_val_if_ok(buffer);
+
+    return result;
+}
+
+#endif
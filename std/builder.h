@@ -0,0 +1,50 @@
+#ifndef MINI_STD_BUILDER_H
+#define MINI_STD_BUILDER_H
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "gc.h"
+#include "val.h"
+
+static void expect_builder(val_t *builder, const char *function) {
+    if (val_type(builder) != VAL_BUILDER) {
+        RUNTIME_ERROR("`%s` expects a string builder, got %s", function, val_type_name(builder));
+    }
+}
+
+val_t *string_builder() {
+    val_t *result = new_val(VAL_BUILDER);
+    new_str(&result->str, "");
+
+    DEBUG("new string builder, %p", result);
+
+    return result;
+}
+
+void *builder_append(val_t *builder, val_t *s) {
+    expect_builder(builder, "builder_append");
+
+    if (val_type(s) != VAL_STR) {
+        RUNTIME_ERROR("`builder_append` expects a string, got %s", val_type_name(s));
+    }
+
+    str_append(&builder->str, s->str.data, s->str.len);
+
+    free_val_if_ok(builder);
+    free_val_if_ok(s);
+
+    return IMM_UNDEFINED;
+}
+
+val_t *builder_to_string(val_t *builder) {
+    expect_builder(builder, "builder_to_string");
+
+    val_t *result = new_str_val(builder->str.data);
+
+    free_val_if_ok(builder);
+
+    return result;
+}
+
+#endif
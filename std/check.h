@@ -0,0 +1,27 @@
+#ifndef MINI_STD_CHECK_H
+#define MINI_STD_CHECK_H
+
+#include <stdbool.h>
+#include <stdio.h>
+
+#include "defs.h"
+#include "error.h"
+
+// the parentheses keep the name from expanding to the macro of <assert.h>
+void *(assert)(val_t *condition, val_t *message) {
+    if (val_type(condition) != VAL_BOOL) {
+        RUNTIME_ERROR("`assert` expects a boolean, got %s", val_type_name(condition));
+    }
+
+    if (!val_b(condition)) {
+        if (val_type(message) == VAL_STR) {
+            RUNTIME_ERROR("assertion failed: %s", message->str.data);
+        }
+
+        RUNTIME_ERROR("assertion failed");
+    }
+
+    return IMM_UNDEFINED;
+}
+
+#endif
@@ -1,8 +1,14 @@
 #ifndef MINI_STD_DEFS_H
 #define MINI_STD_DEFS_H
 
+// Every thread has its own heap: the state of the runtime is thread-local, and
+// values only cross threads as copies, see threads.h.
+#define THREAD_LOCAL _Thread_local
+
 typedef struct {
     uint64_t len;
+    // bytes available for the characters, not counting the terminating null
+    uint64_t capacity;
     char *data;
 } str_t;
 
@@ -13,13 +19,66 @@ typedef struct {
 } array_t;
 
 typedef struct {
-    size_t capacity;
+    uint64_t len;
+    uint8_t *data;
+} buffer_t;
+
+// the items of a Float64Array or an Int64Array, which are not boxed
+typedef struct {
+    uint64_t len;
+    union {
+        double *f64;
+        int64_t *i64;
+    };
+} typed_array_t;
+
+// a symbol, whose name is interned like the keys of objects
+typedef struct {
+    char *name;
+    // the interned key the symbol is in objects, NULL until it is used as one
+    char *key;
+} symbol_t;
+
+// `start..end`, whose end is not in it, `start..=end` being `start..end + 1`
+typedef struct {
+    int64_t start;
+    int64_t end;
+} range_t;
+
+// Objects that got the same keys in the same order share a shape, which holds
+// their interned keys in the order of the slots of their values.
+typedef struct shape_t {
     size_t len;
     char **keys;
+    // shapes with one more key, created as objects of this shape get new keys
+    size_t transition_count;
+    struct shape_t **transitions;
+} shape_t;
+
+typedef struct {
+    size_t capacity;
+    size_t len;
+    shape_t *shape;
     void **vals;
 } object_t;
 
+// A property key of the generated code, shared by the access sites of the module
+// and interned by each thread on its first use.
+typedef struct {
+    char *name;
+    char *interned;
+} property_key_t;
+
+// The shape an object property access site saw last, zeroed until it runs.
+typedef struct {
+    shape_t *shape;
+    size_t index;
+    // the shape objects take when the key is added, NULL if they already have it
+    shape_t *transition;
+} inline_cache_t;
+
 typedef enum  {
+    VAL_UNDEFINED,
     VAL_NULL,
     VAL_BOOL,
     VAL_INT,
@@ -27,11 +86,23 @@ typedef enum  {
     VAL_STR,
     VAL_ARRAY,
     VAL_OBJECT,
+    VAL_BUILDER,
+    VAL_BUFFER,
+    VAL_FUNCTION,
+    VAL_THREAD,
+    VAL_CHANNEL,
+    VAL_RANGE,
+    VAL_F64_ARRAY,
+    VAL_I64_ARRAY,
+    VAL_SYMBOL,
 } val_type_t;
 
-typedef struct {
+typedef struct val_t {
     val_type_t type;
     int32_t ref_count;
+    // neighbours in the list of the live heap values
+    struct val_t *prev_live;
+    struct val_t *next_live;
     union {
         bool b;
         int64_t i64;
@@ -39,7 +110,71 @@ typedef struct {
         str_t str;
         array_t array;
         object_t object;
+        buffer_t buffer;
+        range_t range;
+        typed_array_t typed_array;
+        symbol_t symbol;
+        // the code of a mini function, taking and returning values
+        void *fn;
+        struct thread_t *thread;
+        struct channel_t *channel;
     };
 } val_t;
 
+// Values are passed around as `val_t *` words. Words with their lowest bit set are
+// integers shifted left by one, and words tagged with IMM_TAG_SPECIAL are undefined,
+// null or booleans. Only the remaining words point to values allocated on the heap,
+// and only those are reference counted.
+#define IMM_TAG_MASK 0x7
+#define IMM_TAG_INT 0x1
+#define IMM_TAG_SPECIAL 0x2
+
+#define IMM_NULL ((val_t *) (uintptr_t) ((0 << 3) | IMM_TAG_SPECIAL))
+#define IMM_FALSE ((val_t *) (uintptr_t) ((1 << 3) | IMM_TAG_SPECIAL))
+#define IMM_TRUE ((val_t *) (uintptr_t) ((2 << 3) | IMM_TAG_SPECIAL))
+#define IMM_UNDEFINED ((val_t *) (uintptr_t) ((3 << 3) | IMM_TAG_SPECIAL))
+
+#define IMM_INT_MIN (INTPTR_MIN >> 1)
+#define IMM_INT_MAX (INTPTR_MAX >> 1)
+
+static inline bool val_is_imm(val_t *v) {
+    return ((uintptr_t) v & IMM_TAG_MASK) != 0;
+}
+
+static inline bool val_is_heap(val_t *v) {
+    return v != NULL && !val_is_imm(v);
+}
+
+static inline val_type_t val_type(val_t *v) {
+    if ((uintptr_t) v & IMM_TAG_INT) {
+        return VAL_INT;
+    }
+
+    if (v == IMM_UNDEFINED) {
+        return VAL_UNDEFINED;
+    }
+
+    if (v == IMM_NULL) {
+        return VAL_NULL;
+    }
+
+    if (v == IMM_TRUE || v == IMM_FALSE) {
+        return VAL_BOOL;
+    }
+
+    return v->type;
+}
+
+static inline int64_t val_i64(val_t *v) {
+    if ((uintptr_t) v & IMM_TAG_INT) {
+        return (int64_t) ((intptr_t) v >> 1);
+    }
+
+    return v->i64;
+}
+
+static inline bool val_b(val_t *v) {
+    return v == IMM_TRUE;
+}
+
 #endif
@@ -0,0 +1,119 @@
+#ifndef MINI_STD_ERROR_H
+#define MINI_STD_ERROR_H
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#include "defs.h"
+
+static THREAD_LOCAL char *source_file = NULL;
+static THREAD_LOCAL int64_t source_line = 0;
+
+void *set_source_location(char *file, int64_t line) {
+    source_file = file;
+    source_line = line;
+
+    return NULL;
+}
+
+static void print_runtime_error_prefix() {
+    if (source_file != NULL) {
+        fprintf(stderr, "runtime error at %s:%lld: ", source_file, source_line);
+    } else {
+        fprintf(stderr, "runtime error: ");
+    }
+}
+
+// the exit status of programs aborted by a runtime error, the same one the
+// interpreter exits with
+#define EXIT_RUNTIME_ERROR 4
+
+#define MAX_RUNTIME_ERROR_LEN 1024
+
+val_t *new_str_val(char *s);
+void *link_val(val_t *val);
+void *unlink_val(val_t *val);
+
+static THREAD_LOCAL val_t *error_handler = NULL;
+
+// reports the error, gives it to the handler installed with `on_error`, then exits
+static void runtime_abort(char *message) {
+    print_runtime_error_prefix();
+    fprintf(stderr, "%s\n", message);
+
+    // an error raised by the handler itself aborts without calling it again
+    val_t *handler = error_handler;
+    error_handler = NULL;
+
+    if (handler != NULL) {
+        ((val_t *(*)(val_t *)) handler->fn)(new_str_val(message));
+    }
+
+    exit(EXIT_RUNTIME_ERROR);
+}
+
+#define RUNTIME_ERROR(args...) { \
+    char runtime_error_message[MAX_RUNTIME_ERROR_LEN]; \
+    snprintf(runtime_error_message, MAX_RUNTIME_ERROR_LEN, ##args); \
+    runtime_abort(runtime_error_message); \
+};
+
+static const char *val_type_name(val_t *v) {
+    switch (val_type(v)) {
+        case VAL_UNDEFINED:
+            return "undefined";
+        case VAL_NULL:
+            return "null";
+        case VAL_BOOL:
+            return "boolean";
+        case VAL_INT:
+        case VAL_FLOAT:
+            return "number";
+        case VAL_STR:
+            return "string";
+        case VAL_ARRAY:
+            return "array";
+        case VAL_OBJECT:
+            return "object";
+        case VAL_BUILDER:
+            return "string builder";
+        case VAL_BUFFER:
+            return "buffer";
+        case VAL_FUNCTION:
+            return "function";
+        case VAL_THREAD:
+            return "thread";
+        case VAL_CHANNEL:
+            return "channel";
+        case VAL_RANGE:
+            return "range";
+        case VAL_F64_ARRAY:
+            return "Float64Array";
+        case VAL_I64_ARRAY:
+            return "Int64Array";
+        case VAL_SYMBOL:
+            return "symbol";
+    }
+
+    return "unknown";
+}
+
+// installs the function called with the message of a runtime error before the
+// program aborts, undefined or null removing it
+void *on_error(val_t *handler) {
+    if (val_type(handler) != VAL_UNDEFINED && val_type(handler) != VAL_NULL && val_type(handler) != VAL_FUNCTION) {
+        RUNTIME_ERROR("`on_error` expects a function, got %s", val_type_name(handler));
+    }
+
+    if (error_handler != NULL) {
+        unlink_val(error_handler);
+    }
+
+    error_handler = val_type(handler) == VAL_FUNCTION ? handler : NULL;
+    link_val(error_handler);
+
+    return IMM_UNDEFINED;
+}
+
+#endif
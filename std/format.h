@@ -0,0 +1,227 @@
+#ifndef MINI_STD_FORMAT_H
+#define MINI_STD_FORMAT_H
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "gc.h"
+#include "val.h"
+#include "ops.h"
+#include "text.h"
+
+// A placeholder of a format string, `{index:<fill><align><0><width>.<precision>}`
+// with every part optional. Widths and precisions count characters, not bytes.
+typedef struct {
+    const char *fill;
+    uint64_t fill_len;
+    char align;
+    bool zero;
+    uint64_t width;
+    int64_t precision;
+} format_spec_t;
+
+static uint64_t parse_format_number(const char *fmt, uint64_t *pos) {
+    uint64_t n = 0;
+
+    while (fmt[*pos] >= '0' && fmt[*pos] <= '9') {
+        n = n * 10 + (fmt[*pos] - '0');
+        (*pos)++;
+    }
+
+    return n;
+}
+
+static bool is_format_align(char c) {
+    return c == '<' || c == '>' || c == '^';
+}
+
+static void parse_format_spec(const char *fmt, uint64_t *pos, format_spec_t *spec) {
+    spec->fill = " ";
+    spec->fill_len = 1;
+    spec->align = 0;
+    spec->zero = false;
+    spec->width = 0;
+    spec->precision = -1;
+
+    if (fmt[*pos] != ':') {
+        return;
+    }
+
+    (*pos)++;
+
+    uint64_t n = fmt[*pos] != 0 ? utf8_char_len(fmt + *pos) : 0;
+
+    if (n > 0 && fmt[*pos] != '}' && is_format_align(fmt[*pos + n])) {
+        spec->fill = fmt + *pos;
+        spec->fill_len = n;
+        spec->align = fmt[*pos + n];
+        *pos += n + 1;
+    } else if (is_format_align(fmt[*pos])) {
+        spec->align = fmt[*pos];
+        (*pos)++;
+    }
+
+    if (fmt[*pos] == '0') {
+        spec->zero = true;
+        (*pos)++;
+    }
+
+    spec->width = parse_format_number(fmt, pos);
+
+    if (fmt[*pos] == '.') {
+        (*pos)++;
+
+        if (fmt[*pos] < '0' || fmt[*pos] > '9') {
+            RUNTIME_ERROR("`format`: expected a precision after `.` in the format string");
+        }
+
+        spec->precision = parse_format_number(fmt, pos);
+    }
+}
+
+static void format_number(str_t *text, const char *fmt, int precision, double f64) {
+    int len = snprintf(NULL, 0, fmt, precision, f64);
+    char *buf = malloc(len + 1);
+    snprintf(buf, len + 1, fmt, precision, f64);
+
+    str_append(text, buf, len);
+    free(buf);
+}
+
+// the text of the value, with the precision of the placeholder applied to numbers
+// and strings
+static void format_val(str_t *text, val_t *v, format_spec_t *spec) {
+    if (spec->precision < 0) {
+        str_append_val(text, v, false, false);
+        return;
+    }
+
+    switch (val_type(v)) {
+        case VAL_INT:
+            format_number(text, "%.*f", spec->precision, (double) val_i64(v));
+            break;
+        case VAL_FLOAT:
+            format_number(text, "%.*f", spec->precision, v->f64);
+            break;
+        case VAL_STR:
+            str_append(text, v->str.data, utf8_prefix_len(v->str.data, v->str.len, spec->precision));
+            break;
+        default:
+            str_append_val(text, v, false, false);
+            break;
+    }
+}
+
+static void format_pad(str_t *result, format_spec_t *spec, uint64_t count) {
+    for (uint64_t i = 0; i < count; i++) {
+        str_append(result, spec->fill, spec->fill_len);
+    }
+}
+
+// pads the text to the width, numbers are aligned to the right by default and
+// zero padded after their sign with the `0` flag
+static void format_append(str_t *result, str_t *text, format_spec_t *spec, bool is_number) {
+    uint64_t len = utf8_count(text->data, text->len);
+    uint64_t padding = spec->width > len ? spec->width - len : 0;
+
+    if (is_number && spec->zero) {
+        uint64_t sign = text->len > 0 && text->data[0] == '-' ? 1 : 0;
+
+        str_append(result, text->data, sign);
+
+        for (uint64_t i = 0; i < padding; i++) {
+            str_append(result, "0", 1);
+        }
+
+        str_append(result, text->data + sign, text->len - sign);
+        return;
+    }
+
+    char align = spec->align != 0 ? spec->align : (is_number ? '>' : '<');
+    uint64_t before = align == '>' ? padding : (align == '^' ? padding / 2 : 0);
+
+    format_pad(result, spec, before);
+    str_append(result, text->data, text->len);
+    format_pad(result, spec, padding - before);
+}
+
+// replaces the `{}` and `{index}` placeholders of the format string with the
+// arguments, `{{` and `}}` stand for the braces themselves
+val_t *format(val_t *fmt, val_t *args) {
+    if (val_type(fmt) != VAL_STR) {
+        RUNTIME_ERROR("`format` expects a format string, got %s", val_type_name(fmt));
+    }
+
+    const char *data = fmt->str.data;
+    uint64_t next_index = 0;
+    uint64_t pos = 0;
+
+    val_t *result = new_str_val("");
+
+    while (pos < fmt->str.len) {
+        char c = data[pos];
+
+        if ((c == '{' || c == '}') && data[pos + 1] == c) {
+            str_append(&result->str, &c, 1);
+            pos += 2;
+            continue;
+        }
+
+        if (c == '}') {
+            RUNTIME_ERROR("`format`: unmatched `}` in the format string");
+        }
+
+        if (c != '{') {
+            str_append(&result->str, &c, 1);
+            pos++;
+            continue;
+        }
+
+        pos++;
+
+        uint64_t index = next_index;
+
+        if (data[pos] >= '0' && data[pos] <= '9') {
+            index = parse_format_number(data, &pos);
+        } else {
+            next_index++;
+        }
+
+        format_spec_t spec;
+        parse_format_spec(data, &pos, &spec);
+
+        if (data[pos] != '}') {
+            RUNTIME_ERROR("`format`: unmatched `{` in the format string");
+        }
+
+        pos++;
+
+        if (index >= args->array.len) {
+            RUNTIME_ERROR("`format`: no argument for the placeholder %llu, got %llu arguments", (unsigned long long) index, (unsigned long long) args->array.len);
+        }
+
+        val_t *v = args->array.data[index];
+        bool is_number = (val_type(v) == VAL_INT || val_type(v) == VAL_FLOAT);
+
+        str_t text;
+        new_str(&text, "");
+
+        format_val(&text, v, &spec);
+        format_append(&result->str, &text, &spec, is_number);
+
+        free_str(&text);
+    }
+
+    free_val_if_ok(fmt);
+    free_val_if_ok(args);
+
+    return result;
+}
+
+#endif
@@ -0,0 +1,146 @@
+#ifndef MINI_STD_FS_H
+#define MINI_STD_FS_H
+
+#include <dirent.h>
+#include <errno.h>
+#include <stdio.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+
+#include "defs.h"
+#include "error.h"
+#include "gc.h"
+#include "val.h"
+#include "ops.h"
+
+#define FS_COPY_LEN 65536
+
+static void expect_path(val_t *path, const char *function) {
+    if (val_type(path) != VAL_STR) {
+        RUNTIME_ERROR("`%s` expects a path string, got %s", function, val_type_name(path));
+    }
+}
+
+static int compare_names(const void *a, const void *b) {
+    return strcmp((*(val_t **) a)->str.data, (*(val_t **) b)->str.data);
+}
+
+// the names of the entries of the directory in order, without `.` and `..`
+val_t *fs_list_dir(val_t *path) {
+    expect_path(path, "fs_list_dir");
+
+    DIR *dir = opendir(path->str.data);
+    if (dir == NULL) {
+        RUNTIME_ERROR("`fs_list_dir` could not open `%s`: %s", path->str.data, strerror(errno));
+    }
+
+    val_t *result = new_array_val(8);
+    struct dirent *entry;
+
+    while ((entry = readdir(dir)) != NULL) {
+        if (strcmp(entry->d_name, ".") != 0 && strcmp(entry->d_name, "..") != 0) {
+            val_array_push(result, new_str_val(entry->d_name));
+        }
+    }
+
+    closedir(dir);
+
+    qsort(result->array.data, result->array.len, sizeof(void *), compare_names);
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+val_t *fs_stat(val_t *path) {
+    expect_path(path, "fs_stat");
+
+    struct stat st;
+    if (stat(path->str.data, &st) != 0) {
+        RUNTIME_ERROR("`fs_stat` could not stat `%s`: %s", path->str.data, strerror(errno));
+    }
+
+    val_t *result = new_object_val();
+    val_object_set(result, "size", new_int_val(st.st_size));
+    val_object_set(result, "is_file", new_bool_val(S_ISREG(st.st_mode)));
+    val_object_set(result, "is_dir", new_bool_val(S_ISDIR(st.st_mode)));
+    val_object_set(result, "modified", new_int_val(st.st_mtime));
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+void *fs_mkdir(val_t *path) {
+    expect_path(path, "fs_mkdir");
+
+    if (mkdir(path->str.data, 0777) != 0) {
+        RUNTIME_ERROR("`fs_mkdir` could not create `%s`: %s", path->str.data, strerror(errno));
+    }
+
+    free_val_if_ok(path);
+
+    return IMM_UNDEFINED;
+}
+
+// removes a file or an empty directory
+void *fs_remove(val_t *path) {
+    expect_path(path, "fs_remove");
+
+    if (remove(path->str.data) != 0) {
+        RUNTIME_ERROR("`fs_remove` could not remove `%s`: %s", path->str.data, strerror(errno));
+    }
+
+    free_val_if_ok(path);
+
+    return IMM_UNDEFINED;
+}
+
+val_t *fs_exists(val_t *path) {
+    expect_path(path, "fs_exists");
+
+    struct stat st;
+    val_t *result = new_bool_val(stat(path->str.data, &st) == 0);
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+void *fs_copy(val_t *from, val_t *to) {
+    expect_path(from, "fs_copy");
+    expect_path(to, "fs_copy");
+
+    FILE *source = fopen(from->str.data, "rb");
+    if (source == NULL) {
+        RUNTIME_ERROR("`fs_copy` could not open `%s`: %s", from->str.data, strerror(errno));
+    }
+
+    FILE *target = fopen(to->str.data, "wb");
+    if (target == NULL) {
+        fclose(source);
+        RUNTIME_ERROR("`fs_copy` could not create `%s`: %s", to->str.data, strerror(errno));
+    }
+
+    char *buf = malloc(FS_COPY_LEN);
+    size_t len;
+
+    while ((len = fread(buf, 1, FS_COPY_LEN, source)) > 0) {
+        if (fwrite(buf, 1, len, target) != len) {
+            RUNTIME_ERROR("`fs_copy` could not write `%s`: %s", to->str.data, strerror(errno));
+        }
+    }
+
+    free(buf);
+    fclose(source);
+    fclose(target);
+
+    free_val_if_ok(from);
+    free_val_if_ok(to);
+
+    return IMM_UNDEFINED;
+}
+
+#endif
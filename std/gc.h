@@ -3,14 +3,54 @@
 
 #include "defs.h"
 
-static int32_t active_val_count = 0;
+static void release_thread(struct thread_t *thread);
+static void release_channel(struct channel_t *channel);
+
+static THREAD_LOCAL int32_t active_val_count = 0;
+
+static THREAD_LOCAL val_t *live_vals = NULL;
+static THREAD_LOCAL int64_t live_val_count = 0;
+
+static void track_val(val_t *val) {
+    val->prev_live = NULL;
+    val->next_live = live_vals;
+
+    if (live_vals != NULL) {
+        live_vals->prev_live = val;
+    }
+
+    live_vals = val;
+    live_val_count++;
+}
+
+static void untrack_val(val_t *val) {
+    if (val->prev_live != NULL) {
+        val->prev_live->next_live = val->next_live;
+    } else {
+        live_vals = val->next_live;
+    }
+
+    if (val->next_live != NULL) {
+        val->next_live->prev_live = val->prev_live;
+    }
+
+    live_val_count--;
+}
 
 static void free_val_if_ok(val_t *val) {
-    if (val != NULL && val->type != VAL_NULL && val->type != VAL_BOOL && val->ref_count == 0) {
+    if (val_is_heap(val) && val->ref_count == 0) {
         DEBUG("GC: %p, type: %d", val, val->type);
 
-        if (val->type == VAL_STR) {
+        if (val->type == VAL_STR || val->type == VAL_BUILDER) {
             free_str(&val->str);
+        } else if (val->type == VAL_BUFFER) {
+            free(val->buffer.data);
+        } else if (val->type == VAL_F64_ARRAY || val->type == VAL_I64_ARRAY) {
+            free(val->typed_array.f64);
+        } else if (val->type == VAL_THREAD) {
+            release_thread(val->thread);
+        } else if (val->type == VAL_CHANNEL) {
+            release_channel(val->channel);
         } else if (val->type == VAL_ARRAY) {
             for (size_t i = 0; i < val->array.len; i++) {
                 unlink_val(val->array.data[i]);
@@ -25,12 +65,13 @@ static void free_val_if_ok(val_t *val) {
             free_object(&val->object);
         }
 
+        untrack_val(val);
         free(val);
     }
 }
 
 void *link_val(val_t *val) {
-    if (val != NULL && val->type != VAL_NULL && val->type != VAL_BOOL) {
+    if (val_is_heap(val)) {
         active_val_count++;
         val->ref_count++;
 
@@ -44,18 +85,32 @@ void *link_val(val_t *val) {
 }
 
 void *unlink_val(val_t *val) {
-    if (val != NULL && val->type != VAL_NULL && val->type != VAL_BOOL) {
+    if (val_is_heap(val)) {
         active_val_count--;
         val->ref_count--;
 
         assert(active_val_count >= 0);
         assert(val->ref_count >= 0);
 
+        DEBUG("unlink: %p, type: %d, active: %d", val, val->type, active_val_count);
+
         if (val->ref_count == 0) {
             free_val_if_ok(val);
         }
+    }
 
-        DEBUG("unlink: %p, type: %d, active: %d", val, val->type, active_val_count);
+    return NULL;
+}
+
+// drops a reference without freeing the value, handing it to the caller like a
+// newly created value
+void *release_val(val_t *val) {
+    if (val_is_heap(val)) {
+        active_val_count--;
+        val->ref_count--;
+
+        assert(active_val_count >= 0);
+        assert(val->ref_count >= 0);
     }
 
     return NULL;
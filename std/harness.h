@@ -0,0 +1,68 @@
+#ifndef MINI_STD_HARNESS_H
+#define MINI_STD_HARNESS_H
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "defs.h"
+#include "error.h"
+#include "text.h"
+#include "val.h"
+#include "timers.h"
+
+// The test harness `mini test` builds: `main` runs the top level statements, then
+// every test with `run_test`, and exits with the status `finish_tests` returns.
+
+static int64_t tests_passed = 0;
+static int64_t tests_failed = 0;
+
+void *start_tests(int64_t count) {
+    printf("running %lld tests\n", (long long) count);
+
+    return IMM_UNDEFINED;
+}
+
+// runs the test in a child process, so that a failing assertion only stops the test
+void *run_test(const char *name, val_t *(*test)(void)) {
+    fflush(stdout);
+    fflush(stderr);
+
+    pid_t pid = fork();
+    if (pid < 0) {
+        RUNTIME_ERROR("could not start the test `%s`", name);
+    }
+
+    if (pid == 0) {
+        test();
+        run_timers();
+
+        fflush(stdout);
+        _exit(0);
+    }
+
+    int status;
+    waitpid(pid, &status, 0);
+
+    if (WIFEXITED(status) && WEXITSTATUS(status) == 0) {
+        tests_passed++;
+        printf("test %s ... %sok%s\n", name, TEXT_COLOR_GREEN, TEXT_COLOR_RESET);
+    } else {
+        tests_failed++;
+        printf("test %s ... %sFAILED%s\n", name, TEXT_COLOR_RED, TEXT_COLOR_RESET);
+    }
+
+    return IMM_UNDEFINED;
+}
+
+val_t *finish_tests() {
+    const char *result = tests_failed == 0 ? TEXT_COLOR_GREEN "ok" : TEXT_COLOR_RED "FAILED";
+
+    printf("\ntest result: %s%s. %lld passed; %lld failed\n", result, TEXT_COLOR_RESET, (long long) tests_passed, (long long) tests_failed);
+
+    return new_int_val(tests_failed == 0 ? 0 : EXIT_RUNTIME_ERROR);
+}
+
+#endif
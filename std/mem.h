@@ -0,0 +1,91 @@
+#ifndef MINI_STD_MEM_H
+#define MINI_STD_MEM_H
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#include "defs.h"
+#include "error.h"
+#include "gc.h"
+#include "val.h"
+#include "ops.h"
+
+// bytes allocated for the value, including its strings and its item tables
+static int64_t val_size(val_t *v) {
+    int64_t size = sizeof(val_t);
+
+    switch (v->type) {
+        case VAL_STR:
+        case VAL_BUILDER:
+            size += v->str.capacity + 1;
+            break;
+        case VAL_BUFFER:
+            size += v->buffer.len;
+            break;
+        case VAL_F64_ARRAY:
+        case VAL_I64_ARRAY:
+            size += v->typed_array.len * 8;
+            break;
+        case VAL_ARRAY:
+            size += v->array.capacity * sizeof(void *);
+            break;
+        case VAL_OBJECT:
+            size += v->object.capacity * sizeof(void *);
+            break;
+        default:
+            break;
+    }
+
+    return size;
+}
+
+static int64_t live_val_bytes() {
+    int64_t bytes = 0;
+
+    for (val_t *v = live_vals; v != NULL; v = v->next_live) {
+        bytes += val_size(v);
+    }
+
+    return bytes;
+}
+
+val_t *mem_stats() {
+    val_t *result = new_object_val();
+
+    // the result itself is not counted
+    int64_t values = live_val_count - 1;
+    int64_t bytes = live_val_bytes() - val_size(result);
+
+    val_object_set(result, "values", new_int_val(values));
+    val_object_set(result, "bytes", new_int_val(bytes));
+
+    return result;
+}
+
+static void report_leaks() {
+    if (live_vals == NULL) {
+        return;
+    }
+
+    fprintf(stderr, "leaked %lld values, %lld bytes:\n", live_val_count, live_val_bytes());
+
+    for (val_t *v = live_vals; v != NULL; v = v->next_live) {
+        fprintf(stderr, "  %p: %s, %lld bytes, %d references", v, val_type_name(v), val_size(v), v->ref_count);
+
+        if (v->type == VAL_STR) {
+            fprintf(stderr, ", '%s'", v->str.data);
+        }
+
+        fprintf(stderr, "\n");
+    }
+}
+
+// called first thing in main by programs compiled with --debug-leaks
+void *debug_leaks() {
+    atexit(report_leaks);
+
+    return NULL;
+}
+
+#endif
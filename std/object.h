@@ -6,52 +6,196 @@
 void *link_val(val_t *val);
 void *unlink_val(val_t *val);
 
+// Object keys are interned: each distinct key is stored once, so the keys of
+// objects are compared by pointer. Interned keys live until the program exits,
+// and every thread interns its own.
+typedef struct {
+    size_t capacity;
+    size_t len;
+    char **keys;
+} key_table_t;
+
+static THREAD_LOCAL key_table_t key_table = {0, 0, NULL};
+
+static uint64_t hash_key(const char *k) {
+    uint64_t hash = 14695981039346656037ULL;
+
+    for (; *k != 0; k++) {
+        hash = (hash ^ (unsigned char) *k) * 1099511628211ULL;
+    }
+
+    return hash;
+}
+
+// the slot of the key in the table, or the empty slot it would take
+static size_t key_slot(char **keys, size_t capacity, const char *k) {
+    size_t i = hash_key(k) & (capacity - 1);
+
+    while (keys[i] != NULL && keys[i] != k && strcmp(keys[i], k) != 0) {
+        i = (i + 1) & (capacity - 1);
+    }
+
+    return i;
+}
+
+static void grow_key_table() {
+    size_t capacity = key_table.capacity == 0 ? 64 : key_table.capacity * 2;
+    char **keys = calloc(capacity, sizeof(char *));
+
+    for (size_t i = 0; i < key_table.capacity; i++) {
+        if (key_table.keys[i] != NULL) {
+            keys[key_slot(keys, capacity, key_table.keys[i])] = key_table.keys[i];
+        }
+    }
+
+    free(key_table.keys);
+
+    key_table.capacity = capacity;
+    key_table.keys = keys;
+}
+
+// the interned key equal to k, NULL if no object ever had such a key
+static char *find_key(const char *k) {
+    if (key_table.len == 0) {
+        return NULL;
+    }
+
+    return key_table.keys[key_slot(key_table.keys, key_table.capacity, k)];
+}
+
+static char *intern_key(const char *k) {
+    if ((key_table.len + 1) * 2 > key_table.capacity) {
+        grow_key_table();
+    }
+
+    size_t i = key_slot(key_table.keys, key_table.capacity, k);
+
+    if (key_table.keys[i] == NULL) {
+        key_table.keys[i] = strdup(k);
+        key_table.len++;
+    }
+
+    return key_table.keys[i];
+}
+
+static char *property_key(property_key_t *key) {
+    if (key->interned == NULL) {
+        key->interned = intern_key(key->name);
+    }
+
+    return key->interned;
+}
+
+static THREAD_LOCAL shape_t root_shape = {0, NULL, 0, NULL};
+
+// the shape objects of the given shape take when the key is added to them
+static shape_t *shape_transition(shape_t *shape, char *k) {
+    for (size_t i = 0; i < shape->transition_count; i++) {
+        if (shape->transitions[i]->keys[shape->len] == k) {
+            return shape->transitions[i];
+        }
+    }
+
+    shape_t *result = malloc(sizeof(shape_t));
+    result->len = shape->len + 1;
+    result->keys = malloc(result->len * sizeof(char *));
+    if (shape->len > 0) {
+        memcpy(result->keys, shape->keys, shape->len * sizeof(char *));
+    }
+
+    result->keys[shape->len] = k;
+    result->transition_count = 0;
+    result->transitions = NULL;
+
+    shape->transitions = realloc(shape->transitions, (shape->transition_count + 1) * sizeof(shape_t *));
+    shape->transitions[shape->transition_count] = result;
+    shape->transition_count++;
+
+    return result;
+}
+
+// the slot of the key in objects of the shape, -1 if they do not have it
+static int64_t shape_index(shape_t *shape, char *k) {
+    for (size_t i = 0; i < shape->len; i++) {
+        if (shape->keys[i] == k) {
+            return i;
+        }
+    }
+
+    return -1;
+}
+
+static void free_shape_transitions(shape_t *shape) {
+    for (size_t i = 0; i < shape->transition_count; i++) {
+        free_shape_transitions(shape->transitions[i]);
+        free(shape->transitions[i]->keys);
+        free(shape->transitions[i]);
+    }
+
+    free(shape->transitions);
+    shape->transitions = NULL;
+    shape->transition_count = 0;
+}
+
+// frees the interned keys and the shapes of the thread, once it has no objects left
+static void free_object_tables() {
+    free_shape_transitions(&root_shape);
+
+    for (size_t i = 0; i < key_table.capacity; i++) {
+        free(key_table.keys[i]);
+    }
+
+    free(key_table.keys);
+    key_table.capacity = 0;
+    key_table.len = 0;
+    key_table.keys = NULL;
+}
+
 static void free_object(object_t *kv) {
-    free(kv->keys);
     free(kv->vals);
 }
 
 static void new_object(object_t *result) {
-    char **keys = malloc(sizeof(char *));
     void **vals = malloc(sizeof(void *));
 
     result->capacity = 1;
     result->len = 0;
-    result->keys = keys;
+    result->shape = &root_shape;
     result->vals = vals;
 }
 
-static bool object_set(object_t *result, char *k, void *v) {
-    for (size_t i = 0; i < result->len; i++) {
-        if (strcmp(result->keys[i], k) == 0) {
-            result->vals[i] = v;
-
-            return false; // means we didn't add a new key
-        }
-    }
-
+// appends the value of a new key, given the shape the object takes with it
+static void object_push(object_t *result, void *v, shape_t *shape) {
     if (result->len == result->capacity) {
         result->capacity *= 2;
-        result->keys = realloc(result->keys, result->capacity * sizeof(void *));
         result->vals = realloc(result->vals, result->capacity * sizeof(void *));
     }
 
-    result->keys[result->len] = k;
     result->vals[result->len] = v;
     result->len++;
+    result->shape = shape;
+}
+
+// the key must be interned
+static bool object_set(object_t *result, char *k, void *v) {
+    int64_t index = shape_index(result->shape, k);
+
+    if (index >= 0) {
+        result->vals[index] = v;
+
+        return false; // means we didn't add a new key
+    }
+
+    object_push(result, v, shape_transition(result->shape, k));
 
     return true; // means we added a new key
 }
 
+// the key must be interned, or NULL
 static void *object_get(object_t *result, char *k) {
-    for (size_t i = 0; i < result->len; i++) {
-        if (strcmp(result->keys[i], k) == 0) {
-            return result->vals[i];
-        }
-    }
+    int64_t index = shape_index(result->shape, k);
 
-    return NULL;
+    return index >= 0 ? result->vals[index] : IMM_UNDEFINED;
 }
 
-
 #endif
@@ -9,10 +9,14 @@
 #include <string.h>
 
 #include "defs.h"
+#include "error.h"
 #include "str.h"
 #include "array.h"
+#include "typed.h"
 #include "object.h"
+#include "symbol.h"
 #include "gc.h"
+#include "text.h"
 
 static val_t *new_str_with_combine(val_t *v1, val_t *v2) {
     val_t *result = new_val(VAL_STR);
@@ -23,26 +27,128 @@ static val_t *new_str_with_combine(val_t *v1, val_t *v2) {
     return result;
 }
 
+// whether conditions and logical operators take the value as true: false, 0, NaN,
+// the empty string, null and undefined are false, every other value is true
+static bool val_is_truthy(val_t *v) {
+    switch (val_type(v)) {
+        case VAL_UNDEFINED:
+        case VAL_NULL:
+            return false;
+        case VAL_BOOL:
+            return val_b(v);
+        case VAL_INT:
+            return val_i64(v) != 0;
+        case VAL_FLOAT:
+            return v->f64 != 0.0 && v->f64 == v->f64;
+        case VAL_STR:
+            return v->str.len > 0;
+        default:
+            return true;
+    }
+}
+
+bool val_to_bool(val_t *v) {
+    bool result = val_is_truthy(v);
+
+    free_val_if_ok(v);
+
+    return result;
+}
+
+// the argument of an `int32` parameter of an external function, truncated to its
+// low 32 bits like the integers of C, NaN and floats out of the int64 range are 0
+int32_t val_to_int32(val_t *v) {
+    int64_t n;
+
+    switch (val_type(v)) {
+        case VAL_INT:
+            n = val_i64(v);
+            break;
+        case VAL_FLOAT:
+            n = v->f64 > -9.2e18 && v->f64 < 9.2e18 ? (int64_t) v->f64 : 0;
+            break;
+        default:
+            RUNTIME_ERROR("an int32 parameter expects a number, got %s", val_type_name(v));
+    }
+
+    free_val_if_ok(v);
+
+    return (int32_t) (uint32_t) n;
+}
+
+// the function an object defines under the name to overload an operator, NULL
+// if it defines none
+static val_t *val_operator(val_t *v, const char *name) {
+    if (val_type(v) != VAL_OBJECT) {
+        return NULL;
+    }
+
+    val_t *fn = object_get(&v->object, find_key(name));
+
+    return val_type(fn) == VAL_FUNCTION ? fn : NULL;
+}
+
+// the operator function of the left operand, or of the right one if the left
+// defines none
+static val_t *val_binary_operator(val_t *v1, val_t *v2, const char *name) {
+    val_t *fn = val_operator(v1, name);
+
+    return fn != NULL ? fn : val_operator(v2, name);
+}
+
+// calls the operator function with the operands, which it takes over like the
+// arguments of any other call
+static val_t *val_call_operator(val_t *fn, val_t *v1, val_t *v2) {
+    return ((val_t *(*)(val_t *, val_t *)) fn->fn)(v1, v2);
+}
+
 val_t *val_op_add(val_t *v1, val_t *v2) {
     val_t *result = NULL;
+    val_t *fn = val_binary_operator(v1, v2, "__add");
+
+    if (fn != NULL) {
+        return val_call_operator(fn, v1, v2);
+    }
+
+    if (val_type(v1) == VAL_STR && val_type(v2) == VAL_STR) {
+        // nothing else refers to a temporary, like the result of the previous
+        // `+` of a chain, so it is extended in place
+        if (v1->ref_count == 0 && v1 != v2) {
+            str_append(&v1->str, v2->str.data, v2->str.len);
+            free_val_if_ok(v2);
+
+            return v1;
+        }
 
-    if (v1->type == VAL_STR && v2->type == VAL_STR) {
         result = new_str_with_combine(v1, v2);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_FLOAT) {
+    else if (val_type(v1) == VAL_STR || val_type(v2) == VAL_STR) {
+        // the other operand is added with its text, as `val_to_str` gives it
+        result = new_str_val("");
+        str_append_val(&result->str, v1, false, false);
+        str_append_val(&result->str, v2, false, false);
+    }
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_FLOAT) {
         result = new_float_val(v1->f64 + v2->f64);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_FLOAT) {
-        result = new_float_val((float) v1->i64 + v2->f64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_FLOAT) {
+        result = new_float_val((double) val_i64(v1) + v2->f64);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_INT) {
-        result = new_float_val(v1->f64 + (float) v2->i64);
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_INT) {
+        result = new_float_val(v1->f64 + (double) val_i64(v2));
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        result = new_int_val(v1->i64 + v2->i64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        int64_t n;
+
+        // an overflowing result becomes a float, like in the interpreter
+        if (__builtin_add_overflow(val_i64(v1), val_i64(v2), &n)) {
+            result = new_float_val((double) val_i64(v1) + (double) val_i64(v2));
+        } else {
+            result = new_int_val(n);
+        }
     }
     else {
-        assert(false);
+        RUNTIME_ERROR("cannot add values of types %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
     free_val_if_ok(v1);
@@ -54,21 +160,32 @@ val_t *val_op_add(val_t *v1, val_t *v2) {
 
 val_t *val_op_sub(val_t *v1, val_t *v2) {
     val_t *result = NULL;
+    val_t *fn = val_binary_operator(v1, v2, "__sub");
+
+    if (fn != NULL) {
+        return val_call_operator(fn, v1, v2);
+    }
 
-    if (v1->type == VAL_FLOAT && v2->type == VAL_FLOAT) {
+    if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_FLOAT) {
         result = new_float_val(v1->f64 - v2->f64);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_FLOAT) {
-        result = new_float_val((float) v1->i64 - v2->f64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_FLOAT) {
+        result = new_float_val((double) val_i64(v1) - v2->f64);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_INT) {
-        result = new_float_val(v1->f64 - (float) v2->i64);
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_INT) {
+        result = new_float_val(v1->f64 - (double) val_i64(v2));
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        result = new_int_val(v1->i64 - v2->i64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        int64_t n;
+
+        if (__builtin_sub_overflow(val_i64(v1), val_i64(v2), &n)) {
+            result = new_float_val((double) val_i64(v1) - (double) val_i64(v2));
+        } else {
+            result = new_int_val(n);
+        }
     }
     else {
-        assert(false);
+        RUNTIME_ERROR("cannot subtract values of types %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
     free_val_if_ok(v1);
@@ -79,21 +196,32 @@ val_t *val_op_sub(val_t *v1, val_t *v2) {
 
 val_t *val_op_mul(val_t *v1, val_t *v2) {
     val_t *result = NULL;
+    val_t *fn = val_binary_operator(v1, v2, "__mul");
+
+    if (fn != NULL) {
+        return val_call_operator(fn, v1, v2);
+    }
 
-    if (v1->type == VAL_FLOAT && v2->type == VAL_FLOAT) {
+    if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_FLOAT) {
         result = new_float_val(v1->f64 * v2->f64);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_FLOAT) {
-        result = new_float_val((float) v1->i64 * v2->f64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_FLOAT) {
+        result = new_float_val((double) val_i64(v1) * v2->f64);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_INT) {
-        result = new_float_val(v1->f64 * (float) v2->i64);
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_INT) {
+        result = new_float_val(v1->f64 * (double) val_i64(v2));
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        result = new_int_val(v1->i64 * v2->i64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        int64_t n;
+
+        if (__builtin_mul_overflow(val_i64(v1), val_i64(v2), &n)) {
+            result = new_float_val((double) val_i64(v1) * (double) val_i64(v2));
+        } else {
+            result = new_int_val(n);
+        }
     }
     else {
-        assert(false);
+        RUNTIME_ERROR("cannot multiply values of types %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
     free_val_if_ok(v1);
@@ -104,21 +232,26 @@ val_t *val_op_mul(val_t *v1, val_t *v2) {
 
 val_t *val_op_div(val_t *v1, val_t *v2) {
     val_t *result = NULL;
+    val_t *fn = val_binary_operator(v1, v2, "__div");
 
-    if (v1->type == VAL_FLOAT && v2->type == VAL_FLOAT) {
+    if (fn != NULL) {
+        return val_call_operator(fn, v1, v2);
+    }
+
+    if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_FLOAT) {
         result = new_float_val(v1->f64 / v2->f64);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_FLOAT) {
-        result = new_float_val((float) v1->i64 / v2->f64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_FLOAT) {
+        result = new_float_val((double) val_i64(v1) / v2->f64);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_INT) {
-        result = new_float_val(v1->f64 / (float) v2->i64);
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_INT) {
+        result = new_float_val(v1->f64 / (double) val_i64(v2));
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        result = new_float_val((float) v1->i64 / (float) v2->i64);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        result = new_float_val((double) val_i64(v1) / (double) val_i64(v2));
     }
     else {
-        assert(false);
+        RUNTIME_ERROR("cannot divide values of types %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
     free_val_if_ok(v1);
@@ -130,12 +263,25 @@ val_t *val_op_div(val_t *v1, val_t *v2) {
 
 val_t *val_op_mod(val_t *v1, val_t *v2) {
     val_t *result = NULL;
+    val_t *fn = val_binary_operator(v1, v2, "__mod");
+
+    if (fn != NULL) {
+        return val_call_operator(fn, v1, v2);
+    }
+
+    if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        if (val_i64(v2) == 0) {
+            RUNTIME_ERROR("division by zero");
+        }
+
+        if (val_i64(v1) == INT64_MIN && val_i64(v2) == -1) {
+            RUNTIME_ERROR("integer overflow in modulo");
+        }
 
-    if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        result = new_int_val(v1->i64 % v2->i64);
+        result = new_int_val(val_i64(v1) % val_i64(v2));
     }
     else {
-        assert(false);
+        RUNTIME_ERROR("cannot take modulo of values of types %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
     free_val_if_ok(v1);
@@ -146,48 +292,98 @@ val_t *val_op_mod(val_t *v1, val_t *v2) {
 
 
 short val_compare(val_t *v1, val_t *v2) {
-    if (v1->type == VAL_FLOAT && v2->type == VAL_FLOAT) {
+    if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_FLOAT) {
         return (v1->f64 < v2->f64) ? -1 : ((v1->f64 > v2->f64) ? 1 : 0);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_FLOAT) {
-        return ((float) v1->i64 < v2->f64) ? -1 : (((float) v1->i64 > v2->f64) ? 1 : 0);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_FLOAT) {
+        return ((double) val_i64(v1) < v2->f64) ? -1 : (((double) val_i64(v1) > v2->f64) ? 1 : 0);
     }
-    else if (v1->type == VAL_FLOAT && v2->type == VAL_INT) {
-        return (v1->f64 < (float) v2->i64) ? -1 : ((v1->f64 > (float) v2->i64) ? 1 : 0);
+    else if (val_type(v1) == VAL_FLOAT && val_type(v2) == VAL_INT) {
+        return (v1->f64 < (double) val_i64(v2)) ? -1 : ((v1->f64 > (double) val_i64(v2)) ? 1 : 0);
     }
-    else if (v1->type == VAL_INT && v2->type == VAL_INT) {
-        return (v1->i64 < v2->i64) ? -1 : ((v1->i64 > v2->i64) ? 1 : 0);
+    else if (val_type(v1) == VAL_INT && val_type(v2) == VAL_INT) {
+        return (val_i64(v1) < val_i64(v2)) ? -1 : ((val_i64(v1) > val_i64(v2)) ? 1 : 0);
+    }
+    else if (val_type(v1) == VAL_STR && val_type(v2) == VAL_STR) {
+        int status = strcmp(v1->str.data, v2->str.data);
+        return (status < 0) ? -1 : ((status > 0) ? 1 : 0);
+    }
+    else if (val_type(v1) == VAL_BOOL && val_type(v2) == VAL_BOOL) {
+        return (short) val_b(v1) - (short) val_b(v2);
+    }
+    else if (val_type(v1) == VAL_SYMBOL && val_type(v2) == VAL_SYMBOL) {
+        // symbols of the same name share it, and others are ordered by name
+        if (v1->symbol.name == v2->symbol.name) {
+            return 0;
+        }
+
+        return strcmp(v1->symbol.name, v2->symbol.name) < 0 ? -1 : 1;
     }
+    else if (val_type(v1) == VAL_RANGE && val_type(v2) == VAL_RANGE) {
+        if (v1->range.start != v2->range.start) {
+            return v1->range.start < v2->range.start ? -1 : 1;
+        }
 
-    assert(false);
+        return (v1->range.end < v2->range.end) ? -1 : ((v1->range.end > v2->range.end) ? 1 : 0);
+    }
+
+    RUNTIME_ERROR("cannot compare values of types %s and %s", val_type_name(v1), val_type_name(v2));
     return 0;
 }
 
-void *val_op_eq(val_t *v1, val_t *v2) {
-    short status = val_compare(v1, v2);
+// undefined and null are only equal to each other, and `===` tells them apart
+static bool val_is_nullish(val_t *v) {
+    return val_type(v) == VAL_UNDEFINED || val_type(v) == VAL_NULL;
+}
 
-    free_val_if_ok(v1);
-    free_val_if_ok(v2);
+// the exit status of the process from the value `main` returns, which is the
+// result of the entry function, 0 when it returns nothing
+int32_t val_exit_status(val_t *v) {
+    if (val_is_nullish(v)) {
+        return 0;
+    }
 
-    return new_bool_val(status == 0);
+    if (val_type(v) != VAL_INT && val_type(v) != VAL_FLOAT) {
+        RUNTIME_ERROR("the exit status must be a number, got %s", val_type_name(v));
+    }
+
+    return val_to_int32(v);
 }
 
-void *val_op_neq(val_t *v1, val_t *v2) {
-    short status = val_compare(v1, v2);
+// `==` of the operands, which it frees, through the `__eq` function of an object
+// operand if it defines one
+static bool val_loose_equals(val_t *v1, val_t *v2) {
+    val_t *fn = val_binary_operator(v1, v2, "__eq");
+
+    if (fn != NULL) {
+        return val_to_bool(val_call_operator(fn, v1, v2));
+    }
+
+    bool is_equal = val_is_nullish(v1) || val_is_nullish(v2)
+        ? val_is_nullish(v1) && val_is_nullish(v2)
+        : val_compare(v1, v2) == 0;
 
     free_val_if_ok(v1);
     free_val_if_ok(v2);
 
-    return new_bool_val(status != 0);
+    return is_equal;
+}
+
+void *val_op_eq(val_t *v1, val_t *v2) {
+    return new_bool_val(val_loose_equals(v1, v2));
+}
+
+void *val_op_neq(val_t *v1, val_t *v2) {
+    return new_bool_val(!val_loose_equals(v1, v2));
 }
 
 void *val_op_seq(val_t *v1, val_t *v2) {
-    if (v1 == NULL || v2 == NULL) {
-        return new_bool_val(v1 == v2);
+    if (val_type(v1) != val_type(v2)) {
+        return new_bool_val(false);
     }
 
-    if (v1->type != v2->type) {
-        return new_bool_val(false);
+    if (val_is_nullish(v1)) {
+        return new_bool_val(true);
     }
 
     short status = val_compare(v1, v2);
@@ -199,11 +395,11 @@ void *val_op_seq(val_t *v1, val_t *v2) {
 }
 
 void *val_op_sneq(val_t *v1, val_t *v2) {
-    if (v1 == NULL || v2 == NULL) {
-        return new_bool_val(v1 == v2);
+    if (val_type(v1) != val_type(v2)) {
+        return new_bool_val(true);
     }
 
-    if (v1->type != v2->type) {
+    if (val_is_nullish(v1)) {
         return new_bool_val(false);
     }
 
@@ -251,55 +447,90 @@ void *val_op_gte(val_t *v1, val_t *v2) {
     return new_bool_val(status >= 0);
 }
 
-void *val_op_and(val_t *v1, val_t *v2) {
-    if (v1->type != VAL_BOOL || v2->type != VAL_BOOL) {
-        assert(false);
+val_t *val_op_range(val_t *v1, val_t *v2, bool is_inclusive) {
+    if (val_type(v1) != VAL_INT || val_type(v2) != VAL_INT) {
+        RUNTIME_ERROR("range bounds must be integers, got %s and %s", val_type_name(v1), val_type_name(v2));
     }
 
-    bool result = v1->b && v2->b;
+    val_t *result = new_range_val(val_i64(v1), is_inclusive ? val_i64(v2) + 1 : val_i64(v2));
 
     free_val_if_ok(v1);
     free_val_if_ok(v2);
 
-    return new_bool_val(result);
+    return result;
+}
+
+void *val_op_and(val_t *v1, val_t *v2) {
+    bool left = val_to_bool(v1);
+    bool right = val_to_bool(v2);
+
+    return new_bool_val(left && right);
 }
 
 void *val_op_or(val_t *v1, val_t *v2) {
-    if (v1->type != VAL_BOOL || v2->type != VAL_BOOL) {
-        assert(false);
+    bool left = val_to_bool(v1);
+    bool right = val_to_bool(v2);
+
+    return new_bool_val(left || right);
+}
+
+void *val_op_not(val_t *v) {
+    return new_bool_val(!val_to_bool(v));
+}
+
+// the unboxing helpers of `__llvm_ir` snippets, which box their results with
+// the `new_*_val` functions
+bool val_unbox_bool(val_t *v) {
+    if (val_type(v) != VAL_BOOL) {
+        RUNTIME_ERROR("`__llvm_ir` expects a boolean, got %s", val_type_name(v));
     }
 
-    bool result = v1->b || v2->b;
+    bool result = val_b(v);
 
-    free_val_if_ok(v1);
-    free_val_if_ok(v2);
+    free_val_if_ok(v);
 
-    return new_bool_val(result);
+    return result;
 }
 
-void *val_op_not(val_t *v) {
-    if (v->type != VAL_BOOL) {
-        assert(false);
+int64_t val_unbox_int(val_t *v) {
+    if (val_type(v) != VAL_INT) {
+        RUNTIME_ERROR("`__llvm_ir` expects an integer, got %s", val_type_name(v));
     }
 
-    bool result = !v->b;
+    int64_t result = val_i64(v);
 
     free_val_if_ok(v);
 
-    return new_bool_val(result);
+    return result;
+}
+
+double val_unbox_float(val_t *v) {
+    double result;
+
+    if (val_type(v) == VAL_FLOAT) {
+        result = v->f64;
+    } else if (val_type(v) == VAL_INT) {
+        result = (double) val_i64(v);
+    } else {
+        RUNTIME_ERROR("`__llvm_ir` expects a number, got %s", val_type_name(v));
+    }
+
+    free_val_if_ok(v);
+
+    return result;
 }
 
 void *val_op_pos(val_t *v) {
-    if (v->type == VAL_INT || v->type == VAL_FLOAT) {
+    if (val_type(v) == VAL_INT || val_type(v) == VAL_FLOAT) {
         return v;
     }
 
     val_t *result;
 
-    if (v->type == VAL_BOOL) {
-        result = new_int_val(v->b ? 1 : 0);
+    if (val_type(v) == VAL_BOOL) {
+        result = new_int_val(val_b(v) ? 1 : 0);
     } else {
-        assert(false);
+        RUNTIME_ERROR("unary `+` expects a number or boolean, got %s", val_type_name(v));
     }
 
     free_val_if_ok(v);
@@ -310,12 +541,14 @@ void *val_op_pos(val_t *v) {
 void *val_op_neg(val_t *v) {
     val_t *result;
 
-    if (v->type == VAL_INT) {
-        return new_int_val(-v->i64);
-    } else if (v->type == VAL_FLOAT) {
+    if (val_type(v) == VAL_INT && val_i64(v) == INT64_MIN) {
+        return new_float_val(-(double) val_i64(v));
+    } else if (val_type(v) == VAL_INT) {
+        return new_int_val(-val_i64(v));
+    } else if (val_type(v) == VAL_FLOAT) {
         return new_float_val(-v->f64);
     } else {
-        assert(false);
+        RUNTIME_ERROR("unary `-` expects a number, got %s", val_type_name(v));
     }
 
     free_val_if_ok(v);
@@ -324,55 +557,325 @@ void *val_op_neg(val_t *v) {
 }
 
 void *val_array_push(val_t *items, val_t *v) {
-    if (items->type != VAL_ARRAY) {
-        assert(false);
+    if (val_type(items) != VAL_ARRAY) {
+        RUNTIME_ERROR("expected an array, got %s", val_type_name(items));
     }
 
     array_push(&items->array, v);
 
     link_val(v);
 
-    return NULL;
+    return IMM_UNDEFINED;
 }
 
 void *val_array_insert(val_t *items, val_t *i, val_t *v) {
-    if (items->type != VAL_ARRAY) {
-        assert(false);
+    if (val_type(items) != VAL_ARRAY) {
+        RUNTIME_ERROR("expected an array, got %s", val_type_name(items));
+    }
+
+    if (val_type(i) != VAL_INT) {
+        RUNTIME_ERROR("array index must be a number, got %s", val_type_name(i));
     }
 
-    if (i->type != VAL_INT) {
-        assert(false);
+    if (val_i64(i) < 0 || val_i64(i) >= MAX_ARRAY_LEN) {
+        RUNTIME_ERROR("array index %lld out of bounds", val_i64(i));
     }
 
-    void *old_value = array_get(&items->array, i->i64);;
+    void *old_value = array_get(&items->array, val_i64(i));;
     if (old_value != NULL) {
         unlink_val(old_value);
     }
 
-    array_insert(&items->array, i->i64, v);
+    array_insert(&items->array, val_i64(i), v);
 
     link_val(v);
 
-    return NULL;
+    return IMM_UNDEFINED;
 }
 
 void *val_array_get(val_t *items, val_t *i) {
-    if (items->type != VAL_ARRAY) {
-        assert(false);
+    if (val_type(items) != VAL_ARRAY) {
+        RUNTIME_ERROR("expected an array, got %s", val_type_name(items));
     }
 
-    if (i->type != VAL_INT) {
-        assert(false);
+    if (val_type(i) != VAL_INT) {
+        RUNTIME_ERROR("array index must be a number, got %s", val_type_name(i));
     }
 
-    return array_get(&items->array, i->i64);
+    return array_get(&items->array, val_i64(i));
+}
+
+static void expect_array(val_t *items, const char *function) {
+    if (val_type(items) != VAL_ARRAY) {
+        RUNTIME_ERROR("`%s` expects an array, got %s", function, val_type_name(items));
+    }
+}
+
+// clamps an index of a slice to the array or string, negative indices counting from the end
+static int64_t array_slice_index(val_t *i, int64_t len, const char *function) {
+    if (val_type(i) != VAL_INT) {
+        RUNTIME_ERROR("`%s` expects an integer index, got %s", function, val_type_name(i));
+    }
+
+    int64_t index = val_i64(i);
+
+    if (index < 0) {
+        return index + len < 0 ? 0 : index + len;
+    }
+
+    return index > len ? len : index;
+}
+
+val_t *val_array_len(val_t *items) {
+    expect_array(items, "val_array_len");
+
+    val_t *result = new_int_val(items->array.len);
+
+    free_val_if_ok(items);
+
+    return result;
+}
+
+val_t *val_array_pop(val_t *items) {
+    expect_array(items, "val_array_pop");
+
+    val_t *result = IMM_UNDEFINED;
+
+    if (items->array.len > 0) {
+        result = items->array.data[items->array.len - 1];
+        release_val(result);
+
+        array_truncate(&items->array, items->array.len - 1);
+    }
+
+    free_val_if_ok(items);
+
+    return result;
+}
+
+val_t *val_array_shift(val_t *items) {
+    expect_array(items, "val_array_shift");
+
+    val_t *result = IMM_UNDEFINED;
+
+    if (items->array.len > 0) {
+        result = items->array.data[0];
+        release_val(result);
+
+        memmove(items->array.data, items->array.data + 1, (items->array.len - 1) * sizeof(void *));
+        array_truncate(&items->array, items->array.len - 1);
+    }
+
+    free_val_if_ok(items);
+
+    return result;
+}
+
+void *val_array_unshift(val_t *items, val_t *v) {
+    expect_array(items, "val_array_unshift");
+
+    array_push(&items->array, NULL);
+    memmove(items->array.data + 1, items->array.data, (items->array.len - 1) * sizeof(void *));
+    items->array.data[0] = v;
+
+    link_val(v);
+    free_val_if_ok(items);
+
+    return IMM_UNDEFINED;
+}
+
+// a new array with the items from the index to the other, both clamped already
+static val_t *array_slice(val_t *items, int64_t from, int64_t to) {
+    val_t *result = new_array_val(to > from ? to - from : 0);
+
+    for (int64_t i = from; i < to; i++) {
+        val_array_push(result, items->array.data[i]);
+    }
+
+    return result;
+}
+
+val_t *val_array_slice(val_t *items, val_t *start, val_t *end) {
+    expect_array(items, "val_array_slice");
+
+    int64_t len = items->array.len;
+    int64_t from = array_slice_index(start, len, "val_array_slice");
+    int64_t to = val_type(end) == VAL_UNDEFINED ? len : array_slice_index(end, len, "val_array_slice");
+
+    val_t *result = array_slice(items, from, to);
+
+    free_val_if_ok(items);
+
+    return result;
+}
+
+val_t *val_str_slice(val_t *s, val_t *start, val_t *end) {
+    if (val_type(s) != VAL_STR) {
+        RUNTIME_ERROR("`val_str_slice` expects a string, got %s", val_type_name(s));
+    }
+
+    // indices count characters, not bytes
+    int64_t len = utf8_count(s->str.data, s->str.len);
+    int64_t from = array_slice_index(start, len, "val_str_slice");
+    int64_t to = val_type(end) == VAL_UNDEFINED ? len : array_slice_index(end, len, "val_str_slice");
+
+    uint64_t from_byte = utf8_prefix_len(s->str.data, s->str.len, from);
+    uint64_t to_byte = to > from ? utf8_prefix_len(s->str.data, s->str.len, to) : from_byte;
+
+    val_t *result = new_str_val("");
+    str_append(&result->str, s->str.data + from_byte, to_byte - from_byte);
+
+    free_val_if_ok(s);
+
+    return result;
+}
+
+// `v[start:end]`, either bound left out for the start or the end of the array or string
+val_t *val_slice(val_t *v, val_t *start, val_t *end) {
+    if (val_type(start) == VAL_UNDEFINED) {
+        start = new_int_val(0);
+    }
+
+    if (val_type(v) == VAL_STR) {
+        return val_str_slice(v, start, end);
+    }
+
+    if (val_type(v) != VAL_ARRAY) {
+        RUNTIME_ERROR("expected an array or a string, got %s", val_type_name(v));
+    }
+
+    return val_array_slice(v, start, end);
+}
+
+val_t *val_array_concat(val_t *items, val_t *others) {
+    expect_array(items, "val_array_concat");
+    expect_array(others, "val_array_concat");
+
+    val_t *result = new_array_val(items->array.len + others->array.len);
+
+    for (size_t i = 0; i < items->array.len; i++) {
+        val_array_push(result, items->array.data[i]);
+    }
+
+    for (size_t i = 0; i < others->array.len; i++) {
+        val_array_push(result, others->array.data[i]);
+    }
+
+    free_val_if_ok(items);
+
+    if (others != items) {
+        free_val_if_ok(others);
+    }
+
+    return result;
+}
+
+val_t *val_array_join(val_t *items, val_t *separator) {
+    expect_array(items, "val_array_join");
+
+    if (val_type(separator) != VAL_STR) {
+        RUNTIME_ERROR("`val_array_join` expects a string separator, got %s", val_type_name(separator));
+    }
+
+    val_t *result = new_str_val("");
+
+    for (size_t i = 0; i < items->array.len; i++) {
+        val_t *v = items->array.data[i];
+
+        if (i > 0) {
+            str_append(&result->str, separator->str.data, separator->str.len);
+        }
+
+        // undefined and null are joined as empty strings, like in JavaScript
+        if (val_type(v) != VAL_UNDEFINED && val_type(v) != VAL_NULL) {
+            str_append_val(&result->str, v, false, false);
+        }
+    }
+
+    free_val_if_ok(items);
+    free_val_if_ok(separator);
+
+    return result;
+}
+
+// arrays and objects are only equal to themselves, other values are compared like `===`
+static bool val_strict_equals(val_t *v1, val_t *v2) {
+    if (v1 == v2) {
+        return true;
+    }
+
+    bool is_number1 = val_type(v1) == VAL_INT || val_type(v1) == VAL_FLOAT;
+    bool is_number2 = val_type(v2) == VAL_INT || val_type(v2) == VAL_FLOAT;
+
+    if (is_number1 && is_number2) {
+        return val_compare(v1, v2) == 0;
+    }
+
+    if (val_type(v1) != val_type(v2)) {
+        return false;
+    }
+
+    if (val_type(v1) == VAL_STR || val_type(v1) == VAL_BOOL) {
+        return val_compare(v1, v2) == 0;
+    }
+
+    return false;
+}
+
+val_t *val_array_index_of(val_t *items, val_t *v) {
+    expect_array(items, "val_array_index_of");
+
+    int64_t index = -1;
+
+    for (size_t i = 0; i < items->array.len; i++) {
+        if (val_strict_equals(items->array.data[i], v)) {
+            index = i;
+            break;
+        }
+    }
+
+    free_val_if_ok(items);
+    free_val_if_ok(v);
+
+    return new_int_val(index);
+}
+
+static int val_sort_compare(const void *a, const void *b) {
+    return val_compare(*(val_t **) a, *(val_t **) b);
+}
+
+// sorts in place in the order of `<`, which requires values of comparable types
+void *val_array_sort(val_t *items) {
+    expect_array(items, "val_array_sort");
+
+    qsort(items->array.data, items->array.len, sizeof(void *), val_sort_compare);
+
+    free_val_if_ok(items);
+
+    return IMM_UNDEFINED;
+}
+
+void *val_array_reverse(val_t *items) {
+    expect_array(items, "val_array_reverse");
+
+    for (size_t i = 0, j = items->array.len; i + 1 < j; i++, j--) {
+        void *v = items->array.data[i];
+        items->array.data[i] = items->array.data[j - 1];
+        items->array.data[j - 1] = v;
+    }
+
+    free_val_if_ok(items);
+
+    return IMM_UNDEFINED;
 }
 
 void *val_object_set(val_t *kv, char *k, val_t *v) {
-    if (kv->type != VAL_OBJECT) {
-        assert(false);
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
     }
 
+    k = intern_key(k);
+
     val_t *old = object_get(&kv->object, k);
     if (old != NULL) {
         unlink_val(old);
@@ -382,56 +885,170 @@ void *val_object_set(val_t *kv, char *k, val_t *v) {
 
     link_val(v);
 
-    return NULL;
+    return IMM_UNDEFINED;
 }
 
 void *val_object_get(val_t *kv, char *k) {
-    if (kv->type != VAL_OBJECT) {
-        assert(false);
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
+    }
+
+    return object_get(&kv->object, find_key(k));
+}
+
+// val_object_get for the generated code, skipping the key lookup when the object
+// has the shape the site saw last
+void *val_object_get_cached(val_t *kv, property_key_t *key, inline_cache_t *cache) {
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
+    }
+
+    object_t *object = &kv->object;
+
+    if (object->shape != cache->shape) {
+        int64_t index = shape_index(object->shape, property_key(key));
+
+        if (index < 0) {
+            return IMM_UNDEFINED;
+        }
+
+        cache->shape = object->shape;
+        cache->index = index;
+        cache->transition = NULL;
+    }
+
+    return object->vals[cache->index];
+}
+
+// val_object_set for the generated code, skipping the key lookup and the shape
+// transition when the object has the shape the site saw last
+void *val_object_set_cached(val_t *kv, property_key_t *key, val_t *v, inline_cache_t *cache) {
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
     }
 
-    return object_get(&kv->object, k);
+    object_t *object = &kv->object;
+
+    if (object->shape != cache->shape) {
+        char *k = property_key(key);
+        int64_t index = shape_index(object->shape, k);
+
+        cache->shape = object->shape;
+
+        if (index >= 0) {
+            cache->index = index;
+            cache->transition = NULL;
+        } else {
+            cache->index = object->len;
+            cache->transition = shape_transition(object->shape, k);
+        }
+    }
+
+    if (cache->transition == NULL) {
+        unlink_val(object->vals[cache->index]);
+        object->vals[cache->index] = v;
+    } else {
+        object_push(object, v, cache->transition);
+    }
+
+    link_val(v);
+
+    return IMM_UNDEFINED;
+}
+
+// the text of the key the value is in objects, NULL if it cannot be a key
+static const char *object_key_name(val_t *k) {
+    if (val_type(k) == VAL_STR) {
+        return k->str.data;
+    }
+
+    if (val_type(k) == VAL_SYMBOL) {
+        return symbol_key(k);
+    }
+
+    return NULL;
 }
 
 void *val_set(val_t *kv, val_t *k, val_t *v) {
-    if (kv->type == VAL_ARRAY) {
+    if (val_type(kv) == VAL_ARRAY) {
         return val_array_insert(kv, k, v);
     }
 
-    if (kv->type != VAL_OBJECT) {
-        assert(false);
+    if (val_type(kv) == VAL_F64_ARRAY || val_type(kv) == VAL_I64_ARRAY) {
+        typed_array_set(kv, k, v);
+
+        return IMM_UNDEFINED;
     }
 
-    if (k->type != VAL_STR) {
-        assert(false);
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
     }
 
-    val_t *old = object_get(&kv->object, k->str.data);
+    const char *name = object_key_name(k);
+
+    if (name == NULL) {
+        RUNTIME_ERROR("object key must be a string or a symbol, got %s", val_type_name(k));
+    }
+
+    char *key = intern_key(name);
+
+    val_t *old = object_get(&kv->object, key);
     if (old != NULL) {
         unlink_val(old);
     }
 
-    object_set(&kv->object, k->str.data, v);
+    object_set(&kv->object, key, v);
 
     link_val(v);
 
-    return NULL;
+    return IMM_UNDEFINED;
 }
 
 void *val_get(val_t *kv, val_t *k) {
-    if (kv->type == VAL_ARRAY) {
+    // `items[start..end]` is a slice, its bounds clamped to the array
+    if (val_type(kv) == VAL_ARRAY && val_type(k) == VAL_RANGE) {
+        int64_t len = kv->array.len;
+        int64_t from = k->range.start < 0 ? 0 : (k->range.start > len ? len : k->range.start);
+        int64_t to = k->range.end < 0 ? 0 : (k->range.end > len ? len : k->range.end);
+
+        return array_slice(kv, from, to);
+    }
+
+    if (val_type(kv) == VAL_ARRAY) {
         return val_array_get(kv, k);
     }
 
-    if (kv->type != VAL_OBJECT) {
-        assert(false);
+    if (val_type(kv) == VAL_F64_ARRAY || val_type(kv) == VAL_I64_ARRAY) {
+        return typed_array_get(kv, k);
+    }
+
+    if (val_type(kv) != VAL_OBJECT) {
+        RUNTIME_ERROR("expected an object, got %s", val_type_name(kv));
+    }
+
+    val_t *fn = val_operator(kv, "__index");
+    const char *name = object_key_name(k);
+
+    // the keys the object lacks, and the values that are not keys, are looked up
+    // with its `__index` function
+    if (fn != NULL && (name == NULL || object_get(&kv->object, find_key(name)) == IMM_UNDEFINED)) {
+        // the object and the key stay with the caller
+        link_val(kv);
+        link_val(k);
+
+        val_t *result = val_call_operator(fn, kv, k);
+
+        release_val(kv);
+        release_val(k);
+
+        return result;
     }
 
-    if (k->type != VAL_STR) {
-        assert(false);
+    if (name == NULL) {
+        RUNTIME_ERROR("object key must be a string or a symbol, got %s", val_type_name(k));
     }
 
-    return object_get(&kv->object, k->str.data);
+    return object_get(&kv->object, find_key(name));
 }
 
 #endif
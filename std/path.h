@@ -0,0 +1,151 @@
+#ifndef MINI_STD_PATH_H
+#define MINI_STD_PATH_H
+
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "gc.h"
+#include "val.h"
+
+// Paths are handled like the POSIX functions of the `path` module of Node.js,
+// without resolving `.` and `..` segments.
+
+static void expect_path_str(val_t *path, const char *function) {
+    if (val_type(path) != VAL_STR) {
+        RUNTIME_ERROR("`%s` expects a path string, got %s", function, val_type_name(path));
+    }
+}
+
+// the length of the path without its trailing slashes, keeping a root slash
+static uint64_t path_trimmed_len(str_t *path) {
+    uint64_t len = path->len;
+
+    while (len > 1 && path->data[len - 1] == '/') {
+        len--;
+    }
+
+    return len;
+}
+
+// the index the last segment starts at
+static uint64_t path_basename_start(str_t *path, uint64_t len) {
+    uint64_t start = len;
+
+    while (start > 0 && path->data[start - 1] != '/') {
+        start--;
+    }
+
+    return start;
+}
+
+static val_t *new_str_val_with_len(const char *data, uint64_t len) {
+    val_t *result = new_str_val("");
+    str_append(&result->str, data, len);
+
+    return result;
+}
+
+// joins the non-empty parts with single slashes
+val_t *path_join(val_t *parts) {
+    if (val_type(parts) != VAL_ARRAY) {
+        RUNTIME_ERROR("`path_join` expects path strings, got %s", val_type_name(parts));
+    }
+
+    val_t *result = new_str_val("");
+
+    for (size_t i = 0; i < parts->array.len; i++) {
+        val_t *part = parts->array.data[i];
+        expect_path_str(part, "path_join");
+
+        if (part->str.len == 0) {
+            continue;
+        }
+
+        if (result->str.len > 0 && result->str.data[result->str.len - 1] != '/') {
+            str_append(&result->str, "/", 1);
+        }
+
+        for (uint64_t j = 0; j < part->str.len; j++) {
+            char c = part->str.data[j];
+
+            if (c != '/' || result->str.len == 0 || result->str.data[result->str.len - 1] != '/') {
+                str_append(&result->str, &c, 1);
+            }
+        }
+    }
+
+    if (result->str.len == 0) {
+        str_append(&result->str, ".", 1);
+    }
+
+    free_val_if_ok(parts);
+
+    return result;
+}
+
+val_t *path_dirname(val_t *path) {
+    expect_path_str(path, "path_dirname");
+
+    uint64_t len = path_trimmed_len(&path->str);
+    uint64_t start = path_basename_start(&path->str, len);
+
+    val_t *result = NULL;
+
+    if (start == 0) {
+        result = new_str_val(len > 0 && path->str.data[0] == '/' ? "/" : ".");
+    } else {
+        while (start > 1 && path->str.data[start - 1] == '/') {
+            start--;
+        }
+
+        result = new_str_val_with_len(path->str.data, start);
+    }
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+val_t *path_basename(val_t *path) {
+    expect_path_str(path, "path_basename");
+
+    uint64_t len = path_trimmed_len(&path->str);
+    uint64_t start = path_basename_start(&path->str, len);
+
+    val_t *result = new_str_val_with_len(path->str.data + start, len - start);
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+// the extension of the last segment from its last dot, empty for dotfiles
+val_t *path_ext(val_t *path) {
+    expect_path_str(path, "path_ext");
+
+    uint64_t len = path_trimmed_len(&path->str);
+    uint64_t start = path_basename_start(&path->str, len);
+    uint64_t dot = len;
+
+    while (dot > start && path->str.data[dot - 1] != '.') {
+        dot--;
+    }
+
+    val_t *result = NULL;
+
+    if (dot > start + 1) {
+        result = new_str_val_with_len(path->str.data + dot - 1, len - dot + 1);
+    } else {
+        result = new_str_val("");
+    }
+
+    free_val_if_ok(path);
+
+    return result;
+}
+
+#endif
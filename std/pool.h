@@ -0,0 +1,87 @@
+#ifndef MINI_STD_POOL_H
+#define MINI_STD_POOL_H
+
+#include <stdint.h>
+#include <stdlib.h>
+
+#include "defs.h"
+#include "gc.h"
+#include "val.h"
+
+// The array and object literals made only of constants are built once per thread
+// into a slot of the program, and every evaluation of the literal copies them.
+static THREAD_LOCAL val_t ***pooled_slots = NULL;
+static THREAD_LOCAL size_t pooled_slot_count = 0;
+static THREAD_LOCAL size_t pooled_slot_capacity = 0;
+
+// copies the array or object with the arrays and objects in it, sharing the other
+// values, which cannot change
+static val_t *copy_val(val_t *v) {
+    if (!val_is_heap(v) || (v->type != VAL_ARRAY && v->type != VAL_OBJECT)) {
+        return v;
+    }
+
+    val_t *result = new_val(v->type);
+
+    if (v->type == VAL_ARRAY) {
+        new_array(&result->array, v->array.len);
+
+        for (size_t i = 0; i < v->array.len; i++) {
+            val_t *item = copy_val(v->array.data[i]);
+
+            array_push(&result->array, item);
+            link_val(item);
+        }
+    } else {
+        result->object.capacity = v->object.capacity;
+        result->object.len = v->object.len;
+        result->object.shape = v->object.shape;
+        result->object.vals = malloc(v->object.capacity * sizeof(void *));
+
+        for (size_t i = 0; i < v->object.len; i++) {
+            val_t *item = copy_val(v->object.vals[i]);
+
+            result->object.vals[i] = item;
+            link_val(item);
+        }
+    }
+
+    return result;
+}
+
+// a copy of the literal in the slot, undefined until the thread built it
+val_t *val_pooled(val_t **slot) {
+    return *slot == NULL ? NULL : copy_val(*slot);
+}
+
+// keeps the literal the thread built in the slot, returning a copy of it
+val_t *val_pool(val_t **slot, val_t *v) {
+    if (pooled_slot_count == pooled_slot_capacity) {
+        pooled_slot_capacity = pooled_slot_capacity > 0 ? pooled_slot_capacity * 2 : 8;
+        pooled_slots = realloc(pooled_slots, pooled_slot_capacity * sizeof(val_t **));
+    }
+
+    pooled_slots[pooled_slot_count++] = slot;
+
+    link_val(v);
+    *slot = v;
+
+    return copy_val(v);
+}
+
+// drops the literals of the thread, once its program or function finished
+void *release_pooled_vals() {
+    for (size_t i = 0; i < pooled_slot_count; i++) {
+        unlink_val(*pooled_slots[i]);
+        *pooled_slots[i] = NULL;
+    }
+
+    free(pooled_slots);
+    pooled_slots = NULL;
+    pooled_slot_count = 0;
+    pooled_slot_capacity = 0;
+
+    return NULL;
+}
+
+#endif
@@ -0,0 +1,133 @@
+#ifndef MINI_STD_PROCESS_H
+#define MINI_STD_PROCESS_H
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "gc.h"
+#include "val.h"
+#include "ops.h"
+
+#define PROCESS_READ_LEN 4096
+
+// the exit status of a child that could not run the command, like in shells
+#define EXIT_COMMAND_NOT_FOUND 127
+
+// the command followed by the strings of the array, ending with NULL like execvp expects
+static char **process_argv(val_t *cmd, val_t *args, const char *function) {
+    if (val_type(cmd) != VAL_STR) {
+        RUNTIME_ERROR("`%s` expects a command string, got %s", function, val_type_name(cmd));
+    }
+
+    if (val_type(args) != VAL_ARRAY) {
+        RUNTIME_ERROR("`%s` expects an array of arguments, got %s", function, val_type_name(args));
+    }
+
+    char **argv = malloc((args->array.len + 2) * sizeof(char *));
+    argv[0] = cmd->str.data;
+
+    for (size_t i = 0; i < args->array.len; i++) {
+        val_t *arg = args->array.data[i];
+
+        if (val_type(arg) != VAL_STR) {
+            RUNTIME_ERROR("`%s` expects string arguments, got %s", function, val_type_name(arg));
+        }
+
+        argv[i + 1] = arg->str.data;
+    }
+
+    argv[args->array.len + 1] = NULL;
+
+    return argv;
+}
+
+// runs the command until it exits, returning its exit status and what it wrote to stdout
+val_t *exec(val_t *cmd, val_t *args) {
+    char **argv = process_argv(cmd, args, "exec");
+
+    int fds[2];
+    if (pipe(fds) != 0) {
+        RUNTIME_ERROR("`exec` could not create a pipe for `%s`", argv[0]);
+    }
+
+    fflush(stdout);
+
+    pid_t pid = fork();
+    if (pid < 0) {
+        RUNTIME_ERROR("`exec` could not start `%s`", argv[0]);
+    }
+
+    if (pid == 0) {
+        close(fds[0]);
+        dup2(fds[1], STDOUT_FILENO);
+        close(fds[1]);
+
+        execvp(argv[0], argv);
+        _exit(EXIT_COMMAND_NOT_FOUND);
+    }
+
+    close(fds[1]);
+
+    val_t *output = new_str_val("");
+    char buf[PROCESS_READ_LEN];
+    ssize_t len;
+
+    while ((len = read(fds[0], buf, PROCESS_READ_LEN)) > 0) {
+        str_append(&output->str, buf, len);
+    }
+
+    close(fds[0]);
+
+    int status = 0;
+    waitpid(pid, &status, 0);
+
+    // killed children report the signal like shells do
+    int64_t code = WIFEXITED(status) ? WEXITSTATUS(status) : 128 + WTERMSIG(status);
+
+    val_t *result = new_object_val();
+    val_object_set(result, "code", new_int_val(code));
+    val_object_set(result, "output", output);
+
+    free(argv);
+    free_val_if_ok(cmd);
+    free_val_if_ok(args);
+
+    return result;
+}
+
+// starts the command without waiting for it. The command runs in a grandchild,
+// which is adopted by init once the child exits, so it never has to be waited for.
+void *spawn(val_t *cmd, val_t *args) {
+    char **argv = process_argv(cmd, args, "spawn");
+
+    fflush(stdout);
+
+    pid_t pid = fork();
+    if (pid < 0) {
+        RUNTIME_ERROR("`spawn` could not start `%s`", argv[0]);
+    }
+
+    if (pid == 0) {
+        if (fork() == 0) {
+            execvp(argv[0], argv);
+        }
+
+        _exit(EXIT_COMMAND_NOT_FOUND);
+    }
+
+    waitpid(pid, NULL, 0);
+
+    free(argv);
+    free_val_if_ok(cmd);
+    free_val_if_ok(args);
+
+    return IMM_UNDEFINED;
+}
+
+#endif
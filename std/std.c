@@ -18,6 +18,23 @@
 #endif
 
 #include "defs.h"
+#include "error.h"
 #include "val.h"
+#include "text.h"
 #include "ops.h"
 #include "echo.h"
+#include "validate.h"
+#include "check.h"
+#include "mem.h"
+#include "builder.h"
+#include "buffer.h"
+#include "typed.h"
+#include "process.h"
+#include "fs.h"
+#include "path.h"
+#include "format.h"
+#include "timers.h"
+#include "harness.h"
+#include "pool.h"
+#include "threads.h"
+#include "trace.h"
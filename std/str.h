@@ -13,6 +13,7 @@ static void new_str(str_t *result, char *s) {
     memcpy(data, s, len + 1);
 
     result->len = len;
+    result->capacity = len;
     result->data = data;
 }
 
@@ -22,7 +23,58 @@ static void str_combine(str_t *result, str_t *s1, str_t *s2) {
     memcpy(data + s1->len, s2->data, s2->len + 1);
 
     result->len = s1->len + s2->len;
+    result->capacity = result->len;
     result->data = data;
 }
 
+// grows the buffer geometrically, so that appending n times takes linear time
+static void str_append(str_t *s, const char *data, uint64_t len) {
+    if (s->len + len > s->capacity) {
+        s->capacity = s->capacity * 2 > s->len + len ? s->capacity * 2 : s->len + len;
+        s->data = realloc(s->data, s->capacity + 1);
+    }
+
+    memcpy(s->data + s->len, data, len);
+    s->len += len;
+    s->data[s->len] = 0;
+}
+
+static uint64_t utf8_char_len(const char *data) {
+    uint8_t c = (uint8_t) data[0];
+
+    if (c >= 0xF0) {
+        return 4;
+    } else if (c >= 0xE0) {
+        return 3;
+    } else if (c >= 0xC0) {
+        return 2;
+    }
+
+    return 1;
+}
+
+static uint64_t utf8_count(const char *data, uint64_t len) {
+    uint64_t count = 0;
+
+    for (uint64_t i = 0; i < len; i++) {
+        if (((uint8_t) data[i] & 0xC0) != 0x80) {
+            count++;
+        }
+    }
+
+    return count;
+}
+
+// the number of bytes the first `count` characters take
+static uint64_t utf8_prefix_len(const char *data, uint64_t len, uint64_t count) {
+    uint64_t i = 0;
+
+    while (i < len && count > 0) {
+        i += utf8_char_len(data + i);
+        count--;
+    }
+
+    return i < len ? i : len;
+}
+
 #endif
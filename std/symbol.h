@@ -0,0 +1,60 @@
+#ifndef MINI_STD_SYMBOL_H
+#define MINI_STD_SYMBOL_H
+
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "object.h"
+#include "gc.h"
+#include "val.h"
+
+// Symbols hold their names interned like object keys, so that two symbols are
+// equal when their names are the same pointer.
+
+static val_t *new_symbol_val(char *interned) {
+    val_t *result = new_val(VAL_SYMBOL);
+    result->symbol.name = interned;
+    result->symbol.key = NULL;
+
+    DEBUG("new symbol: %s, %p", interned, result);
+
+    return result;
+}
+
+// `Symbol(name)` of a name known at runtime
+val_t *val_symbol(val_t *name) {
+    if (val_type(name) != VAL_STR) {
+        RUNTIME_ERROR("`Symbol` expects a string name, got %s", val_type_name(name));
+    }
+
+    val_t *result = new_symbol_val(intern_key(name->str.data));
+
+    free_val_if_ok(name);
+
+    return result;
+}
+
+// `Symbol("name")`, whose name is interned once per thread
+val_t *val_symbol_cached(property_key_t *name) {
+    return new_symbol_val(property_key(name));
+}
+
+// the interned key of the symbol in objects, which reads `[Symbol(name)]`
+static char *symbol_key(val_t *v) {
+    if (v->symbol.key == NULL) {
+        str_t key;
+        new_str(&key, "[Symbol(");
+        str_append(&key, v->symbol.name, strlen(v->symbol.name));
+        str_append(&key, ")]", 2);
+
+        v->symbol.key = intern_key(key.data);
+
+        free_str(&key);
+    }
+
+    return v->symbol.key;
+}
+
+#endif
@@ -0,0 +1,214 @@
+#ifndef MINI_STD_TEXT_H
+#define MINI_STD_TEXT_H
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "val.h"
+
+// The text of values, which `echo`, `format`, `val_array_join` and the `+` of
+// strings share. Strings are taken as they are, unless they are in an array or
+// object, where they are quoted. Arrays read `[1, 2]` and objects `{ a: 1 }`.
+
+// enough for the six decimals of the largest doubles
+#define MAX_TEXT_NUMBER_LEN 320
+
+#define TEXT_COLOR_DIM "\x1B[2m"
+#define TEXT_COLOR_BOLD "\x1B[1m"
+#define TEXT_COLOR_YELLOW "\x1B[0;33m"
+#define TEXT_COLOR_GREEN "\x1B[0;32m"
+#define TEXT_COLOR_CYAN "\x1B[0;36m"
+#define TEXT_COLOR_RED "\x1B[0;31m"
+#define TEXT_COLOR_RESET "\x1B[0m"
+
+// appends the data in the color, the colors are only used by `echo`
+static void text_append(str_t *text, const char *color, const char *data, uint64_t len, bool is_colored) {
+    if (is_colored) {
+        str_append(text, color, strlen(color));
+    }
+
+    str_append(text, data, len);
+
+    if (is_colored) {
+        str_append(text, TEXT_COLOR_RESET, strlen(TEXT_COLOR_RESET));
+    }
+}
+
+// floats have up to six decimals, without the trailing zeros
+static uint64_t text_float(char *buf, double f64) {
+    int len = snprintf(buf, MAX_TEXT_NUMBER_LEN, "%.6f", f64);
+
+    // inf and nan have no decimals
+    if (strchr(buf, '.') == NULL) {
+        return len;
+    }
+
+    while (buf[len - 1] == '0') {
+        len--;
+    }
+
+    if (buf[len - 1] == '.') {
+        len--;
+    }
+
+    buf[len] = 0;
+
+    return len;
+}
+
+static void str_append_val(str_t *text, val_t *v, bool is_nested, bool is_colored) {
+    char buf[MAX_TEXT_NUMBER_LEN];
+
+    switch (val_type(v)) {
+        case VAL_UNDEFINED:
+            text_append(text, TEXT_COLOR_DIM, "undefined", 9, is_colored);
+            break;
+        case VAL_NULL:
+            text_append(text, TEXT_COLOR_BOLD, "null", 4, is_colored);
+            break;
+        case VAL_BOOL:
+            text_append(text, TEXT_COLOR_YELLOW, val_b(v) ? "true" : "false", val_b(v) ? 4 : 5, is_colored);
+            break;
+        case VAL_INT:
+            text_append(text, TEXT_COLOR_YELLOW, buf, snprintf(buf, MAX_TEXT_NUMBER_LEN, "%lld", (long long) val_i64(v)), is_colored);
+            break;
+        case VAL_FLOAT:
+            text_append(text, TEXT_COLOR_YELLOW, buf, text_float(buf, v->f64), is_colored);
+            break;
+        case VAL_STR:
+        case VAL_BUILDER:
+            if (!is_nested) {
+                str_append(text, v->str.data, v->str.len);
+                break;
+            }
+
+            if (is_colored) {
+                str_append(text, TEXT_COLOR_GREEN, strlen(TEXT_COLOR_GREEN));
+            }
+
+            str_append(text, "'", 1);
+            str_append(text, v->str.data, v->str.len);
+            str_append(text, "'", 1);
+
+            if (is_colored) {
+                str_append(text, TEXT_COLOR_RESET, strlen(TEXT_COLOR_RESET));
+            }
+            break;
+        case VAL_ARRAY:
+            str_append(text, "[", 1);
+
+            for (uint64_t i = 0; i < v->array.len; i++) {
+                if (i > 0) {
+                    str_append(text, ", ", 2);
+                }
+
+                str_append_val(text, v->array.data[i], true, is_colored);
+            }
+
+            str_append(text, "]", 1);
+            break;
+        case VAL_OBJECT:
+            if (v->object.len == 0) {
+                str_append(text, "{}", 2);
+                break;
+            }
+
+            str_append(text, "{ ", 2);
+
+            for (uint64_t i = 0; i < v->object.len; i++) {
+                char *k = v->object.shape->keys[i];
+
+                if (i > 0) {
+                    str_append(text, ", ", 2);
+                }
+
+                str_append(text, k, strlen(k));
+                str_append(text, ": ", 2);
+                str_append_val(text, v->object.vals[i], true, is_colored);
+            }
+
+            str_append(text, " }", 2);
+            break;
+        case VAL_SYMBOL:
+            if (is_colored) {
+                str_append(text, TEXT_COLOR_GREEN, strlen(TEXT_COLOR_GREEN));
+            }
+
+            str_append(text, "Symbol(", 7);
+            str_append(text, v->symbol.name, strlen(v->symbol.name));
+            str_append(text, ")", 1);
+
+            if (is_colored) {
+                str_append(text, TEXT_COLOR_RESET, strlen(TEXT_COLOR_RESET));
+            }
+            break;
+        case VAL_RANGE: {
+            int len = snprintf(buf, MAX_TEXT_NUMBER_LEN, "%lld..%lld", (long long) v->range.start, (long long) v->range.end);
+
+            text_append(text, TEXT_COLOR_YELLOW, buf, len, is_colored);
+            break;
+        }
+        case VAL_F64_ARRAY:
+        case VAL_I64_ARRAY: {
+            const char *name = val_type_name(v);
+            str_append(text, name, strlen(name));
+            str_append(text, " [", 2);
+
+            for (uint64_t i = 0; i < v->typed_array.len; i++) {
+                if (i > 0) {
+                    str_append(text, ", ", 2);
+                }
+
+                int len = v->type == VAL_F64_ARRAY
+                    ? text_float(buf, v->typed_array.f64[i])
+                    : snprintf(buf, MAX_TEXT_NUMBER_LEN, "%lld", (long long) v->typed_array.i64[i]);
+
+                text_append(text, TEXT_COLOR_YELLOW, buf, len, is_colored);
+            }
+
+            str_append(text, "]", 1);
+            break;
+        }
+        case VAL_BUFFER:
+            str_append(text, "<Buffer", 7);
+
+            for (uint64_t i = 0; i < v->buffer.len; i++) {
+                str_append(text, buf, snprintf(buf, MAX_TEXT_NUMBER_LEN, " %02x", v->buffer.data[i]));
+            }
+
+            str_append(text, ">", 1);
+            break;
+        default: {
+            int len = snprintf(buf, MAX_TEXT_NUMBER_LEN, "[%s]", val_type_name(v));
+
+            text_append(text, TEXT_COLOR_CYAN, buf, len, is_colored);
+            break;
+        }
+    }
+}
+
+// the text of the value as a string
+val_t *val_to_str(val_t *v) {
+    if (val_type(v) == VAL_STR) {
+        return v;
+    }
+
+    str_t text;
+    new_str(&text, "");
+    str_append_val(&text, v, false, false);
+
+    val_t *result = new_val(VAL_STR);
+    result->str = text;
+
+    free_val_if_ok(v);
+
+    return result;
+}
+
+#endif
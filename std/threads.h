@@ -0,0 +1,386 @@
+#ifndef MINI_STD_THREADS_H
+#define MINI_STD_THREADS_H
+
+#include <pthread.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "defs.h"
+#include "error.h"
+#include "str.h"
+#include "gc.h"
+#include "val.h"
+#include "ops.h"
+#include "pool.h"
+
+// A value copied out of the heap of a thread, to be rebuilt in the heap of another.
+// Values are copied deeply, so arrays and objects must not contain themselves.
+typedef struct message_t {
+    // the word of immediates, which need no copy
+    val_t *word;
+    val_type_t type;
+    double f64;
+    range_t range;
+    uint64_t len;
+    // the bytes of strings, string builders, buffers and typed arrays, and the
+    // names of symbols
+    char *data;
+    // the items of arrays and objects, with the keys of objects
+    struct message_t **items;
+    char **keys;
+    void *fn;
+    struct channel_t *channel;
+    // the message sent after this one over a channel
+    struct message_t *next;
+} message_t;
+
+typedef struct thread_t {
+    // the handle and the thread itself, whichever is done last frees it
+    int32_t ref_count;
+    pthread_t id;
+    bool is_joined;
+    void *fn;
+    message_t *argument;
+    message_t *result;
+} thread_t;
+
+typedef struct channel_t {
+    // the values referring to it in every thread, and the messages carrying it
+    int32_t ref_count;
+    pthread_mutex_t lock;
+    pthread_cond_t ready;
+    message_t *first;
+    message_t *last;
+} channel_t;
+
+static message_t *new_message(val_t *v) {
+    message_t *m = calloc(1, sizeof(message_t));
+
+    if (!val_is_heap(v)) {
+        m->word = v;
+        return m;
+    }
+
+    m->type = v->type;
+
+    switch (v->type) {
+        case VAL_FLOAT:
+            m->f64 = v->f64;
+            break;
+        case VAL_STR:
+        case VAL_BUILDER:
+            m->len = v->str.len;
+            m->data = malloc(v->str.len + 1);
+            memcpy(m->data, v->str.data, v->str.len + 1);
+            break;
+        case VAL_BUFFER:
+            m->len = v->buffer.len;
+            m->data = malloc(v->buffer.len > 0 ? v->buffer.len : 1);
+            memcpy(m->data, v->buffer.data, v->buffer.len);
+            break;
+        case VAL_F64_ARRAY:
+        case VAL_I64_ARRAY:
+            m->len = v->typed_array.len;
+            m->data = malloc(v->typed_array.len > 0 ? v->typed_array.len * 8 : 1);
+            memcpy(m->data, v->typed_array.f64, v->typed_array.len * 8);
+            break;
+        case VAL_ARRAY:
+            m->len = v->array.len;
+            m->items = malloc(v->array.len * sizeof(message_t *));
+
+            for (size_t i = 0; i < v->array.len; i++) {
+                m->items[i] = new_message(v->array.data[i]);
+            }
+            break;
+        case VAL_OBJECT:
+            m->len = v->object.len;
+            m->items = malloc(v->object.len * sizeof(message_t *));
+            m->keys = malloc(v->object.len * sizeof(char *));
+
+            for (size_t i = 0; i < v->object.len; i++) {
+                m->items[i] = new_message(v->object.vals[i]);
+                m->keys[i] = strdup(v->object.shape->keys[i]);
+            }
+            break;
+        case VAL_FUNCTION:
+            m->fn = v->fn;
+            break;
+        case VAL_RANGE:
+            m->range = v->range;
+            break;
+        case VAL_SYMBOL:
+            m->data = strdup(v->symbol.name);
+            break;
+        case VAL_CHANNEL:
+            __atomic_add_fetch(&v->channel->ref_count, 1, __ATOMIC_RELAXED);
+            m->channel = v->channel;
+            break;
+        default:
+            RUNTIME_ERROR("values of type %s cannot be sent to another thread", val_type_name(v));
+    }
+
+    return m;
+}
+
+static void free_message(message_t *m) {
+    if (m == NULL) {
+        return;
+    }
+
+    for (size_t i = 0; m->items != NULL && i < m->len; i++) {
+        free_message(m->items[i]);
+
+        if (m->keys != NULL) {
+            free(m->keys[i]);
+        }
+    }
+
+    if (m->channel != NULL) {
+        release_channel(m->channel);
+    }
+
+    free(m->items);
+    free(m->keys);
+    free(m->data);
+    free(m);
+}
+
+// rebuilds the value in the heap of the current thread, freeing the message
+static val_t *message_to_val(message_t *m) {
+    val_t *result = m->word;
+
+    if (m->word == NULL) {
+        switch (m->type) {
+            case VAL_FLOAT:
+                result = new_float_val(m->f64);
+                break;
+            case VAL_STR:
+            case VAL_BUILDER:
+                result = new_val(m->type);
+                new_str(&result->str, "");
+                str_append(&result->str, m->data, m->len);
+                break;
+            case VAL_BUFFER:
+                result = new_val(VAL_BUFFER);
+                result->buffer.len = m->len;
+                result->buffer.data = (uint8_t *) m->data;
+                m->data = NULL;
+                break;
+            case VAL_F64_ARRAY:
+            case VAL_I64_ARRAY:
+                result = new_val(m->type);
+                result->typed_array.len = m->len;
+                result->typed_array.f64 = (double *) m->data;
+                m->data = NULL;
+                break;
+            case VAL_ARRAY:
+                result = new_array_val(m->len);
+
+                for (size_t i = 0; i < m->len; i++) {
+                    val_array_push(result, message_to_val(m->items[i]));
+                    m->items[i] = NULL;
+                }
+                break;
+            case VAL_OBJECT:
+                result = new_object_val();
+
+                for (size_t i = 0; i < m->len; i++) {
+                    val_object_set(result, m->keys[i], message_to_val(m->items[i]));
+                    m->items[i] = NULL;
+                }
+                break;
+            case VAL_FUNCTION:
+                result = new_function_val(m->fn);
+                break;
+            case VAL_RANGE:
+                result = new_range_val(m->range.start, m->range.end);
+                break;
+            case VAL_SYMBOL:
+                // the receiving thread interns the name in its own table
+                result = new_symbol_val(intern_key(m->data));
+                break;
+            case VAL_CHANNEL:
+                result = new_val(VAL_CHANNEL);
+                result->channel = m->channel;
+                m->channel = NULL;
+                break;
+            default:
+                break;
+        }
+    }
+
+    free_message(m);
+
+    return result;
+}
+
+static void release_thread(thread_t *thread) {
+    if (__atomic_sub_fetch(&thread->ref_count, 1, __ATOMIC_ACQ_REL) == 0) {
+        free_message(thread->argument);
+        free_message(thread->result);
+        free(thread);
+    }
+}
+
+static void release_channel(channel_t *channel) {
+    if (__atomic_sub_fetch(&channel->ref_count, 1, __ATOMIC_ACQ_REL) == 0) {
+        while (channel->first != NULL) {
+            message_t *m = channel->first;
+            channel->first = m->next;
+            free_message(m);
+        }
+
+        pthread_mutex_destroy(&channel->lock);
+        pthread_cond_destroy(&channel->ready);
+        free(channel);
+    }
+}
+
+static void *thread_main(void *data) {
+    thread_t *thread = data;
+
+    val_t *argument = message_to_val(thread->argument);
+    thread->argument = NULL;
+
+    // kept alive until the result is copied, which may be the argument itself
+    link_val(argument);
+
+    val_t *result = ((val_t *(*)(val_t *)) thread->fn)(argument);
+    thread->result = new_message(result);
+
+    free_val_if_ok(result);
+    unlink_val(argument);
+    release_pooled_vals();
+
+    // the keys and shapes of values the thread leaked stay with them
+    if (live_vals == NULL) {
+        free_object_tables();
+    }
+
+    release_thread(thread);
+
+    return NULL;
+}
+
+// runs the function with a copy of the argument in a new thread, which has a heap
+// of its own
+val_t *thread_spawn(val_t *callback, val_t *argument) {
+    if (val_type(callback) != VAL_FUNCTION) {
+        RUNTIME_ERROR("`thread_spawn` expects a function, got %s", val_type_name(callback));
+    }
+
+    thread_t *thread = calloc(1, sizeof(thread_t));
+    thread->ref_count = 2;
+    thread->fn = callback->fn;
+    thread->argument = new_message(argument);
+
+    if (pthread_create(&thread->id, NULL, thread_main, thread) != 0) {
+        RUNTIME_ERROR("`thread_spawn` could not start a thread");
+    }
+
+    val_t *result = new_val(VAL_THREAD);
+    result->thread = thread;
+
+    free_val_if_ok(callback);
+    free_val_if_ok(argument);
+
+    return result;
+}
+
+// waits for the thread to finish, returning a copy of what its function returned
+val_t *thread_join(val_t *handle) {
+    if (val_type(handle) != VAL_THREAD) {
+        RUNTIME_ERROR("`thread_join` expects a thread, got %s", val_type_name(handle));
+    }
+
+    thread_t *thread = handle->thread;
+
+    if (thread->is_joined) {
+        RUNTIME_ERROR("`thread_join`: the thread was already joined");
+    }
+
+    pthread_join(thread->id, NULL);
+    thread->is_joined = true;
+
+    val_t *result = message_to_val(thread->result);
+    thread->result = NULL;
+
+    free_val_if_ok(handle);
+
+    return result;
+}
+
+val_t *channel_new() {
+    channel_t *channel = calloc(1, sizeof(channel_t));
+    channel->ref_count = 1;
+    pthread_mutex_init(&channel->lock, NULL);
+    pthread_cond_init(&channel->ready, NULL);
+
+    val_t *result = new_val(VAL_CHANNEL);
+    result->channel = channel;
+
+    return result;
+}
+
+static channel_t *expect_channel(val_t *channel, const char *function) {
+    if (val_type(channel) != VAL_CHANNEL) {
+        RUNTIME_ERROR("`%s` expects a channel, got %s", function, val_type_name(channel));
+    }
+
+    return channel->channel;
+}
+
+// queues a copy of the value, without waiting for it to be received
+void *channel_send(val_t *channel, val_t *v) {
+    channel_t *c = expect_channel(channel, "channel_send");
+    message_t *m = new_message(v);
+
+    pthread_mutex_lock(&c->lock);
+
+    if (c->last != NULL) {
+        c->last->next = m;
+    } else {
+        c->first = m;
+    }
+
+    c->last = m;
+
+    pthread_cond_signal(&c->ready);
+    pthread_mutex_unlock(&c->lock);
+
+    free_val_if_ok(channel);
+    free_val_if_ok(v);
+
+    return IMM_UNDEFINED;
+}
+
+// waits until a value was sent over the channel, returning the first one queued
+val_t *channel_recv(val_t *channel) {
+    channel_t *c = expect_channel(channel, "channel_recv");
+
+    pthread_mutex_lock(&c->lock);
+
+    while (c->first == NULL) {
+        pthread_cond_wait(&c->ready, &c->lock);
+    }
+
+    message_t *m = c->first;
+    c->first = m->next;
+
+    if (c->first == NULL) {
+        c->last = NULL;
+    }
+
+    pthread_mutex_unlock(&c->lock);
+
+    m->next = NULL;
+    val_t *result = message_to_val(m);
+
+    free_val_if_ok(channel);
+
+    return result;
+}
+
+#endif
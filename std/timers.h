@@ -0,0 +1,118 @@
+#ifndef MINI_STD_TIMERS_H
+#define MINI_STD_TIMERS_H
+
+#include <stdint.h>
+#include <stdlib.h>
+#include <time.h>
+
+#include "defs.h"
+#include "error.h"
+#include "gc.h"
+#include "val.h"
+
+typedef struct {
+    int64_t id;
+    val_t *callback;
+    // milliseconds of the monotonic clock the callback is due at
+    int64_t due;
+} timeout_t;
+
+static THREAD_LOCAL timeout_t *timeouts = NULL;
+static THREAD_LOCAL size_t timeout_count = 0;
+static THREAD_LOCAL size_t timeout_capacity = 0;
+static THREAD_LOCAL int64_t last_timeout_id = 0;
+
+static int64_t monotonic_ms() {
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+
+    return (int64_t) now.tv_sec * 1000 + now.tv_nsec / 1000000;
+}
+
+static void sleep_for_ms(int64_t ms) {
+    if (ms <= 0) {
+        return;
+    }
+
+    struct timespec duration = { ms / 1000, (ms % 1000) * 1000000 };
+
+    while (nanosleep(&duration, &duration) != 0) {}
+}
+
+static int64_t expect_ms(val_t *ms, const char *function) {
+    if (val_type(ms) == VAL_INT) {
+        return val_i64(ms);
+    }
+
+    if (val_type(ms) == VAL_FLOAT) {
+        return (int64_t) ms->f64;
+    }
+
+    RUNTIME_ERROR("`%s` expects a number of milliseconds, got %s", function, val_type_name(ms));
+    return 0;
+}
+
+void *sleep_ms(val_t *ms) {
+    sleep_for_ms(expect_ms(ms, "sleep_ms"));
+
+    free_val_if_ok(ms);
+
+    return IMM_UNDEFINED;
+}
+
+// schedules the callback to be called once the program finished, after the given
+// milliseconds passed, returning the id of the timeout
+val_t *set_timeout(val_t *callback, val_t *ms) {
+    if (val_type(callback) != VAL_FUNCTION) {
+        RUNTIME_ERROR("`set_timeout` expects a function, got %s", val_type_name(callback));
+    }
+
+    int64_t delay = expect_ms(ms, "set_timeout");
+
+    if (timeout_count == timeout_capacity) {
+        timeout_capacity = timeout_capacity > 0 ? timeout_capacity * 2 : 8;
+        timeouts = realloc(timeouts, timeout_capacity * sizeof(timeout_t));
+    }
+
+    last_timeout_id++;
+
+    timeouts[timeout_count].id = last_timeout_id;
+    timeouts[timeout_count].callback = callback;
+    timeouts[timeout_count].due = monotonic_ms() + (delay > 0 ? delay : 0);
+    timeout_count++;
+
+    link_val(callback);
+    free_val_if_ok(ms);
+
+    return new_int_val(last_timeout_id);
+}
+
+// called when main returns, calls the callbacks in the order they are due, those
+// due at the same time in the order they were scheduled
+void *run_timers() {
+    while (timeout_count > 0) {
+        size_t next = 0;
+
+        for (size_t i = 1; i < timeout_count; i++) {
+            if (timeouts[i].due < timeouts[next].due) {
+                next = i;
+            }
+        }
+
+        timeout_t timeout = timeouts[next];
+
+        timeout_count--;
+        memmove(timeouts + next, timeouts + next + 1, (timeout_count - next) * sizeof(timeout_t));
+
+        sleep_for_ms(timeout.due - monotonic_ms());
+
+        val_t *result = ((val_t *(*)(val_t *)) timeout.callback->fn)(IMM_UNDEFINED);
+        free_val_if_ok(result);
+
+        unlink_val(timeout.callback);
+    }
+
+    return NULL;
+}
+
+#endif
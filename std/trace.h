@@ -0,0 +1,54 @@
+#ifndef MINI_STD_TRACE_H
+#define MINI_STD_TRACE_H
+
+#include <stdarg.h>
+#include <stdio.h>
+
+#include "defs.h"
+#include "error.h"
+
+// Called before every runtime call of programs compiled with `--trace-runtime`.
+// Each character of `kinds` describes an argument of the call, only the values,
+// marked with `v`, are passed on to be inspected.
+void *trace_call(char *name, char *kinds, ...) {
+    va_list values;
+    va_start(values, kinds);
+
+    if (source_file != NULL) {
+        fprintf(stderr, "trace: %s:%lld: %s(", source_file, (long long) source_line, name);
+    } else {
+        fprintf(stderr, "trace: %s(", name);
+    }
+
+    for (char *kind = kinds; *kind != 0; kind++) {
+        if (kind != kinds) {
+            fprintf(stderr, ", ");
+        }
+
+        switch (*kind) {
+            case 'v':
+                fprintf(stderr, "%s", val_type_name(va_arg(values, val_t *)));
+                break;
+            case 'b':
+                fprintf(stderr, "bool");
+                break;
+            case 'i':
+                fprintf(stderr, "int");
+                break;
+            case 'f':
+                fprintf(stderr, "float");
+                break;
+            default:
+                fprintf(stderr, "pointer");
+                break;
+        }
+    }
+
+    fprintf(stderr, ")\n");
+
+    va_end(values);
+
+    return NULL;
+}
+
+#endif
@@ -0,0 +1,238 @@
+#ifndef MINI_STD_TYPED_H
+#define MINI_STD_TYPED_H
+
+#include <stdint.h>
+#include <stdlib.h>
+
+#include "defs.h"
+#include "error.h"
+#include "gc.h"
+#include "val.h"
+
+// Float64Arrays and Int64Arrays keep their items unboxed. The generated code loads
+// and stores the items of the variables it knows to be typed arrays in place, and
+// goes through val_get and val_set otherwise.
+
+static void expect_typed_array(val_t *items, const char *function) {
+    if (val_type(items) != VAL_F64_ARRAY && val_type(items) != VAL_I64_ARRAY) {
+        RUNTIME_ERROR("`%s` expects a typed array, got %s", function, val_type_name(items));
+    }
+}
+
+static val_t *new_typed_array_val(val_type_t type, uint64_t len) {
+    val_t *result = new_val(type);
+    result->typed_array.len = len;
+    // the items of both types take 8 bytes, and start as zeros
+    result->typed_array.f64 = calloc(len > 0 ? len : 1, 8);
+
+    DEBUG("new typed array: %llu, %p", len, result);
+
+    return result;
+}
+
+static uint64_t expect_typed_array_len(val_t *len, const char *function) {
+    if (val_type(len) != VAL_INT || val_i64(len) < 0) {
+        RUNTIME_ERROR("`%s` expects a non-negative integer length, got %s", function, val_type_name(len));
+    }
+
+    return val_i64(len);
+}
+
+val_t *float64_array(val_t *len) {
+    return new_typed_array_val(VAL_F64_ARRAY, expect_typed_array_len(len, "float64_array"));
+}
+
+val_t *int64_array(val_t *len) {
+    return new_typed_array_val(VAL_I64_ARRAY, expect_typed_array_len(len, "int64_array"));
+}
+
+val_t *typed_array_len(val_t *items) {
+    expect_typed_array(items, "typed_array_len");
+
+    val_t *result = new_int_val(items->typed_array.len);
+
+    free_val_if_ok(items);
+
+    return result;
+}
+
+static uint64_t typed_array_index(val_t *items, val_t *i) {
+    if (val_type(i) != VAL_INT) {
+        RUNTIME_ERROR("array index must be a number, got %s", val_type_name(i));
+    }
+
+    int64_t index = val_i64(i);
+
+    if (index < 0 || (uint64_t) index >= items->typed_array.len) {
+        RUNTIME_ERROR("index %lld is out of bounds, the %s has %llu items", index, val_type_name(items), items->typed_array.len);
+    }
+
+    return index;
+}
+
+static val_t *typed_array_get(val_t *items, val_t *i) {
+    uint64_t index = typed_array_index(items, i);
+
+    if (items->type == VAL_F64_ARRAY) {
+        return new_float_val(items->typed_array.f64[index]);
+    }
+
+    return new_int_val(items->typed_array.i64[index]);
+}
+
+// the item is a copy of the number, the value stays with the caller
+static void typed_array_set(val_t *items, val_t *i, val_t *v) {
+    uint64_t index = typed_array_index(items, i);
+
+    if (items->type == VAL_I64_ARRAY) {
+        if (val_type(v) != VAL_INT) {
+            RUNTIME_ERROR("the items of an Int64Array are integers, got %s", val_type_name(v));
+        }
+
+        items->typed_array.i64[index] = val_i64(v);
+    } else if (val_type(v) == VAL_INT) {
+        items->typed_array.f64[index] = (double) val_i64(v);
+    } else if (val_type(v) == VAL_FLOAT) {
+        items->typed_array.f64[index] = v->f64;
+    } else {
+        RUNTIME_ERROR("the items of a Float64Array are numbers, got %s", val_type_name(v));
+    }
+}
+
+static void expect_matching_typed_arrays(val_t *a, val_t *b, const char *function) {
+    expect_typed_array(a, function);
+    expect_typed_array(b, function);
+
+    if (a->type != b->type) {
+        RUNTIME_ERROR("`%s` expects arrays of the same type, got %s and %s", function, val_type_name(a), val_type_name(b));
+    }
+
+    if (a->typed_array.len != b->typed_array.len) {
+        RUNTIME_ERROR("`%s` expects arrays of the same length, got %llu and %llu items", function, a->typed_array.len, b->typed_array.len);
+    }
+}
+
+static void free_typed_array_operands(val_t *a, val_t *b) {
+    free_val_if_ok(a);
+
+    if (b != a) {
+        free_val_if_ok(b);
+    }
+}
+
+// The loops of the bulk operations are kept plain, so that the C compiler
+// vectorizes them. Integers wrap around on overflow.
+
+val_t *array_add(val_t *a, val_t *b) {
+    expect_matching_typed_arrays(a, b, "array_add");
+
+    uint64_t len = a->typed_array.len;
+    val_t *result = new_typed_array_val(a->type, len);
+
+    if (a->type == VAL_F64_ARRAY) {
+        double *restrict r = result->typed_array.f64;
+        const double *x = a->typed_array.f64;
+        const double *y = b->typed_array.f64;
+
+        for (uint64_t i = 0; i < len; i++) {
+            r[i] = x[i] + y[i];
+        }
+    } else {
+        int64_t *restrict r = result->typed_array.i64;
+        const int64_t *x = a->typed_array.i64;
+        const int64_t *y = b->typed_array.i64;
+
+        for (uint64_t i = 0; i < len; i++) {
+            r[i] = (int64_t) ((uint64_t) x[i] + (uint64_t) y[i]);
+        }
+    }
+
+    free_typed_array_operands(a, b);
+
+    return result;
+}
+
+val_t *array_scale(val_t *a, val_t *k) {
+    expect_typed_array(a, "array_scale");
+
+    uint64_t len = a->typed_array.len;
+    val_t *result = new_typed_array_val(a->type, len);
+
+    if (a->type == VAL_F64_ARRAY) {
+        if (val_type(k) != VAL_INT && val_type(k) != VAL_FLOAT) {
+            RUNTIME_ERROR("`array_scale` expects a number, got %s", val_type_name(k));
+        }
+
+        double factor = val_type(k) == VAL_INT ? (double) val_i64(k) : k->f64;
+        double *restrict r = result->typed_array.f64;
+        const double *x = a->typed_array.f64;
+
+        for (uint64_t i = 0; i < len; i++) {
+            r[i] = x[i] * factor;
+        }
+    } else {
+        if (val_type(k) != VAL_INT) {
+            RUNTIME_ERROR("`array_scale` expects an integer for an Int64Array, got %s", val_type_name(k));
+        }
+
+        uint64_t factor = (uint64_t) val_i64(k);
+        int64_t *restrict r = result->typed_array.i64;
+        const int64_t *x = a->typed_array.i64;
+
+        for (uint64_t i = 0; i < len; i++) {
+            r[i] = (int64_t) ((uint64_t) x[i] * factor);
+        }
+    }
+
+    free_val_if_ok(a);
+    free_val_if_ok(k);
+
+    return result;
+}
+
+// floats are summed in DOT_LANES partial sums, so that the loop vectorizes without
+// reordering the additions of each sum, and the interpreter sums them the same way
+#define DOT_LANES 4
+
+val_t *dot(val_t *a, val_t *b) {
+    expect_matching_typed_arrays(a, b, "dot");
+
+    uint64_t len = a->typed_array.len;
+    val_t *result;
+
+    if (a->type == VAL_F64_ARRAY) {
+        const double *x = a->typed_array.f64;
+        const double *y = b->typed_array.f64;
+        double sums[DOT_LANES] = {0};
+        uint64_t i = 0;
+
+        // the item i goes to the sum i % DOT_LANES
+        for (; i + DOT_LANES <= len; i += DOT_LANES) {
+            for (uint64_t lane = 0; lane < DOT_LANES; lane++) {
+                sums[lane] += x[i + lane] * y[i + lane];
+            }
+        }
+
+        for (; i < len; i++) {
+            sums[i % DOT_LANES] += x[i] * y[i];
+        }
+
+        result = new_float_val((sums[0] + sums[1]) + (sums[2] + sums[3]));
+    } else {
+        const int64_t *x = a->typed_array.i64;
+        const int64_t *y = b->typed_array.i64;
+        uint64_t sum = 0;
+
+        for (uint64_t i = 0; i < len; i++) {
+            sum += (uint64_t) x[i] * (uint64_t) y[i];
+        }
+
+        result = new_int_val((int64_t) sum);
+    }
+
+    free_typed_array_operands(a, b);
+
+    return result;
+}
+
+#endif
@@ -14,27 +14,29 @@
 #include "object.h"
 #include "gc.h"
 
-static val_t null_val = {VAL_NULL, 0};
-static val_t true_val = {VAL_BOOL, 0, .b = true};
-static val_t false_val = {VAL_BOOL, 0, .b = false};
-
 static val_t *new_val(val_type_t type) {
     val_t *result = malloc(sizeof(val_t));
     result->type = type;
     result->ref_count = 0;
 
+    track_val(result);
+
     return result;
 }
 
 val_t *new_null_val() {
-    return &null_val;
+    return IMM_NULL;
 }
 
 val_t *new_bool_val(bool b) {
-    return b ? &true_val : &false_val;
+    return b ? IMM_TRUE : IMM_FALSE;
 }
 
 val_t *new_int_val(int64_t n) {
+    if (n >= IMM_INT_MIN && n <= IMM_INT_MAX) {
+        return (val_t *) (((uintptr_t) n << 1) | IMM_TAG_INT);
+    }
+
     val_t *result = new_val(VAL_INT);
     result->i64 = n;
 
@@ -61,6 +63,25 @@ val_t *new_str_val(char *s) {
     return result;
 }
 
+val_t *new_function_val(void *fn) {
+    val_t *result = new_val(VAL_FUNCTION);
+    result->fn = fn;
+
+    DEBUG("new function: %p, %p", fn, result);
+
+    return result;
+}
+
+val_t *new_range_val(int64_t start, int64_t end) {
+    val_t *result = new_val(VAL_RANGE);
+    result->range.start = start;
+    result->range.end = end;
+
+    DEBUG("new range: %lld..%lld, %p", (long long) start, (long long) end, result);
+
+    return result;
+}
+
 val_t *new_array_val(uint64_t len) {
     val_t *result = new_val(VAL_ARRAY);
     new_array(&result->array, len);
@@ -82,7 +103,10 @@ val_t *new_object_val() {
 val_t *val_get_type(val_t *v) {
     val_t *result = NULL;
 
-    switch (v->type) {
+    switch (val_type(v)) {
+        case VAL_UNDEFINED:
+            result = new_str_val("undefined");
+            break;
         case VAL_BOOL:
             result = new_str_val("boolean");
             break;
@@ -95,6 +119,12 @@ val_t *val_get_type(val_t *v) {
         case VAL_STR:
             result = new_str_val("string");
             break;
+        case VAL_FUNCTION:
+            result = new_str_val("function");
+            break;
+        case VAL_SYMBOL:
+            result = new_str_val("symbol");
+            break;
         default:
             result = new_str_val("object");
             break;
@@ -0,0 +1,148 @@
+#ifndef MINI_STD_VALIDATE_H
+#define MINI_STD_VALIDATE_H
+
+#include <stdio.h>
+#include <stdint.h>
+
+#include "defs.h"
+#include "error.h"
+#include "object.h"
+
+#define MAX_VALIDATE_PATH_LEN 256
+
+static const char *schema_name(const char *schema) {
+    switch (*schema) {
+        case 'u':
+            return "undefined";
+        case 'z':
+            return "null";
+        case 'b':
+            return "boolean";
+        case 's':
+            return "string";
+        case 'n':
+            return "number";
+        case 'i':
+            return "int32";
+        case 'F':
+            return "Float64Array";
+        case 'I':
+            return "Int64Array";
+        case 'y':
+            return "symbol";
+        case 'o':
+        case '{':
+            return "object";
+        case '[':
+            return "array";
+        default:
+            return "any";
+    }
+}
+
+static const char *skip_schema(const char *schema) {
+    switch (*schema) {
+        case '[':
+            return skip_schema(schema + 1);
+        case '{':
+            schema++;
+
+            while (*schema != '}') {
+                schema = skip_schema(strchr(schema, ':') + 1);
+
+                if (*schema == ',') schema++;
+            }
+
+            return schema + 1;
+        default:
+            return schema + 1;
+    }
+}
+
+static void validate_val(val_t *v, const char *schema, const char *path, const char *failure) {
+    switch (*schema) {
+        case 'a':
+            return;
+        case 'u':
+            if (val_type(v) == VAL_UNDEFINED) return;
+            break;
+        case 'z':
+            if (val_type(v) == VAL_NULL) return;
+            break;
+        case 'b':
+            if (val_type(v) == VAL_BOOL) return;
+            break;
+        case 's':
+            if (val_type(v) == VAL_STR) return;
+            break;
+        case 'n':
+            if ((val_type(v) == VAL_INT || val_type(v) == VAL_FLOAT)) return;
+            break;
+        case 'i':
+            if (val_type(v) == VAL_INT && val_i64(v) >= INT32_MIN && val_i64(v) <= INT32_MAX) return;
+            break;
+        case 'F':
+            if (val_type(v) == VAL_F64_ARRAY) return;
+            break;
+        case 'I':
+            if (val_type(v) == VAL_I64_ARRAY) return;
+            break;
+        case 'y':
+            if (val_type(v) == VAL_SYMBOL) return;
+            break;
+        case 'o':
+            if (val_type(v) == VAL_OBJECT) return;
+            break;
+        case '{':
+            if (val_type(v) == VAL_OBJECT) {
+                char field_path[MAX_VALIDATE_PATH_LEN];
+                char field_name[MAX_VALIDATE_PATH_LEN];
+
+                schema++;
+
+                while (*schema != '}') {
+                    size_t name_len = strchr(schema, ':') - schema;
+
+                    snprintf(field_name, MAX_VALIDATE_PATH_LEN, "%.*s", (int) name_len, schema);
+                    snprintf(field_path, MAX_VALIDATE_PATH_LEN, "%s.%s", path, field_name);
+
+                    schema += name_len + 1;
+                    validate_val(object_get(&v->object, find_key(field_name)), schema, field_path, failure);
+                    schema = skip_schema(schema);
+
+                    if (*schema == ',') schema++;
+                }
+
+                return;
+            }
+            break;
+        case '[':
+            if (val_type(v) == VAL_ARRAY) {
+                char item_path[MAX_VALIDATE_PATH_LEN];
+
+                for (size_t i = 0; i < v->array.len; i++) {
+                    snprintf(item_path, MAX_VALIDATE_PATH_LEN, "%s[%zu]", path, i);
+                    validate_val(v->array.data[i], schema + 1, item_path, failure);
+                }
+
+                return;
+            }
+            break;
+    }
+
+    RUNTIME_ERROR("%s at %s: expected %s, got %s", failure, path, schema_name(schema), val_type_name(v));
+}
+
+void *val_validate(val_t *v, char *schema) {
+    validate_val(v, schema, "$", "validation failed");
+
+    return v;
+}
+
+void *val_assert_type(val_t *v, char *schema, char *path) {
+    validate_val(v, schema, path, "type assertion failed");
+
+    return v;
+}
+
+#endif
@@ -0,0 +1,30 @@
+use mini::{bench, CompileOptions};
+
+const BENCHMARKS: &str = "\
+@bench
+function add(n?: any) { return 1 + 2; }
+@bench
+function concat(...s: any[]) { return \"a\" + \"b\"; }
+";
+
+#[test]
+fn benchmarks_are_measured_in_order() {
+    let results = bench::run(BENCHMARKS, &CompileOptions::new(), 1, 3).unwrap();
+    let names = results
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["add", "concat"]);
+    assert!(results
+        .iter()
+        .all(|(_, statistics)| statistics.iterations == 3));
+}
+
+#[test]
+fn benchmarks_cannot_take_required_parameters() {
+    let content = "@bench\nfunction f(n: any) { return n; }\n";
+    let err = bench::run(content, &CompileOptions::new(), 0, 1).unwrap_err();
+
+    assert_eq!(err.code(), "E0028");
+}
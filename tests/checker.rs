@@ -0,0 +1,92 @@
+use mini::{compile_source, CompileOptions, CompilerError, EmitKind};
+
+/// Compiles the program with the type checker, to LLVM IR so nothing is linked.
+/// `echo` is declared ahead of it.
+fn check(content: &str) -> Result<(), CompilerError> {
+    let content = format!("declare function echo(...s: any[]): void;\n{}", content);
+    let options = CompileOptions::new().strict(true).emit(EmitKind::LlvmIr);
+
+    compile_source(&content, &options).map(|_| ())
+}
+
+fn error_code(content: &str) -> &'static str {
+    match check(content) {
+        Ok(()) => panic!("the program was expected to fail the type check"),
+        Err(err) => err.code(),
+    }
+}
+
+const ABS: &str = "declare function abs(n: int32): int32;\n";
+
+#[test]
+fn int32_widens_to_number() {
+    check(&format!("{}let x: number = abs(-3);\necho(x);\n", ABS)).unwrap();
+}
+
+#[test]
+fn numbers_are_truncated_to_int32_parameters_of_external_functions() {
+    check(&format!("{}echo(abs(2.5));\n", ABS)).unwrap();
+}
+
+#[test]
+fn numbers_do_not_narrow_to_int32() {
+    assert_eq!(error_code("let x: int32 = 5;\necho(x);\n"), "E0007");
+}
+
+#[test]
+fn int32_parameters_of_other_functions_take_no_numbers() {
+    let content = "function f(n: int32) { return n; }\nf(3);\n";
+
+    assert_eq!(error_code(content), "E0006");
+}
+
+#[test]
+fn int32_parameters_take_no_strings() {
+    assert_eq!(error_code(&format!("{}abs(\"s\");\n", ABS)), "E0006");
+}
+
+#[test]
+fn if_conditions_can_be_any_value() {
+    let content = "let s = \"x\";\nif (s) { s = \"y\"; }\nif (0) { echo(s); }\n";
+
+    check(content).unwrap();
+}
+
+#[test]
+fn typeof_guards_narrow_the_variable() {
+    let content = "\
+function f(x: any) {
+    if (typeof x == \"number\") {
+        let s: string = x;
+        echo(s);
+    }
+}
+f(1);
+";
+
+    assert_eq!(error_code(content), "E0007");
+}
+
+#[test]
+fn negated_typeof_guards_narrow_the_else_branch() {
+    let content = "\
+function f(x: any) {
+    if (typeof x != \"string\") {
+        echo(x);
+    } else {
+        let n: number = x;
+        echo(n);
+    }
+}
+f(1);
+";
+
+    assert_eq!(error_code(content), "E0007");
+}
+
+#[test]
+fn errors_have_stable_codes() {
+    assert_eq!(error_code("echo(y);\n"), "E0001");
+    assert_eq!(error_code("let x = 1;\nlet x = 2;\necho(x);\n"), "E0002");
+    assert_eq!(error_code("let s: string = 1;\necho(s);\n"), "E0007");
+}
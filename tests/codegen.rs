@@ -0,0 +1,53 @@
+use inkwell::context::Context;
+use mini::gen::{EmitKind, IRGenerator};
+use mini::{pipeline, st, CompileOptions};
+
+#[test]
+fn every_target_is_emitted_from_the_generated_module() {
+    let content = "declare function echo(...s: any[]): void;\necho(1 + 2);\n";
+    let options = CompileOptions::new().lto(true);
+
+    let program = pipeline::parse(content).unwrap();
+    let main_def = pipeline::main_definition(content);
+    let symbol_table = st::SymbolTable::from(&main_def, &program).unwrap();
+
+    let context = Context::create();
+    let mut ir_generator = IRGenerator::new(&symbol_table, None, &context, &options).unwrap();
+    ir_generator.generate().unwrap();
+
+    let generated = ir_generator.module().print_to_string();
+
+    let triple = target_lexicon::Triple::host();
+    let first = ir_generator.emit(&triple, EmitKind::LlvmIr).unwrap();
+    let second = ir_generator.emit(&triple, EmitKind::LlvmIr).unwrap();
+
+    assert_eq!(ir_generator.module().print_to_string(), generated);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn the_harness_runs_every_test_through_its_caller() {
+    let content = "function test_a() {}\n@test\nfunction b() {}\nfunction c() {}\n";
+    let options = CompileOptions::new();
+
+    let program = pipeline::parse(content).unwrap();
+    let main_def = pipeline::main_definition(content);
+    let symbol_table = st::SymbolTable::test_harness(&main_def, &program).unwrap();
+
+    let context = Context::create();
+    let mut ir_generator = IRGenerator::new(&symbol_table, None, &context, &options).unwrap();
+    ir_generator.generate().unwrap();
+    ir_generator.module().verify().unwrap();
+
+    let ir = ir_generator.module().print_to_string().to_string();
+
+    let runs = ir
+        .lines()
+        .filter(|line| line.contains(" call ") && line.contains("@run_test("))
+        .count();
+
+    assert_eq!(runs, 2);
+    assert!(ir.contains("@mini.test_a.caller"));
+    assert!(ir.contains("@mini.b.caller"));
+    assert!(!ir.contains("@mini.c.caller"));
+}
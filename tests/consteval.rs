@@ -0,0 +1,26 @@
+use mini::repl::Repl;
+use mini::{compile_source, CompileOptions, EmitKind};
+
+const OVERFLOWING_MOD: &str = "const X = (-9223372036854775807 - 1) % -1;\necho(X);\n";
+
+#[test]
+fn overflowing_constants_are_left_to_the_runtime() {
+    let content = format!("declare function echo(...s: any[]): void;\n{}", OVERFLOWING_MOD);
+    let options = CompileOptions::new().emit(EmitKind::LlvmIr);
+
+    compile_source(&content, &options).unwrap();
+}
+
+#[test]
+fn overflowing_modulo_is_a_runtime_error_in_the_interpreter() {
+    let err = Repl::new().eval(OVERFLOWING_MOD).result.unwrap_err();
+
+    assert_eq!(err.code(), "E0025");
+}
+
+#[test]
+fn constants_combine_earlier_constants() {
+    let content = "const KB = 1024;\nconst SIZE = 3 * KB + 7 % 4;\necho(\"\" + SIZE);\n";
+
+    assert_eq!(Repl::new().eval(content).output, "3075\n");
+}
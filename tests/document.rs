@@ -0,0 +1,35 @@
+use mini::repl::Repl;
+use mini::{ast, Document};
+
+#[test]
+fn documents_keep_their_program_once_the_buffer_is_gone() {
+    let documents = ["let a = 1;\n", "function f() {}\nf();\n"]
+        .iter()
+        .map(|content| Document::parse(content.to_string()).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(documents[1].program().statements.len(), 2);
+
+    let name = documents[1]
+        .with_symbol_table(|symbol_table| {
+            symbol_table.definition_at(10).map(|d| d.name.to_string())
+        })
+        .unwrap();
+
+    assert_eq!(name.as_deref(), Some("f"));
+}
+
+#[test]
+fn the_repl_keeps_the_program_of_the_inputs_that_ran() {
+    let mut repl = Repl::new();
+
+    assert!(repl.eval("let x = 1;").result.is_ok());
+    assert!(repl.eval("y;").result.is_err());
+
+    match repl.history().program().statements.last() {
+        Some(ast::Statement::DefinitionStatement { definition, .. }) => {
+            assert_eq!(definition.name, "x")
+        }
+        statement => panic!("unexpected statement {:?}", statement),
+    }
+}
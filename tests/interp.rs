@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+use mini::interp::Interpreter;
+use mini::repl::Repl;
+use mini::{parse, pipeline, st, CompilerError};
+
+/// Runs the program with the interpreter, returning what it printed.
+fn run(content: &str) -> Result<String, CompilerError> {
+    let evaluation = Repl::new().eval(content);
+
+    evaluation.result.map(|_| evaluation.output)
+}
+
+/// Runs the program with the function as its entry, returning the exit status.
+fn exit_status(content: &str, entry: &str) -> Result<i32, CompilerError> {
+    let program = parse(content)?;
+    let main_def = pipeline::main_definition(content);
+    let symbol_table = st::SymbolTable::with_entry(&main_def, &program, Some(entry))?;
+
+    let mut interpreter = Interpreter::new(&symbol_table, None);
+    interpreter.set_output(Rc::new(RefCell::new(io::sink())));
+
+    let value = interpreter.run()?;
+    interpreter.exit_status(&value)
+}
+
+/// Runs the test harness of the program, returning what it printed and its exit status.
+fn run_tests(content: &str) -> Result<(String, i32), CompilerError> {
+    let program = parse(content)?;
+    let main_def = pipeline::main_definition(content);
+    let symbol_table = st::SymbolTable::test_harness(&main_def, &program)?;
+
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::new(&symbol_table, None);
+    interpreter.set_output(output.clone());
+
+    let value = interpreter.run()?;
+    let status = interpreter.exit_status(&value)?;
+    let output = String::from_utf8(output.take()).unwrap();
+
+    Ok((output, status))
+}
+
+#[test]
+fn typed_arrays_start_with_zeros() {
+    let content = "\
+let a = float64_array(3);
+a[1] = 2.5;
+echo(\"\" + typed_array_len(a), \"\" + a[0], \"\" + a[1]);
+";
+
+    assert_eq!(run(content).unwrap(), "3 0 2.5\n");
+}
+
+#[test]
+fn int64_arrays_only_take_integers() {
+    let err = run("let i = int64_array(1);\ni[0] = 2.9;\n").unwrap_err();
+
+    assert_eq!(err.code(), "E0025");
+}
+
+#[test]
+fn typed_arrays_compute_on_their_items() {
+    let content = "\
+let a = float64_array(2);
+a[0] = 1;
+a[1] = 2.5;
+let b = array_scale(a, 2);
+let c = array_add(a, b);
+echo(\"\" + dot(a, b), \"\" + typed_array_len(b), \"\" + b[1], \"\" + c[1]);
+";
+
+    assert_eq!(run(content).unwrap(), "14.5 2 5 7.5\n");
+}
+
+#[test]
+fn defer_runs_when_the_block_is_left() {
+    let content = "\
+function f() {
+    defer echo(\"a\");
+    defer echo(\"b\");
+    echo(\"c\");
+
+    if (true) {
+        defer echo(\"e\");
+        echo(\"f\");
+    }
+
+    return \"r\";
+}
+echo(f());
+";
+
+    assert_eq!(run(content).unwrap(), "c\nf\ne\nb\na\nr\n");
+}
+
+#[test]
+fn entry_result_is_the_exit_status() {
+    let content = "let base = 40;\nfunction start() { return base + 2; }\n";
+
+    assert_eq!(exit_status(content, "start").unwrap(), 42);
+}
+
+#[test]
+fn entry_without_result_exits_with_zero() {
+    assert_eq!(exit_status("function start() {}\n", "start").unwrap(), 0);
+}
+
+#[test]
+fn entry_must_return_a_number() {
+    let content = "function start() { return \"oops\"; }\n";
+
+    assert_eq!(exit_status(content, "start").unwrap_err().code(), "E0025");
+}
+
+#[test]
+fn entry_must_be_defined_at_the_top_level() {
+    let err = exit_status("function f() {}\nf();\n", "start").unwrap_err();
+
+    assert_eq!(err.code(), "E0028");
+}
+
+#[test]
+fn entry_cannot_take_required_parameters() {
+    let err = exit_status("function start(n: number) {}\n", "start").unwrap_err();
+
+    assert_eq!(err.code(), "E0028");
+}
+
+/// Which branch of an `if` the value selects.
+fn branch(value: &str) -> String {
+    let content = format!(
+        "if ({}) {{ echo(\"true\"); }} else {{ echo(\"false\"); }}\n",
+        value
+    );
+
+    run(&content).unwrap()
+}
+
+#[test]
+fn falsy_values_take_the_else_branch() {
+    for value in ["false", "0", "0.0", "0 / 0", "\"\"", "null", "undefined"] {
+        assert_eq!(branch(value), "false\n", "{} is falsy", value);
+    }
+}
+
+#[test]
+fn other_values_take_the_then_branch() {
+    for value in ["true", "1", "-1", "0.5", "\"0\"", "\" \"", "[]", "{}"] {
+        assert_eq!(branch(value), "true\n", "{} is truthy", value);
+    }
+}
+
+#[test]
+fn logical_operators_use_truthiness() {
+    let content = "echo(\"\" + (!\"\"), \"\" + (!0), \"\" + (!\"a\"), \"\" + (!null));\n";
+
+    assert_eq!(run(content).unwrap(), "true true false true\n");
+}
+
+// the runtime in std/ops.h computes the same results, which a program compiled
+// with it prints alike
+
+#[test]
+fn overflowing_integers_become_floats() {
+    let content = "\
+function max() { return 9223372036854775807; }
+echo(\"\" + (max() * 2), \"\" + -(-max() - 1), \"\" + (max() + 1 - max()));
+";
+
+    assert_eq!(
+        run(content).unwrap(),
+        "18446744073709551616 9223372036854775808 0\n"
+    );
+}
+
+#[test]
+fn integers_of_float_arithmetic_keep_double_precision() {
+    let content =
+        "function tenth() { return 0.1; }\necho(\"\" + (16777217 + tenth() - 16777217));\n";
+
+    assert_eq!(run(content).unwrap(), "0.1\n");
+}
+
+#[test]
+fn modulo_by_zero_is_a_runtime_error() {
+    let err = run("function zero() { return 0; }\necho(5 % zero());\n").unwrap_err();
+
+    assert_eq!(err.code(), "E0025");
+}
+
+#[test]
+fn array_indices_are_bounded() {
+    let err = run("let a = [1];\na[1099511627776] = 2;\n").unwrap_err();
+
+    assert_eq!(err.code(), "E0025");
+}
+
+#[test]
+fn tests_run_one_after_the_other_in_the_harness() {
+    let content = "\
+declare function echo(...s: any[]): void;
+declare function assert(c: any, m?: any): void;
+echo(\"top\");
+function test_passes() { echo(\"passes\"); }
+@test
+function fails() { assert(false, \"fails\"); }
+function helper() {}
+function test_runs_after_failure(a?: any) {}
+";
+
+    let (output, status) = run_tests(content).unwrap();
+
+    assert_eq!(
+        output,
+        "top\nrunning 3 tests\npasses\n\
+test test_passes ... \x1B[0;32mok\x1B[0m\n\
+test fails ... \x1B[0;31mFAILED\x1B[0m\n\
+test test_runs_after_failure ... \x1B[0;32mok\x1B[0m\n\
+\ntest result: \x1B[0;31mFAILED\x1B[0m. 2 passed; 1 failed\n"
+    );
+    assert_eq!(status, 4);
+}
+
+#[test]
+fn test_functions_cannot_take_required_parameters() {
+    let err = run_tests("function test_takes(a: any) {}\n").unwrap_err();
+
+    assert_eq!(err.code(), "E0028");
+}
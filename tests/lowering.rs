@@ -0,0 +1,103 @@
+use mini::repl::Repl;
+
+/// Runs the program with the interpreter, returning what it printed.
+fn run(content: &str) -> String {
+    let evaluation = Repl::new().eval(content);
+
+    if let Err(err) = evaluation.result {
+        panic!("the program failed: {:?}", err);
+    }
+
+    evaluation.output
+}
+
+const CALLS: &str = "\
+function g() { echo(\"g\"); return 1; }
+function h() { echo(\"h\"); return 2; }
+function k() { echo(\"k\"); return 3; }
+";
+
+#[test]
+fn match_values_the_first_matching_arm() {
+    let content = "\
+let x = { kind: \"circle\", radius: 2 };
+echo(match (x) { { kind: \"square\" } => \"square\", { kind: \"circle\", radius: r } => \"circle \" + r, _ => \"?\" });
+echo(match (5) { 1 => \"one\", _ => \"other\" });
+";
+
+    assert_eq!(run(content), "circle 2\nother\n");
+}
+
+#[test]
+fn match_without_a_matching_arm_is_undefined() {
+    let content = "let x = match (1) { 2 => \"two\" };\necho(typeof x);\n";
+
+    assert_eq!(run(content), "undefined\n");
+}
+
+#[test]
+fn match_arguments_run_after_the_ones_before_them() {
+    let content = format!(
+        "{}function f(a: any, b: any, c: any) {{ echo(b); }}\nf(g(), match (h()) {{ 2 => \"two\", _ => \"?\" }}, k());\n",
+        CALLS
+    );
+
+    assert_eq!(run(&content), "g\nh\nk\ntwo\n");
+}
+
+#[test]
+fn match_operands_run_after_the_ones_before_them() {
+    let content = format!(
+        "{}let s = g() + match (h()) {{ 2 => 10, _ => 0 }} + k();\necho(\"\" + s);\n",
+        CALLS
+    );
+
+    assert_eq!(run(&content), "g\nh\nk\n14\n");
+}
+
+#[test]
+fn match_items_run_after_the_ones_before_them() {
+    let content = format!(
+        "{}let o = {{ x: g(), y: [h(), match (k()) {{ 3 => \"three\", _ => \"?\" }}] }};\necho(o.y[1]);\n",
+        CALLS
+    );
+
+    assert_eq!(run(&content), "g\nh\nk\nthree\n");
+}
+
+#[test]
+fn match_statements_run_in_their_block() {
+    let content = format!(
+        "{}function f() {{ if (false) {{ echo(match (h()) {{ _ => \"no\" }}); }} return g(); }}\nf();\n",
+        CALLS
+    );
+
+    assert_eq!(run(&content), "g\n");
+}
+
+#[test]
+fn compound_assignments_apply_the_operator() {
+    let content = "let x = 2;\nx += 3;\nx *= 4;\necho(\"\" + x);\n";
+
+    assert_eq!(run(content), "20\n");
+}
+
+#[test]
+fn compound_assignments_update_properties() {
+    let content = "let o = { n: 1 };\no.n -= 3;\necho(\"\" + o.n);\n";
+
+    assert_eq!(run(content), "-2\n");
+}
+
+#[test]
+fn match_arguments_run_after_macro_variables_before_them() {
+    let content = "\
+macro m() {
+    let x = \"a\";
+    echo(x, match (0) { _ => x = \"b\" }, x);
+}
+m!();
+";
+
+    assert_eq!(run(content), "a b b\n");
+}